@@ -0,0 +1,86 @@
+// Batyr Break Benchmarks
+// Copyright (C) 2023 Gene Yu
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Times line counting and break finding on pathological input
+//!
+//! Run with <tt>cargo bench</tt>.  Each input comes in growing sizes,
+//! so a pass that is worse than linear shows up as a time that grows
+//! faster than the token count.
+
+use batyr::document::breaking::find_break_points;
+use batyr::text;
+use batyr::text::parser::Parser;
+use batyr::text::tokens::{Token, TokenType};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion,
+                Throughput};
+
+const SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+const LINE_LENGTH: usize = 57;
+
+/// A single paragraph of ordinary words, about <tt>n</tt> tokens long
+fn paragraph(n: usize) -> Vec<TokenType> {
+    let sentence = "Emma waits by the door, and the rain comes down. ";
+    let text = sentence.repeat(n / 20 + 1);
+
+    Parser::new(&text, Vec::new(), Default::default())
+        .run()
+        .get_tokens()
+}
+
+/// Nothing but discretionary breaks
+fn spaces(n: usize) -> Vec<TokenType> {
+    (0..n).map(|_| TokenType::Space(Token::from(1))).collect()
+}
+
+/// One word too long for any line, between a pair of breaks
+fn long_word(n: usize) -> Vec<TokenType> {
+    let text = format!("a {} b", "x".repeat(n));
+
+    Parser::new(&text, Vec::new(), Default::default())
+        .run()
+        .get_tokens()
+}
+
+fn bench(c: &mut Criterion, name: &str, input: fn(usize) -> Vec<TokenType>) {
+    let mut group = c.benchmark_group(name);
+
+    for size in SIZES {
+        let tokens = input(size);
+        group.throughput(Throughput::Elements(tokens.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("count_lines", size), &tokens,
+                               |b, tokens| {
+            b.iter(|| text::count_lines(&tokens[..], LINE_LENGTH))
+        });
+
+        group.bench_with_input(BenchmarkId::new("find_break_points", size),
+                               &tokens, |b, tokens| {
+            b.iter(|| find_break_points(&tokens[..], LINE_LENGTH))
+        });
+    }
+
+    group.finish();
+}
+
+fn breaking(c: &mut Criterion) {
+    bench(c, "paragraph", paragraph);
+    bench(c, "spaces", spaces);
+    bench(c, "long_word", long_word);
+}
+
+criterion_group!(benches, breaking);
+criterion_main!(benches);
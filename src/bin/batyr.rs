@@ -0,0 +1,589 @@
+// Batyr Main Driver
+// Copyright (C) 2023 Gene Yu
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <https://www.gnu.org/licenses/>.
+
+use std::env;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use clap::{Parser, Subcommand};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use lazy_static::lazy_static;
+
+use batyr::{AtomicFile, Emit, InputEncoding, OutputFormat, RenderOptions};
+use batyr::document::ElementType;
+use batyr::document::breaking::BreakPolicy;
+use batyr::document::formatter::{FormatterObserver, Page};
+use batyr::document::html::HtmlMode;
+use batyr::document::reader::BreakCache;
+use batyr::document::report::{Appearances, list_alternates};
+use batyr::document::sample;
+use batyr::document::writer::{
+    missing_procedures, Duplex, Newline, PageNumberPlace, PageNumbers
+};
+use batyr::text::parser::{TAB_STOP, TabPolicy};
+
+// configuration
+
+/// How often <tt>--watch</tt> looks at the input file
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+lazy_static! {
+    static ref PROGRAM_NAME: String = match get_program_name() {
+        Some(name) => name,
+        None => "batyr".to_string(),
+    };
+
+    /// Path to an installed prologue.ps, which takes precedence over
+    /// the one built into the library if it defines every procedure
+    /// the writer calls
+    static ref PROLOGUE_FILE: Option<PathBuf> = get_prologue_file();
+}
+
+/// Command-line arguments
+#[derive(Parser, Debug)]
+#[clap(author="Gene Yu", version, about="Screenplay Typewriter",
+       args_conflicts_with_subcommands = true,
+       subcommand_negates_reqs = true)]
+struct Arguments {
+    #[clap(subcommand)]
+    command: Option<Command>,
+
+    #[clap(required = true)]
+    /// An XML file conforming to the screenplay schema
+    input_file: Option<PathBuf>,
+
+    #[clap(short, long, value_name = "FILE")]
+    /// Write the output to FILE instead of standard output, replacing
+    /// it only once the output is complete.
+    output: Option<PathBuf>,
+
+    #[clap(short, long, requires = "output")]
+    /// Watch the input file, and write the output again each time it
+    /// changes, for previewing in a viewer that reloads on its own.
+    watch: bool,
+
+    #[clap(short, long)]
+    /// Show the internal element tree instead of the usual output,
+    /// with the source line, break points and line count of each
+    /// element, on standard error unless an output file is given.
+    elements: bool,
+
+    #[clap(long)]
+    /// Typeset the document even if the title or authors are missing.
+    allow_missing_head: bool,
+
+    #[clap(long, value_name = "N")]
+    /// Number of lines on each page of the body [default: 55]
+    lines_per_page: Option<usize>,
+
+    #[clap(long, value_name = "N")]
+    /// Leave at least N blank lines above END OF ACT and THE END [default: 1]
+    end_gap: Option<usize>,
+
+    #[clap(long, value_name = "N")]
+    /// Leave at least N lines of broken direction or dialogue before a page break [default: 1]
+    lines_before_break: Option<usize>,
+
+    #[clap(long, value_name = "N")]
+    /// Carry at least N lines of broken direction or dialogue to the next page [default: 1]
+    lines_after_break: Option<usize>,
+
+    #[clap(long)]
+    /// Allow a page break right after personal direction.
+    break_after_dir: bool,
+
+    #[clap(long, value_name = "FILE")]
+    /// Use this PostScript prologue instead of the installed or
+    /// built-in one.
+    prologue: Option<PathBuf>,
+
+    #[clap(long, default_value = "lf", value_parser = ["lf", "crlf"])]
+    /// Line ending for the output.
+    newline: String,
+
+    #[clap(long, value_name = "N")]
+    /// Warn about scenes that run longer than N pages.
+    max_scene_pages: Option<usize>,
+
+    #[clap(long)]
+    /// Check the input against the screenplay schema first, reporting
+    /// unknown elements, misplaced elements and missing children by
+    /// line number.
+    validate: bool,
+
+    #[clap(long)]
+    /// Check that no line of output runs past the right margin.
+    verify: bool,
+
+    #[clap(long)]
+    /// Warn about likely mistakes, such as misspelled transitions.
+    lint: bool,
+
+    #[clap(long, value_name = "N")]
+    /// Report at most N problems from --validate, --lint or --verify,
+    /// and count the rest.
+    max_errors: Option<usize>,
+
+    #[clap(long, value_name = "FILE")]
+    /// Accept the transitions listed in FILE, one per line.
+    transitions: Option<PathBuf>,
+
+    #[clap(long, default_value = "document",
+           value_parser = ["document", "outline"])]
+    /// What to write: the typeset document, or an outline of acts and
+    /// scenes with page numbers.
+    emit: String,
+
+    #[clap(long, default_value = "ps", value_parser = ["ps", "html", "json"])]
+    /// Format of the typeset document: PostScript for printing, or
+    /// HTML for publishing drafts on the web.  json writes the element
+    /// tree instead, with tokens and break points, for other tools; it
+    /// goes to standard output or the output file, uncompressed.
+    format: String,
+
+    #[clap(long)]
+    /// Write HTML pages as preformatted text, line for line as typed,
+    /// instead of a block for each scene heading, cue, speech and so
+    /// on.
+    html_pre: bool,
+
+    #[clap(long, value_parser = ["adr", "characters"])]
+    /// Write a production report instead: adr for a CSV sheet of every
+    /// line of dialogue, for looping sessions, or characters for the
+    /// scenes, speeches, lines and words of each character.
+    report: Option<String>,
+
+    #[clap(long, value_name = "FILE")]
+    /// Write a CSV matrix of characters by scenes to FILE.
+    appearances: Option<PathBuf>,
+
+    #[clap(long)]
+    /// Print a hash of the draft's contents on the title page.
+    stamp_hash: bool,
+
+    #[clap(long)]
+    /// Show a progress bar on standard error while formatting.
+    progress: bool,
+
+    #[clap(long)]
+    /// Write each page as soon as it is formatted, to save memory.
+    stream: bool,
+
+    #[clap(long)]
+    /// Compress the output with gzip, for saving as a .ps.gz file.
+    compress: bool,
+
+    #[clap(long)]
+    /// Find line breaks on all cores, which is faster for long
+    /// screenplays.
+    parallel: bool,
+
+    #[clap(long)]
+    /// Report the time spent reading, parsing, breaking, formatting
+    /// and writing on standard error.
+    timing: bool,
+
+    #[clap(long)]
+    /// Renumber a scene whose number repeats or goes back, adding a
+    /// letter to the highest number so far, as in 12A.
+    resolve_scene_numbers: bool,
+
+    #[clap(long, value_name = "N")]
+    /// Typeset variant N of every <alt> group.
+    alt: Option<usize>,
+
+    #[clap(long, value_name = "FILE")]
+    /// Write a list of the variants in every <alt> group to FILE.
+    alternates: Option<PathBuf>,
+
+    #[clap(long, value_name = "FILE")]
+    /// Write a copy of the input to FILE with the page number of every
+    /// slug and cue in a page attribute.
+    annotate_pages: Option<PathBuf>,
+
+    #[clap(long, value_name = "FILE")]
+    /// Write the page and line where every scene and element landed
+    /// to FILE as JSON.
+    page_map: Option<PathBuf>,
+
+    #[clap(long, value_parser = ["network", "streaming", "intl"])]
+    /// Read only the conditional content meant for this draft.
+    target: Option<String>,
+
+    #[clap(long, default_value = "auto",
+           value_parser = ["auto", "utf-8", "windows-1252", "latin-1"])]
+    /// Character encoding of the input file.  Auto reads UTF-8, and
+    /// falls back to Windows-1252 for files that are not valid UTF-8.
+    input_encoding: String,
+
+    #[clap(long, default_value = "collapse",
+           value_parser = ["collapse", "expand", "error"])]
+    /// What to do with tabs in text: treat them as spaces, expand them
+    /// to the next tab stop, or reject the input.
+    tabs: String,
+
+    #[clap(long, value_name = "N", default_value_t = TAB_STOP)]
+    /// Width of the tab stops for --tabs expand.
+    tab_stop: usize,
+
+    #[clap(long, value_name = "TEMPLATE", default_value = "{n}.")]
+    /// Print page numbers after TEMPLATE.  In braces, n stands for
+    /// the page number, and i and I for the number in Roman numerals.
+    page_number_format: String,
+
+    #[clap(long, value_name = "TEMPLATE")]
+    /// Number the front matter pages after TEMPLATE.  They are
+    /// unnumbered by default.
+    front_matter_page_numbers: Option<String>,
+
+    #[clap(long, default_value = "top-right",
+           value_parser = ["top-right", "top-center", "bottom-center"])]
+    /// Where to print the page number.
+    page_number_place: String,
+
+    #[clap(long, value_name = "N")]
+    /// Ask the printer for N copies.
+    copies: Option<usize>,
+
+    #[clap(long)]
+    /// Ask the printer to collate the copies.
+    collate: bool,
+
+    #[clap(long, value_parser = ["long", "short"])]
+    /// Ask the printer to print on both sides, binding on the long or
+    /// short edge.
+    duplex: Option<String>,
+}
+
+/// Tools besides typesetting
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Write a synthetic screenplay to standard output, for
+    /// benchmarking and stress testing.
+    Gen {
+        #[clap(long, value_name = "N", default_value_t = 100)]
+        /// Make the screenplay roughly N pages long.
+        pages: usize,
+
+        #[clap(long, value_name = "N", default_value_t = 0)]
+        /// Seed for the random choices; the same seed gives the same
+        /// screenplay.
+        seed: u64,
+    },
+}
+
+impl From<Arguments> for RenderOptions {
+    fn from(args: Arguments) -> Self {
+        let mut options = RenderOptions::from_file(args.input_file.unwrap_or_default())
+            .elements(args.elements)
+            .allow_missing_head(args.allow_missing_head)
+            .validate(args.validate)
+            .verify(args.verify)
+            .lint(args.lint)
+            .stamp_hash(args.stamp_hash)
+            .stream(args.stream)
+            .compress(args.compress)
+            .parallel(args.parallel)
+            .timing(args.timing)
+            .resolve_scene_numbers(args.resolve_scene_numbers)
+            .collate(args.collate)
+            .emit(Emit::from(args.report.as_deref().unwrap_or(&args.emit)))
+            .format(OutputFormat::from(args.format.as_str()))
+            .html_mode(match args.html_pre {
+                true => HtmlMode::Pre,
+                false => HtmlMode::Blocks,
+            })
+            .newline(Newline::from(args.newline.as_str()))
+            .input_encoding(InputEncoding::from(args.input_encoding.as_str()));
+
+        options.lines_per_page = args.lines_per_page;
+        options.end_gap = args.end_gap;
+        options.max_scene_pages = args.max_scene_pages;
+        options.max_errors = args.max_errors;
+        options.alternate = args.alt;
+        options.target = args.target;
+        options.annotate_pages = args.annotate_pages;
+        options.page_map = args.page_map;
+        options.copies = args.copies;
+        options.duplex = Duplex::from(args.duplex.as_deref().unwrap_or(""));
+        options.page_numbers = PageNumbers {
+            body: args.page_number_format,
+            front_matter: args.front_matter_page_numbers,
+            place: PageNumberPlace::from(args.page_number_place.as_str()),
+        };
+        options.break_policy = BreakPolicy {
+            min_lines_before: args.lines_before_break
+                .unwrap_or(options.break_policy.min_lines_before),
+            min_lines_after: args.lines_after_break
+                .unwrap_or(options.break_policy.min_lines_after),
+            break_after_dir: args.break_after_dir,
+            ..options.break_policy
+        };
+        options.tabs = match TabPolicy::from(args.tabs.as_str()) {
+            TabPolicy::Expand(_) => TabPolicy::Expand(args.tab_stop),
+            tabs => tabs,
+        };
+        options
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Arguments::parse();
+
+    if let Some(Command::Gen { pages, seed }) = args.command {
+        io::stdout().write_all(sample::generate(pages, seed).as_bytes())?;
+        return Ok(());
+    }
+
+    let prologue = match &args.prologue {
+        Some(path) => Some(read_prologue(path)?),
+        None => match PROLOGUE_FILE.as_ref().filter(|path| path.exists()) {
+            Some(path) => Some(read_prologue(path)?)
+                .filter(|text| missing_procedures(text).is_empty()),
+            None => None,
+        },
+    };
+
+    let show_progress = args.progress;
+    let transitions_file = args.transitions.clone();
+    let input_file = args.input_file.clone();
+    let watch = args.watch;
+
+    let files = OutputFiles {
+        output: args.output.clone(),
+        appearances: args.appearances.clone(),
+        alternates: args.alternates.clone(),
+    };
+
+    let mut options = RenderOptions::from(args)
+        .creator(PROGRAM_NAME.as_str());
+
+    if let Some(text) = prologue {
+        options = options.prologue(text);
+    }
+
+    if let Some(path) = transitions_file {
+        options = options.transitions(read_transitions(&path)?);
+    }
+
+    match input_file {
+        Some(path) if watch => {
+            let options = options.cache(Arc::new(BreakCache::new()));
+
+            watch_file(&path, || build(&options, &files, show_progress))
+        },
+        _ => build(&options, &files, show_progress),
+    }
+}
+
+/// Output files named on the command line
+struct OutputFiles {
+    output: Option<PathBuf>,
+    appearances: Option<PathBuf>,
+    alternates: Option<PathBuf>,
+}
+
+/// Reads, formats and writes the screenplay once
+fn build(options: &RenderOptions, files: &OutputFiles, show_progress: bool)
+         -> Result<(), Box<dyn Error>>
+{
+    let root = batyr::read(options)?;
+
+    if let (Some(path), ElementType::Screenplay(elem)) = (&files.appearances, &root) {
+        batyr::write_atomic(path, Appearances::from(elem).to_csv())?;
+    }
+
+    if let (Some(path), ElementType::Screenplay(elem)) = (&files.alternates, &root) {
+        batyr::write_atomic(path, list_alternates(elem))?;
+    }
+
+    match root {
+        ElementType::Screenplay(_) if !options.elements => {
+            let mut observers: Vec<Box<dyn FormatterObserver>> = Vec::new();
+
+            let bar = if show_progress {
+                let bar = ProgressBar::new_spinner()
+                    .with_style(ProgressStyle::with_template(
+                        "{spinner} {pos} elements, {msg}"
+                    )?);
+
+                observers.push(Box::new(Progress { bar: bar.clone() }));
+                Some(bar)
+            } else {
+                None
+            };
+
+            write_output(&files.output, |mut out| {
+                batyr::write_with_observers(root, options, &mut out, observers)
+            })?;
+
+            if let Some(bar) = bar {
+                bar.finish_and_clear();
+            }
+        },
+        // Element dumps are for reading on screen, so they stay out
+        // of a piped standard output unless a file is named.
+        _ if files.output.is_none() => {
+            batyr::write(root, options, &mut io::stderr())?
+        },
+        _ => write_output(&files.output, |mut out| {
+            batyr::write(root, options, &mut out)
+        })?,
+    }
+
+    Ok(())
+}
+
+/// Writes to the output file, replacing it only once the output is
+/// complete, or else to standard output
+fn write_output<F>(path: &Option<PathBuf>, write: F) -> Result<(), Box<dyn Error>>
+    where F: FnOnce(&mut dyn Write) -> Result<(), Box<dyn Error>>
+{
+    match path {
+        Some(path) => {
+            let mut file = AtomicFile::create(path)?;
+            let mut out = BufWriter::new(&mut file);
+            write(&mut out)?;
+            out.flush()?;
+            drop(out);
+            file.commit()?;
+        },
+        None => {
+            let mut out = BufWriter::new(io::stdout().lock());
+            write(&mut out)?;
+            out.flush()?;
+        },
+    }
+
+    Ok(())
+}
+
+/// Builds the output, and builds it again whenever the input file
+/// changes, until interrupted
+///
+/// The file is polled rather than watched through the operating
+/// system, which works the same everywhere, including on network
+/// drives.  A save that leaves the contents as they were does not
+/// trigger a build, and a failed build is reported without ending
+/// the watch, so the last good output stays in place while the
+/// mistake is fixed.  The caller's options carry a [`BreakCache`],
+/// so only the elements that changed are broken into lines again.
+fn watch_file<F>(path: &Path, build: F) -> Result<(), Box<dyn Error>>
+    where F: Fn() -> Result<(), Box<dyn Error>>
+{
+    let mut modified: Option<SystemTime> = None;
+    let mut contents: Option<Vec<u8>> = None;
+
+    loop {
+        let m = fs::metadata(path).and_then(|m| m.modified()).ok();
+
+        if m.is_some() && m != modified {
+            modified = m;
+
+            // An editor may still be writing; a failed read is tried
+            // again on the next change.
+            let new_contents = fs::read(path).ok();
+
+            if new_contents.is_some() && new_contents != contents {
+                contents = new_contents;
+
+                match build() {
+                    Ok(()) => eprintln!("built {}", path.display()),
+                    Err(e) => eprintln!("error: {}", e),
+                }
+            }
+        }
+
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/// Progress bar driven by the formatter
+struct Progress {
+    bar: ProgressBar,
+}
+
+impl FormatterObserver for Progress {
+    fn on_page_start(&mut self, page: &Page) {
+        if page.number > 0 {
+            self.bar.set_message(format!("page {}", page.number));
+        }
+    }
+
+    fn on_element_formatted(&mut self, _elem: &ElementType, _page: &Page) {
+        self.bar.inc(1);
+    }
+}
+
+/// Reads a list of transitions, one per line, skipping blank lines
+/// and comments beginning with #
+fn read_transitions(path: &Path) -> io::Result<Vec<String>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+fn get_program_name() -> Option<String> {
+    env::current_exe().ok()
+        .as_ref()
+        .map(Path::new)
+        .and_then(Path::file_stem)
+        .and_then(OsStr::to_str)
+        .map(String::from)
+}
+
+#[cfg(windows)]
+fn get_prologue_file() -> Option<PathBuf> {
+    env::var_os("APPDATA")
+        .map(PathBuf::from)
+        .map(|dir| dir.join("batyr").join("prologue.ps"))
+}
+
+#[cfg(not(windows))]
+fn get_prologue_file() -> Option<PathBuf> {
+    Some(PathBuf::from("/home/gene/share/batyr/prologue.ps"))
+}
+
+/// Reads a PostScript prologue, warning about the procedures it lacks
+fn read_prologue(path: &Path) -> Result<String, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let missing = missing_procedures(&text);
+
+    if !missing.is_empty() {
+        let fallback = if Some(path) == PROLOGUE_FILE.as_deref() {
+            "; using the built-in prologue"
+        } else {
+            ""
+        };
+
+        eprintln!("warning: {} does not define {}{}",
+                  path.display(), missing.join(", "), fallback);
+    }
+
+    Ok(text)
+}
@@ -21,103 +21,415 @@
 //!   contents of each text element.
 //!
 //! * The [`formatter`] module breaks token lists into lines, and
-//!   flows the lines into pages.
+//!   flows the lines into pages, following the pagination rules in
+//!   the [`breaking`] module.
 //!
 //! * The [`writer`] module writes the pages to the standard output
 //!   using the Latin-9 character set.
 
 use std::mem;
+use std::sync::Arc;
 
+use crate::text;
 use crate::text::tokens::*;
+use crate::text::parser::SentenceSpacing;
 
 pub mod reader;
+pub mod breaking;
 pub mod formatter;
+pub mod annotate;
+pub mod golden;
+pub mod html;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod report;
+pub mod sample;
+pub mod validate;
 pub mod writer;
 
 // configuration
 
 /// Character width in points
+#[deprecated(note = "use Layout::default().char_width")]
 pub const CHAR_WIDTH: f32 = 7.2;
 
 /// Line height in points
+#[deprecated(note = "use Layout::default().line_height")]
 pub const LINE_HEIGHT: f32 = 12.0;
 
 /// Default indent in spaces
+#[deprecated(note = "use Layout::default().indent")]
 pub const INDENT: usize = 5;
 
 /// Center point in spaces
+#[deprecated(note = "use Layout::default().center")]
 pub const CENTER: usize = 43;
 
 /// Left margin in spaces
+#[deprecated(note = "use Layout::default().left_margin")]
 pub const LEFT_MARGIN: usize = 10;
 
 /// Right margin in spaces
+#[deprecated(note = "use Layout::default().right_margin")]
 pub const RIGHT_MARGIN: usize = 75;
 
 /// Transition left margin
+#[deprecated(note = "use Layout::default().trans_begin")]
 pub const TRANS_BEGIN: usize = 60;
 
 /// Transition right margin
+#[deprecated(note = "use Layout::default().trans_end")]
 pub const TRANS_END: usize = 75;
 
 /// Contact left margin
+#[deprecated(note = "use Layout::default().contact_begin")]
 pub const CONTACT_BEGIN: usize = 12;
 
 /// Contact right margin
+#[deprecated(note = "use Layout::default().contact_end")]
 pub const CONTACT_END: usize = 42;
 
 /// Cue tab stop
+#[deprecated(note = "use Layout::default().cue_begin")]
 pub const CUE_BEGIN: usize = 42;
 
 /// Dialogue left margin
+#[deprecated(note = "use Layout::default().d_begin")]
 pub const D_BEGIN: usize = 26;
 
 /// Dialogue right margin
+#[deprecated(note = "use Layout::default().d_end")]
 pub const D_END: usize = 59;
 
 /// Personal direction left margin
+#[deprecated(note = "use Layout::default().dir_begin")]
 pub const DIR_BEGIN: usize = 34;
 
 /// Personal direction right margin
+#[deprecated(note = "use Layout::default().dir_end")]
 pub const DIR_END: usize = 52;
 
+/// Cue tab stop in Riley's _The Hollywood Standard_
+pub const RILEY_CUE_BEGIN: usize = 37;
+
+/// Dialogue left margin in Riley's _The Hollywood Standard_
+pub const RILEY_D_BEGIN: usize = 25;
+
+/// Personal direction left margin in Riley's _The Hollywood Standard_
+pub const RILEY_DIR_BEGIN: usize = 31;
+
+/// Dialogue left margin in Cole and Haag's _Complete Guide to
+/// Standard Script Formats_
+pub const COLE_HAAG_D_BEGIN: usize = 29;
+
+/// Personal direction left margin in Cole and Haag's _Complete Guide
+/// to Standard Script Formats_
+pub const COLE_HAAG_DIR_BEGIN: usize = 35;
+
 /// Stage direction left margin
+#[deprecated(note = "use Layout::default().p_begin")]
 pub const P_BEGIN: usize = 16;
 
 /// Stage direction right margin
+#[deprecated(note = "use Layout::default().p_end")]
 pub const P_END: usize = 72;
 
 /// Page number tab stop
+#[deprecated(note = "use Layout::default().page_no_begin")]
 pub const PAGE_NO_BEGIN: usize = 72;
 
 /// Left scene number tab stop
-pub const LH_SCENE_NO_BEGIN: usize = 12;
+#[deprecated(note = "use Layout::default().lh_scene_no_begin")]
+pub const LH_SCENE_NO_BEGIN: usize = 10;
 
 /// Right scene number tab stop
-pub const RH_SCENE_NO_BEGIN: usize = 73;
+#[deprecated(note = "use Layout::default().rh_scene_no_begin")]
+pub const RH_SCENE_NO_BEGIN: usize = 74;
 
 /// Line number of the page header
+#[deprecated(note = "use Layout::default().header_line")]
 pub const HEADER_LINE: usize = 62;
 
 /// Line number of the top line of the page
+#[deprecated(note = "use Layout::default().top_line")]
 pub const TOP_LINE: usize = 60;
 
 /// Line number of the middle of the page
+#[deprecated(note = "Layout has no middle line")]
 pub const MIDDLE_LINE: usize = 27;
 
 /// Line number of the bottom line of the page
+#[deprecated(note = "use Layout::default().bottom_line")]
 pub const BOTTOM_LINE: usize = 6;
 
+/// Line number of a page number at the bottom of the page
+#[deprecated(note = "use Layout::default().footer_line")]
+pub const FOOTER_LINE: usize = 4;
+
 /// The number of lines to skip before the title on the fly page
+#[deprecated(note = "use Layout::default().title_skip")]
 pub const TITLE_SKIP: usize = 19;
 
+/// Characters of text shown for each element by [`ElementType::dump`]
+const DUMP_TEXT: usize = 40;
+
+/// Page geometry
+///
+/// Columns are counted in spaces from the left edge of the page, and
+/// lines from the bottom edge.  The default layout is the standard
+/// screenplay page; the constants above are deprecated in its favor.
+/// Library users can start from it and move margins and tab stops to
+/// suit a different format; the reader, the formatter and both
+/// writers take the layout as an option.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::Layout;
+/// # use batyr::document::formatter::{Formatter, FormatterOptions, Row};
+/// # use batyr::document::reader::Reader;
+/// let layout = Layout {
+///     cue_begin: 40,
+///     d_begin: 20,
+///     d_end: 64,
+///     ..Layout::default()
+/// };
+///
+/// let xml = "<screenplay><head/><body>\
+///            <cue>EMMA</cue><d>Late again.</d>\
+///            </body></screenplay>";
+///
+/// let root = Reader::new(xml).layout(layout).run().unwrap();
+///
+/// let mut formatter = Formatter::with_options(FormatterOptions {
+///     layout: layout,
+///     ..FormatterOptions::default()
+/// });
+/// formatter.run(root);
+///
+/// let columns: Vec<(usize, String)> = formatter.body[1].lines.iter()
+///     .filter_map(|row| match row {
+///         Row::Text(line) => Some((line.column, line.text())),
+///         _ => None,
+///     })
+///     .collect();
+///
+/// assert_eq!(columns, vec![(40, "EMMA".to_string()),
+///                          (20, "Late again.".to_string())]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Layout {
+    /// Character width in points
+    pub char_width: f32,
+    /// Line height in points
+    pub line_height: f32,
+    /// Indent in spaces
+    pub indent: usize,
+    /// Center point
+    pub center: usize,
+    /// Left margin
+    pub left_margin: usize,
+    /// Right margin
+    pub right_margin: usize,
+    /// Transition left margin
+    pub trans_begin: usize,
+    /// Transition right margin
+    pub trans_end: usize,
+    /// Contact left margin
+    pub contact_begin: usize,
+    /// Contact right margin
+    pub contact_end: usize,
+    /// Cue tab stop
+    pub cue_begin: usize,
+    /// Dialogue left margin
+    pub d_begin: usize,
+    /// Dialogue right margin
+    pub d_end: usize,
+    /// Personal direction left margin
+    pub dir_begin: usize,
+    /// Personal direction right margin
+    pub dir_end: usize,
+    /// Stage direction left margin
+    pub p_begin: usize,
+    /// Stage direction right margin
+    pub p_end: usize,
+    /// Page number tab stop
+    pub page_no_begin: usize,
+    /// Left scene number tab stop.  A number too long to fit between
+    /// it and the heading starts further left.
+    pub lh_scene_no_begin: usize,
+    /// Right scene number tab stop.  A number after a heading that
+    /// runs past it follows the heading after a space.
+    pub rh_scene_no_begin: usize,
+    /// Line number of the page header
+    pub header_line: usize,
+    /// Line number of the top line of the page
+    pub top_line: usize,
+    /// Line number of the bottom line of the page
+    pub bottom_line: usize,
+    /// Line number of a page number at the bottom of the page
+    pub footer_line: usize,
+    /// The number of lines to skip before the title on the fly page
+    pub title_skip: usize,
+}
+
+// The constants stay only for code written before Layout.
+#[allow(deprecated)]
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            char_width: CHAR_WIDTH,
+            line_height: LINE_HEIGHT,
+            indent: INDENT,
+            center: CENTER,
+            left_margin: LEFT_MARGIN,
+            right_margin: RIGHT_MARGIN,
+            trans_begin: TRANS_BEGIN,
+            trans_end: TRANS_END,
+            contact_begin: CONTACT_BEGIN,
+            contact_end: CONTACT_END,
+            cue_begin: CUE_BEGIN,
+            d_begin: D_BEGIN,
+            d_end: D_END,
+            dir_begin: DIR_BEGIN,
+            dir_end: DIR_END,
+            p_begin: P_BEGIN,
+            p_end: P_END,
+            page_no_begin: PAGE_NO_BEGIN,
+            lh_scene_no_begin: LH_SCENE_NO_BEGIN,
+            rh_scene_no_begin: RH_SCENE_NO_BEGIN,
+            header_line: HEADER_LINE,
+            top_line: TOP_LINE,
+            bottom_line: BOTTOM_LINE,
+            footer_line: FOOTER_LINE,
+            title_skip: TITLE_SKIP,
+        }
+    }
+}
+
+impl Layout {
+    /// Returns the number of lines from the top line to the bottom
+    /// line
+    pub fn lines_per_page(&self) -> usize {
+        self.top_line - self.bottom_line + 1
+    }
+
+    /// Returns the layout with the bottom line moved so that a page
+    /// of the body holds the given number of lines
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::Layout;
+    /// let layout = Layout::default().with_lines_per_page(50);
+    /// assert_eq!(layout.lines_per_page(), 50);
+    /// assert_eq!(layout.top_line, Layout::default().top_line);
+    /// ```
+    pub fn with_lines_per_page(mut self, n: usize) -> Self {
+        self.bottom_line = self.top_line + 1 - n;
+        self
+    }
+
+    /// Returns the left margin of centered text, two indents in from
+    /// the left margin
+    pub fn centered_left_margin(&self) -> usize {
+        self.left_margin + 2 * self.indent
+    }
+
+    /// Returns the right margin of centered text, two indents in from
+    /// the right margin
+    pub fn centered_right_margin(&self) -> usize {
+        self.right_margin - 2 * self.indent
+    }
+
+    /// Returns the column where text of the given length starts when
+    /// centered
+    pub fn centered(&self, len: usize) -> usize {
+        self.center - len / 2 - len % 2
+    }
+
+    /// Returns the layout with the tab stops of a reference book
+    ///
+    /// The built-in layout keeps the tab stops it has, so that a
+    /// custom layout is left alone.
+    pub fn with_authority(mut self, authority: Authority) -> Self {
+        if authority != Authority::None {
+            self.cue_begin = authority.cue_begin();
+            self.d_begin = authority.d_begin();
+            self.dir_begin = authority.dir_begin();
+        }
+
+        self
+    }
+}
+
+/// Marker at the bottom of a page that breaks a speech
+pub const MORE_TEXT: &str = "(MORE)";
+
+/// Marker after the cue of a speech continued from the previous page,
+/// or after the heading of a resumed scene
+pub const CONTD_TEXT: &str = "(CONT'D)";
+
+/// Word marking a scene continued across a page break, as in
+/// "CONTINUED:" at the top of the page and "(CONTINUED)" at the
+/// bottom
+pub const CONTINUED_TEXT: &str = "CONTINUED";
+
+/// Transitions accepted by the lint without further configuration
+pub const TRANSITIONS: [&str; 15] = [
+    "BACK TO:",
+    "CUT TO:",
+    "CUT TO BLACK.",
+    "DISSOLVE TO:",
+    "FADE OUT.",
+    "FADE TO BLACK.",
+    "FLASHBACK TO:",
+    "FORWARD TO:",
+    "INTERCUT WITH:",
+    "JUMP CUT TO:",
+    "MATCH CUT TO:",
+    "MATCH DISSOLVE TO:",
+    "SMASH CUT TO:",
+    "TIME CUT:",
+    "WIPE TO:",
+];
+
 // element type enum
 
 /// Element type enum for in-memory representation of XML elements
-#[derive(Debug)]
+///
+/// Cloning copies the whole tree, tokens included, so that one read
+/// can feed several consumers that each need to own it, such as the
+/// [`Formatter`](formatter::Formatter).  Consumers that only borrow the
+/// tree can share a single copy instead, behind an
+/// [`Arc`](std::sync::Arc) if they run on other threads.
+///
+/// # Examples
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use std::thread;
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head/><body><p>Snow.</p></body></screenplay>";
+/// let root = Arc::new(Reader::new(xml).run().unwrap());
+///
+/// let shared = Arc::clone(&root);
+/// let count = thread::spawn(move || shared.iter().count());
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(root.as_ref().clone());
+///
+/// assert_eq!(count.join().unwrap(), 3);
+/// assert_eq!(formatter.body.len(), 2);
+/// ```
+#[derive(Debug, Clone)]
 pub enum ElementType {
     Act       (TextElement     <Act       >),
     Authors   (ContainerElement<Authors   >),
+    B         (TextElement     <B         >),
+    BackMatter(ContainerElement<BackMatter>),
+    Blank     (EmptyElement    <Blank     >),
     Body      (ContainerElement<Body      >),
     Br        (EmptyElement    <Br        >),
     Trans     (TextElement     <Trans     >),
@@ -127,16 +439,22 @@ pub enum ElementType {
     Dir       (TextElement     <Dir       >),
     Em        (TextElement     <Em        >),
     End       (TextElement     <End       >),
+    Episode   (TextElement     <Episode   >),
+    FrontMatter(ContainerElement<FrontMatter>),
     FullName  (TextElement     <FullName  >),
     Head      (ContainerElement<Head      >),
     Note      (TextElement     <Note      >),
     Open      (TextElement     <Open      >),
     P         (TextElement     <P         >),
     PageBreak (EmptyElement    <PageBreak >),
+    ProductionNumber(TextElement<ProductionNumber>),
     Screenplay(ContainerElement<Screenplay>),
     Series    (TextElement     <Series    >),
+    Shot      (TextElement     <Shot      >),
     Slug      (TextElement     <Slug      >),
+    Source    (TextElement     <Source    >),
     Title     (TextElement     <Title     >),
+    U         (TextElement     <U         >),
 }
 
 /// Data type for a sequence of elements
@@ -147,19 +465,37 @@ impl ElementType {
     pub fn has_children(&self) -> bool {
         match self {
             ElementType::Authors   (_) |
+            ElementType::BackMatter(_) |
             ElementType::Body      (_) |
+            ElementType::FrontMatter(_) |
             ElementType::Head      (_) |
             ElementType::Screenplay(_) => true,
             _ => false,
         }
     }
 
+    /// If the element is a container, returns a reference to the
+    /// children
+    pub fn children(&self) -> Option<&ElementList> {
+        match self {
+            ElementType::Authors(elem) => Some(&elem.children),
+            ElementType::BackMatter(elem) => Some(&elem.children),
+            ElementType::Body(elem) => Some(&elem.children),
+            ElementType::FrontMatter(elem) => Some(&elem.children),
+            ElementType::Head(elem) => Some(&elem.children),
+            ElementType::Screenplay(elem) =>  Some(&elem.children),
+            _ => None,
+        }
+    }
+
     /// If the element is a container, returns a mutable reference to
     /// the children
     pub fn children_mut(&mut self) -> Option<&mut ElementList> {
         match self {
             ElementType::Authors(elem) => Some(&mut elem.children),
+            ElementType::BackMatter(elem) => Some(&mut elem.children),
             ElementType::Body(elem) => Some(&mut elem.children),
+            ElementType::FrontMatter(elem) => Some(&mut elem.children),
             ElementType::Head(elem) => Some(&mut elem.children),
             ElementType::Screenplay(elem) =>  Some(&mut elem.children),
             _ => None,
@@ -172,9 +508,15 @@ impl ElementType {
             ElementType::Authors(elem) => {
                 Some(elem.children.drain(..).collect())
             },
+            ElementType::BackMatter(elem) => {
+                Some(elem.children.drain(..).collect())
+            },
             ElementType::Body(elem) => {
                 Some(elem.children.drain(..).collect())
             },
+            ElementType::FrontMatter(elem) => {
+                Some(elem.children.drain(..).collect())
+            },
             ElementType::Head(elem) => {
                 Some(elem.children.drain(..).collect())
             },
@@ -185,6 +527,177 @@ impl ElementType {
         }
     }
 
+    /// Returns the name of the XML element
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::reader::Reader;
+    /// let b = Reader::new("<b>Stop.</b>").run().unwrap();
+    /// let u = Reader::new("<u>Now.</u>").run().unwrap();
+    ///
+    /// assert_eq!((b.tag(), u.tag()), ("b", "u"));
+    /// assert!(b.dump().starts_with("b, line 1"));
+    /// assert!(u.dump().starts_with("u, line 1"));
+    /// ```
+    pub fn tag(&self) -> &'static str {
+        match self {
+            ElementType::Act       (_) => "act",
+            ElementType::Authors   (_) => "authors",
+            ElementType::B         (_) => "b",
+            ElementType::BackMatter(_) => "backMatter",
+            ElementType::Blank     (_) => "blank",
+            ElementType::Body      (_) => "body",
+            ElementType::Br        (_) => "br",
+            ElementType::Trans     (_) => "trans",
+            ElementType::Contact   (_) => "contact",
+            ElementType::Cue       (_) => "cue",
+            ElementType::D         (_) => "d",
+            ElementType::Dir       (_) => "dir",
+            ElementType::Em        (_) => "em",
+            ElementType::End       (_) => "end",
+            ElementType::Episode   (_) => "episode",
+            ElementType::FrontMatter(_) => "frontMatter",
+            ElementType::FullName  (_) => "fullName",
+            ElementType::Head      (_) => "head",
+            ElementType::Note      (_) => "note",
+            ElementType::Open      (_) => "open",
+            ElementType::P         (_) => "p",
+            ElementType::PageBreak (_) => "pageBreak",
+            ElementType::ProductionNumber(_) => "productionNumber",
+            ElementType::Screenplay(_) => "screenplay",
+            ElementType::Series    (_) => "series",
+            ElementType::Shot      (_) => "shot",
+            ElementType::Slug      (_) => "slug",
+            ElementType::Source    (_) => "source",
+            ElementType::Title     (_) => "title",
+            ElementType::U         (_) => "u",
+        }
+    }
+
+    /// Return an iterator over the element and its descendants, in
+    /// document order, leaving the tree in place
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\
+    ///            <p>Snow.</p><cue>EMMA</cue><d>Cold.</d><p>Wind.</p>\
+    ///            </body></screenplay>";
+    /// let root = Reader::new(xml).run().unwrap();
+    ///
+    /// let n = root.iter().filter(|elem| matches!(elem, ElementType::P(_))).count();
+    /// assert_eq!(n, 2);
+    ///
+    /// // The tree can still be formatted afterward.
+    /// assert_eq!(root.into_iter().count(), 6);
+    /// ```
+    pub fn iter(&self) -> ElementIterator<'_> {
+        ElementIterator {
+            inner: self.iter_with_depth(),
+        }
+    }
+
+    /// Return an iterator of mutable references to the elements
+    /// under this one that are not containers, in document order
+    ///
+    /// Containers are descended into rather than returned, since
+    /// their children are borrowed in turn.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\
+    ///            <p>Snow.</p><p indent=\"2\">Wind.</p>\
+    ///            </body></screenplay>";
+    /// let mut root = Reader::new(xml).run().unwrap();
+    ///
+    /// for elem in root.iter_mut() {
+    ///     if let ElementType::P(p) = elem {
+    ///         p.attributes.padding_after = 0;
+    ///     }
+    /// }
+    ///
+    /// assert!(root.iter().all(|elem| elem.get_padding_after().unwrap_or(0) == 0));
+    /// ```
+    pub fn iter_mut(&mut self) -> ElementIterMut<'_> {
+        ElementIterMut {
+            stack: vec![std::slice::from_mut(self).iter_mut()],
+        }
+    }
+
+    /// Return an iterator over the element and its descendants, in
+    /// document order, with the depth of each below this element
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head><title>FOO</title></head>\
+    ///            <body><p>Snow.</p></body></screenplay>";
+    /// let root = Reader::new(xml).run().unwrap();
+    ///
+    /// let tags: Vec<(usize, &str)> = root.iter_with_depth()
+    ///     .map(|(depth, elem)| (depth, elem.tag()))
+    ///     .collect();
+    ///
+    /// assert_eq!(tags, vec![(0, "screenplay"), (1, "head"), (2, "title"),
+    ///                       (1, "body"), (2, "p")]);
+    /// ```
+    pub fn iter_with_depth(&self) -> ElementDepthIterator<'_> {
+        ElementDepthIterator {
+            stack: vec![(0, self)],
+        }
+    }
+
+    /// Call the visitor on the element and each of its descendants,
+    /// in document order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::{ElementType, ElementVisitor};
+    /// # use batyr::document::reader::Reader;
+    /// struct Tags(Vec<String>);
+    ///
+    /// impl ElementVisitor for Tags {
+    ///     fn visit(&mut self, elem: &ElementType, depth: usize,
+    ///              parent: Option<&'static str>) {
+    ///         if parent == Some("body") {
+    ///             self.0.push(format!("{}{}", "  ".repeat(depth), elem.tag()));
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let xml = "<screenplay><head/><body>\
+    ///            <slug>INT. KITCHEN - DAY</slug><p>Snow.</p>\
+    ///            </body></screenplay>";
+    /// let root = Reader::new(xml).run().unwrap();
+    ///
+    /// let mut tags = Tags(Vec::new());
+    /// root.accept(&mut tags);
+    ///
+    /// assert_eq!(tags.0, vec!["    slug", "    p"]);
+    /// ```
+    pub fn accept<V: ElementVisitor + ?Sized>(&self, visitor: &mut V) {
+        self.walk(visitor, 0, None);
+    }
+
+    fn walk<V: ElementVisitor + ?Sized>(&self, visitor: &mut V, depth: usize,
+                                        parent: Option<&'static str>) {
+        visitor.visit(self, depth, parent);
+
+        if let Some(children) = self.children() {
+            for child in children.iter() {
+                child.walk(visitor, depth + 1, Some(self.tag()));
+            }
+        }
+    }
+
     /// Return an into iterator for the element
     pub fn into_iter(self) -> ElementIntoIterator {
         ElementIntoIterator {
@@ -194,10 +707,19 @@ impl ElementType {
     }
 
     /// If the element has a padding_before attribute, return its value
+    ///
+    /// The formatter separates two elements with the larger of the
+    /// padding after the first and the padding before the second, in
+    /// blank lines.  Padding is dropped at the top of a page.  An
+    /// element that must start a new page says so with
+    /// [`get_page_break_before`](Self::get_page_break_before) instead.
     pub fn get_padding_before(&self) -> Option<i32> {
         match self {
             ElementType::Act       (elem) => Some(elem.attributes.padding_before),
             ElementType::Authors   (_) => None,
+            ElementType::B         (_) => None,
+            ElementType::BackMatter(_) => None,
+            ElementType::Blank     (_) => None,
             ElementType::Body      (_) => None,
             ElementType::Br        (_) => None,
             ElementType::Trans     (elem) => Some(elem.attributes.padding_before),
@@ -207,16 +729,58 @@ impl ElementType {
             ElementType::Dir       (elem) => Some(elem.attributes.padding_before),
             ElementType::Em        (_) => None,
             ElementType::End       (elem) => Some(elem.attributes.padding_before),
+            ElementType::Episode   (_) => None,
+            ElementType::FrontMatter(_) => None,
             ElementType::FullName  (_) => None,
             ElementType::Head      (_) => None,
             ElementType::Note      (_) => None,
             ElementType::Open      (elem) => Some(elem.attributes.padding_before),
             ElementType::P         (elem) => Some(elem.attributes.padding_before),
             ElementType::PageBreak (_) => None,
+            ElementType::ProductionNumber(_) => None,
             ElementType::Screenplay(_) => None,
             ElementType::Series    (elem) => Some(elem.attributes.padding_before),
+            ElementType::Shot      (elem) => Some(elem.attributes.padding_before),
             ElementType::Slug      (elem) => Some(elem.attributes.padding_before),
+            ElementType::Source    (_) => None,
             ElementType::Title     (elem) => Some(elem.attributes.padding_before),
+            ElementType::U         (_) => None,
+        }
+    }
+
+    /// Returns true if the element starts a new page
+    pub fn get_page_break_before(&self) -> bool {
+        match self {
+            ElementType::Act       (elem) => elem.attributes.page_break_before,
+            ElementType::Authors   (_) => false,
+            ElementType::B         (_) => false,
+            ElementType::BackMatter(_) => false,
+            ElementType::Blank     (_) => false,
+            ElementType::Body      (_) => false,
+            ElementType::Br        (_) => false,
+            ElementType::Trans     (elem) => elem.attributes.page_break_before,
+            ElementType::Contact   (_) => false,
+            ElementType::Cue       (elem) => elem.attributes.page_break_before,
+            ElementType::D         (_) => false,
+            ElementType::Dir       (_) => false,
+            ElementType::Em        (_) => false,
+            ElementType::End       (elem) => elem.attributes.page_break_before,
+            ElementType::Episode   (_) => false,
+            ElementType::FrontMatter(_) => false,
+            ElementType::FullName  (_) => false,
+            ElementType::Head      (_) => false,
+            ElementType::Note      (_) => false,
+            ElementType::Open      (elem) => elem.attributes.page_break_before,
+            ElementType::P         (elem) => elem.attributes.page_break_before,
+            ElementType::PageBreak (_) => false,
+            ElementType::ProductionNumber(_) => false,
+            ElementType::Screenplay(_) => false,
+            ElementType::Series    (_) => false,
+            ElementType::Shot      (elem) => elem.attributes.page_break_before,
+            ElementType::Slug      (elem) => elem.attributes.page_break_before,
+            ElementType::Source    (_) => false,
+            ElementType::Title     (_) => false,
+            ElementType::U         (_) => false,
         }
     }
 
@@ -225,6 +789,9 @@ impl ElementType {
         match self {
             ElementType::Act       (elem) => Some(elem.attributes.padding_after),
             ElementType::Authors   (_) => None,
+            ElementType::B         (_) => None,
+            ElementType::BackMatter(_) => None,
+            ElementType::Blank     (_) => None,
             ElementType::Body      (_) => None,
             ElementType::Br        (_) => None,
             ElementType::Trans     (elem) => Some(elem.attributes.padding_after),
@@ -234,20 +801,497 @@ impl ElementType {
             ElementType::Dir       (elem) => Some(elem.attributes.padding_after),
             ElementType::Em        (_) => None,
             ElementType::End       (elem) => Some(elem.attributes.padding_after),
+            ElementType::Episode   (_) => None,
+            ElementType::FrontMatter(_) => None,
             ElementType::FullName  (_) => None,
             ElementType::Head      (_) => None,
             ElementType::Note      (_) => None,
             ElementType::Open      (elem) => Some(elem.attributes.padding_after),
             ElementType::P         (elem) => Some(elem.attributes.padding_after),
             ElementType::PageBreak (_) => None,
+            ElementType::ProductionNumber(_) => None,
             ElementType::Screenplay(_) => None,
             ElementType::Series    (elem) => Some(elem.attributes.padding_after),
+            ElementType::Shot      (elem) => Some(elem.attributes.padding_after),
             ElementType::Slug      (elem) => Some(elem.attributes.padding_after),
+            ElementType::Source    (_) => None,
             ElementType::Title     (elem) => Some(elem.attributes.padding_after),
+            ElementType::U         (_) => None,
+        }
+    }
+
+    /// If the element is a text element, returns its tokens
+    pub fn tokens(&self) -> Option<&TokenList> {
+        match self {
+            ElementType::Act       (elem) => Some(&elem.tokens),
+            ElementType::Authors   (_) => None,
+            ElementType::B         (elem) => Some(&elem.tokens),
+            ElementType::BackMatter(_) => None,
+            ElementType::Blank     (_) => None,
+            ElementType::Body      (_) => None,
+            ElementType::Br        (_) => None,
+            ElementType::Trans     (elem) => Some(&elem.tokens),
+            ElementType::Contact   (elem) => Some(&elem.tokens),
+            ElementType::Cue       (elem) => Some(&elem.tokens),
+            ElementType::D         (elem) => Some(&elem.tokens),
+            ElementType::Dir       (elem) => Some(&elem.tokens),
+            ElementType::Em        (elem) => Some(&elem.tokens),
+            ElementType::End       (elem) => Some(&elem.tokens),
+            ElementType::Episode   (elem) => Some(&elem.tokens),
+            ElementType::FrontMatter(_) => None,
+            ElementType::FullName  (elem) => Some(&elem.tokens),
+            ElementType::Head      (_) => None,
+            ElementType::Note      (elem) => Some(&elem.tokens),
+            ElementType::Open      (elem) => Some(&elem.tokens),
+            ElementType::P         (elem) => Some(&elem.tokens),
+            ElementType::PageBreak (_) => None,
+            ElementType::ProductionNumber(elem) => Some(&elem.tokens),
+            ElementType::Screenplay(_) => None,
+            ElementType::Series    (elem) => Some(&elem.tokens),
+            ElementType::Shot      (elem) => Some(&elem.tokens),
+            ElementType::Slug      (elem) => Some(&elem.tokens),
+            ElementType::Source    (elem) => Some(&elem.tokens),
+            ElementType::Title     (elem) => Some(&elem.tokens),
+            ElementType::U         (elem) => Some(&elem.tokens),
+        }
+    }
+
+    /// Returns the line of the source where the element begins, or 0
+    /// for an element the reader made up, such as a cast list
+    pub fn line(&self) -> usize {
+        match self {
+            ElementType::Act       (elem) => elem.line,
+            ElementType::Authors   (elem) => elem.line,
+            ElementType::B         (elem) => elem.line,
+            ElementType::BackMatter(elem) => elem.line,
+            ElementType::Blank     (elem) => elem.line,
+            ElementType::Body      (elem) => elem.line,
+            ElementType::Br        (elem) => elem.line,
+            ElementType::Trans     (elem) => elem.line,
+            ElementType::Contact   (elem) => elem.line,
+            ElementType::Cue       (elem) => elem.line,
+            ElementType::D         (elem) => elem.line,
+            ElementType::Dir       (elem) => elem.line,
+            ElementType::Em        (elem) => elem.line,
+            ElementType::End       (elem) => elem.line,
+            ElementType::Episode   (elem) => elem.line,
+            ElementType::FrontMatter(elem) => elem.line,
+            ElementType::FullName  (elem) => elem.line,
+            ElementType::Head      (elem) => elem.line,
+            ElementType::Note      (elem) => elem.line,
+            ElementType::Open      (elem) => elem.line,
+            ElementType::P         (elem) => elem.line,
+            ElementType::PageBreak (elem) => elem.line,
+            ElementType::ProductionNumber(elem) => elem.line,
+            ElementType::Screenplay(elem) => elem.line,
+            ElementType::Series    (elem) => elem.line,
+            ElementType::Shot      (elem) => elem.line,
+            ElementType::Slug      (elem) => elem.line,
+            ElementType::Source    (elem) => elem.line,
+            ElementType::Title     (elem) => elem.line,
+            ElementType::U         (elem) => elem.line,
+        }
+    }
+
+    /// Sets the line of the source where the element begins
+    pub fn set_line(&mut self, line: usize) {
+        match self {
+            ElementType::Act       (elem) => elem.line = line,
+            ElementType::Authors   (elem) => elem.line = line,
+            ElementType::B         (elem) => elem.line = line,
+            ElementType::BackMatter(elem) => elem.line = line,
+            ElementType::Blank     (elem) => elem.line = line,
+            ElementType::Body      (elem) => elem.line = line,
+            ElementType::Br        (elem) => elem.line = line,
+            ElementType::Trans     (elem) => elem.line = line,
+            ElementType::Contact   (elem) => elem.line = line,
+            ElementType::Cue       (elem) => elem.line = line,
+            ElementType::D         (elem) => elem.line = line,
+            ElementType::Dir       (elem) => elem.line = line,
+            ElementType::Em        (elem) => elem.line = line,
+            ElementType::End       (elem) => elem.line = line,
+            ElementType::Episode   (elem) => elem.line = line,
+            ElementType::FrontMatter(elem) => elem.line = line,
+            ElementType::FullName  (elem) => elem.line = line,
+            ElementType::Head      (elem) => elem.line = line,
+            ElementType::Note      (elem) => elem.line = line,
+            ElementType::Open      (elem) => elem.line = line,
+            ElementType::P         (elem) => elem.line = line,
+            ElementType::PageBreak (elem) => elem.line = line,
+            ElementType::ProductionNumber(elem) => elem.line = line,
+            ElementType::Screenplay(elem) => elem.line = line,
+            ElementType::Series    (elem) => elem.line = line,
+            ElementType::Shot      (elem) => elem.line = line,
+            ElementType::Slug      (elem) => elem.line = line,
+            ElementType::Source    (elem) => elem.line = line,
+            ElementType::Title     (elem) => elem.line = line,
+            ElementType::U         (elem) => elem.line = line,
+        }
+    }
+
+    /// If the element is not a container, returns its break
+    /// information
+    pub fn break_info(&self) -> Option<&BreakType> {
+        match self {
+            ElementType::Act       (elem) => Some(&elem.break_info),
+            ElementType::Authors   (_) => None,
+            ElementType::B         (elem) => Some(&elem.break_info),
+            ElementType::BackMatter(_) => None,
+            ElementType::Blank     (elem) => Some(&elem.break_info),
+            ElementType::Body      (_) => None,
+            ElementType::Br        (elem) => Some(&elem.break_info),
+            ElementType::Trans     (elem) => Some(&elem.break_info),
+            ElementType::Contact   (elem) => Some(&elem.break_info),
+            ElementType::Cue       (elem) => Some(&elem.break_info),
+            ElementType::D         (elem) => Some(&elem.break_info),
+            ElementType::Dir       (elem) => Some(&elem.break_info),
+            ElementType::Em        (elem) => Some(&elem.break_info),
+            ElementType::End       (elem) => Some(&elem.break_info),
+            ElementType::Episode   (elem) => Some(&elem.break_info),
+            ElementType::FrontMatter(_) => None,
+            ElementType::FullName  (elem) => Some(&elem.break_info),
+            ElementType::Head      (_) => None,
+            ElementType::Note      (elem) => Some(&elem.break_info),
+            ElementType::Open      (elem) => Some(&elem.break_info),
+            ElementType::P         (elem) => Some(&elem.break_info),
+            ElementType::PageBreak (elem) => Some(&elem.break_info),
+            ElementType::ProductionNumber(elem) => Some(&elem.break_info),
+            ElementType::Screenplay(_) => None,
+            ElementType::Series    (elem) => Some(&elem.break_info),
+            ElementType::Shot      (elem) => Some(&elem.break_info),
+            ElementType::Slug      (elem) => Some(&elem.break_info),
+            ElementType::Source    (elem) => Some(&elem.break_info),
+            ElementType::Title     (elem) => Some(&elem.break_info),
+            ElementType::U         (elem) => Some(&elem.break_info),
+        }
+    }
+
+    /// If the element is a text element counted by the reader,
+    /// returns the column width and the number of lines
+    pub fn line_count(&self) -> Option<(usize, usize)> {
+        match self {
+            ElementType::Act       (elem) => elem.line_count,
+            ElementType::Authors   (_) => None,
+            ElementType::B         (elem) => elem.line_count,
+            ElementType::BackMatter(_) => None,
+            ElementType::Blank     (_) => None,
+            ElementType::Body      (_) => None,
+            ElementType::Br        (_) => None,
+            ElementType::Trans     (elem) => elem.line_count,
+            ElementType::Contact   (elem) => elem.line_count,
+            ElementType::Cue       (elem) => elem.line_count,
+            ElementType::D         (elem) => elem.line_count,
+            ElementType::Dir       (elem) => elem.line_count,
+            ElementType::Em        (elem) => elem.line_count,
+            ElementType::End       (elem) => elem.line_count,
+            ElementType::Episode   (elem) => elem.line_count,
+            ElementType::FrontMatter(_) => None,
+            ElementType::FullName  (elem) => elem.line_count,
+            ElementType::Head      (_) => None,
+            ElementType::Note      (elem) => elem.line_count,
+            ElementType::Open      (elem) => elem.line_count,
+            ElementType::P         (elem) => elem.line_count,
+            ElementType::PageBreak (_) => None,
+            ElementType::ProductionNumber(elem) => elem.line_count,
+            ElementType::Screenplay(_) => None,
+            ElementType::Series    (elem) => elem.line_count,
+            ElementType::Shot      (elem) => elem.line_count,
+            ElementType::Slug      (elem) => elem.line_count,
+            ElementType::Source    (elem) => elem.line_count,
+            ElementType::Title     (elem) => elem.line_count,
+            ElementType::U         (elem) => elem.line_count,
+        }
+    }
+
+    /// Writes the element and its descendants as an indented tree,
+    /// one element to a line, with the source line, the break
+    /// information and the line count of each
+    ///
+    /// This is what the <tt>-e</tt> option prints, for tracing a bad
+    /// page break back to the source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\n\
+    ///            <slug>INT. KITCHEN - DAY</slug>\n\
+    ///            <p>Snow falls on the window.</p>\n\
+    ///            <cue>EMMA</cue>\n\
+    ///            <d>Cold.</d>\n\
+    ///            </body></screenplay>";
+    /// let root = Reader::new(xml).run().unwrap();
+    /// let dump = root.dump();
+    /// let lines: Vec<&str> = dump.lines().collect();
+    ///
+    /// assert_eq!(lines[0], "screenplay, line 1");
+    /// assert_eq!(lines[3], "    p, line 3: atomic, 1 line; \"Snow falls on the window.\"");
+    /// assert_eq!(lines[4], "    cue, line 4: forbidden, 1 line; \
+    ///                       1 line at width 34; \"EMMA\"");
+    /// ```
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+
+        for (depth, elem) in self.iter_with_depth() {
+            out.push_str(&"  ".repeat(depth));
+            out.push_str(elem.tag());
+
+            if elem.line() > 0 {
+                out.push_str(&format!(", line {}", elem.line()));
+            }
+
+            let mut details = Vec::new();
+
+            if let Some(info) = elem.break_info() {
+                details.push(info.to_string());
+            }
+
+            if let Some((width, n)) = elem.line_count() {
+                details.push(format!("{} line{} at width {}", n,
+                                     if n == 1 { "" } else { "s" }, width));
+            }
+
+            if let Some(tokens) = elem.tokens() {
+                let text: String = tokens.iter().map(|t| t.text()).collect();
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+                details.push(match text.chars().count() > DUMP_TEXT {
+                    true => format!("{:?}...",
+                                    text.chars().take(DUMP_TEXT).collect::<String>()),
+                    false => format!("{:?}", text),
+                });
+            }
+
+            if !details.is_empty() {
+                out.push_str(": ");
+                out.push_str(&details.join("; "));
+            }
+
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// Writes the element hierarchy in a canonical text form, for
+    /// hashing
+    ///
+    /// Each element takes one line: its depth, its tag and the
+    /// attributes that change its text or where it goes, as
+    /// <tt>name=value</tt>.  Each token of a text element follows on
+    /// a line of its own: the depth, its kind after a <tt>#</tt>, its
+    /// display flags as a number, and its text.  Text is written as its length in bytes,
+    /// a colon and the text itself, so it needs no escaping.  Line
+    /// numbers, break points and anything else worked out from the
+    /// layout are left out.
+    ///
+    /// The form is stable: it changes only with the schema, and not
+    /// with the internal representation, so that
+    /// [`content_hash`](crate::content_hash) gives the same hash for
+    /// the same draft from one version of Batyr to the next.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::reader::Reader;
+    /// let root = Reader::new("<cue ext=\"V.O.\">EMMA</cue>").run().unwrap();
+    /// assert_eq!(root.canonical(),
+    ///            "0 cue ext=4:V.O.\n1 #word 0 4:EMMA\n1 #space 0 1: \n\
+    ///             1 #open 0 1:(\n1 #word 0 1:V\n1 #punct 0 1:.\n\
+    ///             1 #word 0 1:O\n1 #punct 0 1:.\n1 #close 0 1:)\n");
+    /// ```
+    pub fn canonical(&self) -> String {
+        let text = |s: &str| format!("{}:{}", s.len(), s);
+        let mut out = String::new();
+
+        for (depth, elem) in self.iter_with_depth() {
+            let mut attributes: Vec<(&str, String)> = Vec::new();
+
+            if elem.get_page_break_before() {
+                attributes.push(("pageBreakBefore", "true".to_string()));
+            }
+
+            match elem {
+                ElementType::Act(elem) => {
+                    attributes.push(("number", elem.attributes.number.to_string()));
+                },
+                ElementType::Blank(elem) => {
+                    attributes.push(("lines", elem.attributes.lines.to_string()));
+                },
+                ElementType::Cue(elem) => {
+                    if let Some(ext) = &elem.attributes.ext {
+                        attributes.push(("ext", text(ext)));
+                    }
+                },
+                ElementType::D(elem) => {
+                    attributes.push(("indent", elem.attributes.indent.to_string()));
+                },
+                ElementType::End(elem) => {
+                    attributes.push(("place", match elem.attributes.place {
+                        Placement::Inline => "inline",
+                        Placement::Center => "center",
+                        Placement::Bottom => "bottom",
+                    }.to_string()));
+                },
+                ElementType::Note(elem) => {
+                    attributes.push(("align", alignment_name(elem.attributes.align)));
+                },
+                ElementType::P(elem) => {
+                    attributes.push(("indent", elem.attributes.indent.to_string()));
+                    attributes.push(("keepWithNext",
+                                     elem.attributes.keep_with_next.to_string()));
+                },
+                ElementType::Screenplay(elem) => {
+                    let settings = &elem.attributes;
+                    let markers = &settings.markers;
+
+                    attributes.push(("numbering", match settings.numbering {
+                        Numbering::None => "none",
+                        Numbering::Left => "left",
+                        Numbering::Right => "right",
+                        Numbering::Full => "full",
+                    }.to_string()));
+                    attributes.push(("justify", settings.justify.to_string()));
+                    attributes.push(("slugCaps", settings.slug_caps.to_string()));
+                    attributes.push(("autocase", settings.autocase.to_string()));
+                    attributes.push(("moreText", text(&markers.more)));
+                    attributes.push(("contdText", text(&markers.contd)));
+                    attributes.push(("continuedText", text(&markers.continued)));
+                    attributes.push(("continuedNumbers",
+                                     markers.continued_numbers.to_string()));
+                },
+                ElementType::Slug(elem) => {
+                    let suffix: String = elem.attributes.suffix.iter()
+                        .map(|token| token.text())
+                        .collect();
+
+                    attributes.push(("number", elem.attributes.number.to_string()));
+
+                    if let Some(addition) = elem.attributes.addition {
+                        attributes.push(("addition", text(&addition.to_string())));
+                    }
+
+                    attributes.push(("numbered", elem.attributes.numbered.to_string()));
+                    attributes.push(("continued", elem.attributes.continued.to_string()));
+                    attributes.push(("suffix", text(&suffix)));
+                },
+                ElementType::Trans(elem) => {
+                    attributes.push(("align", alignment_name(elem.attributes.align)));
+                },
+                _ => (),
+            }
+
+            out.push_str(&format!("{} {}", depth, elem.tag()));
+
+            for (name, value) in attributes {
+                out.push_str(&format!(" {}={}", name, value));
+            }
+
+            out.push('\n');
+
+            for token in elem.tokens().into_iter().flatten() {
+                out.push_str(&format!("{} #{} {} {}\n", depth + 1, token.kind(),
+                                      token.display_flags().bits(),
+                                      text(&token.text())));
+            }
+        }
+
+        out
+    }
+}
+
+/// Names an alignment as the schema does
+fn alignment_name(align: Alignment) -> String {
+    match align {
+        Alignment::Tab => "tab",
+        Alignment::Left => "left",
+        Alignment::Center => "center",
+        Alignment::Right => "right",
+    }.to_string()
+}
+
+/// Iterator over an element and its descendants, with their depths
+///
+/// Returned by [`ElementType::iter_with_depth`].
+#[derive(Debug)]
+pub struct ElementDepthIterator<'a> {
+    stack: Vec<(usize, &'a ElementType)>,
+}
+
+impl<'a> Iterator for ElementDepthIterator<'a> {
+    type Item = (usize, &'a ElementType);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, elem) = self.stack.pop()?;
+
+        if let Some(children) = elem.children() {
+            // reversed, so that the first child comes off first
+            for child in children.iter().rev() {
+                self.stack.push((depth + 1, child));
+            }
+        }
+
+        Some((depth, elem))
+    }
+}
+
+/// Iterator over an element and its descendants
+///
+/// Returned by [`ElementType::iter`].
+#[derive(Debug)]
+pub struct ElementIterator<'a> {
+    inner: ElementDepthIterator<'a>,
+}
+
+impl<'a> Iterator for ElementIterator<'a> {
+    type Item = &'a ElementType;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, elem)| elem)
+    }
+}
+
+/// Iterator of mutable references to the elements under an element
+/// that are not containers
+///
+/// Returned by [`ElementType::iter_mut`].
+#[derive(Debug)]
+pub struct ElementIterMut<'a> {
+    stack: Vec<std::slice::IterMut<'a, ElementType>>,
+}
+
+impl<'a> Iterator for ElementIterMut<'a> {
+    type Item = &'a mut ElementType;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let elem = match self.stack.last_mut()?.next() {
+                Some(elem) => elem,
+                None => {
+                    self.stack.pop();
+                    continue;
+                },
+            };
+
+            if !elem.has_children() {
+                return Some(elem);
+            }
+
+            if let Some(children) = elem.children_mut() {
+                self.stack.push(children.iter_mut());
+            }
         }
     }
 }
 
+/// Callback for walking the element tree with [`ElementType::accept`]
+pub trait ElementVisitor {
+    /// Called for each element, with its depth below the element
+    /// where the walk began and the tag of its parent, if any
+    fn visit(&mut self, elem: &ElementType, depth: usize,
+             parent: Option<&'static str>);
+}
+
 /// Into iterator for an element (flattens the children)
 #[derive(Debug)]
 pub struct ElementIntoIterator {
@@ -292,6 +1336,7 @@ impl Default for ElementIntoIterator {
 
 /// Break options
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
 pub enum BreakType {
     None,
     Mandatory,
@@ -302,8 +1347,36 @@ pub enum BreakType {
     List(BreakPointList),
 }
 
+impl std::fmt::Display for BreakType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let lines = |n: &usize| if *n == 1 { "line" } else { "lines" };
+
+        match self {
+            BreakType::None => write!(f, "no break"),
+            BreakType::Mandatory => write!(f, "mandatory break"),
+            BreakType::Forbidden(n) => write!(f, "forbidden, {} {}", n, lines(n)),
+            BreakType::Atomic(n) => write!(f, "atomic, {} {}", n, lines(n)),
+            BreakType::Disposable(n) => write!(f, "disposable, {} {}", n, lines(n)),
+            BreakType::Point(point) => {
+                write!(f, "break point in line {}", point.line_no)
+            },
+            BreakType::List(points) if points.is_empty() => {
+                write!(f, "no break points")
+            },
+            BreakType::List(points) => {
+                let lines: Vec<String> = points.iter()
+                    .map(|point| point.line_no.to_string())
+                    .collect();
+
+                write!(f, "break points in lines {}", lines.join(", "))
+            },
+        }
+    }
+}
+
 /// Candidate break point
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
 pub struct BreakPoint {
     /// Token list index
     pub token_index: usize,
@@ -341,63 +1414,520 @@ impl From<&str> for Numbering {
     }
 }
 
-// generic elements
-
-/// Generic container element contains only other elements, no text
-#[derive(Debug)]
-pub struct ContainerElement<Attributes> {
-    /// Parameter struct
-    pub attributes: Attributes,
-    /// Sequence of child elements
-    pub children: ElementList,
+/// Horizontal alignment setting
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Alignment {
+    /// Begin at the tab stop, hanging from the right margin only if
+    /// the text is too long
+    Tab,
+    /// Begin at the left margin
+    Left,
+    /// Center between the margins
+    Center,
+    /// Always hang from the right margin
+    Right,
 }
 
-impl<Attributes> ContainerElement<Attributes> {
-    pub fn new(attributes: Attributes) -> Self {
-        Self {
-            attributes: attributes,
-            children: Vec::new(),
+impl From<&str> for Alignment {
+    fn from(s: &str) -> Self {
+        match s {
+            "left" => Alignment::Left,
+            "center" => Alignment::Center,
+            "right" => Alignment::Right,
+            _ => Alignment::Tab,
         }
     }
 }
 
-/// Generic empty element contains only attributes, no content
-#[derive(Debug)]
-pub struct EmptyElement<Attributes> {
-    /// Parameter struct
-    pub attributes: Attributes,
-    /// Break type for this element
-    pub break_info: BreakType,
+/// Vertical placement setting
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Placement {
+    /// Follow the preceding element, skipping a few extra lines if
+    /// there is room
+    #[default]
+    Inline,
+    /// Center in the space left on the page
+    Center,
+    /// Set at the foot of the page
+    Bottom,
 }
 
-impl<Attributes> EmptyElement<Attributes> {
-    pub fn new(attributes: Attributes) -> Self {
-        Self {
-            attributes: attributes,
-            break_info: BreakType::None,
+impl From<&str> for Placement {
+    fn from(s: &str) -> Self {
+        match s {
+            "center" => Placement::Center,
+            "bottom" => Placement::Bottom,
+            _ => Placement::Inline,
         }
     }
 }
 
-/// Generic text element contains mixed content, and footnote elements
-/// are set aside
-#[derive(Debug)]
-pub struct TextElement<Attributes> {
-    pub attributes: Attributes,
-    pub tokens: TokenList,
-    pub break_info: BreakType,
-    pub at_scene_end: bool,
-}
+/// Vertical spacing style
+///
+/// Controls the default number of blank lines between action
+/// paragraphs, and before character cues.  Spec scripts are usually
+/// single spaced; some shooting scripts open up the page with double
+/// spacing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Spacing {
+    /// One blank line between elements
+    Single = 1,
+    /// Two blank lines between elements
+    Double = 2,
+}
+
+impl Spacing {
+    /// Returns the number of blank lines between elements
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::Spacing;
+    /// assert_eq!(Spacing::Single.blank_lines(), 1);
+    /// assert_eq!(Spacing::from("double").blank_lines(), 2);
+    /// ```
+    pub fn blank_lines(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl From<&str> for Spacing {
+    fn from(s: &str) -> Self {
+        match s {
+            "double" => Spacing::Double,
+            _ => Spacing::Single,
+        }
+    }
+}
+
+/// Production format
+///
+/// Taped multi-camera sitcom drafts open with a cast list and a set
+/// list.  With the multicam profile, these are generated from the
+/// cues and scene headings and typeset as front matter after the
+/// title page.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::reader::Reader;
+/// # use batyr::document::formatter::Formatter;
+/// let mut xml = String::from("<screenplay profile=\"multicam\">");
+/// xml.push_str("<head><title>FOO</title>");
+/// xml.push_str("<authors><fullName>BAR</fullName></authors></head><body>");
+/// xml.push_str("<slug>INT. KITCHEN - DAY</slug>");
+/// xml.push_str("<cue>EMMA</cue><d>Hello.</d>");
+/// xml.push_str("<cue>THOMAS (O.S.)</cue><d>Hi.</d>");
+/// xml.push_str("<slug>INT. KITCHEN - NIGHT</slug>");
+/// xml.push_str("<cue>EMMA</cue><d>Goodnight.</d>");
+/// xml.push_str("</body></screenplay>");
+///
+/// let root = Reader::new(&xml).run().unwrap();
+/// let mut formatter = Formatter::new();
+/// formatter.run(root);
+///
+/// let text = |i: usize| -> Vec<String> {
+///     formatter.body[i].lines.iter().filter_map(|row| match row {
+///         batyr::document::formatter::Row::Text(line) => Some(line.text()),
+///         _ => None,
+///     }).collect()
+/// };
+///
+/// assert_eq!(text(1), vec!["CAST LIST", "EMMA", "THOMAS"]);
+/// assert_eq!(text(2), vec!["SET LIST", "INT.  KITCHEN"]);
+/// assert_eq!(formatter.body[3].number, 1);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Profile {
+    /// Single-camera film or television
+    Film,
+    /// Taped multi-camera television
+    Multicam,
+}
+
+impl From<&str> for Profile {
+    fn from(s: &str) -> Self {
+        match s {
+            "multicam" => Profile::Multicam,
+            _ => Profile::Film,
+        }
+    }
+}
+
+/// Reference book that the layout complies with
+///
+/// The two standard references differ in a few details.  Riley's
+/// _The Hollywood Standard_ sets cues and dialogue further left, hangs
+/// transitions from the right margin, and leaves out the CONTINUED
+/// lines at page breaks within a scene.  Cole and Haag's _Complete
+/// Guide to Standard Script Formats_ keeps the cue at the default tab
+/// stop, narrows the dialogue, and sets transitions at the tab stop.
+/// With neither, the built-in layout is used.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::formatter::{Formatter, Row};
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay authority=\"riley\" numbering=\"left\"><head/><body>\
+///            <slug>INT. KITCHEN - DAY</slug>\
+///            <cue>EMMA</cue><d>Late again.</d>\
+///            <trans>CUT TO:</trans>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let columns: Vec<(usize, String)> = formatter.body[1].lines.iter()
+///     .filter_map(|row| match row {
+///         Row::Text(line) => Some((line.column, line.text())),
+///         _ => None,
+///     })
+///     .skip(1)
+///     .collect();
+///
+/// assert_eq!(columns, vec![(37, "EMMA".to_string()),
+///                          (25, "Late again.".to_string()),
+///                          (68, "CUT TO:".to_string())]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Authority {
+    /// Built-in layout
+    #[default]
+    None,
+    /// Christopher Riley, _The Hollywood Standard_
+    Riley,
+    /// Hillis R. Cole and Judith H. Haag, _The Complete Guide to
+    /// Standard Script Formats_
+    ColeHaag,
+}
+
+impl From<&str> for Authority {
+    fn from(s: &str) -> Self {
+        match s {
+            "riley" => Authority::Riley,
+            "coleHaag" => Authority::ColeHaag,
+            _ => Authority::None,
+        }
+    }
+}
+
+impl Authority {
+    /// Returns the name of the reference book, for messages
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Authority::None => "the built-in layout",
+            Authority::Riley => "The Hollywood Standard",
+            Authority::ColeHaag => "Cole and Haag",
+        }
+    }
+
+    /// Returns the cue tab stop
+    pub fn cue_begin(&self) -> usize {
+        match self {
+            Authority::Riley => RILEY_CUE_BEGIN,
+            _ => Layout::default().cue_begin,
+        }
+    }
+
+    /// Returns the dialogue left margin
+    pub fn d_begin(&self) -> usize {
+        match self {
+            Authority::None => Layout::default().d_begin,
+            Authority::Riley => RILEY_D_BEGIN,
+            Authority::ColeHaag => COLE_HAAG_D_BEGIN,
+        }
+    }
+
+    /// Returns the personal direction left margin
+    pub fn dir_begin(&self) -> usize {
+        match self {
+            Authority::None => Layout::default().dir_begin,
+            Authority::Riley => RILEY_DIR_BEGIN,
+            Authority::ColeHaag => COLE_HAAG_DIR_BEGIN,
+        }
+    }
+
+    /// Returns the alignment of transitions that do not set one
+    pub fn trans_align(&self) -> Alignment {
+        match self {
+            Authority::Riley => Alignment::Right,
+            _ => Alignment::Tab,
+        }
+    }
+
+    /// Returns true if page breaks within a numbered scene are
+    /// marked CONTINUED
+    pub fn continued(&self) -> bool {
+        *self != Authority::Riley
+    }
+}
+
+/// Version of the screenplay schema that a document is written to
+///
+/// Set with the <tt>version</tt> attribute of
+/// <tt>&lt;screenplay&gt;</tt>.  A document without one is read as
+/// the current version.  Elements added to the schema later are
+/// errors in a document that declares an older version, and a
+/// document from a newer version than this library knows is refused
+/// rather than misread.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::SchemaVersion;
+/// # use batyr::document::reader::Reader;
+/// let v = SchemaVersion::parse("1.0").unwrap();
+///
+/// assert!(v < SchemaVersion::CURRENT);
+/// assert!(!SchemaVersion::parse("9.0").unwrap().is_supported());
+/// assert_eq!(SchemaVersion::introducing(b"ol").to_string(), "1.1");
+///
+/// let xml = "<screenplay version=\"1.0\"><head/><body>\
+///            <ol><li>Eggs</li></ol></body></screenplay>";
+/// assert!(Reader::new(xml).try_run().unwrap_err().contains("<ol>"));
+///
+/// let xml = "<screenplay version=\"2.0\"><head/><body/></screenplay>";
+/// assert!(Reader::new(xml).try_run().is_err());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SchemaVersion {
+    /// Incremented for changes that older readers cannot follow
+    pub major: u32,
+    /// Incremented for additions to the element set
+    pub minor: u32,
+}
+
+impl SchemaVersion {
+    /// The first published schema
+    pub const V1_0: SchemaVersion = SchemaVersion { major: 1, minor: 0 };
+
+    /// Adds numbered lists
+    pub const V1_1: SchemaVersion = SchemaVersion { major: 1, minor: 1 };
+
+    /// Adds underline and bold
+    pub const V1_2: SchemaVersion = SchemaVersion { major: 1, minor: 2 };
+
+    /// The newest version this library reads
+    pub const CURRENT: SchemaVersion = SchemaVersion::V1_2;
+
+    /// Parses a version such as <tt>1.1</tt>
+    pub fn parse(s: &str) -> Option<Self> {
+        let (major, minor) = s.trim().split_once('.')?;
+
+        Some(SchemaVersion {
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+        })
+    }
+
+    /// Returns true if this library can read documents of the version
+    pub fn is_supported(&self) -> bool {
+        SchemaVersion::V1_0 <= *self && *self <= SchemaVersion::CURRENT
+    }
+
+    /// Returns the version that added an element, given its name
+    pub fn introducing(name: &[u8]) -> Self {
+        match name {
+            b"li" | b"ol" => SchemaVersion::V1_1,
+            b"b" | b"u" => SchemaVersion::V1_2,
+            _ => SchemaVersion::V1_0,
+        }
+    }
+}
+
+impl Default for SchemaVersion {
+    fn default() -> Self {
+        SchemaVersion::CURRENT
+    }
+}
+
+impl std::fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+// generic elements
+
+/// Generic container element contains only other elements, no text
+#[derive(Debug, Clone)]
+pub struct ContainerElement<Attributes> {
+    /// Parameter struct
+    pub attributes: Attributes,
+    /// Sequence of child elements
+    pub children: ElementList,
+    /// Line of the source where the element begins
+    pub line: usize,
+}
+
+impl<Attributes> ContainerElement<Attributes> {
+    pub fn new(attributes: Attributes) -> Self {
+        Self {
+            attributes,
+            children: Vec::new(),
+            line: 0,
+        }
+    }
+}
+
+/// Generic empty element contains only attributes, no content
+#[derive(Debug, Clone)]
+pub struct EmptyElement<Attributes> {
+    /// Parameter struct
+    pub attributes: Attributes,
+    /// Break type for this element
+    pub break_info: BreakType,
+    /// Line of the source where the element begins
+    pub line: usize,
+}
+
+impl<Attributes> EmptyElement<Attributes> {
+    pub fn new(attributes: Attributes) -> Self {
+        Self {
+            attributes,
+            break_info: BreakType::None,
+            line: 0,
+        }
+    }
+}
+
+/// Generic text element contains mixed content, and footnote elements
+/// are set aside
+#[derive(Debug, Clone)]
+pub struct TextElement<Attributes> {
+    pub attributes: Attributes,
+    /// Text of the element
+    ///
+    /// The tokens are shared, so that cloning an element or a whole
+    /// document copies no text; use [`Arc::make_mut`] to change them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let Some(ElementType::P(p)) = Reader::new("<p>Rain.</p>").run() else {
+    ///     panic!();
+    /// };
+    /// let mut copy = p.clone();
+    /// assert!(Arc::ptr_eq(&p.tokens, &copy.tokens));
+    ///
+    /// Arc::make_mut(&mut copy.tokens).clear();
+    /// assert!(!p.tokens.is_empty());
+    /// ```
+    pub tokens: Arc<TokenList>,
+    pub break_info: BreakType,
+    /// Whether the element is the last of its scene, so that a page
+    /// break after it needs no (CONTINUED)
+    ///
+    /// The reader sets the flag on the last element before each slug,
+    /// passing over elements such as <tt>br</tt>, and on the cue of
+    /// dialogue that ends a scene.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\
+    ///            <slug>INT. HALL - DAY</slug>\
+    ///            <p>Emma waits by the door.</p>\
+    ///            <cue>EMMA</cue>\
+    ///            <d>Well?</d>\
+    ///            <dir>beat</dir>\
+    ///            <d>Come on.</d>\
+    ///            <br/>\
+    ///            <slug>EXT. STREET - DAY</slug>\
+    ///            <p>Rain.</p>\
+    ///            </body></screenplay>";
+    ///
+    /// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+    ///     panic!();
+    /// };
+    /// let Some(ElementType::Body(body)) = root.children.last() else {
+    ///     panic!();
+    /// };
+    /// let ends: Vec<_> = body.children.iter().map(|child| match child {
+    ///     ElementType::Slug(elem) => elem.at_scene_end,
+    ///     ElementType::P(elem) => elem.at_scene_end,
+    ///     ElementType::Cue(elem) => elem.at_scene_end,
+    ///     ElementType::D(elem) => elem.at_scene_end,
+    ///     ElementType::Dir(elem) => elem.at_scene_end,
+    ///     _ => false,
+    /// }).collect();
+    ///
+    /// assert_eq!(ends, [false, false, true, false, false, true, false,
+    ///                   false, false]);
+    /// ```
+    pub at_scene_end: bool,
+    /// Column width and the number of lines the text takes in it, as
+    /// counted by the reader
+    pub line_count: Option<(usize, usize)>,
+    /// Line of the source where the element begins
+    pub line: usize,
+}
 
 impl<Attributes> TextElement<Attributes> {
     pub fn new(attributes: Attributes) -> Self {
         Self {
-            attributes: attributes,
-            tokens: Vec::new(),
+            attributes,
+            tokens: Arc::new(Vec::new()),
             break_info: BreakType::None,
             at_scene_end: false,
+            line_count: None,
+            line: 0,
         }
     }
+
+    /// Counts the lines the text takes in a column of the given width
+    ///
+    /// The count made by the reader is returned if it was made for
+    /// the same width; otherwise the tokens are counted again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<dir>not looking up</dir>";
+    /// let Some(ElementType::Dir(dir)) = Reader::new(xml).run() else {
+    ///     panic!();
+    /// };
+    ///
+    /// assert_eq!(dir.line_count, Some((dir.attributes.right_margin
+    ///                                  - dir.attributes.left_margin + 1, 1)));
+    /// assert_eq!(dir.lines_at(7), 3);
+    /// ```
+    pub fn lines_at(&self, width: usize) -> usize {
+        match self.line_count {
+            Some((w, n)) if w == width => n,
+            _ => text::count_lines(&self.tokens[..], width),
+        }
+    }
+
+    /// Returns the plain text of the element, with each run of
+    /// whitespace collapsed to a single space
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<p>Hello.  <em>Goodbye</em>.</p>";
+    ///
+    /// match Reader::new(xml).run() {
+    ///     Some(ElementType::P(elem)) => {
+    ///         assert_eq!(elem.text(), "Hello. Goodbye.");
+    ///     },
+    ///     _ => panic!(),
+    /// }
+    /// ```
+    pub fn text(&self) -> String {
+        let text: String = self.tokens.iter().map(|t| t.text()).collect();
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
 }
 
 // screenplay elements
@@ -428,12 +1958,43 @@ impl<Attributes> TextElement<Attributes> {
 /// Output:
 ///
 /// <pre width="100%" style="text-align: center;"><ins>TEASER</ins></pre>
-#[derive(Debug)]
+///
+/// Names too long for the margins are balanced over several lines,
+/// each one centered.
+///
+/// ```
+/// # use batyr::document::ElementType;
+/// # use batyr::document::formatter::{Formatter, Row};
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head/><body><act>\
+///            ACT ONE: IN WHICH OUR HEROES SET OUT FROM THE VILLAGE AND \
+///            COME TO THE EDGE OF THE GREAT FOREST</act></body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let lines: Vec<_> = formatter.body[1].lines.iter().filter_map(|row| {
+///     match row {
+///         Row::Text(line) => Some(line),
+///         _ => None,
+///     }
+/// }).collect();
+///
+/// assert_eq!(lines.len(), 3);
+/// assert!(lines.iter().all(|line| line.column + line.length() <= 65));
+/// ```
+#[derive(Debug, Clone)]
 pub struct Act {
     /// Act number.  This is an internal sequence number that does not
     /// appear in any output.  In particular, it but does not
     /// necessarily match any numbering in the act titles.
     pub number: i32,
+    /// Narrow column left margin
+    pub left_margin: usize,
+    /// Narrow column right margin
+    pub right_margin: usize,
+    /// Starts a new page before the element
+    pub page_break_before: bool,
     /// Number of blank lines preceding
     pub padding_before: i32,
     /// Number of blank lines following
@@ -441,15 +2002,77 @@ pub struct Act {
 }
 
 /// Container for a sequence of authors
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Authors {}
 
+/// Bold
+///
+/// Bold text is double-struck, as on a typewriter: each line segment
+/// is typed a second time a fraction of a point to the right.  Its
+/// tokens carry [`DisplayFlags::BOLD`].  For an example, see [`U`].
+#[derive(Debug, Clone)]
+pub struct B {}
+
+/// Back matter following the body, such as a glossary
+///
+/// See [`FrontMatter`].
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::reader::Reader;
+/// # use batyr::document::formatter::Formatter;
+/// let mut xml = String::from("<screenplay><head><title>FOO</title>");
+/// xml.push_str("<authors><fullName>BAR</fullName></authors></head>");
+/// xml.push_str("<frontMatter><title>CAST</title><p>EMMA</p></frontMatter>");
+/// xml.push_str("<body><p>Hello.</p></body>");
+/// xml.push_str("<backMatter><p>Glossary.</p></backMatter></screenplay>");
+///
+/// let root = Reader::new(&xml).run().unwrap();
+/// let mut formatter = Formatter::new();
+/// formatter.run(root);
+///
+/// // title page, cast list, body, glossary
+/// let numbers: Vec<i32> = formatter.body.iter().map(|p| p.number).collect();
+/// assert_eq!(numbers, vec![-1, 0, 1, 0]);
+/// assert_eq!(formatter.title, "FOO");
+/// ```
+#[derive(Debug, Clone)]
+pub struct BackMatter {}
+
+/// Run of blank lines
+///
+/// An intentional gap between two elements, such as before "THE
+/// END".  Like a single <tt>&lt;br/&gt;</tt>, the lines are dropped
+/// when they would fall at the top of a page.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::formatter::{Formatter, Row};
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head/><body>\
+///            <p>Emma waits.</p><blank lines=\"3\"/><p>Thomas runs.</p>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let page = &formatter.body[1];
+/// assert!(matches!(page.lines[1], Row::Spacing(4)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Blank {
+    /// Number of blank lines
+    pub lines: usize,
+}
+
 /// Document body
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Body {}
 
 /// Mandatory line break
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Br {}
 
 /// Contact information
@@ -470,7 +2093,7 @@ pub struct Br {}
 /// P.O. Box 90606
 /// Brooklyn, NY 11209
 /// </pre>
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Contact {
     /// Left edge of the page
     pub left_margin: usize,
@@ -480,9 +2103,10 @@ pub struct Contact {
 
 /// Character cue
 ///
-/// Character cues should be in all caps.  There is no built-in
-/// handling for offscreen dialogue and voice overs.  Use "(O.S.)" and
-/// "(V.O.)" respectively.  As per modern practice, Batyr will
+/// Character cues should be in all caps.  Offscreen dialogue and
+/// voice overs take an extension, given with the <tt>ext</tt>
+/// attribute as <tt>ext="O.S."</tt> or <tt>ext="V.O."</tt>, or typed
+/// after the name in parentheses.  As per modern practice, Batyr will
 /// automatically add "(CONT'D)" to the character cue at the top of
 /// the next page if the dialogue is broken across the page break.  It
 /// is _not_ added automatically to dialogue spoken by the same
@@ -505,13 +2129,73 @@ pub struct Contact {
 ///                           (in tears)
 ///                    You're hurting my sore ear.
 /// </pre>
-#[derive(Debug)]
+///
+/// A cue too long for the right margin wraps at the tab stop.  The
+/// extension stays in one piece, and a <tt>&lt;br/&gt;</tt> puts it
+/// on a line of its own.
+///
+/// ```
+/// # use batyr::document::ElementType;
+/// # use batyr::document::formatter::{Formatter, Row};
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head/><body>\
+///            <cue>MAYOR OF BEDFORD FALLS<br/>(V.O.)</cue>\
+///            <d>You're a fool, George.</d></body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let lines: Vec<_> = formatter.body[1].lines.iter().filter_map(|row| {
+///     match row {
+///         Row::Text(line) => Some(line.text()),
+///         _ => None,
+///     }
+/// }).collect();
+///
+/// assert_eq!(lines[0], "MAYOR OF BEDFORD FALLS");
+/// assert_eq!(lines[1], "(V.O.)");
+/// ```
+///
+/// The extension attribute is set the same way as typed text, and
+/// either way the character's name leaves it out.
+///
+/// ```
+/// # use batyr::document::ElementType;
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head/><body>\
+///            <cue ext=\"V.O.\">GEORGE</cue><d>Mary?</d>\
+///            <cue>GEORGE (V.O.)</cue><d>Mary!</d>\
+///            </body></screenplay>";
+///
+/// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+///     panic!();
+/// };
+/// let Some(ElementType::Body(body)) = root.children.last() else {
+///     panic!();
+/// };
+/// let cues: Vec<_> = body.children.iter().filter_map(|child| match child {
+///     ElementType::Cue(cue) => Some(cue),
+///     _ => None,
+/// }).collect();
+///
+/// assert_eq!(cues[0].text(), "GEORGE (V.O.)");
+/// assert_eq!(cues[0].name(), cues[1].name());
+/// assert_eq!(cues[0].extension(), cues[1].extension());
+/// ```
+#[derive(Debug, Clone)]
 pub struct Cue {
     /// Column number to begin typing at
     pub tab_stop: usize,
+    /// Column number of the right margin
+    pub right_margin: usize,
+    /// Extension from the <tt>ext</tt> attribute, without
+    /// parentheses
+    pub ext: Option<String>,
     /// Break point information for D or Dir elements immediately
     /// following the Cue
     pub train: Vec<BreakType>,
+    /// Starts a new page before the element
+    pub page_break_before: bool,
     /// Number of blank lines preceding
     pub padding_before: i32,
     /// Number of blank lines following
@@ -519,163 +2203,134 @@ pub struct Cue {
 }
 
 impl TextElement<Cue> {
-    /// Counts lines for the cue and its train based on break point
-    /// information only
-    pub fn count_lines(&self) -> usize {
-        let mut line_count: usize = 1;
-
-        for break_info in self.attributes.train.iter() {
-            match break_info {
-                BreakType::None => (),
-                BreakType::Mandatory => (),
-                BreakType::Forbidden(h) => line_count += h,
-                BreakType::Atomic(h) => line_count += h,
-                BreakType::Disposable(h) => line_count += h,
-                BreakType::Point(break_point) => {
-                    line_count += break_point.line_no;
-                },
-                BreakType::List(break_points) => {
-                    let n = break_points.len();
-                    line_count += break_points[n - 1].line_no;
-                },
-            }
+    /// Returns the character name without any extension, such as
+    /// (V.O.) or (CONT'D)
+    pub fn name(&self) -> String {
+        let text = self.text();
+
+        match text.find('(') {
+            Some(i) => text[..i].trim_end().to_string(),
+            None => text,
         }
-
-        line_count
     }
 
-    /// Selects a break point given the number of lines remaining in
-    /// the page
-    pub fn select_break(&self, lines_remaining: i32) -> (i32, BreakType) {
-        if lines_remaining < 2 { // Don't orphan the character cue.
-            return (-1, BreakType::None);
-        }
-
-        let total_height = self.count_lines();
-        let n = self.attributes.train.len();
-        
-        if total_height as i32 <= lines_remaining { // entire dialogue fits
-            return (n as i32, BreakType::None);
-        }
-
-        // We need to add "(MORE)" at the bottom of the page.
-        let mut line_count = 1; // 1 for the character cue
-        let mut prev_break_list_index: i32 = -1;
-        let mut prev_break_info: BreakType = BreakType::None;
-        
-        for i in 0..n {
-            match &self.attributes.train[i] {
-                BreakType::None => (),
-                BreakType::Mandatory => {
-                    return (i as i32, BreakType::Mandatory);
-                },
-                BreakType::Forbidden(h) => {
-                    if i == n - 1 { // last break
-                        if (line_count + h) as i32 > lines_remaining {
-                            return (prev_break_list_index, prev_break_info);
-                        }
-                    } else {
-                        // plus 1 for (MORE)
-                        if (line_count + h + 1) as i32 > lines_remaining {
-                            return (prev_break_list_index, prev_break_info);
-                        }
-                    }
-
-                    line_count += h;
-                },
-                BreakType::Atomic(h) => {
-                    if i == n - 1 { // last break
-                        if (line_count + h) as i32 > lines_remaining {
-                            return (prev_break_list_index, prev_break_info);
-                        }
-                    } else {
-                        // plus 1 for (MORE)
-                        if (line_count + h + 1) as i32 > lines_remaining {
-                            return (prev_break_list_index, prev_break_info);
-                        }
-                    }
-
-                    prev_break_info = BreakType::Atomic(*h);
-                    prev_break_list_index = i as i32;
-
-                    line_count += h;
-                },
-                BreakType::Disposable(h) => {
-                    if i == n - 1 { // last break
-                        if (line_count + h) as i32 > lines_remaining {
-                            return (prev_break_list_index, prev_break_info);
-                        }
-                    } else {
-                        // plus 1 for (MORE)
-                        if (line_count + h + 1) as i32 > lines_remaining {
-                            return (prev_break_list_index, prev_break_info);
-                        }
-                    }
+    /// Returns the extension of the cue, such as V.O. or O.S., from
+    /// the <tt>ext</tt> attribute or else the text in parentheses
+    /// after the name
+    ///
+    /// Spaces and case are normalized, so "(v. o.)" and "(V.O.)" are
+    /// the same extension.
+    pub fn extension(&self) -> Option<String> {
+        let ext = match &self.attributes.ext {
+            Some(ext) => ext.clone(),
+            None => {
+                let text = self.text();
+                let i = text.find('(')?;
+                let j = text[i..].find(')').map(|j| i + j).unwrap_or(text.len());
+                text[i + 1..j].to_string()
+            },
+        };
 
-                    prev_break_info = BreakType::Disposable(*h);
-                    prev_break_list_index = i as i32;
+        let ext: String = ext.chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(|c| c.to_uppercase())
+            .collect();
 
-                    line_count += h;
-                },
-                BreakType::Point(break_point) => {
-                    if i == n - 1 { // last break
-                        if (line_count + break_point.line_no) as i32
-                            > lines_remaining
-                        {
-                            return (prev_break_list_index, prev_break_info);
-                        }
-                    } else {
-                        // plus 1 for (MORE)
-                        if (line_count + break_point.line_no + 1) as i32
-                            > lines_remaining
-                        {
-                            return (prev_break_list_index, prev_break_info);
-                        }
-                    }
+        match ext.is_empty() {
+            true => None,
+            false => Some(ext),
+        }
+    }
 
-                    prev_break_info = BreakType::Point(break_point.clone());
-                    prev_break_list_index = i as i32;
-                },
-                BreakType::List(break_points) => {
-                    let m = break_points.len();
-
-                    for j in 0..m {
-                        let break_point = &break_points[j];
-
-                        // plus 1 for (MORE)
-                        if (line_count + break_point.line_no + 1) as i32
-                            > lines_remaining
-                        {
-                            if prev_break_info == BreakType::None
-                                || prev_break_list_index < 0
-                            {
-                                return (-1, BreakType::None);
-                                
-                            } else {
-                                return (prev_break_list_index, prev_break_info);
-                            }
-                        }
+    /// Counts the lines taken by the cue itself, which wraps at the
+    /// right margin
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\
+    ///            <cue>THE VERY HONORABLE MAYOR OF BEDFORD FALLS</cue>\
+    ///            <d>Hello.</d></body></screenplay>";
+    ///
+    /// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+    ///     panic!();
+    /// };
+    /// let Some(ElementType::Body(body)) = root.children.last() else {
+    ///     panic!();
+    /// };
+    /// let Some(ElementType::Cue(cue)) = body.children.first() else {
+    ///     panic!();
+    /// };
+    ///
+    /// assert_eq!(cue.cue_lines(), 2);
+    /// assert_eq!(cue.count_lines(), 3);
+    /// ```
+    pub fn cue_lines(&self) -> usize {
+        let w = self.attributes.right_margin - self.attributes.tab_stop + 1;
+        self.lines_at(w)
+    }
 
-                        prev_break_info = BreakType::Point(break_point.clone());
-                        prev_break_list_index = i as i32;
-                    }
+    /// Counts lines for the cue and its train based on break point
+    /// information only
+    ///
+    /// See [`breaking::train_lines_needed`], which allows one line for
+    /// the cue.
+    pub fn count_lines(&self) -> usize {
+        self.cue_lines() - 1
+            + breaking::train_lines_needed(&self.attributes.train[..])
+    }
 
-                    line_count += break_points[m - 1].line_no;
-                },
-            }
-        }
-        
-        (-1, BreakType::None)
+    /// Selects a break point given the number of lines remaining in
+    /// the page
+    ///
+    /// See [`breaking::best_train_break_with`].
+    pub fn select_break(&self, lines_remaining: i32,
+                        policy: &breaking::BreakPolicy)
+                        -> (i32, BreakType)
+    {
+        let extra_lines = self.cue_lines() as i32 - 1;
+
+        breaking::best_train_break_with(&self.attributes.train[..],
+                                        lines_remaining - extra_lines,
+                                        policy)
     }
 }
 
 /// Dialogue
 ///
 /// For an example, see [`Cue`].
-#[derive(Debug)]
+///
+/// Dialogue that trails in from an ellipsis or a dash takes one space
+/// after it, and never breaks to a new line or page right after it.
+///
+/// ```
+/// # use batyr::document::formatter::{Formatter, Row};
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head/><body>\
+///            <cue>EMMA</cue><d>... and then nothing.</d>\
+///            <cue>EMMA</cue><d>\u{2014} Wait.</d>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let lines: Vec<String> = formatter.body[1].lines.iter().filter_map(|row| {
+///     match row {
+///         Row::Text(line) => Some(line.text()),
+///         _ => None,
+///     }
+/// }).collect();
+///
+/// assert!(lines.contains(&"... and then nothing.".to_string()));
+/// assert!(lines.contains(&"-- Wait.".to_string()));
+/// ```
+#[derive(Debug, Clone)]
 pub struct D {
-    /// Number of spaces to indent (default to 0)
-    pub indent: usize,
+    /// Number of spaces to indent (default to 0), or if negative, to
+    /// hang the lines that wrap
+    pub indent: i32,
     /// Narrow column left margin
     pub left_margin: usize,
     /// Narrow column right margin
@@ -686,10 +2341,22 @@ pub struct D {
     pub padding_after: usize,
 }
 
+impl TextElement<D> {
+    /// Returns the indent of the first line
+    pub fn first_indent(&self) -> usize {
+        self.attributes.indent.max(0) as usize
+    }
+
+    /// Returns the hanging indent of the lines that wrap
+    pub fn hang(&self) -> usize {
+        (-self.attributes.indent).max(0) as usize
+    }
+}
+
 /// Personal direction
 ///
 /// For an example, see [`Cue`].
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Dir {
     /// Narrow column left margin
     pub left_margin: usize,
@@ -702,7 +2369,45 @@ pub struct Dir {
 }
 
 /// Emphasis
-#[derive(Debug)]
+///
+/// Emphasized text is underlined.  Emphasis nested inside emphasis
+/// is underlined and set in capitals as well, and its tokens carry
+/// both [`DisplayFlags::EM`] and [`DisplayFlags::CAPS`], so it prints
+/// as a segment of its own.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::formatter::{Formatter, Row};
+/// # use batyr::document::reader::Reader;
+/// # use batyr::text::tokens::DisplayFlags;
+/// let xml = "<screenplay><head/><body>\
+///            <p>She <em>never <em>ever</em> calls</em> back.</p>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let line = formatter.body[1].lines.iter()
+///     .find_map(|row| match row {
+///         Row::Text(line) if line.text().starts_with("She") => Some(line),
+///         _ => None,
+///     })
+///     .unwrap();
+///
+/// let segments: Vec<(&str, DisplayFlags)> = line.segments.iter()
+///     .map(|x| (x.text.as_str(), x.dpy))
+///     .collect();
+///
+/// assert_eq!(segments, vec![
+///     ("She ", DisplayFlags::empty()),
+///     ("never ", DisplayFlags::EM),
+///     ("EVER", DisplayFlags::EM | DisplayFlags::CAPS),
+///     (" calls", DisplayFlags::EM),
+///     (" back.", DisplayFlags::empty()),
+/// ]);
+/// ```
+#[derive(Debug, Clone)]
 pub struct Em {}
 
 /// End of sequence
@@ -716,133 +2421,1105 @@ pub struct Em {}
 /// Output:
 ///
 /// <pre style="text-align: center;"><ins>END OF ACT ONE</ins></pre>
-#[derive(Debug)]
+///
+/// With <tt>place="center"</tt> or <tt>place="bottom"</tt>, the text
+/// is centered vertically in the rest of the page or set at its foot.
+///
+/// ```
+/// # use batyr::document::formatter::{Formatter, Row};
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head/><body>\
+///            <p>Emma waits.</p><end place=\"bottom\">THE END</end>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let page = &formatter.body[1];
+/// assert_eq!(page.line_count(), page.height);
+/// assert!(matches!(page.lines.last(), Some(Row::Text(line)) if line.text() == "THE END"));
+/// ```
+///
+/// The text is set at least [`FormatterOptions::end_gap`] blank lines
+/// below the last content.  When that does not fit, the last block
+/// of the page moves to the next page with it, so the text is never
+/// left alone at the top of a page, unless the page before is exactly
+/// full.
+///
+/// [`FormatterOptions::end_gap`]: formatter::FormatterOptions::end_gap
+///
+/// ```
+/// # use batyr::document::Layout;
+/// # use batyr::document::formatter::{Formatter, FormatterOptions};
+/// # use batyr::document::golden::page_text;
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head/><body>\
+///            <p>One.</p><p>Two.</p><p>Three.</p><p>Four.</p><p>Five.</p>\
+///            <end>END OF ACT ONE</end>\
+///            </body></screenplay>";
+///
+/// let options = FormatterOptions {
+///     layout: Layout::default().with_lines_per_page(10),
+///     ..Default::default()
+/// };
+/// let mut formatter = Formatter::with_options(options);
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let text = page_text(&formatter.body[2]);
+/// let lines: Vec<&str> = text.lines().map(str::trim).collect();
+///
+/// assert_eq!(lines, vec!["Five.", "", "END OF ACT ONE"]);
+/// ```
+#[derive(Debug, Clone)]
 pub struct End {
+    /// Narrow column left margin
+    pub left_margin: usize,
+    /// Narrow column right margin
+    pub right_margin: usize,
+    /// Vertical placement on the page
+    pub place: Placement,
+    /// Starts a new page before the element
+    pub page_break_before: bool,
+    /// Number of blank lines preceding
+    pub padding_before: i32,
+    /// Number of blank lines following
+    pub padding_after: usize,
+}
+
+/// Episode title for the running page header
+///
+/// # Examples
+///
+/// ```xml
+/// <episode>The Pilot</episode>
+/// <productionNumber>304</productionNumber>
+/// ```
+///
+/// Output at the top of each numbered page:
+///
+/// <pre style="text-align: right;">#304 'The Pilot'  23.</pre>
+#[derive(Debug, Clone)]
+pub struct Episode {}
+
+/// Front matter preceding the body, such as a cast list or a
+/// dedication
+///
+/// Front and back matter are typeset on pages of their own without
+/// page numbers, and do not affect the numbering of the body pages.
+/// Titles are centered.
+///
+/// # Examples
+///
+/// ```xml
+/// <frontMatter>
+///   <title>CAST OF CHARACTERS</title>
+///   <p>EMMA, a bookkeeper</p>
+///   <p>THOMAS, her brother</p>
+/// </frontMatter>
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrontMatter {}
+
+/// Author's name
+#[derive(Debug, Clone)]
+pub struct FullName {}
+
+/// Document head
+#[derive(Debug, Clone)]
+pub struct Head {}
+
+/// Scene opening
+///
+/// # Examples
+///
+/// ```xml
+/// <open>FADE IN:</open>
+/// ```
+///
+/// Output:
+///
+/// <pre>FADE IN:</pre>
+///
+/// The opening begins at the action margin unless a `tabStop` column
+/// is given.  Directly after an act heading, the `afterAct` attribute
+/// sets the exact number of blank lines in between (default 1):
+///
+/// ```
+/// # use batyr::document::ElementType;
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head><title>FOO</title></head><body>\
+///            <act>ACT ONE</act>\
+///            <open tabStop=\"12\" afterAct=\"2\">FADE IN:</open>\
+///            </body></screenplay>";
+///
+/// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+///     panic!();
+/// };
+/// let Some(ElementType::Body(body)) = root.children.last() else {
+///     panic!();
+/// };
+/// let Some(ElementType::Open(open)) = body.children.last() else {
+///     panic!();
+/// };
+///
+/// assert_eq!(open.attributes.tab_stop, 12);
+/// assert_eq!(open.attributes.padding_before, 2);
+/// assert!(open.attributes.after_act);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Open {
+    /// Column number to begin typing at
+    pub tab_stop: usize,
+    /// Starts a new page before the element
+    pub page_break_before: bool,
+    /// Number of blank lines preceding
+    pub padding_before: i32,
+    /// Number of blank lines following
+    pub padding_after: usize,
+    /// Directly follows an act heading, in which case padding_before
+    /// is used as is instead of being merged with the act's padding
+    pub after_act: bool,
+}
+
+/// Title page note
+///
+/// Any number of notes may follow the authors and source, each one
+/// set off by blank lines.  Notes are centered by default; a left
+/// note lines up under the byline, and a right note hangs from the
+/// right margin.
+///
+/// # Examples
+///
+/// ```xml
+/// <note>Based on a true story</note>
+/// <note align="left">Revised pages in blue</note>
+/// <note align="right">Draft 3</note>
+/// ```
+#[derive(Debug, Clone)]
+pub struct Note {
+    /// Center, left, or right
+    pub align: Alignment,
+}
+
+/// Stage direction
+///
+/// Each item of a numbered list, <tt>&lt;ol&gt;</tt>, is read as a
+/// stage direction of its own, with the lines that wrap hanging past
+/// the number and no blank lines between the items.  A list inside a
+/// paragraph splits it, and the paragraph goes on after the list.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::ElementType;
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::golden::page_text;
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head/><body>\
+///            <p>MONTAGE:<ol>\
+///            <li>EMMA packs a suitcase, then unpacks it, then packs it \
+///            again.</li>\
+///            <li>The train pulls out.</li>\
+///            </ol></p>\
+///            <p>Back to the kitchen.</p>\
+///            </body></screenplay>";
+///
+/// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+///     panic!();
+/// };
+/// let mut formatter = Formatter::new();
+/// formatter.run(ElementType::Screenplay(root));
+///
+/// let text = page_text(&formatter.body[1]);
+/// let lines: Vec<&str> = text.lines().skip_while(|l| l.is_empty()).collect();
+///
+/// assert_eq!(lines, vec![
+///     "                MONTAGE:",
+///     "                1. EMMA packs a suitcase, then unpacks it, then packs it",
+///     "                   again.",
+///     "                2. The train pulls out.",
+///     "",
+///     "                Back to the kitchen.",
+/// ]);
+/// ```
+///
+/// A paragraph with <tt>breakBefore="true"</tt> starts a new page,
+/// as if a <tt>&lt;pageBreak/&gt;</tt> came before it.  Cues, scene
+/// headings, transitions, openings and end texts take the attribute
+/// too.
+///
+/// ```
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::golden::page_text;
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head/><body>\
+///            <p>The lights go out.</p>\
+///            <p breakBefore=\"true\">Morning.</p>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// assert_eq!(formatter.body.len(), 3);
+/// assert!(page_text(&formatter.body[1]).contains("The lights go out."));
+/// assert!(page_text(&formatter.body[2]).contains("Morning."));
+/// ```
+///
+/// A paragraph with <tt>keepWithNext="true"</tt> moves to the next
+/// page rather than end the page without the first lines of the
+/// element after it.
+///
+/// ```
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::golden::page_text;
+/// # use batyr::document::reader::Reader;
+/// let paragraphs = |attrs: &str| {
+///     let mut xml = String::from("<screenplay><head/><body>");
+///
+///     for _ in 0..26 {
+///         xml.push_str("<p>Waiting.</p>");
+///     }
+///
+///     xml.push_str(&format!("<p {}>The door opens.</p>", attrs));
+///     xml.push_str("<cue>EMMA</cue><d>At last.</d></body></screenplay>");
+///
+///     let mut formatter = Formatter::new();
+///     formatter.run(Reader::new(&xml).run().unwrap());
+///     formatter.body.iter().map(page_text).collect::<Vec<_>>()
+/// };
+///
+/// let pages = paragraphs("");
+/// assert!(pages[1].contains("The door opens."));
+/// assert!(pages[2].contains("EMMA"));
+///
+/// let pages = paragraphs("keepWithNext=\"true\"");
+/// assert!(!pages[1].contains("The door opens."));
+/// assert!(pages[2].contains("The door opens."));
+/// assert!(pages[2].contains("EMMA"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct P {
+    /// Number of spaces to indent (default to 0), or if negative, to
+    /// hang the lines that wrap
+    pub indent: i32,
+    /// Full-width column left margin
+    pub left_margin: usize,
+    /// Full-width column right margin
+    pub right_margin: usize,
+    /// Starts a new page before the element
+    pub page_break_before: bool,
+    /// Keeps the element on the same page as the next one
+    pub keep_with_next: bool,
+    /// Break point information for the element immediately following,
+    /// if the element is kept with it
+    pub train: Vec<BreakType>,
     /// Number of blank lines preceding
     pub padding_before: i32,
     /// Number of blank lines following
     pub padding_after: usize,
 }
 
-/// Author's name
-#[derive(Debug)]
-pub struct FullName {}
-
-/// Document head
-#[derive(Debug)]
-pub struct Head {}
+impl TextElement<P> {
+    /// Returns the hanging indent of the lines that wrap
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::formatter::Formatter;
+    /// # use batyr::document::golden::page_text;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\
+    ///            <p indent=\"-3\">1) Buy milk, eggs, flour, sugar, \
+    ///            butter, and a bag of lemons for the tart.</p>\
+    ///            </body></screenplay>";
+    ///
+    /// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+    ///     panic!();
+    /// };
+    /// let mut formatter = Formatter::new();
+    /// formatter.run(ElementType::Screenplay(root));
+    ///
+    /// let text = page_text(&formatter.body[1]);
+    /// let lines: Vec<&str> = text.lines().filter(|l| !l.is_empty()).collect();
+    ///
+    /// assert_eq!(lines, vec![
+    ///     "                1) Buy milk, eggs, flour, sugar, butter, and a bag of",
+    ///     "                   lemons for the tart.",
+    /// ]);
+    /// ```
+    pub fn hang(&self) -> usize {
+        (-self.attributes.indent).max(0) as usize
+    }
+
+    /// Returns the indent of the first line
+    ///
+    /// The first line is that much narrower than the others, which
+    /// the break points take into account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::formatter::Formatter;
+    /// # use batyr::document::golden::page_text;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\
+    ///            <p indent=\"10\">Emma waits by the kitchen door with her \
+    ///            coat on and her bag packed, listening for the car.</p>\
+    ///            </body></screenplay>";
+    ///
+    /// let mut formatter = Formatter::new();
+    /// formatter.run(Reader::new(xml).run().unwrap());
+    ///
+    /// let text = page_text(&formatter.body[1]);
+    /// let lines: Vec<&str> = text.lines().filter(|l| !l.is_empty()).collect();
+    ///
+    /// assert_eq!(lines, vec![
+    ///     "                          Emma waits by the kitchen door with her coat on",
+    ///     "                and her bag packed, listening for the car.",
+    /// ]);
+    /// ```
+    pub fn first_indent(&self) -> usize {
+        self.attributes.indent.max(0) as usize
+    }
+
+    /// Counts lines for the paragraph based on break point
+    /// information only
+    ///
+    /// See [`breaking::lines_needed`].
+    pub fn count_lines(&self) -> usize {
+        breaking::lines_needed(&self.break_info)
+    }
+
+    /// Selects a break point given the number of lines remaining in
+    /// the page
+    ///
+    /// See [`breaking::best_break_with`].
+    pub fn select_break(&self, lines_remaining: i32,
+                        policy: &breaking::BreakPolicy)
+                        -> BreakType
+    {
+        breaking::best_break_with(&self.break_info, lines_remaining, policy)
+    }
+}
+
+/// Mandatory page break
+#[derive(Debug, Clone)]
+pub struct PageBreak {}
+
+/// Production number for the running page header
+///
+/// See [`Episode`].
+#[derive(Debug, Clone)]
+pub struct ProductionNumber {}
+
+/// Document root
+///
+/// With <tt>autocase="true"</tt>, the reader sets the text of scene
+/// headings, cues, transitions and act openings in capitals, so they
+/// can be typed in any case.  The same attribute on one of those
+/// elements overrides the setting for that element alone.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::formatter::{Formatter, Row};
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay autocase=\"true\"><head/><body>\
+///            <slug>int. McCue's kitchen - day</slug>\
+///            <cue>Emma</cue><d>Late again.</d>\
+///            <trans autocase=\"false\">Cut to:</trans>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let lines: Vec<String> = formatter.body[1].lines.iter()
+///     .filter_map(|row| match row {
+///         Row::Text(line) => Some(line.text()),
+///         _ => None,
+///     })
+///     .collect();
+///
+/// assert_eq!(lines, vec!["INT.  McCUE'S KITCHEN - DAY", "EMMA",
+///                        "Late again.", "Cut to:"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Screenplay {
+    numbering: Numbering,
+    spacing: Spacing,
+    justify: bool,
+    slug_caps: bool,
+    autocase: bool,
+    casing: Casing,
+    profile: Profile,
+    authority: Authority,
+    markers: Markers,
+    lines_before_break: Option<usize>,
+    lines_after_break: Option<usize>,
+    break_after_dir: Option<bool>,
+    version: SchemaVersion,
+    unresolved: Vec<String>,
+    alternates: Vec<Alternates>,
+    references: Vec<(String, Option<i32>)>,
+    control_chars: Vec<(usize, char)>,
+    scene_warnings: Vec<String>,
+    lang: Language,
+}
+
+/// Markers added by the formatter at page breaks
+///
+/// Set with the <tt>moreText</tt>, <tt>contdText</tt> and
+/// <tt>continuedText</tt> attributes of <tt>&lt;screenplay&gt;</tt>,
+/// for productions with house rules of their own; the defaults come
+/// from the [`Language`] of the screenplay.  An empty
+/// <tt>contdText</tt> leaves "(CONT'D)" out entirely.  The
+/// <tt>continuedCount</tt> and <tt>continuedNumbers</tt> attributes
+/// decide what else goes on the "CONTINUED:" line at the top of a
+/// page.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::golden::page_text;
+/// # use batyr::document::reader::Reader;
+/// let mut xml = String::from("<screenplay moreText=\"(PLUS)\" \
+///                             contdText=\"(SUITE)\"><head/><body>\
+///                             <cue>ANNE</cue><d>");
+///
+/// for i in 0..100 {
+///     xml.push_str(&format!("Phrase {} qui dure un moment.  ", i));
+/// }
+///
+/// xml.push_str("</d></body></screenplay>");
+///
+/// let root = Reader::new(&xml).run().unwrap();
+/// let mut formatter = Formatter::new();
+/// formatter.run(root);
+///
+/// let first = page_text(&formatter.body[1]);
+/// let second = page_text(&formatter.body[2]);
+///
+/// assert_eq!(first.lines().last().unwrap().trim(), "(PLUS)");
+/// assert_eq!(second.lines().next().unwrap().trim(), "ANNE (SUITE)");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Markers {
+    /// Bottom of a page that breaks a speech, "(MORE)" by default
+    pub more: String,
+    /// After a repeated cue or a resumed scene heading, "(CONT'D)" by
+    /// default
+    pub contd: String,
+    /// Scene continued across a page break, "CONTINUED" by default
+    pub continued: String,
+    /// When "CONTINUED:" counts the pages of the scene
+    pub continued_count: ContinuedCount,
+    /// Put the scene numbers on the "CONTINUED:" line, as on the slug
+    pub continued_numbers: bool,
+}
+
+impl Default for Markers {
+    fn default() -> Self {
+        Self {
+            more: MORE_TEXT.to_string(),
+            contd: CONTD_TEXT.to_string(),
+            continued: CONTINUED_TEXT.to_string(),
+            continued_count: ContinuedCount::default(),
+            continued_numbers: true,
+        }
+    }
+}
+
+/// When the "CONTINUED:" line at the top of a page counts the pages
+/// of the scene
+///
+/// The count is the number of the continuation page: the page after
+/// the one where the scene begins is the first.  By default the
+/// count starts on the second, as in "CONTINUED: (2)".
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::golden::page_text;
+/// # use batyr::document::reader::Reader;
+/// let top_lines = |attrs: &str| {
+///     let mut xml = format!("<screenplay numbering=\"full\" {}>\
+///                            <head/><body><slug>INT. HALL - DAY</slug>",
+///                           attrs);
+///
+///     for _ in 0..100 {
+///         xml.push_str("<p>Waiting.</p>");
+///     }
+///
+///     xml.push_str("</body></screenplay>");
+///
+///     let mut formatter = Formatter::new();
+///     formatter.run(Reader::new(&xml).run().unwrap());
+///
+///     formatter.body[2..].iter()
+///         .map(|page| {
+///             let text = page_text(page);
+///             let words: Vec<_> = text.lines().next().unwrap()
+///                 .split_whitespace()
+///                 .collect();
+///             words.join(" ")
+///         })
+///         .collect::<Vec<_>>()
+/// };
+///
+/// assert_eq!(top_lines(""),
+///            ["1 CONTINUED: 1", "1 CONTINUED: (2) 1", "1 CONTINUED: (3) 1"]);
+/// assert_eq!(top_lines("continuedCount=\"never\" continuedNumbers=\"false\""),
+///            ["CONTINUED:", "CONTINUED:", "CONTINUED:"]);
+/// assert_eq!(top_lines("continuedCount=\"1\" continuedNumbers=\"false\""),
+///            ["CONTINUED: (1)", "CONTINUED: (2)", "CONTINUED: (3)"]);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ContinuedCount {
+    /// Never counted
+    Never,
+    /// Counted from the given continuation page on
+    From(usize),
+}
+
+impl Default for ContinuedCount {
+    fn default() -> Self {
+        ContinuedCount::From(2)
+    }
+}
+
+impl From<&str> for ContinuedCount {
+    fn from(s: &str) -> Self {
+        match s {
+            "never" => ContinuedCount::Never,
+            _ => match s.parse() {
+                Ok(n) if n > 0 => ContinuedCount::From(n),
+                _ => ContinuedCount::default(),
+            },
+        }
+    }
+}
+
+impl ContinuedCount {
+    /// Returns true if the given continuation page shows its count
+    pub fn shows(&self, page: i32) -> bool {
+        match self {
+            ContinuedCount::Never => false,
+            ContinuedCount::From(n) => page >= *n as i32,
+        }
+    }
+}
 
-/// Scene opening
+/// Language of a screenplay
+///
+/// Set with the <tt>lang</tt> attribute of <tt>&lt;screenplay&gt;</tt>,
+/// as a language tag such as <tt>fr</tt> or <tt>fr-CA</tt>.  The
+/// language chooses the defaults for the typographic settings that
+/// differ from one language to another: the spaces after a sentence,
+/// the capitals and the [`Markers`].  Guillemets in the source are
+/// kept as they are, and other quotes are never turned into them.
+/// Attributes given alongside it, such as <tt>sentenceSpacing</tt> or
+/// <tt>contdText</tt>, still take precedence.  Unknown languages are
+/// treated as English.
 ///
 /// # Examples
 ///
-/// ```xml
-/// <open>FADE IN:</open>
 /// ```
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::golden::page_text;
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay lang=\"fr\" numbering=\"full\"><head/><body>\
+///            <slug>INT. CUISINE - JOUR</slug>\
+///            <p>Anne entre.  Elle dit \u{ab}bonjour\u{bb}.</p>\
+///            <p>Paul dit \u{201c}salut\u{201d}.</p>\
+///            </body></screenplay>";
 ///
-/// Output:
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
 ///
-/// <pre>FADE IN:</pre>
-#[derive(Debug)]
-pub struct Open {
-    /// Column number to begin typing at
-    pub tab_stop: usize,
-    /// Number of blank lines preceding
-    pub padding_before: i32,
-    /// Number of blank lines following
-    pub padding_after: usize,
+/// let text = page_text(&formatter.body[1]);
+/// assert!(text.contains("Anne entre. Elle dit \u{ab}bonjour\u{bb}."));
+///
+/// // Curly quotes are typed as in English, not as guillemets.
+/// assert!(text.contains("Paul dit \"salut\"."));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    French,
+    German,
+    Spanish,
+    Turkish,
 }
 
-/// Title page note
-#[derive(Debug)]
-pub struct Note {}
+impl From<&str> for Language {
+    fn from(s: &str) -> Self {
+        let primary = s.split(['-', '_']).next().unwrap_or("");
+
+        match primary.to_ascii_lowercase().as_str() {
+            "fr" => Language::French,
+            "de" => Language::German,
+            "es" => Language::Spanish,
+            "tr" => Language::Turkish,
+            _ => Language::English,
+        }
+    }
+}
 
-/// Stage direction
-#[derive(Debug)]
-pub struct P {
-    /// Number of spaces to indent (default to 0)
-    pub indent: usize,
-    /// Full-width column left margin
-    pub left_margin: usize,
-    /// Full-width column right margin
-    pub right_margin: usize,
-    /// Number of blank lines preceding
-    pub padding_before: i32,
-    /// Number of blank lines following
-    pub padding_after: usize,
+impl Language {
+    /// Returns the spaces after a sentence: two in English, after the
+    /// typewriter, and one elsewhere
+    pub fn sentence_spacing(&self) -> SentenceSpacing {
+        match self {
+            Language::English => SentenceSpacing::Double,
+            _ => SentenceSpacing::Single,
+        }
+    }
+
+    /// Returns the uppercasing policy
+    pub fn casing(&self) -> Casing {
+        match self {
+            Language::Turkish => Casing::Turkic,
+            _ => Casing::Default,
+        }
+    }
+
+    /// Returns the marker text for page breaks
+    pub fn markers(&self) -> Markers {
+        let (more, contd, continued) = match self {
+            Language::English => return Markers::default(),
+            Language::French => ("(PLUS)", "(SUITE)", "SUITE"),
+            Language::German => ("(MEHR)", "(FORTS.)", "FORTSETZUNG"),
+            Language::Spanish => ("(MÁS)", "(CONT.)", "CONTINÚA"),
+            Language::Turkish => ("(DEVAMI)", "(DEVAM)", "DEVAM"),
+        };
+
+        Markers {
+            more: more.to_string(),
+            contd: contd.to_string(),
+            continued: continued.to_string(),
+            ..Markers::default()
+        }
+    }
 }
 
-impl TextElement<P> {
-    pub fn count_lines(&self) -> usize {
-        match &self.break_info {
-            BreakType::None => 0,
-            BreakType::Mandatory => 0,
-            BreakType::Forbidden(n) => *n,
-            BreakType::Atomic(n) => *n,
-            BreakType::Disposable(n) => *n,
-            BreakType::Point(break_point) => break_point.line_no,
-            BreakType::List(break_points) => {
-                if let Some(break_point) = break_points.last() {
-                    break_point.line_no
-                } else {
-                    0
+/// Variant lines of dialogue from an <tt>&lt;alt&gt;</tt> group
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alternates {
+    /// Character speaking the lines
+    pub cue: String,
+    /// Variant that is typeset, counting from 1
+    pub chosen: usize,
+    /// Text of each variant
+    pub variants: Vec<String>,
+}
+
+impl ContainerElement<Screenplay> {
+    /// Checks the document head for required elements
+    ///
+    /// Returns a hint for each required element that is missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head><title>FOO</title></head><body/></screenplay>";
+    ///
+    /// match Reader::new(xml).run() {
+    ///     Some(ElementType::Screenplay(root)) => {
+    ///         let hints = root.check_head();
+    ///         assert_eq!(hints.len(), 1);
+    ///         assert!(hints[0].contains("<authors>"));
+    ///     },
+    ///     _ => panic!(),
+    /// }
+    /// ```
+    pub fn check_head(&self) -> Vec<String> {
+        let mut hints: Vec<String> = Vec::new();
+
+        let head = self.children.iter().find_map(|child| match child {
+            ElementType::Head(elem) => Some(elem),
+            _ => None,
+        });
+
+        let head = match head {
+            Some(head) => head,
+            None => {
+                hints.push("missing <head>: add <head> with <title> and \
+                            <authors> before <body>".to_string());
+                return hints;
+            },
+        };
+
+        let has_title = head.children.iter()
+            .any(|child| matches!(child, ElementType::Title(_)));
+
+        if !has_title {
+            hints.push("missing <title>: add <title> under <head>".to_string());
+        }
+
+        let authors = head.children.iter().find_map(|child| match child {
+            ElementType::Authors(elem) => Some(elem),
+            _ => None,
+        });
+
+        match authors {
+            Some(authors) => {
+                let has_full_name = authors.children.iter()
+                    .any(|child| matches!(child, ElementType::FullName(_)));
+
+                if !has_full_name {
+                    hints.push("missing <fullName>: add at least one \
+                                <fullName> under <authors>".to_string());
                 }
             },
+            None => {
+                hints.push("missing <authors>: add <authors> with one \
+                            <fullName> per author under <head>".to_string());
+            },
         }
+
+        hints
     }
 
-    pub fn select_break(&self, lines_remaining: i32) -> BreakType {
-        let total_height = self.count_lines();
+    /// Checks that the document has a body with something in it
+    ///
+    /// Returns a hint if the body is missing or empty.  An empty body
+    /// is still formatted, to a title page alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::formatter::Formatter;
+    /// # use batyr::document::reader::Reader;
+    /// for xml in ["<screenplay><head/><body/></screenplay>",
+    ///             "<screenplay><head/><body></body></screenplay>",
+    ///             "<screenplay><head/></screenplay>"]
+    /// {
+    ///     let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+    ///         panic!();
+    ///     };
+    ///     assert!(root.check_body().is_some());
+    ///
+    ///     let mut formatter = Formatter::new();
+    ///     formatter.run(ElementType::Screenplay(root));
+    ///     assert_eq!(formatter.body.len(), 1);
+    /// }
+    ///
+    /// let xml = "<screenplay><head/><body><slug>INT. HOUSE - DAY</slug></body></screenplay>";
+    /// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+    ///     panic!();
+    /// };
+    /// assert!(root.check_body().is_none());
+    /// ```
+    pub fn check_body(&self) -> Option<String> {
+        let body = self.children.iter().find_map(|child| match child {
+            ElementType::Body(elem) => Some(elem),
+            _ => None,
+        });
 
-        if total_height as i32 <= lines_remaining { // entire paragraph fits
-            return BreakType::None;
+        match body {
+            Some(body) if !body.children.is_empty() => None,
+            Some(_) => Some("empty <body>: add at least one scene heading \
+                             or paragraph".to_string()),
+            None => Some("missing <body>: add <body> after <head>".to_string()),
         }
+    }
+
+    /// Checks transitions against the list of known ones
+    ///
+    /// Accepts the built-in [`TRANSITIONS`] and any extras given.
+    /// Returns a warning for each transition that is not on either
+    /// list, suggesting the closest known one if it is only a typo
+    /// away.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\
+    ///            <trans>CUTT TO:</trans>\
+    ///            <trans>SMASH CUT TO:</trans>\
+    ///            <trans>IRIS OUT.</trans>\
+    ///            </body></screenplay>";
+    ///
+    /// match Reader::new(xml).run() {
+    ///     Some(ElementType::Screenplay(root)) => {
+    ///         let warnings = root.check_transitions(&[]);
+    ///         assert_eq!(warnings.len(), 2);
+    ///         assert!(warnings[0].contains("did you mean \"CUT TO:\""));
+    ///
+    ///         let extra = vec!["IRIS OUT.".to_string()];
+    ///         assert_eq!(root.check_transitions(&extra).len(), 1);
+    ///     },
+    ///     _ => panic!(),
+    /// }
+    /// ```
+    pub fn check_transitions(&self, extra: &[String]) -> Vec<String> {
+        without_positions(self.transition_warnings(extra))
+    }
 
-        let mut prev_break_info: BreakType = BreakType::None;
+    /// Checks transitions like [`Screenplay::check_transitions`],
+    /// keeping the position of each one in the body
+    fn transition_warnings(&self, extra: &[String]) -> Vec<(usize, String)> {
+        let mut warnings: Vec<(usize, String)> = Vec::new();
 
-        match &self.break_info {
-            BreakType::Atomic(n) => {
-                if *n as i32 <= lines_remaining {
-                    if prev_break_info == BreakType::None {
-                        return BreakType::Mandatory;
-                    } else {
-                        return prev_break_info;
-                    }
-                } else {
-                    return BreakType::Mandatory;
-                }
-            },
-            BreakType::List(break_points) => {
-                for break_point in break_points.iter() {
-                    if break_point.line_no as i32 > lines_remaining {
-                        if prev_break_info == BreakType::None {
-                            return BreakType::Mandatory;
-                        } else {
-                            return prev_break_info;
-                        }
-                    }
+        let known: Vec<&str> = TRANSITIONS.iter().copied()
+            .chain(extra.iter().map(|s| s.as_str()))
+            .collect();
+
+        let body = self.children.iter().find_map(|child| match child {
+            ElementType::Body(elem) => Some(elem),
+            _ => None,
+        });
+
+        let body = match body {
+            Some(body) => body,
+            None => return warnings,
+        };
+
+        for (i, child) in body.children.iter().enumerate() {
+            let elem = match child {
+                ElementType::Trans(elem) => elem,
+                _ => continue,
+            };
+
+            let text = elem.text();
+
+            if known.contains(&text.as_str()) {
+                continue;
+            }
+
+            let closest = known.iter()
+                .map(|k| (edit_distance(&text, k), k))
+                .min_by_key(|(n, _)| *n);
+
+            match closest {
+                Some((n, k)) if n <= 2 => {
+                    warnings.push((i, format!(
+                        "unknown transition \"{}\": did you mean \"{}\"?",
+                        text, k
+                    )));
+                },
+                _ => {
+                    warnings.push((i, format!(
+                        "unknown transition \"{}\": add it to the list of \
+                         transitions if it is intended", text
+                    )));
+                },
+            }
+        }
+
+        warnings
+    }
+
+    /// Checks the document against the reference book it names in
+    /// its <tt>authority</tt> attribute
+    ///
+    /// Returns a warning for each transition whose alignment departs
+    /// from the book.  Documents that name no book are not checked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay authority=\"coleHaag\"><head/><body>\
+    ///            <trans>CUT TO:</trans>\
+    ///            <trans align=\"right\">FADE OUT.</trans>\
+    ///            </body></screenplay>";
+    ///
+    /// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+    ///     panic!();
+    /// };
+    /// let warnings = root.check_authority();
+    ///
+    /// assert_eq!(warnings, vec!["transition \"FADE OUT.\" hangs from the \
+    ///                            right margin, but Cole and Haag sets it at \
+    ///                            the tab stop"]);
+    /// ```
+    pub fn check_authority(&self) -> Vec<String> {
+        without_positions(self.authority_warnings())
+    }
 
-                    prev_break_info = BreakType::Point(break_point.clone());
+    /// Checks alignments like [`Screenplay::check_authority`],
+    /// keeping the position of each element in the body
+    fn authority_warnings(&self) -> Vec<(usize, String)> {
+        let mut warnings: Vec<(usize, String)> = Vec::new();
+        let authority = self.attributes.authority;
+
+        if authority == Authority::None {
+            return warnings;
+        }
+
+        let describe = |align: Alignment| match align {
+            Alignment::Tab => "at the tab stop",
+            Alignment::Left => "at the left margin",
+            Alignment::Center => "centered",
+            Alignment::Right => "from the right margin",
+        };
+
+        let body = self.children.iter().find_map(|child| match child {
+            ElementType::Body(elem) => Some(elem),
+            _ => None,
+        });
+
+        let children = body.into_iter().flat_map(|body| body.children.iter());
+
+        for (i, child) in children.enumerate() {
+            if let ElementType::Trans(elem) = child {
+                let expected = authority.trans_align();
+
+                if elem.attributes.align != expected {
+                    let verb = match elem.attributes.align {
+                        Alignment::Right => "hangs",
+                        _ => "is set",
+                    };
+
+                    warnings.push((i, format!(
+                        "transition \"{}\" {} {}, but {} sets it {}",
+                        elem.text(), verb, describe(elem.attributes.align),
+                        authority.as_str(), match expected {
+                            Alignment::Right => "flush right",
+                            align => describe(align),
+                        }
+                    )));
                 }
-            },
-            _ => (),
+            }
         }
 
-        BreakType::Mandatory
+        warnings
     }
-}
 
-/// Mandatory page break
-#[derive(Debug)]
-pub struct PageBreak {}
+    /// Runs all the checks of <tt>--lint</tt>
+    ///
+    /// Returns the warnings of [`Screenplay::check_transitions`] and
+    /// [`Screenplay::check_authority`] together, in the order of the
+    /// elements they concern.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay authority=\"coleHaag\"><head/><body>\
+    ///            <trans align=\"right\">FADE OUT.</trans>\
+    ///            <trans>CUTT TO:</trans>\
+    ///            </body></screenplay>";
+    ///
+    /// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+    ///     panic!();
+    /// };
+    /// let warnings = root.check_lint(&[]);
+    ///
+    /// assert_eq!(warnings.len(), 2);
+    /// assert!(warnings[0].starts_with("transition \"FADE OUT.\""));
+    /// assert!(warnings[1].starts_with("unknown transition \"CUTT TO:\""));
+    /// ```
+    pub fn check_lint(&self, extra: &[String]) -> Vec<String> {
+        let mut warnings = self.transition_warnings(extra);
+        warnings.extend(self.authority_warnings());
+        warnings.sort_by_key(|(i, _)| *i);
+        without_positions(warnings)
+    }
 
-/// Document root
-#[derive(Debug)]
-pub struct Screenplay {
-    numbering: Numbering,
-}
+    /// Returns the names of variables that were referenced in the
+    /// text but never defined
+    pub fn unresolved_variables(&self) -> &[String] {
+        &self.attributes.unresolved[..]
+    }
+
+    /// Returns the scene numbers named by <tt>&lt;ref&gt;</tt>
+    /// elements, each with the page it was resolved to, if any
+    ///
+    /// See [`Reader::scene_pages`](crate::document::reader::Reader::scene_pages).
+    pub fn references(&self) -> &[(String, Option<i32>)] {
+        &self.attributes.references[..]
+    }
+
+    /// Returns the control characters stripped from the text, each
+    /// with the line of the source where it appeared
+    ///
+    /// See [`is_stripped_control`](crate::text::parser::is_stripped_control).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\n\
+    ///            <p>Ding!\u{0007}</p>\n\
+    ///            <p>Next\u{000c}page.</p>\n\
+    ///            </body></screenplay>";
+    ///
+    /// match Reader::new(xml).run().unwrap() {
+    ///     ElementType::Screenplay(root) => {
+    ///         assert_eq!(root.control_characters(),
+    ///                    [(2, '\u{0007}'), (3, '\u{000c}')]);
+    ///     },
+    ///     _ => panic!(),
+    /// }
+    /// ```
+    pub fn control_characters(&self) -> &[(usize, char)] {
+        &self.attributes.control_chars[..]
+    }
+
+    /// Returns a warning for each scene whose number repeats an
+    /// earlier one or goes back, which throws off the scene report
+    /// and the CONTINUED labels
+    ///
+    /// See [`Reader::resolve_scene_numbers`](crate::document::reader::Reader::resolve_scene_numbers).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\n\
+    ///            <slug number=\"12\">INT. HALL - DAY</slug>\n\
+    ///            <slug number=\"10\">INT. STAIRS - DAY</slug>\n\
+    ///            <slug>INT. ATTIC - DAY</slug>\n\
+    ///            </body></screenplay>";
+    ///
+    /// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+    ///     panic!();
+    /// };
+    ///
+    /// assert_eq!(root.scene_number_warnings(),
+    ///            ["scene 10 on line 3 comes after scene 12",
+    ///             "scene 11 on line 4 comes after scene 12"]);
+    /// ```
+    pub fn scene_number_warnings(&self) -> &[String] {
+        &self.attributes.scene_warnings[..]
+    }
+
+    /// Returns the <tt>&lt;alt&gt;</tt> groups in document order
+    pub fn alternates(&self) -> &[Alternates] {
+        &self.attributes.alternates[..]
+    }
+
+    /// Returns the marker text for page breaks
+    pub fn markers(&self) -> &Markers {
+        &self.attributes.markers
+    }
+
+    /// Returns the language of the screenplay
+    pub fn language(&self) -> Language {
+        self.attributes.lang
+    }
+
+    /// Returns the schema version the document is written to
+    pub fn version(&self) -> SchemaVersion {
+        self.attributes.version
+    }
 
-impl ContainerElement<Screenplay> {
     pub fn body(&mut self) -> Option<&mut ContainerElement<Body>> {
         for child in self.children.iter_mut() {
             match child {
@@ -858,7 +3535,7 @@ impl ContainerElement<Screenplay> {
 }
 
 /// Series name
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Series {
     /// Narrow column left margin
     pub left_margin: usize,
@@ -886,7 +3563,80 @@ pub struct Series {
 /// Output:
 ///
 /// <pre>EXT. OFFICE BUILDING - CLOSE ANGLE - ENTRANCE - DAY</pre>
-#[derive(Debug)]
+///
+/// A scene that resumes after an act break keeps its number and gets
+/// "(CONT'D)" after the heading.  It does not count as a new scene.
+///
+/// ```
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay numbering=\"full\"><head/><body>\
+///            <act>ACT ONE</act>\
+///            <slug>INT. KITCHEN - DAY</slug><p>Emma waits.</p>\
+///            <act>ACT TWO</act>\
+///            <slug continued=\"true\">INT. KITCHEN - DAY</slug>\
+///            <p>Still waiting.</p>\
+///            <slug>EXT. STREET - DAY</slug><p>Thomas runs.</p>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// assert_eq!(formatter.scenes.len(), 2);
+/// assert_eq!(formatter.scenes[0].page_count(), 2);
+/// assert_eq!(formatter.scenes[1].number, "2");
+/// ```
+///
+/// A scene with <tt>numbered="false"</tt>, such as a title card,
+/// prints without a number and leaves the numbering of the other
+/// scenes alone.
+///
+/// ```
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay numbering=\"full\"><head/><body>\
+///            <slug>INT. KITCHEN - DAY</slug><p>Emma waits.</p>\
+///            <slug numbered=\"false\">TITLE CARD</slug><p>Years later.</p>\
+///            <slug>EXT. STREET - DAY</slug><p>Thomas runs.</p>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let numbers: Vec<&str> = formatter.scenes.iter()
+///     .map(|scene| scene.number.as_str())
+///     .collect();
+///
+/// assert_eq!(numbers, ["1", "", "2"]);
+/// ```
+///
+/// A suffix such as "- FLASHBACK" is set after the heading once it
+/// has been broken into lines.  It is not part of the location, so a
+/// flashback shares its set with the scenes in the same place.
+///
+/// ```
+/// # use batyr::document::formatter::{Formatter, Row};
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay profile=\"multicam\"><head/><body>\
+///            <slug>INT. KITCHEN - DAY</slug><p>Emma waits.</p>\
+///            <slug suffix=\"- FLASHBACK\">INT. KITCHEN - NIGHT</slug>\
+///            <p>Emma, younger, waits.</p>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let text: Vec<Vec<String>> = formatter.body.iter()
+///     .map(|page| page.lines.iter().filter_map(|row| match row {
+///         Row::Text(line) => Some(line.text()),
+///         _ => None,
+///     }).collect())
+///     .collect();
+///
+/// assert_eq!(text[2], vec!["SET LIST", "INT.  KITCHEN"]);
+/// assert!(text[3].contains(&"INT.  KITCHEN - NIGHT - FLASHBACK".to_string()));
+/// ```
+#[derive(Debug, Clone)]
 pub struct Slug {
     /// Scene number
     pub number: i32,
@@ -898,56 +3648,186 @@ pub struct Slug {
     pub left_margin: usize,
     /// Full-width column right margin
     pub right_margin: usize,
+    /// Starts a new page before the element
+    pub page_break_before: bool,
     /// Number of blank lines preceding
     pub padding_before: i32,
     /// Number of blank lines following
     pub padding_after: usize,
     /// Numbering setting
     pub numbering: Numbering,
+    /// Resumes the previous scene, as after an act break
+    pub continued: bool,
+    /// Takes a scene number; false for a title card or the like
+    pub numbered: bool,
+    /// Tag set after the heading, such as "- FLASHBACK"
+    pub suffix: Vec<TokenType>,
 }
 
 impl TextElement<Slug> {
+    /// Returns the scene number with its addition, if any, such as
+    /// <tt>12A</tt>, or nothing for an unnumbered scene
+    pub fn label(&self) -> String {
+        if !self.attributes.numbered {
+            return String::new();
+        }
+
+        match self.attributes.addition {
+            Some(c) => format!("{}{}", self.attributes.number, c),
+            None => format!("{}", self.attributes.number),
+        }
+    }
+
+    /// Breaks the heading into lines and sets the suffix after it
+    ///
+    /// The suffix goes at the end of the last line if there is room,
+    /// and on a line of its own otherwise.  It is never split.
+    pub fn lines(&self) -> Vec<text::Line> {
+        let w = self.attributes.right_margin - self.attributes.left_margin + 1;
+        let mut lines = text::linebreak_fill(&self.tokens[..], w);
+
+        if self.attributes.suffix.is_empty() {
+            return lines;
+        }
+
+        let suffix = text::Line::from(&self.attributes.suffix[..]);
+
+        match lines.last_mut() {
+            Some(line) if line.length() + 1 + suffix.length() <= w => {
+                line.segments.push(text::Segment::from(" "));
+                line.segments.extend(suffix.segments);
+            },
+            _ => lines.push(suffix),
+        }
+
+        lines
+    }
+
     /// Counts the number of lines until the first valid break
     /// following the Slug element
     pub fn lines_to_first_break(&self) -> usize {
-        let mut line_count: usize = 1;
+        lines_to_first_break(&self.break_info, self.attributes.padding_after,
+                             &self.attributes.train[..])
+    }
+}
 
-        if let BreakType::Forbidden(n) = self.break_info {
-            line_count += n + self.attributes.padding_after;
-        }
+/// Shot heading within a scene
+///
+/// Set like a slug line, but it is not numbered, and it neither ends
+/// a scene nor starts one.  Use it to call for a camera angle or a
+/// detail, such as "ANGLE ON GEORGE" or "CLOSE ON THE LETTER".
+///
+/// # Examples
+///
+/// ```xml
+/// <shot>CLOSE ON THE LETTER</shot>
+/// ```
+///
+/// Output:
+///
+/// <pre>CLOSE ON THE LETTER</pre>
+///
+/// ```
+/// # use batyr::document::formatter::{Formatter, Row};
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay numbering=\"full\"><head/><body>\
+///            <slug>INT. KITCHEN - DAY</slug><p>Emma waits.</p>\
+///            <shot>CLOSE ON THE LETTER</shot><p>It is unopened.</p>\
+///            <slug>EXT. STREET - DAY</slug><p>Thomas runs.</p>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let text: Vec<String> = formatter.body[1].lines.iter()
+///     .filter_map(|row| match row {
+///         Row::Text(line) => Some(line.text()),
+///         _ => None,
+///     })
+///     .collect();
+///
+/// assert_eq!(formatter.scenes.len(), 2);
+/// assert_eq!(formatter.scenes[1].number, "2");
+/// assert!(text.contains(&"CLOSE ON THE LETTER".to_string()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Shot {
+    /// Break point information for the element immediately following
+    /// the Shot
+    pub train: Vec<BreakType>,
+    /// Full-width column left margin
+    pub left_margin: usize,
+    /// Full-width column right margin
+    pub right_margin: usize,
+    /// Starts a new page before the element
+    pub page_break_before: bool,
+    /// Number of blank lines preceding
+    pub padding_before: i32,
+    /// Number of blank lines following
+    pub padding_after: usize,
+}
 
-        for break_info in self.attributes.train.iter() {
-            match break_info {
-                BreakType::None => (),
-                BreakType::Mandatory => {
-                    return line_count;
-                },
-                BreakType::Forbidden(h) => {
-                    line_count += h;
-                },
-                BreakType::Atomic(h) => {
-                    return line_count + h;
-                },
-                BreakType::Disposable(_) => {
-                    return line_count;
-                },
-                BreakType::Point(break_point) => {
+impl TextElement<Shot> {
+    /// Breaks the heading into lines
+    pub fn lines(&self) -> Vec<text::Line> {
+        let w = self.attributes.right_margin - self.attributes.left_margin + 1;
+        text::linebreak_fill(&self.tokens[..], w)
+    }
+
+    /// Counts the number of lines until the first valid break
+    /// following the Shot element
+    pub fn lines_to_first_break(&self) -> usize {
+        lines_to_first_break(&self.break_info, self.attributes.padding_after,
+                             &self.attributes.train[..])
+    }
+}
+
+/// Counts the lines of a heading and what must follow it on the same
+/// page, up to the first valid break in its train
+fn lines_to_first_break(break_info: &BreakType, padding_after: usize,
+                        train: &[BreakType]) -> usize
+{
+    let mut line_count: usize = 1;
+
+    if let BreakType::Forbidden(n) = break_info {
+        line_count += n + padding_after;
+    }
+
+    for break_info in train.iter() {
+        match break_info {
+            BreakType::None => (),
+            BreakType::Mandatory => {
+                return line_count;
+            },
+            BreakType::Forbidden(h) => {
+                line_count += h;
+            },
+            BreakType::Atomic(h) => {
+                return line_count + h;
+            },
+            BreakType::Disposable(_) => {
+                return line_count;
+            },
+            BreakType::Point(break_point) => {
+                return line_count + break_point.line_no;
+            },
+            BreakType::List(break_points) => {
+                if let Some(break_point) = break_points.first() {
                     return line_count + break_point.line_no;
-                },
-                BreakType::List(break_points) => {
-                    if let Some(break_point) = break_points.first() {
-                        return line_count + break_point.line_no;
-                    }
-                },
-            }
+                }
+            },
         }
-
-        line_count
     }
+
+    line_count
 }
 
+/// Source material credit, such as "Based on the novel by ..."
+#[derive(Debug, Clone)]
+pub struct Source {}
+
 /// Document title
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Title {
     /// Narrow column left margin
     pub left_margin: usize,
@@ -982,16 +3862,93 @@ pub struct Title {
 /// Output:
 ///
 /// <pre style="text-align: right;">FADE OUT.</pre>
-#[derive(Debug)]
+///
+/// To hang a short transition from the right margin:
+///
+/// ```xml
+/// <trans align="right">SMASH CUT TO:</trans>
+/// ```
+#[derive(Debug, Clone)]
 pub struct Trans {
     /// Column number to begin typing at
     pub tab_stop: usize,
     /// If the transition text is longer than <tt>right_margin -
     /// tab_stop + 1</tt>, hang the text from the right margin.
     pub right_margin: usize,
+    /// Right alignment forces the text to hang from the right margin
+    pub align: Alignment,
+    /// Starts a new page before the element
+    pub page_break_before: bool,
     /// Number of blank lines preceding
     pub padding_before: i32,
     /// Number of blank lines following
     pub padding_after: usize,
 }
 
+/// Drops the positions that warnings were sorted by
+fn without_positions(warnings: Vec<(usize, String)>) -> Vec<String> {
+    warnings.into_iter().map(|(_, warning)| warning).collect()
+}
+
+/// Counts the single-character insertions, deletions and
+/// substitutions needed to turn one string into another
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Underline
+///
+/// Where [`Em`] marks emphasis, <tt>&lt;u&gt;</tt> asks for an
+/// underline as such.  Its tokens carry [`DisplayFlags::UL`].
+/// Underline, bold and emphasis may be nested inside one another in
+/// any order, and the flags add up.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::formatter::{Formatter, Row};
+/// # use batyr::document::reader::Reader;
+/// # use batyr::text::tokens::DisplayFlags;
+/// let xml = "<screenplay><head/><body>\
+///            <p>A <u>sign</u> reads <b>KEEP <u>OUT</u></b>.</p>\
+///            </body></screenplay>";
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(xml).run().unwrap());
+///
+/// let line = formatter.body[1].lines.iter()
+///     .find_map(|row| match row {
+///         Row::Text(line) if line.text().starts_with("A ") => Some(line),
+///         _ => None,
+///     })
+///     .unwrap();
+///
+/// let segments: Vec<(&str, DisplayFlags, &str)> = line.segments.iter()
+///     .map(|x| (x.text.as_str(), x.dpy, x.ps.as_str()))
+///     .collect();
+///
+/// assert_eq!(segments, vec![
+///     ("A ", DisplayFlags::empty(), "(A ) show "),
+///     ("sign", DisplayFlags::UL, "(sign) ushow "),
+///     (" reads ", DisplayFlags::empty(), "( reads ) show "),
+///     ("KEEP ", DisplayFlags::BOLD, "(KEEP ) bshow "),
+///     ("OUT", DisplayFlags::BOLD | DisplayFlags::UL, "(OUT) ubshow "),
+///     (".", DisplayFlags::empty(), "(.) show "),
+/// ]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct U {}
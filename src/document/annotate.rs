@@ -0,0 +1,112 @@
+// Batyr Page Annotation
+// Copyright (C) 2023 Gene Yu
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Page numbers written back into the source
+//!
+//! After formatting, the page where each scene heading and character
+//! cue fell is known.  [`annotate_pages`] copies the source XML with
+//! a <tt>page</tt> attribute added to every <tt>&lt;slug&gt;</tt> and
+//! <tt>&lt;cue&gt;</tt> start tag.  Everything else, including
+//! comments and whitespace, is copied byte for byte, so the copy can
+//! be diffed against the original or typeset again.
+
+use quick_xml::events::Event;
+use regex::Regex;
+
+use crate::document::formatter::Formatter;
+
+/// Copies a screenplay with page numbers on slugs and cues
+///
+/// The source must be the document the formatter was run on, read
+/// for the same target, so that the tags line up with the elements.
+/// An existing <tt>page</tt> attribute is replaced.  Elements left
+/// out for the target are copied unchanged, as are any tags past the
+/// last element the formatter saw.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::annotate::annotate_pages;
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::reader::Reader;
+/// let xml = "<screenplay><head/><body>\
+///            <slug page=\"9\">INT. HOUSE - DAY</slug>\
+///            <cue>AMY</cue><d>Hi.</d></body></screenplay>";
+///
+/// let root = Reader::new(xml).try_run().unwrap().unwrap();
+/// let mut formatter = Formatter::new();
+/// formatter.run(root);
+///
+/// assert_eq!(
+///     annotate_pages(xml, None, &formatter),
+///     "<screenplay><head/><body>\
+///      <slug page=\"1\">INT. HOUSE - DAY</slug>\
+///      <cue page=\"1\">AMY</cue><d>Hi.</d></body></screenplay>"
+/// );
+/// ```
+pub fn annotate_pages(source: &str, target: Option<&str>,
+                      formatter: &Formatter) -> String
+{
+    let page_attr = Regex::new(r#"\s+page\s*=\s*("[^"]*"|'[^']*')"#).unwrap();
+
+    let mut xml_reader = quick_xml::Reader::from_str(source);
+    let mut slug_pages = formatter.slug_pages.iter();
+    let mut cue_pages = formatter.cue_pages.iter();
+
+    let mut result = String::with_capacity(source.len());
+    let mut copied = 0;
+
+    loop {
+        let start = xml_reader.buffer_position();
+
+        let event = match xml_reader.read_event() {
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(Event::Start(event)) => event,
+            Ok(_) => continue,
+        };
+
+        let excluded = event.try_get_attribute("when").ok().flatten()
+            .and_then(|attr| attr.unescape_value().ok().map(|x| x.to_string()))
+            .is_some_and(|when| match target {
+                Some(target) => !when.split_whitespace().any(|x| x == target),
+                None => false,
+            });
+
+        if excluded {
+            let _ = xml_reader.read_to_end(event.name());
+            continue;
+        }
+
+        let page = match event.local_name().into_inner() {
+            b"slug" => slug_pages.next(),
+            b"cue" => cue_pages.next(),
+            _ => None,
+        };
+
+        if let Some(page) = page {
+            let end = xml_reader.buffer_position();
+            let tag = page_attr.replace_all(&source[start..end - 1], "");
+
+            result.push_str(&source[copied..start]);
+            result.push_str(&format!("{} page=\"{}\">", tag, page));
+            copied = end;
+        }
+    }
+
+    result.push_str(&source[copied..]);
+    result
+}
@@ -0,0 +1,711 @@
+// Batyr Page Breaking
+// Copyright (C) 2023 Gene Yu
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Pagination rules
+//!
+//! The reader computes [`BreakType`] information for each element,
+//! and the formatter consults the functions in this module to decide
+//! where a page break may fall.  There are two kinds of input:
+//!
+//! * A single element, such as a stage direction paragraph, is
+//!   described by one [`BreakType`].
+//!
+//! * A character cue is followed by a _train_ of dialogue and
+//!   personal direction elements, described by a slice of
+//!   [`BreakType`]s.  The cue itself always occupies the first line,
+//!   and a break inside the train requires extra lines at the bottom
+//!   of the page for "(MORE)" ([`MORE_LINES`] by default).  Personal
+//!   direction is [`BreakType::Forbidden`], so it is never chosen as
+//!   a break: a parenthetical always moves to the next page together
+//!   with the dialogue that follows it.
+//!
+//! The functions are free of formatter state, so alternative
+//! formatters can reuse the exact rules.
+
+use crate::document::*;
+use crate::text;
+
+/// The fewest lines that must remain on a page for a cue to start
+/// there, so the cue is never orphaned from its dialogue
+pub const CUE_ORPHAN_THRESHOLD: usize = 2;
+
+/// The number of lines reserved at the bottom of the page for
+/// "(MORE)" when a cue's train is broken
+pub const MORE_LINES: usize = 1;
+
+/// The fewest blank lines between the last content and the text of
+/// an <tt>&lt;end&gt;</tt> element
+pub const END_GAP: usize = 1;
+
+/// The number of lines reserved at the bottom of the page for
+/// "(CONTINUED)" when a page break falls inside a numbered scene
+pub const CONTINUED_LINES: usize = 2;
+
+/// Rules for where a page break may fall inside a paragraph or a
+/// speech
+///
+/// A break inside stage direction or dialogue must leave at least
+/// <tt>min_lines_before</tt> lines of it on the page and carry at
+/// least <tt>min_lines_after</tt> lines over to the next.  The
+/// defaults allow any break between sentences.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::*;
+/// # use batyr::document::breaking::{best_break_with, BreakPolicy};
+/// let break_points = vec![
+///     BreakPoint { token_index: 4, discard_flag: true, line_no: 1 },
+///     BreakPoint { token_index: 9, discard_flag: true, line_no: 3 },
+///     BreakPoint { token_index: 14, discard_flag: false, line_no: 4 },
+/// ];
+/// let break_info = BreakType::List(break_points);
+///
+/// let policy = BreakPolicy::default();
+/// assert!(matches!(best_break_with(&break_info, 3, &policy),
+///                  BreakType::Point(BreakPoint { line_no: 3, .. })));
+///
+/// // No widows: two lines must follow the break.
+/// let policy = BreakPolicy { min_lines_after: 2, ..Default::default() };
+/// assert!(matches!(best_break_with(&break_info, 3, &policy),
+///                  BreakType::Point(BreakPoint { line_no: 1, .. })));
+///
+/// // No orphans: two lines must stay before the break.
+/// let policy = BreakPolicy { min_lines_before: 2, ..Default::default() };
+/// assert_eq!(best_break_with(&break_info, 2, &policy), BreakType::Mandatory);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BreakPolicy {
+    /// The fewest lines that must remain on a page for a cue to
+    /// start there
+    pub cue_orphan_threshold: usize,
+    /// Lines reserved at the bottom of the page for "(MORE)" when
+    /// dialogue is broken
+    pub more_lines: usize,
+    /// Lines reserved at the bottom of the page for "(CONTINUED)"
+    /// when a scene is broken
+    pub continued_lines: usize,
+    /// The fewest lines of a broken paragraph or speech left on the
+    /// page before the break
+    pub min_lines_before: usize,
+    /// The fewest lines of a broken paragraph or speech carried over
+    /// to the next page
+    pub min_lines_after: usize,
+    /// Allow a break right after personal direction, rather than
+    /// always moving it to the next page with the dialogue that
+    /// follows
+    pub break_after_dir: bool,
+}
+
+impl Default for BreakPolicy {
+    fn default() -> Self {
+        BreakPolicy {
+            cue_orphan_threshold: CUE_ORPHAN_THRESHOLD,
+            more_lines: MORE_LINES,
+            continued_lines: CONTINUED_LINES,
+            min_lines_before: 1,
+            min_lines_after: 1,
+            break_after_dir: false,
+        }
+    }
+}
+
+impl BreakPolicy {
+    /// Returns true if a break may leave the given number of lines
+    /// before it on the page and carry the rest over
+    pub fn allows(&self, before: usize, after: usize) -> bool {
+        before >= self.min_lines_before && after >= self.min_lines_after
+    }
+}
+
+/// Finds the points in a run of dialogue where a page break may fall
+///
+/// Breaks are allowed at the end of a sentence and after a mandatory
+/// line break.  The last break point is always the end of the text,
+/// so its line number is the total number of lines.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::breaking::find_break_points;
+/// # use batyr::text::parser::Parser;
+/// let tokens = Parser::new("One.  Two.", Vec::new(), Default::default())
+///     .run()
+///     .get_tokens();
+///
+/// let break_points = find_break_points(&tokens[..], 35);
+/// assert_eq!(break_points.len(), 2);
+/// assert_eq!(break_points[1].token_index, tokens.len());
+/// assert_eq!(break_points[1].line_no, 1);
+/// ```
+///
+/// A sentence may end inside quotation marks or brackets.  It gets
+/// the same spacing and the same break point either way:
+///
+/// ```
+/// # use batyr::document::breaking::find_break_points;
+/// # use batyr::text::parser::Parser;
+/// # use batyr::text::tokens::TokenType;
+/// for input in ["Go.  Now.", "\u{201c}Go.\u{201d}  Now.", "\"Go.\"  Now.",
+///               "'Go.'  Now.", "(Go.)  Now."]
+/// {
+///     let tokens = Parser::new(input, Vec::new(), Default::default())
+///         .run()
+///         .get_tokens();
+///     let text: String = tokens.iter().map(TokenType::text).collect();
+///
+///     let break_points = find_break_points(&tokens[..], 35);
+///     let first = break_points[0].token_index;
+///
+///     assert!(text.ends_with("  Now."), "{}", text);
+///     assert_eq!(break_points.len(), 2, "{}", text);
+///     assert_eq!(tokens[first - 1].text(), "  ", "{}", text);
+/// }
+/// ```
+pub fn find_break_points(tokens: &[TokenType], line_length: usize)
+                         -> BreakPointList
+{
+    find_hanging_break_points(tokens, line_length, 0)
+}
+
+/// Finds the break points in a run of text with a hanging indent
+///
+/// Counts lines the way [`text::linebreak_hang`] breaks them, and
+/// otherwise works like [`find_break_points`].
+pub fn find_hanging_break_points(tokens: &[TokenType], line_length: usize,
+                                 hang: usize) -> BreakPointList
+{
+    find_indented_break_points(tokens, line_length, 0, hang)
+}
+
+/// Finds the break points in a run of text with a first-line indent
+/// and a hanging indent
+///
+/// Counts lines the way [`text::linebreak_indent`] breaks them, so
+/// the first line is <tt>indent</tt> columns narrower than the rest.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::breaking::find_indented_break_points;
+/// # use batyr::text::parser::Parser;
+/// let tokens = Parser::new("Emma waits by the door", Vec::new(),
+///                          Default::default())
+///     .run()
+///     .get_tokens();
+///
+/// let flush = find_indented_break_points(&tokens[..], 12, 0, 0);
+/// let indented = find_indented_break_points(&tokens[..], 12, 5, 0);
+///
+/// assert_eq!(flush.last().unwrap().line_no, 2);
+/// assert_eq!(indented.last().unwrap().line_no, 3);
+/// ```
+pub fn find_indented_break_points(tokens: &[TokenType], line_length: usize,
+                                  indent: usize, hang: usize)
+                                  -> BreakPointList
+{
+    let mut break_points: BreakPointList = Vec::new();
+    let reach = text::lookahead(tokens);
+    let mut n: usize = 1; // line count
+    let mut x: usize = indent; // char count
+    let mut at_end_of_sentence: bool = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let frm = token.format_flags();
+
+        if at_end_of_sentence {
+            if let TokenType::Punct(_) = token {
+                at_end_of_sentence = false;
+            }
+        }
+        
+        if frm.intersects(FormatFlags::MLB) {
+            break_points.push(BreakPoint {
+                token_index: i + 1,
+                discard_flag: true,
+                line_no: n,
+            });
+            
+            n += 1;
+            x = 0;
+            at_end_of_sentence = false;
+        
+        } else if frm.intersects(FormatFlags::EOS)
+            && !frm.intersects(FormatFlags::DLB)
+        {
+            x += token.length();
+            at_end_of_sentence = true;
+
+        } else if frm.intersects(FormatFlags::DLB) {
+            if at_end_of_sentence || frm.intersects(FormatFlags::EOS) {
+                break_points.push(BreakPoint {
+                    token_index: i + 1,
+                    discard_flag: frm.intersects(FormatFlags::DOB),
+                    line_no: n,
+                });
+
+                at_end_of_sentence = false;
+            }
+
+            if x + reach[i] > line_length {
+                n += 1;
+                x = hang;
+                
+            } else {
+                x += token.length();
+            }
+
+        } else {
+            x += token.length();
+        }
+    }
+
+    if x >= line_length {
+        n += 1;
+    }
+    
+    break_points.push(BreakPoint {
+        token_index: tokens.len(),
+        discard_flag: false,
+        line_no: n,
+    });
+    
+    break_points
+}
+
+/// Counts the lines needed to typeset an element, based on break
+/// point information only
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::*;
+/// # use batyr::document::breaking::lines_needed;
+/// assert_eq!(lines_needed(&BreakType::Atomic(3)), 3);
+/// assert_eq!(lines_needed(&BreakType::Mandatory), 0);
+///
+/// let break_points = vec![
+///     BreakPoint { token_index: 4, discard_flag: true, line_no: 2 },
+///     BreakPoint { token_index: 9, discard_flag: false, line_no: 5 },
+/// ];
+/// assert_eq!(lines_needed(&BreakType::List(break_points)), 5);
+/// ```
+pub fn lines_needed(break_info: &BreakType) -> usize {
+    match break_info {
+        BreakType::None => 0,
+        BreakType::Mandatory => 0,
+        BreakType::Forbidden(n) => *n,
+        BreakType::Atomic(n) => *n,
+        BreakType::Disposable(n) => *n,
+        BreakType::Point(break_point) => break_point.line_no,
+        BreakType::List(break_points) => {
+            if let Some(break_point) = break_points.last() {
+                break_point.line_no
+            } else {
+                0
+            }
+        },
+    }
+}
+
+/// Selects a break point for an element given the number of lines
+/// remaining in the page
+///
+/// Returns [`BreakType::None`] if the entire element fits, a
+/// [`BreakType::Point`] if the element should be split, and
+/// [`BreakType::Mandatory`] if the element must move to the next
+/// page.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::*;
+/// # use batyr::document::breaking::best_break;
+/// let break_points = vec![
+///     BreakPoint { token_index: 4, discard_flag: true, line_no: 2 },
+///     BreakPoint { token_index: 9, discard_flag: false, line_no: 5 },
+/// ];
+/// let break_info = BreakType::List(break_points);
+///
+/// assert_eq!(best_break(&break_info, 5), BreakType::None);
+/// assert_eq!(best_break(&break_info, 1), BreakType::Mandatory);
+///
+/// match best_break(&break_info, 3) {
+///     BreakType::Point(break_point) => assert_eq!(break_point.line_no, 2),
+///     _ => panic!(),
+/// }
+/// ```
+pub fn best_break(break_info: &BreakType, lines_remaining: i32) -> BreakType {
+    best_break_with(break_info, lines_remaining, &BreakPolicy::default())
+}
+
+/// Selects a break point for an element following a break policy
+///
+/// See [`best_break`] for the meaning of the result, and
+/// [`BreakPolicy`] for an example.
+pub fn best_break_with(break_info: &BreakType, lines_remaining: i32,
+                       policy: &BreakPolicy)
+                       -> BreakType
+{
+    let total_height = lines_needed(break_info);
+
+    if total_height as i32 <= lines_remaining { // entire paragraph fits
+        return BreakType::None;
+    }
+
+    let mut prev_break_info: BreakType = BreakType::None;
+
+    match break_info {
+        BreakType::Atomic(n) => {
+            if *n as i32 <= lines_remaining {
+                if prev_break_info == BreakType::None {
+                    return BreakType::Mandatory;
+                } else {
+                    return prev_break_info;
+                }
+            } else {
+                return BreakType::Mandatory;
+            }
+        },
+        BreakType::List(break_points) => {
+            for break_point in break_points.iter() {
+                if break_point.line_no as i32 > lines_remaining {
+                    if prev_break_info == BreakType::None {
+                        return BreakType::Mandatory;
+                    } else {
+                        return prev_break_info;
+                    }
+                }
+
+                let after = total_height - break_point.line_no;
+
+                if policy.allows(break_point.line_no, after) {
+                    prev_break_info = BreakType::Point(break_point.clone());
+                }
+            }
+        },
+        _ => (),
+    }
+
+    BreakType::Mandatory
+}
+
+/// Counts the lines that must fit on a page for an element to stay
+/// with the next one
+///
+/// These are all of the element, the blank lines after it, and the
+/// next element up to its first valid break.  The next element is
+/// described by a train, as for a scene heading: a paragraph, or a
+/// cue followed by its dialogue.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::*;
+/// # use batyr::document::breaking::keep_with_next_lines;
+/// // Two lines, a blank line, and a cue with two lines of dialogue
+/// let train = vec![BreakType::Forbidden(1), BreakType::Atomic(2)];
+/// assert_eq!(keep_with_next_lines(&BreakType::Atomic(2), 1, &train), 6);
+///
+/// // A paragraph that may break after its first line
+/// let break_points = vec![
+///     BreakPoint { token_index: 4, discard_flag: true, line_no: 1 },
+///     BreakPoint { token_index: 9, discard_flag: false, line_no: 3 },
+/// ];
+/// let train = vec![BreakType::List(break_points)];
+/// assert_eq!(keep_with_next_lines(&BreakType::Atomic(2), 1, &train), 4);
+/// ```
+pub fn keep_with_next_lines(break_info: &BreakType, padding_after: usize,
+                            train: &[BreakType]) -> usize
+{
+    let mut line_count = lines_needed(break_info) + padding_after;
+
+    for break_info in train.iter() {
+        match break_info {
+            BreakType::None => (),
+            BreakType::Mandatory => return line_count,
+            BreakType::Forbidden(h) => line_count += h,
+            BreakType::Atomic(h) => return line_count + h,
+            BreakType::Disposable(_) => return line_count,
+            BreakType::Point(break_point) => {
+                return line_count + break_point.line_no;
+            },
+            BreakType::List(break_points) => {
+                if let Some(break_point) = break_points.first() {
+                    return line_count + break_point.line_no;
+                }
+            },
+        }
+    }
+
+    line_count
+}
+
+/// Counts the lines needed to typeset a character cue and its train
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::*;
+/// # use batyr::document::breaking::train_lines_needed;
+/// let train = vec![BreakType::Forbidden(1), BreakType::Atomic(2)];
+/// assert_eq!(train_lines_needed(&train), 4);
+/// ```
+pub fn train_lines_needed(train: &[BreakType]) -> usize {
+    let mut line_count: usize = 1;
+
+    for break_info in train.iter() {
+        match break_info {
+            BreakType::None => (),
+            BreakType::Mandatory => (),
+            BreakType::Forbidden(h) => line_count += h,
+            BreakType::Atomic(h) => line_count += h,
+            BreakType::Disposable(h) => line_count += h,
+            BreakType::Point(break_point) => {
+                line_count += break_point.line_no;
+            },
+            BreakType::List(break_points) => {
+                let n = break_points.len();
+                line_count += break_points[n - 1].line_no;
+            },
+        }
+    }
+
+    line_count
+}
+
+/// Selects a break point in a cue's train given the number of lines
+/// remaining in the page
+///
+/// Returns a tuple of the train index and the break type.  If the
+/// entire train fits, the index is the length of the train.  If the
+/// cue has to move to the next page, the index is -1.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::*;
+/// # use batyr::document::breaking::best_train_break;
+/// let train = vec![BreakType::Atomic(2), BreakType::Atomic(2)];
+///
+/// assert_eq!(best_train_break(&train, 5), (2, BreakType::None));
+/// assert_eq!(best_train_break(&train, 4), (0, BreakType::Atomic(2)));
+/// assert_eq!(best_train_break(&train, 1), (-1, BreakType::None));
+/// ```
+pub fn best_train_break(train: &[BreakType], lines_remaining: i32)
+                        -> (i32, BreakType)
+{
+    best_train_break_with(train, lines_remaining, &BreakPolicy::default())
+}
+
+/// Selects a break point in a cue's train following a break policy
+///
+/// See [`best_train_break`] for the meaning of the result.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::*;
+/// # use batyr::document::breaking::{best_train_break_with, BreakPolicy};
+/// let train = vec![BreakType::Atomic(1), BreakType::Atomic(2)];
+/// let policy = BreakPolicy::default();
+///
+/// // A cue with one line of dialogue may start two lines from the
+/// // bottom unless the house style requires three.
+/// let strict = BreakPolicy { cue_orphan_threshold: 3, ..policy };
+/// assert_eq!(best_train_break_with(&train, 2, &policy).0, -1);
+/// assert_eq!(best_train_break_with(&train, 3, &policy), (0, BreakType::Atomic(1)));
+/// assert_eq!(best_train_break_with(&train, 3, &strict), (0, BreakType::Atomic(1)));
+/// assert_eq!(best_train_break_with(&train, 2, &strict).0, -1);
+///
+/// // Reserving two lines for "(MORE)" pushes the break back.
+/// let more = BreakPolicy { more_lines: 2, ..policy };
+/// assert_eq!(best_train_break_with(&train, 3, &more).0, -1);
+///
+/// // Keeping two lines of dialogue before the break does too.
+/// let orphans = BreakPolicy { min_lines_before: 2, ..policy };
+/// assert_eq!(best_train_break_with(&train, 3, &orphans).0, -1);
+///
+/// // A parenthetical stays with the dialogue chunk that follows it,
+/// // unless the policy allows a break after it.
+/// let train = vec![
+///     BreakType::Atomic(2),    // dialogue
+///     BreakType::Forbidden(1), // personal direction
+///     BreakType::Atomic(3),    // dialogue
+/// ];
+///
+/// for lines_remaining in 4..7 {
+///     let (i, _) = best_train_break_with(&train, lines_remaining, &policy);
+///     assert_eq!(i, 0);
+/// }
+///
+/// let dir = BreakPolicy { break_after_dir: true, ..policy };
+/// assert_eq!(best_train_break_with(&train, 5, &dir).0, 1);
+/// ```
+pub fn best_train_break_with(train: &[BreakType], lines_remaining: i32,
+                             policy: &BreakPolicy)
+                             -> (i32, BreakType)
+{
+    let more_lines = policy.more_lines;
+
+    // Don't orphan the cue.
+    if lines_remaining < policy.cue_orphan_threshold as i32 {
+        return (-1, BreakType::None);
+    }
+
+    let total_height = train_lines_needed(train);
+    let n = train.len();
+
+    if total_height as i32 <= lines_remaining { // entire dialogue fits
+        return (n as i32, BreakType::None);
+    }
+
+    // Lines of dialogue and direction, without the cue
+    let train_height = total_height - 1;
+
+    // We need to add "(MORE)" at the bottom of the page.
+    let mut line_count = 1; // 1 for the character cue
+    let mut prev_break_list_index: i32 = -1;
+    let mut prev_break_info: BreakType = BreakType::None;
+
+    for (i, break_info) in train.iter().enumerate() {
+        match break_info {
+            BreakType::None => (),
+            BreakType::Mandatory => {
+                return (i as i32, BreakType::Mandatory);
+            },
+            BreakType::Forbidden(h) => {
+                if i == n - 1 { // last break
+                    if (line_count + h) as i32 > lines_remaining {
+                        return (prev_break_list_index, prev_break_info);
+                    }
+                } else {
+                    // plus room for (MORE)
+                    if (line_count + h + more_lines) as i32 > lines_remaining {
+                        return (prev_break_list_index, prev_break_info);
+                    }
+                }
+
+                let before = line_count - 1 + h;
+
+                if policy.break_after_dir
+                    && policy.allows(before, train_height - before)
+                {
+                    prev_break_info = BreakType::Atomic(*h);
+                    prev_break_list_index = i as i32;
+                }
+
+                line_count += h;
+            },
+            BreakType::Atomic(h) => {
+                if i == n - 1 { // last break
+                    if (line_count + h) as i32 > lines_remaining {
+                        return (prev_break_list_index, prev_break_info);
+                    }
+                } else {
+                    // plus room for (MORE)
+                    if (line_count + h + more_lines) as i32 > lines_remaining {
+                        return (prev_break_list_index, prev_break_info);
+                    }
+                }
+
+                let before = line_count - 1 + h;
+
+                if policy.allows(before, train_height - before) {
+                    prev_break_info = BreakType::Atomic(*h);
+                    prev_break_list_index = i as i32;
+                }
+
+                line_count += h;
+            },
+            BreakType::Disposable(h) => {
+                if i == n - 1 { // last break
+                    if (line_count + h) as i32 > lines_remaining {
+                        return (prev_break_list_index, prev_break_info);
+                    }
+                } else {
+                    // plus room for (MORE)
+                    if (line_count + h + more_lines) as i32 > lines_remaining {
+                        return (prev_break_list_index, prev_break_info);
+                    }
+                }
+
+                let before = line_count - 1 + h;
+
+                if policy.allows(before, train_height - before) {
+                    prev_break_info = BreakType::Disposable(*h);
+                    prev_break_list_index = i as i32;
+                }
+
+                line_count += h;
+            },
+            BreakType::Point(break_point) => {
+                if i == n - 1 { // last break
+                    if (line_count + break_point.line_no) as i32
+                        > lines_remaining
+                    {
+                        return (prev_break_list_index, prev_break_info);
+                    }
+                } else {
+                    // plus room for (MORE)
+                    if (line_count + break_point.line_no + more_lines) as i32
+                        > lines_remaining
+                    {
+                        return (prev_break_list_index, prev_break_info);
+                    }
+                }
+
+                let before = line_count - 1 + break_point.line_no;
+
+                if policy.allows(before, train_height - before) {
+                    prev_break_info = BreakType::Point(break_point.clone());
+                    prev_break_list_index = i as i32;
+                }
+            },
+            BreakType::List(break_points) => {
+                let m = break_points.len();
+
+                for break_point in break_points.iter() {
+                    // plus room for (MORE)
+                    if (line_count + break_point.line_no + more_lines) as i32
+                        > lines_remaining
+                    {
+                        if prev_break_info == BreakType::None
+                            || prev_break_list_index < 0
+                        {
+                            return (-1, BreakType::None);
+
+                        } else {
+                            return (prev_break_list_index, prev_break_info);
+                        }
+                    }
+
+                    let before = line_count - 1 + break_point.line_no;
+
+                    if policy.allows(before, train_height - before) {
+                        prev_break_info = BreakType::Point(break_point.clone());
+                        prev_break_list_index = i as i32;
+                    }
+                }
+
+                line_count += break_points[m - 1].line_no;
+            },
+        }
+    }
+
+    (-1, BreakType::None)
+}
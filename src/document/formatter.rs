@@ -21,10 +21,16 @@ use std::cmp::max;
 use std::cmp::min;
 use std::collections::VecDeque;
 use std::iter::repeat;
+use std::sync::mpsc::SyncSender;
 
 use crate::document::*;
+use crate::document::breaking::BreakPolicy;
 use crate::text::*;
 
+/// The maximum number of blank lines added to each gap between
+/// elements by vertical justification
+pub const MAX_JUSTIFY_LINES: usize = 1;
+
 /// Information that goes on the fly page
 #[derive(Debug)]
 pub struct FlyInfo {
@@ -34,14 +40,45 @@ pub struct FlyInfo {
     pub title: TokenList,
     /// List of authors
     pub authors: TokenList,
-    /// Optional note prints centered beneath the authors
-    pub note: Option<TokenList>,
+    /// Optional source credit prints centered beneath the authors
+    pub source: Option<TokenList>,
+    /// Optional episode title for the running page header
+    pub episode: Option<TokenList>,
+    /// Optional production number for the running page header
+    pub production_number: Option<TokenList>,
+    /// Notes print beneath the authors and source, each with its
+    /// own alignment
+    pub notes: Vec<(Alignment, TokenList)>,
     /// Contact information goes in the bottom left corner
     pub contact: Option<TokenList>,
 }
 
+/// An entry in the line list of a page
+///
+/// Blank lines are recorded as spacing rather than pushed one by
+/// one, so later passes can adjust the vertical spacing of a page
+/// without having to rediscover the element boundaries.  Spacing is
+/// resolved into blank lines by the writer.
+#[derive(Debug, Clone)]
+pub enum Row {
+    /// A line of text
+    Text(Line),
+    /// A run of blank lines
+    Spacing(usize),
+}
+
+impl Row {
+    /// Returns the number of lines the row occupies on the page
+    pub fn height(&self) -> usize {
+        match self {
+            Row::Text(_) => 1,
+            Row::Spacing(n) => *n,
+        }
+    }
+}
+
 /// A typed page to be output
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Page {
     /// Page number.  Printed in the top left corner if it is a
     /// positive number
@@ -49,27 +86,404 @@ pub struct Page {
     /// Maximum number of lines allowed
     pub height: usize,
     /// Actual lines of text to output
-    pub lines: Vec<Option<Line>>,
+    pub lines: Vec<Row>,
     /// Footer lines go at the bottom of the page
-    pub footer: Vec<Option<Line>>,
+    pub footer: Vec<Row>,
+}
+
+impl Page {
+    /// Returns the number of lines used so far, including spacing
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::formatter::{Page, Row};
+    /// # use batyr::text::{Line, Segment};
+    /// let mut page = Page {
+    ///     number: 1,
+    ///     height: 55,
+    ///     lines: Vec::new(),
+    ///     footer: Vec::new(),
+    /// };
+    /// page.push_line(Line::from(Segment::from("foo")));
+    /// page.push_spacing(1);
+    /// page.push_spacing(2);
+    ///
+    /// assert_eq!(page.lines.len(), 2);
+    /// assert_eq!(page.line_count(), 4);
+    /// ```
+    pub fn line_count(&self) -> usize {
+        self.lines.iter().map(Row::height).sum()
+    }
+
+    /// Appends a line of text
+    pub fn push_line(&mut self, line: Line) {
+        self.lines.push(Row::Text(line));
+    }
+
+    /// Appends blank lines, merging them into any spacing that
+    /// immediately precedes them
+    pub fn push_spacing(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+
+        match self.lines.last_mut() {
+            Some(Row::Spacing(m)) => *m += n,
+            _ => self.lines.push(Row::Spacing(n)),
+        }
+    }
 }
 
 /// Data type for a sequence of pages
 pub type PageList = Vec<Page>;
 
+/// The number of lines on a page of the body by default
+#[deprecated(note = "use Layout::default().lines_per_page()")]
+#[allow(deprecated)]
+pub const LINES_PER_PAGE: usize = TOP_LINE - BOTTOM_LINE + 1;
+
+/// Layout options for the formatter
+#[derive(Debug, Clone)]
+pub struct FormatterOptions {
+    /// Rules for breaking stage direction and dialogue across pages.
+    /// A screenplay may override some of them with its attributes.
+    pub break_policy: BreakPolicy,
+    /// The fewest blank lines between the last content and the text
+    /// of an <tt>&lt;end&gt;</tt> element, such as "END OF ACT ONE"
+    pub end_gap: usize,
+    /// Text for the bottom right corner of the title page, such as a
+    /// draft hash
+    pub stamp: Option<String>,
+    /// Margins and tab stops of the formatter's own lines, such as
+    /// "CONTINUED" and the fly page, and the number of lines on each
+    /// page of the body
+    pub layout: Layout,
+}
+
+impl Default for FormatterOptions {
+    fn default() -> Self {
+        FormatterOptions {
+            break_policy: BreakPolicy::default(),
+            end_gap: breaking::END_GAP,
+            stamp: None,
+            layout: Layout::default(),
+        }
+    }
+}
+
+/// Where a scene falls in the body
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct SceneInfo {
+    /// Scene number, including any addition
+    pub number: String,
+    /// Text of the slug line
+    pub heading: String,
+    /// Line of the source where the slug begins
+    pub line: usize,
+    /// Number of the page where the scene begins
+    pub first_page: i32,
+    /// Line of that page where the slug begins, counting from 1 at
+    /// the top, as in [`ElementInfo::line`]
+    pub page_line: usize,
+    /// Number of the page where the scene ends
+    pub last_page: i32,
+}
+
+impl SceneInfo {
+    /// Returns the number of pages the scene touches
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::formatter::Formatter;
+    /// # use batyr::document::reader::Reader;
+    /// let mut xml = String::from("<screenplay><head><title>FOO</title>");
+    /// xml.push_str("<authors><fullName>BAR</fullName></authors></head>");
+    /// xml.push_str("<body><slug>INT. HOUSE - DAY</slug>");
+    ///
+    /// for _ in 0..40 {
+    ///     xml.push_str("<p>Waiting.</p>");
+    /// }
+    ///
+    /// xml.push_str("<slug>EXT. HOUSE - NIGHT</slug><p>Gone.</p>");
+    /// xml.push_str("</body></screenplay>");
+    ///
+    /// let root = Reader::new(&xml).run().unwrap();
+    /// let mut formatter = Formatter::new();
+    /// formatter.run(root);
+    ///
+    /// assert_eq!(formatter.scenes.len(), 2);
+    /// assert_eq!(formatter.scenes[0].number, "1");
+    /// assert_eq!(formatter.scenes[0].page_count(), 2);
+    /// assert_eq!(formatter.scenes[0].page_line, 4);
+    /// assert_eq!(formatter.scenes[1].first_page, 2);
+    /// assert_eq!(formatter.scenes[1].page_count(), 1);
+    /// ```
+    pub fn page_count(&self) -> usize {
+        (self.last_page - self.first_page + 1) as usize
+    }
+}
+
+/// Where the text of an element lands on the page
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "json", derive(serde::Serialize))]
+pub struct ElementInfo {
+    /// Name of the element, such as "slug"
+    pub tag: &'static str,
+    /// Line of the source where the element begins
+    pub source_line: usize,
+    /// Number of the page where the text begins
+    pub page: i32,
+    /// Line of that page where the text begins, counting from 1 at
+    /// the top.  Lines added by justification are not counted.
+    pub line: usize,
+    /// Index of that line among the rows of the page
+    #[cfg_attr(feature = "json", serde(skip))]
+    pub row: usize,
+}
+
+/// Where an act begins in the body
+#[derive(Debug, Clone)]
+pub struct ActInfo {
+    /// Text of the act heading
+    pub heading: String,
+    /// Number of the page where the act begins
+    pub page: i32,
+}
+
+/// Callbacks from the formatter as it lays out the pages
+///
+/// Every method does nothing by default, so an observer only needs
+/// to implement the events it cares about.  Page numbers seen during
+/// the run are provisional: pages that end up empty are removed, and
+/// the pages after them renumbered, once all elements are formatted.
+/// Observers must be [`Send`] so that the formatter can run on its
+/// own thread when streaming.
+///
+/// # Examples
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use batyr::document::formatter::{Formatter, FormatterObserver, Page};
+/// # use batyr::document::reader::Reader;
+/// struct PageCounter(Arc<AtomicUsize>);
+///
+/// impl FormatterObserver for PageCounter {
+///     fn on_page_start(&mut self, _page: &Page) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let mut xml = String::from("<screenplay><head/><body>");
+///
+/// for _ in 0..40 {
+///     xml.push_str("<p>Waiting.</p>");
+/// }
+///
+/// xml.push_str("</body></screenplay>");
+///
+/// let pages = Arc::new(AtomicUsize::new(0));
+/// let mut formatter = Formatter::new();
+/// formatter.add_observer(PageCounter(pages.clone()));
+/// formatter.run(Reader::new(&xml).run().unwrap());
+///
+/// assert_eq!(pages.load(Ordering::Relaxed), 2);
+/// ```
+pub trait FormatterObserver: Send {
+    /// Called after a new page has been started
+    fn on_page_start(&mut self, _page: &Page) {}
+
+    /// Called after an element has been laid out, with the page it
+    /// ended on
+    fn on_element_formatted(&mut self, _elem: &ElementType, _page: &Page) {}
+
+    /// Called when a page break is chosen inside a dialogue train,
+    /// with the number of lines that were left on the page
+    fn on_break_selected(&mut self, _break_info: &BreakType,
+                         _lines_remaining: i32) {}
+}
+
+impl FormatterObserver for Box<dyn FormatterObserver> {
+    fn on_page_start(&mut self, page: &Page) {
+        (**self).on_page_start(page);
+    }
+
+    fn on_element_formatted(&mut self, elem: &ElementType, page: &Page) {
+        (**self).on_element_formatted(elem, page);
+    }
+
+    fn on_break_selected(&mut self, break_info: &BreakType,
+                         lines_remaining: i32) {
+        (**self).on_break_selected(break_info, lines_remaining);
+    }
+}
+
+/// Output of a streaming formatter, in document order
+#[derive(Debug)]
+pub enum Streamed {
+    /// Document title and running header, sent once before any page
+    Head {
+        /// Document title
+        title: String,
+        /// Running page header
+        header: String,
+    },
+    /// A finished page, starting with the fly page
+    Page(Page),
+}
+
+/// Counters carried from one formatter run to the next
+///
+/// Taken with [`Formatter::snapshot`] and put back with
+/// [`Formatter::restore`], so that a long build can be checkpointed
+/// between documents and resumed later, or so that one state can be
+/// shared by several formatters.  The text form has one
+/// <tt>key: value</tt> line per counter.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::formatter::FormatterState;
+/// let state = FormatterState {
+///     next_page_no: 42,
+///     cur_scene: Some("17A".to_string()),
+///     scene_page_no: 2,
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(FormatterState::from(state.to_string().as_str()), state);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatterState {
+    /// Number of the next page
+    pub next_page_no: i32,
+    /// Blank lines owed after the last element
+    pub last_padding_after: usize,
+    /// Label of the scene in progress, if any
+    pub cur_scene: Option<String>,
+    /// Pages since the scene in progress began, or -1 outside a scene
+    pub scene_page_no: i32,
+    /// Whether pages are left unnumbered, as in the front and back
+    /// matter
+    pub unnumbered: bool,
+}
+
+impl Default for FormatterState {
+    fn default() -> Self {
+        FormatterState {
+            next_page_no: 1,
+            last_padding_after: 0,
+            cur_scene: None,
+            scene_page_no: -1,
+            unnumbered: false,
+        }
+    }
+}
+
+impl std::fmt::Display for FormatterState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "next_page_no: {}", self.next_page_no)?;
+        writeln!(f, "last_padding_after: {}", self.last_padding_after)?;
+
+        if let Some(scene) = &self.cur_scene {
+            writeln!(f, "cur_scene: {}", scene)?;
+        }
+
+        writeln!(f, "scene_page_no: {}", self.scene_page_no)?;
+        writeln!(f, "unnumbered: {}", self.unnumbered)
+    }
+}
+
+impl From<&str> for FormatterState {
+    fn from(s: &str) -> Self {
+        let mut state = FormatterState::default();
+
+        for line in s.lines() {
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+
+            match key {
+                "next_page_no" => {
+                    state.next_page_no = value.parse().unwrap_or(1);
+                },
+                "last_padding_after" => {
+                    state.last_padding_after = value.parse().unwrap_or(0);
+                },
+                "cur_scene" => {
+                    state.cur_scene = Some(value.to_string());
+                },
+                "scene_page_no" => {
+                    state.scene_page_no = value.parse().unwrap_or(-1);
+                },
+                "unnumbered" => {
+                    state.unnumbered = value == "true";
+                },
+                _ => (),
+            }
+        }
+
+        state
+    }
+}
+
 /// Format driver
 pub struct Formatter {
     /// Document title
     pub title: String,
+    /// Running page header printed before the page number, if any
+    pub header: String,
     /// Document body
     pub body: PageList,
+    /// Scenes in order, with their page numbers
+    pub scenes: Vec<SceneInfo>,
+    /// Acts in order, with their page numbers
+    pub acts: Vec<ActInfo>,
+    /// Number of the page where each dialogue element begins, in
+    /// order
+    pub dialogue_pages: Vec<i32>,
+    /// Number of the page where each scene heading falls, in order
+    pub slug_pages: Vec<i32>,
+    /// Number of the page where each character cue falls, in order
+    pub cue_pages: Vec<i32>,
+    /// Where the text of each element lands, in order
+    pub elements: Vec<ElementInfo>,
+    act_pages: Vec<usize>,
+    dialogue_indices: Vec<usize>,
+    slug_indices: Vec<usize>,
+    cue_indices: Vec<usize>,
+    element_indices: Vec<usize>,
+    element_start: Option<(ElementInfo, usize, usize)>,
+    scene_spans: Vec<(usize, usize)>,
+    scene_open: bool,
     next_page_no: i32,
     last_padding_after: usize,
     break_selection: VecDeque<Option<BreakType>>,
-    cur_cue: Option<Line>,
+    cur_tag: &'static str,
+    cur_cue: Vec<Line>,
+    cur_train: VecDeque<BreakType>,
+    cur_train_at_scene_end: bool,
     numbering: Numbering,
+    continued: bool,
+    markers: Markers,
+    justify: bool,
     cur_scene: Option<String>,
     scene_page_no: i32,
+    unnumbered: bool,
+    held_page: Option<(Page, usize)>,
+    options: FormatterOptions,
+    observers: Vec<Box<dyn FormatterObserver>>,
+    stream: Option<SyncSender<Streamed>>,
+    stream_started: bool,
+    sent: usize,
+    pending: Option<Page>,
+    removed: i32,
+    page_map: Vec<(usize, bool)>,
+    page_numbers: Vec<i32>,
 }
 
 impl Formatter {
@@ -83,29 +497,201 @@ impl Formatter {
     /// assert!(formatter.body.is_empty());
     /// ```
     pub fn new() -> Self {
+        Formatter::with_options(Default::default())
+    }
+
+    /// Register an observer to be called back during the run
+    pub fn add_observer<O: FormatterObserver + 'static>(&mut self,
+                                                         observer: O)
+    {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Send each page to the channel as soon as it is finished,
+    /// instead of collecting the pages in the body
+    ///
+    /// Memory use is then bounded by the channel, rather than by the
+    /// length of the document.  The title and running header are sent
+    /// first, once the head has been read, followed by the fly page
+    /// and the rest of the pages in order.  The channel is closed at
+    /// the end of the run.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::sync::mpsc;
+    /// # use std::thread;
+    /// # use batyr::document::formatter::{Formatter, Streamed};
+    /// # use batyr::document::reader::Reader;
+    /// let mut xml = String::from("<screenplay><head><title>FOO</title>");
+    /// xml.push_str("<authors><fullName>BAR</fullName></authors></head><body>");
+    ///
+    /// for _ in 0..40 {
+    ///     xml.push_str("<p>Waiting.</p>");
+    /// }
+    ///
+    /// xml.push_str("</body></screenplay>");
+    ///
+    /// let (sender, receiver) = mpsc::sync_channel(2);
+    /// let root = Reader::new(&xml).run().unwrap();
+    ///
+    /// let formatter = thread::spawn(move || {
+    ///     let mut formatter = Formatter::new();
+    ///     formatter.stream(sender);
+    ///     formatter.run(root);
+    ///     formatter
+    /// });
+    ///
+    /// let messages: Vec<Streamed> = receiver.iter().collect();
+    /// let formatter = formatter.join().unwrap();
+    ///
+    /// assert!(matches!(&messages[0], Streamed::Head { title, .. } if title == "FOO"));
+    /// assert!(matches!(&messages[1], Streamed::Page(page) if page.number == -1));
+    /// assert!(matches!(&messages[3], Streamed::Page(page) if page.number == 2));
+    /// assert_eq!(messages.len(), 4);
+    /// assert!(formatter.body.is_empty());
+    /// ```
+    pub fn stream(&mut self, sender: SyncSender<Streamed>) {
+        self.stream = Some(sender);
+    }
+
+    /// Takes a copy of the counters that carry over between runs
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::formatter::{Formatter, FormatterState};
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body><p>Snow.</p></body></screenplay>";
+    ///
+    /// let mut first = Formatter::new();
+    /// first.run(Reader::new(xml).run().unwrap());
+    /// let checkpoint = first.snapshot().to_string();
+    ///
+    /// let mut second = Formatter::new();
+    /// second.restore(&FormatterState::from(checkpoint.as_str()));
+    /// second.run(Reader::new(xml).run().unwrap());
+    ///
+    /// assert_eq!(second.body[1].number, first.body[1].number + 1);
+    /// ```
+    pub fn snapshot(&self) -> FormatterState {
+        FormatterState {
+            next_page_no: self.next_page_no,
+            last_padding_after: self.last_padding_after,
+            cur_scene: self.cur_scene.clone(),
+            scene_page_no: self.scene_page_no,
+            unnumbered: self.unnumbered,
+        }
+    }
+
+    /// Puts back counters taken with [`Formatter::snapshot`], before
+    /// the run
+    pub fn restore(&mut self, state: &FormatterState) {
+        self.next_page_no = state.next_page_no;
+        self.last_padding_after = state.last_padding_after;
+        self.cur_scene = state.cur_scene.clone();
+        self.scene_page_no = state.scene_page_no;
+        self.unnumbered = state.unnumbered;
+    }
+
+    /// Construct a new formatter with the given layout options
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::Layout;
+    /// # use batyr::document::reader::Reader;
+    /// use batyr::document::formatter::{Formatter, FormatterOptions};
+    /// # let mut xml = String::from("<screenplay><head><title>FOO</title>");
+    /// # xml.push_str("<authors><fullName>BAR</fullName></authors></head>");
+    /// # xml.push_str("<body>");
+    /// # for _ in 0..30 {
+    /// #     xml.push_str("<p>Waiting.</p>");
+    /// # }
+    /// # xml.push_str("</body></screenplay>");
+    /// # let root = Reader::new(&xml).run().unwrap();
+    /// let options = FormatterOptions {
+    ///     layout: Layout::default().with_lines_per_page(20),
+    ///     ..Default::default()
+    /// };
+    /// let mut formatter = Formatter::with_options(options);
+    /// formatter.run(root);
+    ///
+    /// for page in &formatter.body {
+    ///     assert!(page.line_count() <= page.height);
+    /// }
+    ///
+    /// assert_eq!(formatter.body[1].height, 20);
+    /// assert!(formatter.body.len() > 3);
+    /// ```
+    pub fn with_options(options: FormatterOptions) -> Self {
         Formatter {
             title: "Working Title".to_string(),
+            header: String::new(),
             body: Vec::new(),
+            scenes: Vec::new(),
+            acts: Vec::new(),
+            dialogue_pages: Vec::new(),
+            slug_pages: Vec::new(),
+            cue_pages: Vec::new(),
+            elements: Vec::new(),
+            act_pages: Vec::new(),
+            dialogue_indices: Vec::new(),
+            slug_indices: Vec::new(),
+            cue_indices: Vec::new(),
+            element_indices: Vec::new(),
+            element_start: None,
+            scene_spans: Vec::new(),
+            scene_open: false,
             next_page_no: 1,
             last_padding_after: 0,
             break_selection: VecDeque::new(),
-            cur_cue: None,
+            cur_tag: "",
+            cur_cue: Vec::new(),
+            cur_train: VecDeque::new(),
+            cur_train_at_scene_end: false,
             numbering: Numbering::None,
+            continued: true,
+            markers: Markers::default(),
+            justify: false,
             cur_scene: None,
             scene_page_no: -1,
+            unnumbered: false,
+            held_page: None,
+            options,
+            observers: Vec::new(),
+            stream: None,
+            stream_started: false,
+            sent: 0,
+            pending: None,
+            removed: 0,
+            page_map: Vec::new(),
+            page_numbers: Vec::new(),
         }
     }
 
     fn start_a_new_page(&mut self) {
         let page = Page {
-	    number: self.next_page_no,
-	    height: TOP_LINE - BOTTOM_LINE + 1,
+	    number: if self.unnumbered { 0 } else { self.next_page_no },
+	    height: self.options.layout.lines_per_page(),
 	    lines: Vec::new(),
             footer:Vec::new(),
         };
 
         self.body.push(page);
-	self.next_page_no += 1;
+
+        if let Some(page) = self.body.last() {
+            for observer in self.observers.iter_mut() {
+                observer.on_page_start(page);
+            }
+        }
+
+        self.flush_pages();
+
+        if !self.unnumbered {
+	    self.next_page_no += 1;
+        }
+
         self.last_padding_after = 0;
 
         if self.scene_page_no >= 0 {
@@ -113,6 +699,26 @@ impl Formatter {
         }
     }
 
+    /// Starts a new page, continuing the scene if one is open
+    fn break_page(&mut self) {
+        if self.scene_page_no >= 0 {
+            self.push_continued_bottom();
+        }
+
+        self.start_a_new_page();
+
+        if self.scene_page_no >= 0 {
+            self.push_continued_top();
+        }
+    }
+
+    /// Appends a line of the element being laid out to the current
+    /// page, naming the element on the line
+    fn push_text(&mut self, mut line: Line) {
+        line.tag = Some(self.cur_tag);
+        self.cur_page().push_line(line);
+    }
+
     fn cur_page(&mut self) -> &mut Page {
 	assert!(!self.body.is_empty());
 	self.body.iter_mut().last().unwrap()
@@ -120,55 +726,282 @@ impl Formatter {
 
     fn lines_remaining(&self) -> i32 {
         if let Some(page) = self.body.last() {
-            page.height as i32 - page.lines.len() as i32
+            page.height as i32 - page.line_count() as i32
         } else {
             0
         }
     }
 
+    /// Returns the number of lines kept free at the bottom of the page
+    /// for "(CONTINUED)"
+    fn continued_lines(&self) -> i32 {
+        self.options.break_policy.continued_lines as i32
+    }
+
+    /// Takes the last block of text off the current page, so that it
+    /// can go along with what follows onto the next page
+    ///
+    /// A block runs back to the last blank line.  Nothing is taken if
+    /// the block is the whole page, or if it would leave no room for
+    /// <tt>n</tt> more lines on a fresh page.
+    fn take_last_block(&mut self, n: usize) -> Vec<Row> {
+        let lines_per_page = self.options.layout.lines_per_page();
+        let page = self.cur_page();
+
+        let i = match page.lines.iter()
+            .rposition(|row| matches!(row, Row::Spacing(_)))
+        {
+            Some(i) if i > 0 => i,
+            _ => return Vec::new(),
+        };
+
+        let h: usize = page.lines[i + 1..].iter().map(Row::height).sum();
+
+        // Leave room for "CONTINUED:" at the top of the page.
+        if h == 0 || h + n + 2 > lines_per_page {
+            return Vec::new();
+        }
+
+        let block = page.lines.split_off(i + 1);
+        page.lines.pop();
+        block
+    }
+
     fn push_blank_lines(&mut self, n: usize) {
         let r = self.lines_remaining();
         let m = min(n as i32, r);
         
         if m > 0 {
-            for _ in 0..m {
-                self.cur_page().lines.push(None);
-            }
+            self.cur_page().push_spacing(m as usize);
         }
     }
 
     fn push_continued_top(&mut self) {
-        let mut line = Line::from(Segment::from("CONTINUED:"));
-        line.column = P_BEGIN;
+        let text = format!("{}:", self.markers.continued);
+        let mut line = Line::from(Segment::from(text));
+        line.column = self.options.layout.p_begin;
 
-        if self.scene_page_no > 1 {
+        if self.markers.continued_count.shows(self.scene_page_no) {
             let s = format!(" ({})", self.scene_page_no);
             line.segments.push(Segment::from(s));
         }
 
         if let Some(label) = &self.cur_scene {
-            self.add_numbering(label, &mut line);
+            if self.markers.continued_numbers {
+                self.add_numbering(label, &mut line);
+            }
         }
         
-        self.cur_page().lines.push(Some(line));
+        self.cur_page().push_line(line);
         self.push_blank_lines(1);                        
     }
 
     fn push_continued_bottom(&mut self) {
-        let mut line = Line::from(Segment::from("(CONTINUED)"));
-        line.column = TRANS_BEGIN;
+        let text = format!("({})", self.markers.continued);
+        let mut line = Line::from(Segment::from(text));
+        line.column = self.options.layout.trans_begin;
 
         self.push_blank_lines(1);                        
-        self.cur_page().lines.push(Some(line));
+        self.cur_page().push_line(line);
+    }
+
+    /// Notes where the text of an element would begin, at the end of
+    /// the current page
+    fn begin_element(&mut self, elem: &ElementType) {
+        let info = ElementInfo {
+            tag: elem.tag(),
+            source_line: elem.line(),
+            page: 0,
+            line: 0,
+            row: 0,
+        };
+
+        self.element_start = match self.body.last() {
+            Some(page) => Some((info, self.sent + self.body.len() - 1,
+                                page.lines.len())),
+            None => Some((info, self.sent, 0)),
+        };
+    }
+
+    /// Looks for the first line of the element being laid out, from
+    /// where it was begun on
+    ///
+    /// Lines the formatter adds itself, such as "(MORE)" and
+    /// "CONTINUED:", are passed over.  If the page holds no line of
+    /// the element, the search moves on to the next page.  On the
+    /// last page the search waits for more lines unless the element
+    /// is <tt>done</tt>, in which case it has no text of its own.
+    fn find_element_start(&mut self, done: bool) {
+        while let Some((mut info, page_index, row)) = self.element_start.take() {
+            let page = match page_index.checked_sub(self.sent)
+                .and_then(|i| self.body.get(i))
+            {
+                Some(page) => page,
+                None => return,
+            };
+
+            let found = page.lines.iter().skip(row)
+                .position(|r| match r {
+                    Row::Text(line) => !self.is_continued(line)
+                        && line.text() != self.markers.more,
+                    Row::Spacing(_) => false,
+                })
+                .map(|i| row + i);
+
+            if let Some(i) = found {
+                let above: usize = page.lines[..i].iter().map(Row::height).sum();
+                info.line = above + 1;
+                info.row = i;
+                self.elements.push(info);
+                self.element_indices.push(page_index);
+                return;
+            }
+
+            if done || page_index + 1 < self.sent + self.body.len() {
+                self.element_start = Some((info, page_index + 1, 0));
+            } else {
+                self.element_start = Some((info, page_index, row));
+                return;
+            }
+        }
+    }
+
+    /// Records the current page as the last page of the open scene
+    fn close_scene(&mut self) {
+        if !self.scene_open {
+            return;
+        }
+
+        let mut last = self.sent + self.body.len() - 1;
+
+        if let Some((first, end)) = self.scene_spans.last_mut() {
+            if self.body[last - self.sent].lines.is_empty() && last > *first {
+                last -= 1;
+            }
+
+            *end = last;
+        }
+
+        self.scene_open = false;
+    }
+
+    /// Ends the page in the middle of a speech with "(MORE)" and
+    /// repeats the cue on the next page with "(CONT'D)"
+    fn continue_speech_on_next_page(&mut self) {
+        let more_line = Line {
+            column: self.cur_cue.first()
+                .map_or(self.options.layout.cue_begin, |line| line.column),
+            segments: vec![Segment::from(self.markers.more.as_str())],
+            tag: None,
+        };
+
+        self.cur_page().push_line(more_line);
+
+        if self.scene_page_no >= 0 {
+            self.push_continued_bottom();
+        }
+
+        self.start_a_new_page();
+
+        if self.scene_page_no >= 0 {
+            self.push_continued_top();
+        }
+
+        let n = self.cur_cue.len();
+
+        for (i, mut line) in self.cur_cue.clone().into_iter().enumerate() {
+            if i == n - 1 && !self.markers.contd.is_empty() {
+                let s = format!(" {}", self.markers.contd);
+                line.segments.push(Segment::from(s));
+            }
+
+            line.tag = Some("cue");
+            self.cur_page().push_line(line);
+        }
+    }
+
+    /// After a speech continues onto a new page, selects the next
+    /// break for the rest of the speech, if it still doesn't fit
+    ///
+    /// The rest of the current dialogue element is passed in as
+    /// tokens.  A break inside it is returned, with token indices
+    /// relative to the slice.  A break in a later element of the
+    /// train is scheduled in the break selection instead.
+    fn select_next_train_break(&mut self, rest: &[TokenType], w: usize,
+                               hang: usize) -> Option<BreakType>
+    {
+        let mut train: Vec<BreakType> = Vec::new();
+
+        if !rest.is_empty() {
+            let break_points = breaking::find_indented_break_points(
+                rest, w, hang, hang
+            );
+
+            if break_points.len() == 1 {
+                train.push(BreakType::Atomic(break_points[0].line_no));
+            } else {
+                train.push(BreakType::List(break_points));
+            }
+        }
+
+        let offset = train.len();
+        train.extend(self.cur_train.iter().cloned());
+
+        if train.is_empty() {
+            return None;
+        }
+
+        // The cue line is already on the page.
+        let mut r = self.lines_remaining() + 1;
+        let h = breaking::train_lines_needed(&train[..]) as i32;
+
+        if self.scene_page_no >= 0 && (!self.cur_train_at_scene_end || h > r) {
+            r -= self.continued_lines(); // make room for (CONTINUED)
+        }
+
+        // The cue is already placed, so it cannot be orphaned.
+        let policy = BreakPolicy {
+            cue_orphan_threshold: 0,
+            ..self.options.break_policy
+        };
+
+        let (i, break_info) = breaking::best_train_break_with(
+            &train[..], r, &policy
+        );
+
+        if i < 0 || i as usize == train.len() {
+            return None;
+        }
+
+        let i = i as usize;
+
+        if i < offset {
+            return Some(break_info);
+        }
+
+        self.break_selection.clear();
+
+        for _ in offset..i {
+            self.break_selection.push_back(None);
+        }
+
+        for observer in self.observers.iter_mut() {
+            observer.on_break_selected(&break_info, r);
+        }
+
+        self.break_selection.push_back(Some(break_info));
+
+        None
     }
 
     fn add_numbering(&self, label: &str, line: &mut Line) {
-        let w = P_END - P_BEGIN + 1;
+        let layout = &self.options.layout;
         
         if self.numbering == Numbering::Right
             || self.numbering == Numbering::Full
         {
-            let n = w - line.length() + 1;
+            let end = line.column + line.length();
+            let n = max(layout.rh_scene_no_begin.saturating_sub(end), 1);
             let spaces = repeat(' ').take(n).collect::<String>();
             
             let suffix = format!("{}{}", spaces, &label);
@@ -178,11 +1011,12 @@ impl Formatter {
         if self.numbering == Numbering::Left
             || self.numbering == Numbering::Full
         {
-            let n =  max(6 - label.chars().count() as i32, 0) as usize;
+            let w = line.column.saturating_sub(layout.lh_scene_no_begin);
+            let n = w.saturating_sub(label.chars().count());
             let spaces = repeat(' ').take(n).collect::<String>();
                                         
             let prefix = format!("{}{}", label, spaces);
-            line.column -= prefix.chars().count();
+            line.column = line.column.saturating_sub(prefix.chars().count());
             line.segments.insert(0, Segment::from(prefix));
         }
     }
@@ -199,31 +1033,71 @@ impl Formatter {
     /// formatter.run(root);
     /// assert_eq!(formatter.body.len(), 1);
     /// ```
+    ///
+    /// A speech that runs for several pages repeats the cue with
+    /// "(CONT'D)" at the top of every continuation page:
+    ///
+    /// ```
+    /// # use batyr::document::formatter::{Formatter, Row};
+    /// # use batyr::document::reader::Reader;
+    /// let mut xml = String::from("<screenplay><head><title>FOO</title>");
+    /// xml.push_str("<authors><fullName>BAR</fullName></authors></head>");
+    /// xml.push_str("<body><cue>BOB</cue><d>");
+    ///
+    /// for i in 0..150 {
+    ///     xml.push_str(&format!("Sentence number {} goes on a while.  ", i));
+    /// }
+    ///
+    /// xml.push_str("</d></body></screenplay>");
+    ///
+    /// let root = Reader::new(&xml).run().unwrap();
+    /// let mut formatter = Formatter::new();
+    /// formatter.run(root);
+    ///
+    /// let pages = &formatter.body[1..]; // skip the fly page
+    /// assert!(pages.len() >= 3);
+    ///
+    /// for page in pages {
+    ///     assert!(page.line_count() <= page.height);
+    /// }
+    ///
+    /// for page in &pages[1..] {
+    ///     match &page.lines[0] {
+    ///         Row::Text(line) => assert_eq!(line.text(), "BOB (CONT'D)"),
+    ///         _ => panic!(),
+    ///     }
+    /// }
+    /// ```
     pub fn run(&mut self, root: ElementType) {
         let mut fly_info = FlyInfo {
             series: None,
             title: Vec::new(),
             authors: Vec::new(),
-            note: None,
+            source: None,
+            episode: None,
+            production_number: None,
+            notes: Vec::new(),
             contact: None,
         };
     
         for elem in root.into_iter() {
-            let padding_before;
+            if elem.line() > 0 && elem.break_info().is_some() {
+                self.begin_element(&elem);
+            }
 
-            match elem.get_padding_before() {
-                Some(n) => {
-                    if n < 0 {
+            self.cur_tag = elem.tag();
+
+            if elem.get_page_break_before() {
+                match &elem {
+                    // A new act or scene leaves nothing to continue.
+                    ElementType::Act(_) | ElementType::Slug(_) => {
                         self.start_a_new_page();
-                        padding_before = -n - 1;
-                    } else {
-                        padding_before = n;
-                    }
-                },
-                None => {
-                    padding_before = 0;
-                },
-            };
+                    },
+                    _ => self.break_page(),
+                }
+            }
+
+            let padding_before = elem.get_padding_before().unwrap_or(0);
 
             let padding_after = self.last_padding_after;
             
@@ -234,26 +1108,67 @@ impl Formatter {
                 None => (),
             }
             
-            match elem {
+            match &elem {
                 ElementType::Act(elem) => {
+                    self.close_scene();
+
                     if !self.cur_page().lines.is_empty() {
                         self.push_blank_lines(
                             max(padding_before as usize, padding_after)
                         );
                     }
-                    
-                    let mut line = Line::from(&elem.tokens[..]);
-                    let len = line.length();
-                    line.column = CENTER - len / 2 - len % 2;
-                    self.cur_page().lines.push(Some(line));
+
+                    let w = elem.attributes.right_margin
+                        - elem.attributes.left_margin + 1;
+
+                    for mut line in linebreak_balance(&elem.tokens[..], w) {
+                        let len = line.length();
+                        line.column = self.options.layout.centered(len);
+                        self.push_text(line);
+                    }
+
+                    self.act_pages.push(self.sent + self.body.len() - 1);
+                    self.acts.push(ActInfo {
+                        heading: Line::from(&elem.tokens[..]).text(),
+                        page: 0,
+                    });
                 },
                 ElementType::Authors(_) => (),
-                ElementType::Body(_) => (),
+                ElementType::B(_) => (),
+                ElementType::BackMatter(_) => {
+                    self.cur_scene = None;
+                    self.scene_page_no = -1;
+                    self.close_scene();
+
+                    self.unnumbered = true;
+                    self.start_a_new_page();
+                },
+                ElementType::Body(_) => {
+                    self.start_stream(&fly_info);
+
+                    // Put back the first page of the body, which
+                    // was set aside for the front matter.
+                    if self.unnumbered {
+                        self.unnumbered = false;
+
+                        if let Some((page, n)) = self.held_page.take() {
+                            self.body.push(page);
+                            self.last_padding_after = n;
+                        } else {
+                            self.start_a_new_page();
+                        }
+                    }
+                },
+                ElementType::Blank(elem) => {
+                    if !self.cur_page().lines.is_empty() {
+                        self.push_blank_lines(elem.attributes.lines);
+                    }
+                },
                 ElementType::Br(_) => {
                     self.push_blank_lines(1);
                 },
                 ElementType::Contact(elem) => {
-                    fly_info.contact = Some((&elem.tokens[..]).to_vec());
+                    fly_info.contact = Some(elem.tokens[..].to_vec());
                 },
                 ElementType::Cue(elem) => {
                     let h = elem.count_lines() as i32;
@@ -262,7 +1177,8 @@ impl Formatter {
                     if self.scene_page_no >= 0 && !elem.at_scene_end {
                         // If not followed by Slug, the page must be
                         // shortened whether or not the dialogue fits.
-                        r -= 2; // make room for (CONTINUED)
+                        // make room for (CONTINUED)
+                        r -= self.continued_lines();
                     }
                     
                     if r < h as i32 + padding_before { // dialogue won't fit
@@ -270,17 +1186,25 @@ impl Formatter {
                             // Even if followed by Slug, the page must
                             // be shortened because the dialogue will
                             // need to be broken.
-                            r -= 2; // shorten the page for (CONTINUED)
+                            // shorten the page for (CONTINUED)
+                            r -= self.continued_lines();
                         }
                     
-                        let (i, break_info)
-                            = elem.select_break(r - padding_before);
+                        let (i, break_info) = elem.select_break(
+                            r - padding_before, &self.options.break_policy
+                        );
 
                         if i >= 0 {
                             for _ in 0..i {
                                 self.break_selection.push_back(None);
                             }
 
+                            for observer in self.observers.iter_mut() {
+                                observer.on_break_selected(
+                                    &break_info, r - padding_before
+                                );
+                            }
+
                             self.break_selection.push_back(Some(break_info));
 
                             self.push_blank_lines(
@@ -310,133 +1234,142 @@ impl Formatter {
                         }
                     }
 
-                    let mut line = Line::from(&elem.tokens[..]);
-                    line.column = elem.attributes.tab_stop;
-                    self.cur_cue = Some(line.clone());
-                    self.cur_page().lines.push(Some(line));
+                    let w = elem.attributes.right_margin
+                        - elem.attributes.tab_stop + 1;
+
+                    let mut lines = linebreak_fill(&elem.tokens[..], w);
+
+                    for line in lines.iter_mut() {
+                        line.column = elem.attributes.tab_stop;
+                    }
+
+                    self.cur_cue = lines.clone();
+                    self.cur_train = elem.attributes.train.iter()
+                        .cloned()
+                        .collect();
+                    self.cur_train_at_scene_end = elem.at_scene_end;
+
+                    for line in lines {
+                        self.push_text(line);
+                    }
+
+                    self.cue_indices.push(self.sent + self.body.len() - 1);
                 },
                 ElementType::D(elem) => {
                     let w = elem.attributes.right_margin
                         - elem.attributes.left_margin + 1;
+                    let hang = elem.hang();
+
+                    self.cur_train.pop_front();
 
                     self.push_blank_lines(
                         max(padding_before as usize, padding_after)
                     );
+
+                    self.dialogue_indices.push(self.sent + self.body.len() - 1);
                                 
                     if !self.break_selection.is_empty() {
                         match self.break_selection.pop_front().unwrap() {
                             None => {
-                                let lines = linebreak_fill(&elem.tokens[..], w);
+                                let lines = linebreak_indent(
+                                    &elem.tokens[..], w, elem.first_indent(), hang
+                                );
 
                                 for mut line in lines {
-                                    line.column = elem.attributes.left_margin;
-                                    self.cur_page().lines.push(Some(line));
+                                    line.column += elem.attributes.left_margin;
+                                    self.push_text(line);
                                 }
                             },
-                            Some(break_info) => {
-                                match break_info {
-                                    BreakType::Atomic(_) => {
-                                        let lines = linebreak_fill(
-                                            &elem.tokens[..], w
-                                        );
-
-                                        for mut line in lines {
-                                            line.column = elem.attributes.left_margin;
-                                            self.cur_page().lines.push(Some(line));
-                                        }
-
-                                        let more_line = Line {
-                                            column: CUE_BEGIN,
-                                            segments: vec![Segment::from("(MORE)")],
-                                        };
-                                        
-                                        self.cur_page().lines.push(Some(more_line));
-
-                                        if self.scene_page_no >= 0 {
-                                            self.push_continued_bottom();
-                                        }
-
-                                        self.start_a_new_page();
-
-                                        if self.scene_page_no >= 0 {
-                                            self.push_continued_top();
-                                        }
-                                        
-                                        if self.cur_cue.is_some() {
-                                            let mut line = mem::replace(
-                                                &mut self.cur_cue, None
-                                            ).unwrap();
-                                            
-                                            line.segments.push(Segment::from(" (CONT'D)"));
-                                            self.cur_page().lines.push(Some(line));
-                                        }
-                                    },
-                                    BreakType::Point(break_point) => {
-                                        let mut lines = linebreak_fill(
-                                            &elem.tokens[0..break_point.token_index], w
-                                        );
-
-                                        for mut line in lines {
-                                            line.column = elem.attributes.left_margin;
-                                            self.cur_page().lines.push(Some(line));
-                                        }
-
-                                        let more_line = Line {
-                                            column: CUE_BEGIN,
-                                            segments: vec![Segment::from("(MORE)")],
-                                        };
-                                        
-                                        self.cur_page().lines.push(Some(more_line));
-
-                                        if self.scene_page_no >= 0 {
-                                            self.push_continued_bottom();
-                                        }
-                                        
-                                        self.start_a_new_page();
+                            Some(mut break_info) => {
+                                let mut start = 0;
+
+                                // A long speech may need a break on
+                                // every page it runs onto.
+                                loop {
+                                    let end = match &break_info {
+                                        BreakType::Atomic(_) => elem.tokens.len(),
+                                        BreakType::Point(break_point) => {
+                                            break_point.token_index
+                                        },
+                                        _ => break,
+                                    };
+
+                                    // Only the first line of the speech
+                                    // is indented; later pages hang.
+                                    let indent = if start == 0 {
+                                        elem.first_indent()
+                                    } else {
+                                        hang
+                                    };
+
+                                    let lines = linebreak_indent(
+                                        &elem.tokens[start..end], w, indent, hang
+                                    );
+
+                                    for mut line in lines {
+                                        line.column += elem.attributes.left_margin;
+                                        self.push_text(line);
+                                    }
+
+                                    self.continue_speech_on_next_page();
+                                    start = end;
+
+                                    match self.select_next_train_break(
+                                        &elem.tokens[start..], w, hang
+                                    ) {
+                                        Some(BreakType::Point(mut break_point)) => {
+                                            break_point.token_index += start;
+                                            break_info = BreakType::Point(break_point);
+                                        },
+                                        Some(next_break_info) => {
+                                            break_info = next_break_info;
+                                        },
+                                        None => break,
+                                    }
+                                }
 
-                                        if self.scene_page_no >= 0 {
-                                            self.push_continued_top();
-                                        }
-                                        
-                                        if self.cur_cue.is_some() {
-                                            let mut line = mem::replace(
-                                                &mut self.cur_cue, None
-                                            ).unwrap();
-                                            
-                                            line.segments.push(Segment::from(" (CONT'D)"));
-                                            self.cur_page().lines.push(Some(line));
-                                        }
-
-                                        lines = linebreak_fill(
-                                            &elem.tokens[break_point.token_index..], w
-                                        );
-
-                                        for mut line in lines {
-                                            line.column = elem.attributes.left_margin;
-                                            self.cur_page().lines.push(Some(line));
-                                        }
-                                    },
-                                    _ => (),
+                                if start < elem.tokens.len() {
+                                    let indent = if start == 0 {
+                                        elem.first_indent()
+                                    } else {
+                                        hang
+                                    };
+
+                                    let lines = linebreak_indent(
+                                        &elem.tokens[start..], w, indent, hang
+                                    );
+
+                                    for mut line in lines {
+                                        line.column += elem.attributes.left_margin;
+                                        self.push_text(line);
+                                    }
                                 }
                             }
                         }
                             
                     } else { // No break information left by the cue.
-                        let lines = linebreak_fill(&elem.tokens[..], w);
+                        let lines = linebreak_indent(
+                            &elem.tokens[..], w, elem.first_indent(), hang
+                        );
 
                         for mut line in lines {
-                            line.column = elem.attributes.left_margin;
-                            self.cur_page().lines.push(Some(line));
+                            line.column += elem.attributes.left_margin;
+                            self.push_text(line);
                         }
                     }
                 },
                 ElementType::Dir(elem) => {
-                    if !self.break_selection.is_empty() {
-                        // Page breaks must always come before, not
-                        // after, personal direction, so this value
-                        // should always be None.
-                        let _ = self.break_selection.pop_front();
-                    }
+                    self.cur_train.pop_front();
+
+                    // Page breaks come before, not after, personal
+                    // direction unless the break policy allows it.
+                    let break_after = matches!(
+                        self.break_selection.pop_front(),
+                        Some(Some(_))
+                    );
+
+                    debug_assert!(!break_after
+                                  || self.options.break_policy.break_after_dir);
 
                     let w = elem.attributes.right_margin
                         - elem.attributes.left_margin + 1;
@@ -456,40 +1389,92 @@ impl Formatter {
                             line.segments.push(Segment::from(")"));
                         }
 
-                        self.cur_page().lines.push(Some(line));
+                        self.push_text(line);
+                    }
+
+                    if break_after {
+                        self.continue_speech_on_next_page();
+                        self.select_next_train_break(&[], w, 0);
                     }
                 },
                 ElementType::Em(_) => (),
                 ElementType::End(elem) => {
-                   if self.lines_remaining() < padding_before + 1 {
+                    let w = elem.attributes.right_margin
+                        - elem.attributes.left_margin + 1;
+                    let lines = linebreak_balance(&elem.tokens[..], w);
+                    let h = lines.len();
+                    let gap = max(padding_before as usize,
+                                  self.options.end_gap);
+
+                   if self.lines_remaining() < (gap + h) as i32 {
+                        // Rather than leave the text alone at the top
+                        // of the next page, take the last block along,
+                        // unless this page is exactly full.
+                        let block = match self.lines_remaining() > 0 {
+                            true => self.take_last_block(gap + h),
+                            false => Vec::new(),
+                        };
+
                         self.start_a_new_page();
 
                         if self.scene_page_no >= 0 {
                             self.push_continued_top();
                         }
-                        
-                    } else if !self.cur_page().lines.is_empty() {
-                        self.push_blank_lines(
-                            max(padding_before as usize, padding_after)
-                        );
-
-                       // One space will be skipped automatically due
-                       // to the padding_before attribute.  Skip up to
-                       // four additional lines if space allows.
-                       let r = self.lines_remaining() - padding_before;
 
-                       if r > 0 {
-                           self.push_blank_lines(min(r as usize, 4));
+                        if !block.is_empty() {
+                            self.cur_page().lines.extend(block);
+                            self.push_blank_lines(gap);
+                        }
+                        
+                    } else if !self.cur_page().lines.is_empty() {
+                        self.push_blank_lines(max(gap, padding_after));
+
+                       if elem.attributes.place == Placement::Inline {
+                           // One space will be skipped automatically
+                           // due to the gap.  Skip up to four
+                           // additional lines if space allows.
+                           let r = self.lines_remaining() - gap as i32
+                               - (h as i32 - 1);
+
+                           if r > 0 {
+                               self.push_blank_lines(min(r as usize, 4));
+                           }
                        }
                    }
 
-                    let mut line = Line::from(&elem.tokens[..]);
-                    let len = line.length();
-                    line.column = CENTER - len / 2 - len % 2;
-                    self.cur_page().lines.push(Some(line));
+                    let r = self.lines_remaining() - h as i32;
+
+                    match elem.attributes.place {
+                        Placement::Inline => (),
+                        Placement::Center if r > 0 => {
+                            self.push_blank_lines(r as usize / 2);
+                        },
+                        Placement::Bottom if r > 0 => {
+                            self.push_blank_lines(r as usize);
+                        },
+                        _ => (),
+                    }
+
+                    for mut line in lines {
+                        let len = line.length();
+                        line.column = self.options.layout.centered(len);
+                        self.push_text(line);
+                    }
 
                     self.cur_scene = None;
                     self.scene_page_no = -1;
+                    self.close_scene();
+                },
+                ElementType::Episode(elem) => {
+                    fly_info.episode = Some(elem.tokens[..].to_vec());
+                },
+                ElementType::FrontMatter(_) => {
+                    self.start_stream(&fly_info);
+
+                    self.held_page = self.body.pop()
+                        .map(|page| (page, self.last_padding_after));
+                    self.unnumbered = true;
+                    self.start_a_new_page();
                 },
                 ElementType::FullName(elem) => {
                     if !fly_info.authors.is_empty() {
@@ -502,7 +1487,9 @@ impl Formatter {
                 },
                 ElementType::Head(_) => (),
                 ElementType::Note(elem) => {
-                    fly_info.note = Some((&elem.tokens[..]).to_vec());
+                    fly_info.notes.push(
+                        (elem.attributes.align, elem.tokens[..].to_vec())
+                    );
                 },
                 ElementType::Open(elem) => {
                    if self.lines_remaining() < padding_before + 1 {
@@ -512,6 +1499,8 @@ impl Formatter {
                             self.push_continued_top();
                         }
                         
+                    } else if elem.attributes.after_act {
+                        self.push_blank_lines(padding_before as usize);
                     } else if !self.cur_page().lines.is_empty() {
                         self.push_blank_lines(
                             max(padding_before as usize, padding_after)
@@ -520,14 +1509,39 @@ impl Formatter {
                     
                     let mut line = Line::from(&elem.tokens[..]);
                     line.column = elem.attributes.tab_stop;
-                    self.cur_page().lines.push(Some(line));
+                    self.push_text(line);
                 },
                 ElementType::P(elem) => {
+                    let mut fresh_page = false;
+
+                    if elem.attributes.keep_with_next
+                        && !self.cur_page().lines.is_empty()
+                    {
+                        let kept = breaking::keep_with_next_lines(
+                            &elem.break_info, elem.attributes.padding_after,
+                            &elem.attributes.train[..]
+                        ) as i32;
+                        let mut r = self.lines_remaining();
+
+                        if self.scene_page_no >= 0 {
+                            r -= self.continued_lines();
+                        }
+
+                        // Leave room for "CONTINUED:" on a fresh page.
+                        let room = self.options.layout.lines_per_page() as i32 - 2;
+
+                        if r < kept + padding_before && kept <= room {
+                            self.break_page();
+                            fresh_page = true;
+                        }
+                    }
+
                     let h = elem.count_lines();
                     let mut r = self.lines_remaining();
 
                     if self.scene_page_no >= 0 && !elem.at_scene_end {
-                        r -= 2; // make room for (CONTINUED)
+                        // make room for (CONTINUED)
+                        r -= self.continued_lines();
                     }
                     
                     let mut break_point: Option<BreakPoint> = None;
@@ -544,7 +1558,8 @@ impl Formatter {
                         }
 
                     } else if r < h as i32 + padding_before {
-                        match elem.select_break(r - padding_before) {
+                        match elem.select_break(r - padding_before,
+                                                &self.options.break_policy) {
                             BreakType::Mandatory => {
                                 if self.scene_page_no >= 0 {
                                     self.push_continued_bottom();
@@ -572,7 +1587,7 @@ impl Formatter {
                             },
                             _ => (),
                         }
-                    } else if !self.cur_page().lines.is_empty() {
+                    } else if !fresh_page && !self.cur_page().lines.is_empty() {
                         self.push_blank_lines(
                             max(padding_before as usize, padding_after)
                         );
@@ -580,16 +1595,18 @@ impl Formatter {
                     
                     let w = elem.attributes.right_margin
                         - elem.attributes.left_margin + 1;
+                    let hang = elem.hang();
 
                     match break_point {
                         Some(break_point) => {
-                            let mut lines = linebreak_fill(
-                                &elem.tokens[0..break_point.token_index], w
+                            let mut lines = linebreak_indent(
+                                &elem.tokens[0..break_point.token_index], w,
+                                elem.first_indent(), hang
                             );
 
                             for mut line in lines {
-                                line.column = elem.attributes.left_margin;
-                                self.cur_page().lines.push(Some(line));
+                                line.column += elem.attributes.left_margin;
+                                self.push_text(line);
                             }
 
                             if self.scene_page_no >= 0 {
@@ -602,38 +1619,54 @@ impl Formatter {
                                 self.push_continued_top();
                             }
                                 
-                            lines = linebreak_fill(
-                                &elem.tokens[break_point.token_index..], w
+                            lines = linebreak_indent(
+                                &elem.tokens[break_point.token_index..], w,
+                                hang, hang
                             );
 
                             for mut line in lines {
-                                line.column = elem.attributes.left_margin;
-                                self.cur_page().lines.push(Some(line));
+                                line.column += elem.attributes.left_margin;
+                                self.push_text(line);
                             }
                         },
                         None => {
-                            let lines = linebreak_fill(&elem.tokens[..], w);
+                            let lines = linebreak_indent(
+                                &elem.tokens[..], w, elem.first_indent(), hang
+                            );
                         
                             for mut line in lines {
-                                line.column = elem.attributes.left_margin;
-                                self.cur_page().lines.push(Some(line));
+                                line.column += elem.attributes.left_margin;
+                                self.push_text(line);
                             }
                         },
                     }
                 },
                 ElementType::PageBreak(_) => {
-                    if self.scene_page_no >= 0 {
-                        self.push_continued_bottom();
+                    self.break_page();
+                },
+                ElementType::ProductionNumber(elem) => {
+                    fly_info.production_number = Some(elem.tokens[..].to_vec());
+                },
+                ElementType::Screenplay(elem) => {
+                    self.numbering = elem.attributes.numbering;
+                    self.continued = elem.attributes.authority.continued();
+                    self.markers = elem.attributes.markers.clone();
+                    self.justify = elem.attributes.justify;
+
+                    let policy = &mut self.options.break_policy;
+
+                    if let Some(n) = elem.attributes.lines_before_break {
+                        policy.min_lines_before = n;
                     }
 
-                    self.start_a_new_page();
+                    if let Some(n) = elem.attributes.lines_after_break {
+                        policy.min_lines_after = n;
+                    }
 
-                    if self.scene_page_no >= 0 {
-                        self.push_continued_top();
+                    if let Some(value) = elem.attributes.break_after_dir {
+                        policy.break_after_dir = value;
                     }
-                },
-                ElementType::Screenplay(elem) => {
-                    self.numbering = elem.attributes.numbering;
+
                     self.start_a_new_page();
                 },
                 ElementType::Series(elem) => {
@@ -644,37 +1677,61 @@ impl Formatter {
                     
                     for mut line in lines {
                         let len = line.length();
-                        line.column = CENTER - len / 2 - len % 2;
-                        self.cur_page().lines.push(Some(line));
+                        line.column = self.options.layout.centered(len);
+                        self.push_text(line);
+                    }
+
+                    fly_info.series = Some(elem.tokens[..].to_vec());
+                },
+                ElementType::Shot(elem) => {
+                    let lines = elem.lines();
+                    let h = elem.lines_to_first_break();
+                    let mut r = self.lines_remaining();
+
+                    if self.scene_page_no >= 0 && !elem.at_scene_end {
+                        // make room for (CONTINUED)
+                        r -= self.continued_lines();
+                    }
+
+                    // The shot is part of the scene around it, which
+                    // carries on over the page break.
+                    if r < h as i32 + padding_before {
+                        self.break_page();
+
+                    } else if !self.cur_page().lines.is_empty() {
+                        self.push_blank_lines(
+                            max(padding_before as usize, padding_after)
+                        );
                     }
 
-                    fly_info.series = Some((&elem.tokens[..]).to_vec());
+                    for mut line in lines {
+                        line.column = elem.attributes.left_margin;
+                        self.push_text(line);
+                    }
                 },
                 ElementType::Slug(elem) => {
+                    self.close_scene();
+
                     let label;
                     
                     if self.numbering != Numbering::None {
-                        if let Some(c) =  elem.attributes.addition {
-                            label = format!("{}{}", elem.attributes.number, c);
-                        } else {
-                            label = format!("{}", elem.attributes.number);
-                        }
+                        label = elem.label();
 
-                        self.cur_scene = Some(label.clone());
-                        self.scene_page_no = 0;
+                        if self.continued {
+                            self.cur_scene = Some(label.clone())
+                                .filter(|label| !label.is_empty());
+                            self.scene_page_no = 0;
+                        }
                     
                     } else {
                         label = String::new();
                     }
                     
-                    let w: usize = elem.attributes.right_margin
-                        - elem.attributes.left_margin + 1;
-                    
-                    let lines = linebreak_fill(&elem.tokens[..], w);
+                    let lines = elem.lines();
                     let h = elem.lines_to_first_break();
                     let mut r = self.lines_remaining();
 
-                    r -= 2; // Make room for (CONTINUED).
+                    r -= self.continued_lines(); // Make room for (CONTINUED).
 
                     if r < h as i32 + padding_before {
                         self.start_a_new_page();
@@ -688,12 +1745,38 @@ impl Formatter {
                     for (i, mut line) in lines.into_iter().enumerate() {
                         line.column = elem.attributes.left_margin;
 
-                        if i == 0 {
+                        if i == 0 && !label.is_empty() {
                             self.add_numbering(&label, &mut line);
                         }
                         
-                        self.cur_page().lines.push(Some(line));
+                        self.push_text(line);
                     }
+
+                    self.slug_indices.push(self.sent + self.body.len() - 1);
+
+                    if elem.attributes.continued && !self.scenes.is_empty() {
+                        // A continued scene picks up where it left off.
+                        self.scene_open = true;
+
+                    } else {
+                        let number = elem.label();
+
+                        let page_index = self.sent + self.body.len() - 1;
+                        self.scene_spans.push((page_index, page_index));
+                        self.scene_open = true;
+
+                        self.scenes.push(SceneInfo {
+                            number,
+                            heading: Line::from(&elem.tokens[..]).text(),
+                            line: elem.line,
+                            first_page: 0,
+                            page_line: 0,
+                            last_page: 0,
+                        });
+                    }
+                },
+                ElementType::Source(elem) => {
+                    fly_info.source = Some(elem.tokens[..].to_vec());
                 },
                 ElementType::Title(elem) => {
                     let w = elem.attributes.right_margin
@@ -712,11 +1795,13 @@ impl Formatter {
 
                     for mut line in lines {
                         let len = line.length();
-                        line.column = CENTER - len / 2 - len % 2;
-                        self.cur_page().lines.push(Some(line));
+                        line.column = self.options.layout.centered(len);
+                        self.push_text(line);
+                    }
+
+                    if !self.unnumbered {
+                        fly_info.title.extend_from_slice(&elem.tokens[..]);
                     }
-                    
-                    fly_info.title.extend_from_slice(&elem.tokens[..]);
                 },
                 ElementType::Trans(elem) => {
                     let h: usize = 1;
@@ -739,52 +1824,536 @@ impl Formatter {
                     let w = elem.attributes.right_margin
                         - elem.attributes.tab_stop - 1;
                     let n = line.length();
-                    if n > w {
+                    if n > w || elem.attributes.align == Alignment::Right {
                         line.column = elem.attributes.right_margin - n;
                     } else {
                         line.column = elem.attributes.tab_stop;
                     }
                     
-                    self.cur_page().lines.push(Some(line));
+                    self.push_text(line);
 
                     self.cur_scene = None;
                     self.scene_page_no = -1;
+                    self.close_scene();
                 },
-            }            
+                ElementType::U(_) => (),
+            }
+
+            self.find_element_start(true);
+
+            if let Some(page) = self.body.last() {
+                for observer in self.observers.iter_mut() {
+                    observer.on_element_formatted(&elem, page);
+                }
+            }
         }
 
-        let (title, fly_page) = self.format_fly_page(fly_info);
+        if !self.body.is_empty() {
+            self.close_scene();
+        }
+
+        if self.stream.is_some() {
+            self.finish_stream(&fly_info);
+        } else {
+            self.fix_up();
+        }
+
+        for (scene, (first, last)) in self.scenes.iter_mut()
+            .zip(self.scene_spans.iter())
+        {
+            scene.first_page = self.page_numbers[*first];
+            scene.last_page = self.page_numbers[*last];
+        }
+
+        for (act, i) in self.acts.iter_mut().zip(self.act_pages.iter()) {
+            act.page = self.page_numbers[*i];
+        }
+
+        self.dialogue_pages = self.dialogue_indices.iter()
+            .map(|i| self.page_numbers[*i])
+            .collect();
+
+        self.slug_pages = self.slug_indices.iter()
+            .map(|i| self.page_numbers[*i])
+            .collect();
+
+        self.cue_pages = self.cue_indices.iter()
+            .map(|i| self.page_numbers[*i])
+            .collect();
+
+        for (info, i) in self.elements.iter_mut()
+            .zip(self.element_indices.iter())
+        {
+            info.page = self.page_numbers[*i];
+        }
+
+        for scene in self.scenes.iter_mut() {
+            let slug = self.elements.iter().find(|info| info.tag == "slug"
+                && info.source_line == scene.line
+                && info.page == scene.first_page);
+
+            if let Some(info) = slug {
+                scene.page_line = info.line;
+            }
+        }
+
+        if self.stream_started {
+            return;
+        }
+
+        if self.justify {
+            self.justify_pages();
+        }
+
+        self.header = Formatter::format_header(&fly_info);
+
+        let (title, fly_page) = self.format_fly_page(&fly_info);
 
         self.title = title;
         self.body.insert(0, fly_page);
     }
 
-    fn format_fly_page(&self, fly_info: FlyInfo) -> (String, Page) {
+    /// Sends the title, header, and fly page once the head has been
+    /// read, if streaming
+    fn start_stream(&mut self, fly_info: &FlyInfo) {
+        if self.stream.is_none() || self.stream_started {
+            return;
+        }
+
+        self.stream_started = true;
+        self.header = Formatter::format_header(fly_info);
+
+        let (title, fly_page) = self.format_fly_page(fly_info);
+
+        self.title = title;
+        self.send(Streamed::Head {
+            title: self.title.clone(),
+            header: self.header.clone(),
+        });
+        self.send(Streamed::Page(fly_page));
+    }
+
+    /// Sends every page but the current one, if streaming
+    fn flush_pages(&mut self) {
+        if !self.stream_started {
+            return;
+        }
+
+        while self.body.len() > 1 {
+            self.find_element_start(false);
+
+            let page = self.body.remove(0);
+            self.sent += 1;
+            self.send_finished_page(page);
+        }
+    }
+
+    /// Sends the remaining pages and closes the channel
+    fn finish_stream(&mut self, fly_info: &FlyInfo) {
+        self.start_stream(fly_info);
+
+        for page in std::mem::take(&mut self.body) {
+            self.sent += 1;
+            self.send_finished_page(page);
+        }
+
+        // The last page is never justified.
+        if let Some(page) = self.pending.take() {
+            self.send(Streamed::Page(page));
+        }
+
+        self.next_page_no -= self.removed;
+        self.remap_page_indices();
+        self.stream = None;
+    }
+
+    /// Fixes up a page and sends the one before it, holding the page
+    /// back in case it turns out to be the last
+    fn send_finished_page(&mut self, page: Page) {
+        if let Some(page) = self.finish_page(page) {
+            if let Some(mut prev) = self.pending.replace(page) {
+                if self.justify {
+                    Formatter::justify_page(&mut prev);
+                }
+
+                self.send(Streamed::Page(prev));
+            }
+        }
+    }
+
+    fn send(&mut self, message: Streamed) {
+        if let Some(sender) = &self.stream {
+            // Nobody is listening anymore, so stop sending.
+            if sender.send(message).is_err() {
+                self.stream = None;
+            }
+        }
+    }
+
+    /// Normalizes the formatted pages
+    ///
+    /// The page breaking rules occasionally leave degenerate pages
+    /// behind.  This pass removes trailing blank lines from each page,
+    /// drops "(CONTINUED)" markers that end up as the only content on
+    /// a page, and removes pages that are left empty, renumbering the
+    /// pages that follow.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::formatter::{Formatter, Page, Row};
+    /// # use batyr::text::{Line, Segment};
+    /// let line = |s| Row::Text(Line::from(Segment::from(s)));
+    /// let page = |number, lines| Page {
+    ///     number: number,
+    ///     height: 55,
+    ///     lines: lines,
+    ///     footer: Vec::new(),
+    /// };
+    ///
+    /// let mut formatter = Formatter::new();
+    /// formatter.body = vec![
+    ///     page(1, vec![line("foo"), Row::Spacing(2)]),
+    ///     page(2, vec![Row::Spacing(1), line("(CONTINUED)")]),
+    ///     page(3, vec![line("bar")]),
+    /// ];
+    /// formatter.fix_up();
+    ///
+    /// assert_eq!(formatter.body.len(), 2);
+    /// assert_eq!(formatter.body[0].line_count(), 1);
+    /// assert_eq!(formatter.body[1].number, 2);
+    /// assert!(matches!(&formatter.body[1].lines[0],
+    ///                  Row::Text(line) if line.text() == "bar"));
+    /// ```
+    pub fn fix_up(&mut self) {
+        self.removed = 0;
+        self.page_map.clear();
+        self.page_numbers.clear();
+
+        let mut pages: PageList = Vec::with_capacity(self.body.len());
+
+        for page in std::mem::take(&mut self.body) {
+            if let Some(page) = self.finish_page(page) {
+                pages.push(page);
+            }
+        }
+
+        self.body = pages;
+        self.next_page_no -= self.removed;
+        self.remap_page_indices();
+    }
+
+    /// Normalizes a single page, returning it unless it is dropped
+    ///
+    /// Records where the page ends up and, if it is kept, its new
+    /// number.
+    fn finish_page(&mut self, mut page: Page) -> Option<Page> {
+        self.page_map.push((self.page_numbers.len(), false));
+
+        while let Some(Row::Spacing(_)) = page.lines.last() {
+            page.lines.pop();
+        }
+
+        let only_continued = page.lines.iter()
+            .all(|row| match row {
+                Row::Text(line) => self.is_continued(line),
+                Row::Spacing(_) => true,
+            });
+
+        if only_continued {
+            page.lines.clear();
+        }
+
+        if page.lines.is_empty() && page.footer.is_empty() {
+            if page.number > 0 {
+                self.removed += 1;
+            }
+
+            return None;
+        }
+
+        if let Some(entry) = self.page_map.last_mut() {
+            entry.1 = true;
+        }
+
+        if page.number > 0 {
+            page.number -= self.removed;
+        }
+
+        self.page_numbers.push(page.number);
+        Some(page)
+    }
+
+    /// Moves the scene and act page indices to where their pages
+    /// ended up after fixing up
+    fn remap_page_indices(&mut self) {
+        let map = &self.page_map;
+
+        // A scene ending on a removed page ends on the page before.
+        for (first, last) in self.scene_spans.iter_mut() {
+            let (i, kept) = map[*last];
+            let end = if kept { i } else { i.saturating_sub(1) };
+
+            *first = map[*first].0;
+            *last = max(*first, end);
+        }
+
+        for i in self.act_pages.iter_mut()
+            .chain(self.dialogue_indices.iter_mut())
+            .chain(self.slug_indices.iter_mut())
+            .chain(self.cue_indices.iter_mut())
+            .chain(self.element_indices.iter_mut())
+        {
+            *i = map[*i].0;
+        }
+    }
+
+    /// Distributes leftover lines on short pages
+    ///
+    /// Each run of blank lines between two elements may grow by at
+    /// most [`MAX_JUSTIFY_LINES`] lines, so pages that end early
+    /// because of an act break or a mandatory page break are only
+    /// opened up slightly.  The extra lines are spread evenly from
+    /// the top of the page down.  The last page is left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::formatter::{Formatter, Page, Row};
+    /// # use batyr::text::{Line, Segment};
+    /// let line = |s| Row::Text(Line::from(Segment::from(s)));
+    ///
+    /// let mut formatter = Formatter::new();
+    /// formatter.body = vec![
+    ///     Page {
+    ///         number: 1,
+    ///         height: 5,
+    ///         lines: vec![line("foo"), Row::Spacing(1), line("bar")],
+    ///         footer: Vec::new(),
+    ///     },
+    ///     Page {
+    ///         number: 2,
+    ///         height: 5,
+    ///         lines: vec![line("baz")],
+    ///         footer: Vec::new(),
+    ///     },
+    /// ];
+    /// formatter.justify_pages();
+    ///
+    /// assert_eq!(formatter.body[0].line_count(), 4);
+    /// assert_eq!(formatter.body[1].line_count(), 1);
+    /// ```
+    pub fn justify_pages(&mut self) {
+        let n = self.body.len();
+
+        for page in self.body.iter_mut().take(max(n, 1) - 1) {
+            Formatter::justify_page(page);
+        }
+    }
+
+    fn justify_page(page: &mut Page) {
+        let leftover = page.height as i32 - page.line_count() as i32;
+
+        if leftover <= 0 {
+            return;
+        }
+
+        // Only spacing between two lines of text counts as a gap.
+        let m = page.lines.len();
+        let gaps: Vec<usize> = (1..max(m, 1) - 1)
+            .filter(|&i| matches!(page.lines[i], Row::Spacing(_)))
+            .collect();
+
+        if gaps.is_empty() {
+            return;
+        }
+
+        let k = gaps.len();
+        let extra = min(leftover as usize, k * MAX_JUSTIFY_LINES);
+
+        for j in 0..extra {
+            if let Row::Spacing(h) = &mut page.lines[gaps[j * k / extra]] {
+                *h += 1;
+            }
+        }
+    }
+
+    /// Checks that no line of output runs past the right margin
+    ///
+    /// Each line is measured by its physical width in points, using
+    /// the character width of the typeface.  Returns a message for
+    /// each line that is too wide.  Right-hand scene numbers hang in
+    /// the margin by design, so they are not measured.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::formatter::{Formatter, Page, Row};
+    /// # use batyr::text::{Line, Segment};
+    /// let mut formatter = Formatter::new();
+    /// let mut line = Line::from(Segment::from("x".repeat(70)));
+    /// line.column = 16;
+    ///
+    /// formatter.body.push(Page {
+    ///     number: 1,
+    ///     height: 55,
+    ///     lines: vec![Row::Text(line)],
+    ///     footer: Vec::new(),
+    /// });
+    ///
+    /// let problems = formatter.verify();
+    /// assert_eq!(problems.len(), 1);
+    /// assert!(problems[0].starts_with("page 1, line 1:"));
+    /// ```
+    ///
+    /// The example scripts typeset within the margins:
+    ///
+    /// ```
+    /// # use batyr::document::formatter::Formatter;
+    /// # use batyr::document::reader::Reader;
+    /// for name in ["goodrich", "lederer"] {
+    ///     let path = format!("examples/{}.tyr", name);
+    ///     let xml = std::fs::read_to_string(path).unwrap();
+    ///     let root = Reader::new(&xml).run().unwrap();
+    ///     let mut formatter = Formatter::new();
+    ///     formatter.run(root);
+    ///     assert_eq!(formatter.verify(), Vec::<String>::new());
+    /// }
+    /// ```
+    pub fn verify(&self) -> Vec<String> {
+        let layout = &self.options.layout;
+        let mut problems: Vec<String> = Vec::new();
+        let limit = (layout.right_margin + 1) as f32 * layout.char_width;
+
+        for (i, page) in self.body.iter().enumerate() {
+            let rows = page.lines.iter().chain(page.footer.iter());
+            let mut line_no = 0;
+
+            for row in rows {
+                let line = match row {
+                    Row::Text(line) => line,
+                    Row::Spacing(n) => {
+                        line_no += n;
+                        continue;
+                    },
+                };
+
+                line_no += 1;
+
+                let mut text = line.text();
+
+                if let Some(segment) = line.segments.last() {
+                    let label = segment.text.trim_start();
+
+                    if segment.text.starts_with(' ') && !label.is_empty()
+                        && self.scenes.iter().any(|scene| scene.number == label)
+                    {
+                        text.truncate(text.len() - segment.text.len());
+                    }
+                }
+
+                let len = text.trim_end().chars().count();
+                let right_edge = (line.column + len) as f32 * layout.char_width;
+
+                if right_edge > limit {
+                    let place = if page.number > 0 {
+                        format!("page {}", page.number)
+                    } else if i == 0 {
+                        "title page".to_string()
+                    } else {
+                        format!("unnumbered sheet {}", i + 1)
+                    };
+
+                    problems.push(format!(
+                        "{}, line {}: \"{}\" ends at column {}, \
+                         past the right margin at column {}",
+                        place, line_no, text.trim_end(),
+                        line.column + len - 1, layout.right_margin
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Returns true for the "CONTINUED:" and "(CONTINUED)" lines
+    /// generated by the formatter
+    fn is_continued(&self, line: &Line) -> bool {
+        let word = &self.markers.continued;
+        let text = line.text();
+        text == format!("({})", word) || text.contains(&format!("{}:", word))
+    }
+
+    /// Composes the running page header from the production number
+    /// and episode title, as in <tt>#304 'The Pilot'</tt>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::reader::Reader;
+    /// # use batyr::document::formatter::Formatter;
+    /// let mut xml = String::from("<screenplay><head><title>FOO</title>");
+    /// xml.push_str("<episode>The Pilot</episode>");
+    /// xml.push_str("<productionNumber>304</productionNumber>");
+    /// xml.push_str("<authors><fullName>BAR</fullName></authors></head>");
+    /// xml.push_str("<body><p>Hello.</p></body></screenplay>");
+    ///
+    /// let root = Reader::new(&xml).run().unwrap();
+    /// let mut formatter = Formatter::new();
+    /// formatter.run(root);
+    ///
+    /// assert_eq!(formatter.header, "#304 'The Pilot'");
+    /// ```
+    fn format_header(fly_info: &FlyInfo) -> String {
+        let mut parts: Vec<String> = Vec::new();
+
+        if let Some(tokens) = &fly_info.production_number {
+            let number = Line::from(&tokens[..]).text();
+
+            if number.starts_with('#') {
+                parts.push(number);
+            } else {
+                parts.push(format!("#{}", number));
+            }
+        }
+
+        if let Some(tokens) = &fly_info.episode {
+            parts.push(format!("'{}'", Line::from(&tokens[..]).text()));
+        }
+
+        parts.join(" ")
+    }
+
+    fn format_fly_page(&self, fly_info: &FlyInfo) -> (String, Page) {
+        let layout = &self.options.layout;
         let mut title = String::new();
 
+        // The title page keeps the full page whatever the number of
+        // lines per page of the body: it runs down to one line above
+        // the footer
         let mut page = Page {
             number: -1,
-            height: TOP_LINE - BOTTOM_LINE + 1,
+            height: layout.top_line - layout.footer_line - 1,
             lines: Vec::new(),
             footer: Vec::new(),
         };
 
-        let left_margin = LEFT_MARGIN + 2 * INDENT;
-        let right_margin = RIGHT_MARGIN - 2 * INDENT;
+        let left_margin = layout.centered_left_margin();
+        let right_margin = layout.centered_right_margin();
         let w = right_margin - left_margin + 1;
         
-        for _ in 0 .. TITLE_SKIP {
-            page.lines.push(None);
-        }
+        page.push_spacing(layout.title_skip);
         
-        if let Some(series_tokens) = fly_info.series {
+        if let Some(series_tokens) = &fly_info.series {
             let lines = linebreak_balance(&series_tokens[..], w);
 
             for mut line in lines {
                 let len = line.length();
-                line.column = CENTER - len / 2 - len % 2;
-                page.lines.push(Some(line));
-                page.lines.push(None);
+                line.column = layout.centered(len);
+                line.tag = Some("series");
+                page.push_line(line);
+                page.push_spacing(1);
             }
         }
 
@@ -796,47 +2365,80 @@ impl Formatter {
             }
 
             let len = line.length();
-            line.column = CENTER - len / 2 - len % 2;
-            page.lines.push(Some(line));
-            page.lines.push(None);
+            line.column = layout.centered(len);
+            line.tag = Some("title");
+            page.push_line(line);
+            page.push_spacing(1);
         }
         
-        page.lines.push(None);
-        page.lines.push(None);
+        page.push_spacing(2);
 
         let mut author_lines = linebreak_balance(&fly_info.authors[..], w);
         author_lines.insert(0, Line::from(Segment::from("written by")));
         
         for mut line in author_lines {
             let len = line.length();
-            line.column = CENTER - len / 2 - len % 2;
-            page.lines.push(Some(line));
-            page.lines.push(None);
+            line.column = layout.centered(len);
+            line.tag = Some("authors");
+            page.push_line(line);
+            page.push_spacing(1);
         }
 
-        if let Some(note_tokens) = fly_info.note {
-            page.lines.push(None);
-            page.lines.push(None);
+        if let Some(source_tokens) = &fly_info.source {
+            page.push_spacing(1);
             
-            let lines = linebreak_balance(&note_tokens[..], w);
+            let lines = linebreak_balance(&source_tokens[..], w);
+
+            for mut line in lines {
+                let len = line.length();
+                line.column = layout.centered(len);
+                line.tag = Some("source");
+                page.push_line(line);
+                page.push_spacing(1);
+            }
+        }
+
+        for (align, note_tokens) in fly_info.notes.iter() {
+            page.push_spacing(2);
+
+            let lines = match align {
+                Alignment::Left | Alignment::Right => {
+                    linebreak_fill(&note_tokens[..], w)
+                },
+                _ => linebreak_balance(&note_tokens[..], w),
+            };
 
             for mut line in lines {
                 let len = line.length();
-                line.column = CENTER - len / 2 - len % 2;
-                page.lines.push(Some(line));
-                page.lines.push(None);
+
+                line.column = match align {
+                    Alignment::Left => left_margin,
+                    Alignment::Right => right_margin + 1 - len,
+                    _ => layout.centered(len),
+                };
+
+                page.push_line(line);
+                page.push_spacing(1);
             }
         }
 
-        if let Some(contact_tokens) = fly_info.contact {
-            let w = CONTACT_END - CONTACT_BEGIN + 1;
+        if let Some(contact_tokens) = &fly_info.contact {
+            let w = layout.contact_end - layout.contact_begin + 1;
             let lines = linebreak_fill(&contact_tokens[..], w);
 
             for mut line in lines {
-                line.column = CONTACT_BEGIN;
-                page.footer.push(Some(line));
+                line.column = layout.contact_begin;
+                line.tag = Some("contact");
+                page.footer.push(Row::Text(line));
             }
         }
+
+        if let Some(stamp) = &self.options.stamp {
+            let mut line = Line::from(Segment::from(stamp.as_str()));
+            line.column = layout.right_margin + 1
+                - min(line.length(), layout.right_margin);
+            page.footer.push(Row::Text(line));
+        }
         
         (title, page)
     }
@@ -0,0 +1,275 @@
+// Batyr Golden Tests
+// Copyright (C) 2023 Gene Yu
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Regression fixtures for pagination
+//!
+//! A fixture is a screenplay with a golden file beside it, named
+//! after the screenplay with the extension <tt>.golden</tt>.  The
+//! golden file records the total page count and the text of a few
+//! selected pages, as typeset with the default settings.  Any change
+//! to the pagination rules that moves a line shows up as a
+//! difference.
+//!
+//! The crate ships a fixture made from the opening scenes of _It's a
+//! Wonderful Life_ in <tt>examples/golden</tt>.  To add a script of
+//! your own, record its golden file once with [`bless`], check that
+//! the pages look right, and keep both files together.
+//!
+//! # Examples
+//!
+//! ```
+//! use batyr::document::golden;
+//!
+//! let problems = golden::check_dir("examples/golden").unwrap();
+//! assert!(problems.is_empty(), "{}", problems.join("\n"));
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::document::formatter::{Formatter, Page, Row};
+use crate::document::reader::Reader;
+
+/// Extension of golden files
+pub const GOLDEN_EXTENSION: &str = "golden";
+
+/// Expected pagination of a screenplay
+///
+/// Pages are counted by position in the output, so the title page is
+/// page 1.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::golden::Golden;
+/// let golden = Golden {
+///     pages: 12,
+///     snapshots: vec![(2, "          FADE IN:".to_string())],
+/// };
+///
+/// assert_eq!(Golden::from(golden.to_string().as_str()), golden);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Golden {
+    /// Total number of pages, including the title page
+    pub pages: usize,
+    /// Selected pages by position, with their text
+    pub snapshots: Vec<(usize, String)>,
+}
+
+impl Golden {
+    /// Records the page count and the text of the given pages
+    ///
+    /// Positions past the end of the document are left out.
+    pub fn record(formatter: &Formatter, pages: &[usize]) -> Self {
+        let snapshots = pages.iter()
+            .filter(|&&n| n >= 1 && n <= formatter.body.len())
+            .map(|&n| (n, page_text(&formatter.body[n - 1])))
+            .collect();
+
+        Self {
+            pages: formatter.body.len(),
+            snapshots,
+        }
+    }
+
+    /// Compares the expected pagination against an actual one
+    ///
+    /// Returns a description of each difference.  Only the pages
+    /// selected in <tt>self</tt> are compared.
+    pub fn compare(&self, actual: &Formatter) -> Vec<String> {
+        let mut problems: Vec<String> = Vec::new();
+
+        if actual.body.len() != self.pages {
+            problems.push(format!(
+                "expected {} pages, found {}", self.pages, actual.body.len()
+            ));
+        }
+
+        for (n, expected) in self.snapshots.iter() {
+            let text = match actual.body.get(n - 1) {
+                Some(page) => page_text(page),
+                None => {
+                    problems.push(format!("page {} is missing", n));
+                    continue;
+                },
+            };
+
+            let mismatch = text.lines()
+                .zip(expected.lines())
+                .position(|(a, b)| a != b)
+                .or_else(|| {
+                    let (a, b) = (text.lines().count(), expected.lines().count());
+                    if a != b { Some(a.min(b)) } else { None }
+                });
+
+            if let Some(i) = mismatch {
+                problems.push(format!(
+                    "page {} differs at line {}:\n  expected: {}\n  found:    {}",
+                    n, i + 1,
+                    expected.lines().nth(i).unwrap_or(""),
+                    text.lines().nth(i).unwrap_or(""),
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+impl fmt::Display for Golden {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "pages: {}", self.pages)?;
+
+        for (n, text) in self.snapshots.iter() {
+            writeln!(f, "=== page {}", n)?;
+            writeln!(f, "{}", text)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&str> for Golden {
+    fn from(s: &str) -> Self {
+        let mut pages: usize = 0;
+        let mut snapshots: Vec<(usize, Vec<&str>)> = Vec::new();
+
+        for line in s.lines() {
+            if let Some(n) = line.strip_prefix("=== page ") {
+                snapshots.push((n.trim().parse().unwrap_or(0), Vec::new()));
+            } else if let Some((_, lines)) = snapshots.last_mut() {
+                lines.push(line);
+            } else if let Some(n) = line.strip_prefix("pages:") {
+                pages = n.trim().parse().unwrap_or(0);
+            }
+        }
+
+        Self {
+            pages,
+            snapshots: snapshots.into_iter()
+                .map(|(n, lines)| (n, lines.join("\n")))
+                .collect(),
+        }
+    }
+}
+
+/// Renders a page as plain text
+///
+/// Each line is indented to its column, and spacing becomes blank
+/// lines.  The footer follows the body of the page.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::formatter::Page;
+/// # use batyr::document::golden::page_text;
+/// # use batyr::text::{Line, Segment};
+/// let mut page = Page {
+///     number: 1,
+///     height: 55,
+///     lines: Vec::new(),
+///     footer: Vec::new(),
+/// };
+/// let mut line = Line::from(Segment::from("FADE IN:"));
+/// line.column = 2;
+/// page.push_line(line);
+/// page.push_spacing(1);
+/// page.push_line(Line::from(Segment::from("Snow.")));
+///
+/// assert_eq!(page_text(&page), "  FADE IN:\n\nSnow.");
+/// ```
+pub fn page_text(page: &Page) -> String {
+    let mut lines: Vec<String> = Vec::new();
+
+    for row in page.lines.iter().chain(page.footer.iter()) {
+        match row {
+            Row::Text(line) => {
+                let text = format!("{}{}", " ".repeat(line.column), line.text());
+                lines.push(text.trim_end().to_string());
+            },
+            Row::Spacing(n) => {
+                for _ in 0..*n {
+                    lines.push(String::new());
+                }
+            },
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Typesets a screenplay file with the default settings
+fn format_file(path: &Path) -> Result<Formatter, Box<dyn Error>> {
+    let xml = fs::read_to_string(path)?;
+    let root = Reader::new(&xml).try_run()?.ok_or("No elements!")?;
+
+    let mut formatter = Formatter::new();
+    formatter.run(root);
+    Ok(formatter)
+}
+
+/// Records the golden file for a screenplay, with snapshots of the
+/// given pages
+pub fn bless<P: AsRef<Path>>(path: P, pages: &[usize])
+                             -> Result<Golden, Box<dyn Error>>
+{
+    let path = path.as_ref();
+    let golden = Golden::record(&format_file(path)?, pages);
+
+    fs::write(path.with_extension(GOLDEN_EXTENSION), golden.to_string())?;
+    Ok(golden)
+}
+
+/// Checks a screenplay against its golden file
+///
+/// Returns a description of each difference, prefixed with the path
+/// of the screenplay.
+pub fn check<P: AsRef<Path>>(path: P) -> Result<Vec<String>, Box<dyn Error>> {
+    let path = path.as_ref();
+    let expected = fs::read_to_string(path.with_extension(GOLDEN_EXTENSION))?;
+    let golden = Golden::from(expected.as_str());
+
+    Ok(golden.compare(&format_file(path)?)
+        .into_iter()
+        .map(|problem| format!("{}: {}", path.display(), problem))
+        .collect())
+}
+
+/// Checks every screenplay in a directory that has a golden file
+pub fn check_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.with_extension(GOLDEN_EXTENSION).is_file())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext != GOLDEN_EXTENSION)
+        })
+        .collect();
+
+    paths.sort();
+
+    let mut problems: Vec<String> = Vec::new();
+
+    for path in paths {
+        problems.extend(check(&path)?);
+    }
+
+    Ok(problems)
+}
@@ -0,0 +1,770 @@
+// Batyr HTML Writer
+// Copyright (C) 2023 Gene Yu
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Writes formatted pages as HTML
+//!
+//! Each page becomes a <tt>&lt;section&gt;</tt>.  By default every
+//! element of the screenplay on it becomes a block element of its
+//! own, with a class naming it: <tt>&lt;h2 class="act"&gt;</tt>,
+//! <tt>&lt;h3 class="slug"&gt;</tt>, <tt>&lt;p class="cue"&gt;</tt>,
+//! <tt>&lt;p class="d"&gt;</tt> and so on.  A style sheet in the head,
+//! made from the layout, sets the blocks in 12 point Courier at the
+//! tab stops of the typed page, so the pages look much like the
+//! typeset ones, while text pasted elsewhere keeps its paragraphs
+//! rather than columns of spaces.  Lines the formatter adds itself,
+//! such as "(MORE)", and the running header are kept as they were
+//! typed, in <tt>&lt;p class="line"&gt;</tt>.  Emphasis and underline
+//! are marked with <tt>&lt;u&gt;</tt>, and bold with
+//! <tt>&lt;b&gt;</tt>.
+//!
+//! [`HtmlMode::Pre`] writes each page as a <tt>&lt;pre&gt;</tt> block
+//! instead, with every line at the row and column where the
+//! PostScript writer would have put it.
+//!
+//! Given the acts and scenes from the formatter, the writer adds a
+//! sidebar that links to each of them, and an anchor such as
+//! <tt>#scene-12</tt>, for the twelfth scene, on each scene heading.  The <tt>j</tt> and
+//! <tt>k</tt> keys (or <tt>n</tt> and <tt>p</tt>) jump to the next and
+//! previous scene, which makes the page easy to follow along in a
+//! table read over a video call.  Streamed output has no sidebar,
+//! since the scenes are only known once the whole document has been
+//! laid out.
+//!
+//! # Examples
+//!
+//! ```
+//! use batyr::document::formatter::{Page, Row};
+//! use batyr::document::html::{HtmlMode, HtmlWriter};
+//! use batyr::text::{Line, Segment};
+//!
+//! let mut cue = Line::from(Segment::from("TOM"));
+//! cue.column = 43;
+//! cue.tag = Some("cue");
+//!
+//! let mut d = Line::from(Segment::from("Tom & Jerry"));
+//! d.column = 26;
+//! d.tag = Some("d");
+//!
+//! let page = Page {
+//!     number: 1,
+//!     height: 55,
+//!     lines: vec![Row::Text(cue), Row::Text(d)],
+//!     footer: Vec::new(),
+//! };
+//!
+//! let mut writer = HtmlWriter::new(Vec::new(), "WORKING TITLE");
+//! writer.run(vec![page.clone()]).unwrap();
+//!
+//! let html = String::from_utf8(writer.into_inner()).unwrap();
+//! assert!(html.contains("<title>WORKING TITLE</title>"));
+//! assert!(html.contains("<section class=\"page\" id=\"page-1\">"));
+//! assert!(html.contains(">TOM</p>\n<p class=\"d\">Tom &amp; Jerry</p>"));
+//!
+//! let mut writer = HtmlWriter::new(Vec::new(), "WORKING TITLE")
+//!     .mode(HtmlMode::Pre);
+//! writer.run(vec![page]).unwrap();
+//!
+//! let html = String::from_utf8(writer.into_inner()).unwrap();
+//! assert!(html.contains("\n                          Tom &amp; Jerry\n"));
+//! ```
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Write;
+use std::sync::mpsc::Receiver;
+
+use crate::document::*;
+use crate::document::formatter::*;
+use crate::document::writer::{Newline, PageNumbers};
+use crate::text::*;
+use crate::text::tokens::DisplayFlags;
+
+/// Style sheet approximating the typewritten page
+const STYLE: &str = "\
+body { margin: 0; background: #ddd; }
+section.page {
+  box-sizing: border-box;
+  width: 8.5in;
+  min-height: 11in;
+  margin: 0.25in auto;
+  padding-top: 36pt;
+  background: #fff;
+  box-shadow: 0 0 4px #888;
+  font-family: Courier, \"Courier New\", monospace;
+  font-size: 12pt;
+  line-height: 12pt;
+}
+section.page > * { margin: 0; font: inherit; }
+section.page .line { white-space: pre; }
+nav {
+  position: fixed;
+  top: 0;
+  bottom: 0;
+  left: 0;
+  width: 2.5in;
+  overflow-y: auto;
+  background: #f4f4f4;
+  font-family: sans-serif;
+  font-size: 10pt;
+}
+nav ol { list-style: none; margin: 0; padding: 0 0 0 1em; }
+nav a { color: inherit; text-decoration: none; }
+nav a:hover { text-decoration: underline; }
+nav ~ article { margin-left: 2.5in; }
+@media print {
+  body { background: none; }
+  section.page { margin: 0; box-shadow: none; break-after: page; }
+  nav { display: none; }
+  nav ~ article { margin-left: 0; }
+}
+";
+
+/// Script that moves between scenes with the keyboard
+const SCRIPT: &str = "\
+document.addEventListener(\"keydown\", function (e) {
+  if (e.altKey || e.ctrlKey || e.metaKey) return;
+  var step = { j: 1, n: 1, k: -1, p: -1 }[e.key];
+  if (!step) return;
+  var scenes = Array.from(document.querySelectorAll(\"[id^='scene-']\"));
+  var tops = scenes.map(function (s) { return s.getBoundingClientRect().top; });
+  var target = step > 0
+    ? scenes.find(function (s, i) { return tops[i] > 1; })
+    : scenes.filter(function (s, i) { return tops[i] < -1; }).pop();
+  if (target) {
+    target.scrollIntoView();
+    e.preventDefault();
+  }
+});
+";
+
+/// How the text of a page is set out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HtmlMode {
+    /// One block element for each element of the screenplay, placed
+    /// by the style sheet
+    #[default]
+    Blocks,
+    /// One <tt>&lt;pre&gt;</tt> block for each page, with the lines at
+    /// the rows and columns of the typed page
+    Pre,
+}
+
+/// HTML output driver
+pub struct HtmlWriter<W: Write> {
+    out: W,
+    title: String,
+    header: String,
+    creator: String,
+    newline: Newline,
+    stamp: Option<String>,
+    page_numbers: PageNumbers,
+    layout: Layout,
+    mode: HtmlMode,
+    front_page_no: usize,
+    body_started: bool,
+    nav: String,
+    anchors: HashMap<(i32, usize), String>,
+}
+
+impl<W: Write> HtmlWriter<W> {
+    /// Creates an HTML document writer
+    pub fn new(out: W, title: &str) -> HtmlWriter<W> {
+        HtmlWriter {
+            out,
+            title: title.to_string(),
+            header: String::new(),
+            creator: "batyr".to_string(),
+            newline: Newline::Lf,
+            stamp: None,
+            page_numbers: PageNumbers::default(),
+            layout: Layout::default(),
+            mode: HtmlMode::default(),
+            front_page_no: 0,
+            body_started: false,
+            nav: String::new(),
+            anchors: HashMap::new(),
+        }
+    }
+
+    /// Sets the running header printed before each page number
+    pub fn header(mut self, header: &str) -> Self {
+        self.header = header.to_string();
+        self
+    }
+
+    /// Sets the program name recorded in the document head
+    pub fn creator(mut self, creator: &str) -> Self {
+        self.creator = creator.to_string();
+        self
+    }
+
+    /// Sets the line ending for the output
+    pub fn newline(mut self, newline: Newline) -> Self {
+        self.newline = newline;
+        self
+    }
+
+    /// Records the content hash of the draft in the document head
+    pub fn stamp(mut self, hash: &str) -> Self {
+        self.stamp = Some(hash.to_string());
+        self
+    }
+
+    /// Sets how page numbers are printed
+    pub fn page_numbers(mut self, numbers: PageNumbers) -> Self {
+        self.page_numbers = numbers;
+        self
+    }
+
+    /// Sets the page geometry used to place lines, running headers
+    /// and page numbers
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Sets how the text of each page is set out
+    pub fn mode(mut self, mode: HtmlMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Adds a sidebar linking to the acts and scenes, and an anchor
+    /// on the heading of each scene
+    ///
+    /// The acts, scenes and elements are those the formatter collected
+    /// while laying out the pages, which tell where each heading
+    /// landed.  Scenes are grouped under the act they begin in.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::formatter::Formatter;
+    /// # use batyr::document::html::HtmlWriter;
+    /// # use batyr::document::reader::Reader;
+    /// let mut xml = String::from("<screenplay><head><title>FOO</title>");
+    /// xml.push_str("<authors><fullName>BAR</fullName></authors></head>");
+    /// xml.push_str("<body><act>ACT ONE</act>\n<slug>INT. HOUSE - DAY</slug>");
+    /// xml.push_str("\n<p>Snow.</p>\n<slug number=\"1\">EXT. BARN - NIGHT</slug>");
+    /// xml.push_str("\n<p>Rain.</p>\n<slug numbered=\"false\">EXT. ROAD - NIGHT</slug>");
+    /// xml.push_str("\n<p>Fog.</p></body></screenplay>");
+    ///
+    /// let mut formatter = Formatter::new();
+    /// formatter.run(Reader::new(&xml).run().unwrap());
+    ///
+    /// let mut writer = HtmlWriter::new(Vec::new(), "FOO")
+    ///     .contents(&formatter.acts, &formatter.scenes, &formatter.elements);
+    /// writer.run(formatter.body).unwrap();
+    ///
+    /// let html = String::from_utf8(writer.into_inner()).unwrap();
+    /// assert!(html.contains("<a href=\"#page-1\">ACT ONE</a>"));
+    /// assert!(html.contains("<a href=\"#scene-1\">1 INT. HOUSE - DAY</a>"));
+    /// assert!(html.contains("<a href=\"#scene-2\">1 EXT. BARN - NIGHT</a>"));
+    /// assert!(html.contains("<a href=\"#scene-3\">EXT. ROAD - NIGHT</a>"));
+    ///
+    /// for id in ["scene-1", "scene-2", "scene-3"] {
+    ///     let anchor = format!("<h3 class=\"slug\" id=\"{}\"", id);
+    ///     assert_eq!(html.matches(&anchor).count(), 1);
+    /// }
+    /// ```
+    pub fn contents(mut self, acts: &[ActInfo], scenes: &[SceneInfo],
+                    elements: &[ElementInfo]) -> Self
+    {
+        self.anchors.clear();
+
+        if acts.is_empty() && scenes.is_empty() {
+            self.nav.clear();
+            return self;
+        }
+
+        // The first line of each scene heading, by source line and
+        // page
+        let mut slugs: HashMap<(usize, i32), &ElementInfo> = HashMap::new();
+
+        for elem in elements.iter().filter(|elem| elem.tag == "slug") {
+            slugs.entry((elem.source_line, elem.page)).or_insert(elem);
+        }
+
+        let mut nav = String::from("<nav>\n<ol>\n");
+        let mut acts = acts.iter().peekable();
+        let mut in_act = false;
+
+        for (i, scene) in scenes.iter().enumerate() {
+            while let Some(act) = acts.next_if(|act| act.page <= scene.first_page) {
+                if in_act {
+                    nav.push_str("</ol></li>\n");
+                }
+
+                nav.push_str(&format!(
+                    "<li><a href=\"#page-{}\">{}</a><ol>\n",
+                    act.page, escape(&act.heading)
+                ));
+                in_act = true;
+            }
+
+            // Scene numbers may repeat or be missing, so the anchors
+            // count the scenes instead.
+            let id = format!("scene-{}", i + 1);
+
+            if let Some(slug) = slugs.get(&(scene.line, scene.first_page)) {
+                self.anchors.insert((slug.page, slug.row), id.clone());
+            }
+
+            // Headings keep the typewriter's two spaces after a full
+            // stop, which the sidebar does without.
+            let mut heading = scene.heading.split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            if !scene.number.is_empty() {
+                heading = format!("{} {}", scene.number, heading);
+            }
+
+            nav.push_str(&format!("<li><a href=\"#{}\">{}</a></li>\n",
+                                  escape(&id), escape(&heading)));
+        }
+
+        for act in acts {
+            if in_act {
+                nav.push_str("</ol></li>\n");
+            }
+
+            nav.push_str(&format!("<li><a href=\"#page-{}\">{}</a><ol>\n",
+                                  act.page, escape(&act.heading)));
+            in_act = true;
+        }
+
+        if in_act {
+            nav.push_str("</ol></li>\n");
+        }
+
+        nav.push_str("</ol>\n</nav>\n");
+        self.nav = nav;
+        self
+    }
+
+    /// Returns the output stream
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Writes the document to the output stream
+    pub fn run(&mut self, pages: PageList) -> Result<(), Box<dyn Error>> {
+        self.write_head()?;
+
+        for page in pages {
+            self.write_page(page)?;
+        }
+
+        self.write_tail()
+    }
+
+    /// Writes the document to the output stream as the pages arrive
+    /// from a streaming formatter
+    pub fn stream(&mut self, pages: Receiver<Streamed>)
+                  -> Result<(), Box<dyn Error>>
+    {
+        let mut started = false;
+
+        for message in pages {
+            match message {
+                Streamed::Head { title, header } => {
+                    self.title = title;
+                    self.header = header;
+                },
+                Streamed::Page(page) => {
+                    if !started {
+                        self.write_head()?;
+                        started = true;
+                    }
+
+                    self.write_page(page)?;
+                },
+            }
+        }
+
+        if !started {
+            self.write_head()?;
+        }
+
+        self.write_tail()
+    }
+
+    fn write_head(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut head = String::from("<!DOCTYPE html>\n<html>\n<head>\n");
+        head.push_str("<meta charset=\"utf-8\">\n");
+        head.push_str(&format!("<meta name=\"generator\" content=\"{}\">\n",
+                               escape(&self.creator)));
+
+        if let Some(hash) = &self.stamp {
+            head.push_str(&format!("<meta name=\"draft-hash\" content=\"{}\">\n",
+                                   hash));
+        }
+
+        head.push_str(&format!("<title>{}</title>\n", escape(&self.title)));
+        head.push_str(&format!("<style>\n{}", STYLE));
+
+        if self.mode == HtmlMode::Blocks {
+            head.push_str(&block_style(&self.layout));
+        }
+
+        head.push_str("</style>\n");
+
+        if !self.nav.is_empty() {
+            head.push_str(&format!("<script>\n{}</script>\n", SCRIPT));
+        }
+
+        head.push_str("</head>\n<body>\n");
+        head.push_str(&self.nav);
+        head.push_str("<article>\n");
+        self.write(&head)
+    }
+
+    fn write_tail(&mut self) -> Result<(), Box<dyn Error>> {
+        self.write("</article>\n</body>\n</html>\n")
+    }
+
+    fn write_page(&mut self, page: Page) -> Result<(), Box<dyn Error>> {
+        let layout = self.layout;
+
+        // Each line with the line of the page it goes on, counting up
+        // from the bottom edge, and its index among the rows of the
+        // page if it is one of them
+        let mut lines: Vec<(usize, Option<usize>, Line)> = Vec::new();
+
+        let label = if page.number > 0 {
+            self.body_started = true;
+            Some(self.page_numbers.body_label(page.number as usize))
+
+        } else if page.number == 0 && !self.body_started {
+            self.front_page_no += 1;
+            self.page_numbers.front_matter_label(self.front_page_no)
+
+        } else {
+            None
+        };
+
+        if page.number > 0 && !self.header.is_empty() {
+            let column = self.page_numbers.header_column(&self.header, &layout);
+            let mut line = Line::from(Segment::from(self.header.as_str()));
+            line.column = column;
+            lines.push((layout.header_line, None, line));
+        }
+
+        if let Some(label) = label {
+            let (column, y) = self.page_numbers.label_position(&label, &layout);
+            let mut line = Line::from(Segment::from(label));
+            line.column = column;
+            lines.push((y, None, line));
+        }
+
+        let mut y = layout.top_line;
+
+        for (i, row) in page.lines.into_iter().enumerate() {
+            match row {
+                Row::Text(line) => {
+                    lines.push((y, Some(i), line));
+                    y = y.saturating_sub(1);
+                },
+                Row::Spacing(n) => y = y.saturating_sub(n),
+            }
+        }
+
+        if !page.footer.is_empty() {
+            let h: usize = page.footer.iter().map(Row::height).sum();
+            y = layout.bottom_line + h - 1;
+
+            for row in page.footer {
+                match row {
+                    Row::Text(line) => {
+                        lines.push((y, None, line));
+                        y = y.saturating_sub(1);
+                    },
+                    Row::Spacing(n) => y = y.saturating_sub(n),
+                }
+            }
+        }
+
+        let id = match page.number {
+            n if n > 0 => format!(" id=\"page-{}\"", n),
+            _ => String::new(),
+        };
+
+        let mut html = format!("<section class=\"page\"{}>\n", id);
+
+        match self.mode {
+            HtmlMode::Blocks => {
+                html.push_str(&self.blocks(page.number, lines));
+            },
+            HtmlMode::Pre => {
+                html.push_str("<pre>\n");
+                html.push_str(&self.rows(page.number, lines));
+                html.push_str("</pre>\n");
+            },
+        }
+
+        html.push_str("</section>\n");
+        self.write(&html)
+    }
+
+    /// Sets out the lines of a page as one block element for each
+    /// element of the screenplay
+    ///
+    /// Lines of the same element run together into one block; lines
+    /// of no element keep their columns.  Blank lines between blocks
+    /// become a top margin.
+    fn blocks(&self, page_no: i32, mut lines: Vec<(usize, Option<usize>, Line)>)
+              -> String
+    {
+        lines.sort_by_key(|(y, _, _)| Reverse(*y));
+
+        let mut blocks: Vec<Block> = Vec::new();
+
+        for (y, index, line) in lines {
+            let id = index.and_then(|i| self.anchors.get(&(page_no, i)));
+
+            match blocks.last_mut() {
+                Some(block) if line.tag.is_none() && block.tag.is_none()
+                    && block.bottom == y =>
+                {
+                    place_in_row(&mut block.row, &line);
+                    continue;
+                },
+                Some(block) if line.tag.is_some() && block.tag == line.tag
+                    && block.bottom == y + 1 && id.is_none() =>
+                {
+                    // The lines of an address stay as they are; other
+                    // text runs on, but for a word broken after a dash.
+                    if line.tag == Some("contact") {
+                        block.row.1.push_str("<br>");
+                    } else if !block.row.1.ends_with('-') {
+                        block.row.1.push(' ');
+                    }
+
+                    block.row.1.push_str(&segments(&line));
+                    block.bottom = y;
+                    continue;
+                },
+                _ => (),
+            }
+
+            let mut row = (0, String::new());
+
+            match line.tag {
+                Some(_) => row.1 = segments(&line),
+                None => place_in_row(&mut row, &line),
+            }
+
+            blocks.push(Block {
+                tag: line.tag,
+                id: id.cloned(),
+                top: y,
+                bottom: y,
+                row,
+            });
+        }
+
+        let mut html = String::new();
+        let mut above = self.layout.header_line + 1;
+
+        for block in blocks {
+            let (element, class) = match block.tag {
+                Some("act") => ("h2", "act"),
+                Some("slug") => ("h3", "slug"),
+                Some(tag) => ("p", tag),
+                None => ("p", "line"),
+            };
+
+            let mut attributes = format!(" class=\"{}\"", class);
+
+            if let Some(id) = block.id {
+                attributes.push_str(&format!(" id=\"{}\"", escape(&id)));
+            }
+
+            let gap = above.saturating_sub(block.top + 1);
+
+            if gap > 0 {
+                attributes.push_str(&format!(
+                    " style=\"margin-top: {}pt\"",
+                    gap as f32 * self.layout.line_height
+                ));
+            }
+
+            html.push_str(&format!("<{0}{1}>{2}</{0}>\n",
+                                   element, attributes, block.row.1));
+            above = block.bottom;
+        }
+
+        html
+    }
+
+    /// Sets out the lines of a page at their rows and columns, from the
+    /// running header down to the last line
+    fn rows(&self, page_no: i32, lines: Vec<(usize, Option<usize>, Line)>)
+            -> String
+    {
+        let mut rows: Vec<(usize, String)> =
+            vec![(0, String::new()); self.layout.header_line];
+
+        for (y, index, line) in lines {
+            if let Some(id) = index.and_then(|i| self.anchors.get(&(page_no, i))) {
+                mark(&mut rows, y, id);
+            }
+
+            place(&mut rows, y, &line);
+        }
+
+        while rows.last().is_some_and(|(width, _)| *width == 0) {
+            rows.pop();
+        }
+
+        let mut html = String::new();
+
+        for (_, text) in rows {
+            html.push_str(&text);
+            html.push('\n');
+        }
+
+        html
+    }
+
+    /// Writes text to the output stream with the chosen line endings
+    fn write(&mut self, text: &str) -> Result<(), Box<dyn Error>> {
+        let text = match self.newline {
+            Newline::Lf => text.to_string(),
+            _ => text.replace('\n', self.newline.as_str()),
+        };
+
+        self.out.write_all(text.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Lines set out as one block element
+struct Block {
+    /// Name of the element the lines were set from, if any
+    tag: Option<&'static str>,
+    /// Anchor on the block
+    id: Option<String>,
+    /// Line of the page of the first line, counting up from the
+    /// bottom edge
+    top: usize,
+    /// Line of the page of the last line
+    bottom: usize,
+    /// Width typed so far and the HTML, as in a row of a page
+    row: (usize, String),
+}
+
+/// Puts a line on the given line of the page, counting up from the
+/// bottom edge as the PostScript writer does
+///
+/// The rows hold the width typed so far and the HTML.
+fn place(rows: &mut [(usize, String)], y: usize, line: &Line) {
+    if y == 0 || y > rows.len() {
+        return;
+    }
+
+    let n = rows.len();
+    place_in_row(&mut rows[n - y], line);
+}
+
+/// Puts a line at its column in a row
+///
+/// A line that starts left of the text already on its row follows it
+/// after a space.
+fn place_in_row((width, html): &mut (usize, String), line: &Line) {
+    if *width > 0 && line.column <= *width {
+        html.push(' ');
+        *width += 1;
+    } else {
+        html.push_str(&" ".repeat(line.column - *width));
+        *width = line.column;
+    }
+
+    html.push_str(&segments(line));
+    *width += line.length();
+}
+
+/// Returns the text of a line as HTML
+fn segments(line: &Line) -> String {
+    let mut html = String::new();
+
+    for segment in line.segments.iter() {
+        let mut text = escape(&segment.text);
+
+        if segment.dpy.intersects(DisplayFlags::BOLD) {
+            text = format!("<b>{}</b>", text);
+        }
+
+        if segment.dpy.intersects(DisplayFlags::EM | DisplayFlags::UL) {
+            text = format!("<u>{}</u>", text);
+        }
+
+        html.push_str(&text);
+    }
+
+    html
+}
+
+/// Style sheet placing the blocks of each element at the tab stops of
+/// the layout
+fn block_style(layout: &Layout) -> String {
+    let span = |begin: usize, end: usize| {
+        format!("margin-left: {}ch; width: {}ch;", begin, end + 1 - begin)
+    };
+
+    let center = layout.centered_left_margin();
+
+    format!("\
+section.page .slug, section.page .shot, section.page .p, section.page .open {{ {} }}
+section.page .cue {{ margin-left: {}ch; }}
+section.page .d {{ {} }}
+section.page .dir {{ {} text-indent: -1ch; }}
+section.page .trans {{ {} }}
+section.page .contact {{ {} }}
+section.page .act, section.page .end, section.page .series,
+section.page .title, section.page .authors, section.page .source {{
+  margin-left: {}ch; width: {}ch; text-align: center;
+}}
+",
+            span(layout.p_begin, layout.p_end),
+            layout.cue_begin,
+            span(layout.d_begin, layout.d_end),
+            span(layout.dir_begin, layout.dir_end),
+            span(layout.trans_begin, layout.trans_end),
+            span(layout.contact_begin, layout.contact_end),
+            center,
+            2 * layout.center.saturating_sub(center))
+}
+
+/// Puts an anchor at the start of the given line of the page
+fn mark(rows: &mut [(usize, String)], y: usize, id: &str) {
+    if y == 0 || y > rows.len() {
+        return;
+    }
+
+    let n = rows.len();
+    rows[n - y].1.push_str(&format!("<span id=\"{}\"></span>", escape(id)));
+}
+
+/// Escapes the characters that HTML reserves
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,201 @@
+// Batyr JSON Export
+// Copyright (C) 2023 Gene Yu
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! The element hierarchy as JSON, for other tools
+//!
+//! Editors, linters and scripts can read the parse from here instead
+//! of implementing the schema again.  Each element is an object with
+//! its <tt>tag</tt>, the <tt>line</tt> of the source where it
+//! begins, and, where they apply, its <tt>break</tt> information, the
+//! reader's line count as <tt>lines</tt>, its <tt>tokens</tt> and its
+//! <tt>children</tt>.  Each token has a <tt>kind</tt>, its
+//! <tt>text</tt> and the names of its display and format flags.
+//!
+//! The page map of a formatted document, from [`page_map`], lists
+//! where each scene and the text of each element landed, so that a
+//! page of the output can be traced back to the source.
+//!
+//! Needs the <tt>json</tt> feature, which the <tt>cli</tt> feature
+//! turns on.
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::document::*;
+use crate::document::formatter::{ElementInfo, Formatter, SceneInfo};
+
+/// Display flags by name
+const DISPLAY_FLAGS: [(DisplayFlags, &str); 4] = [
+    (DisplayFlags::EM, "em"),
+    (DisplayFlags::CAPS, "caps"),
+    (DisplayFlags::UL, "ul"),
+    (DisplayFlags::BOLD, "bold"),
+];
+
+/// Format flags by name
+const FORMAT_FLAGS: [(FormatFlags, &str); 5] = [
+    (FormatFlags::FS, "fs"),
+    (FormatFlags::DLB, "dlb"),
+    (FormatFlags::MLB, "mlb"),
+    (FormatFlags::DOB, "dob"),
+    (FormatFlags::EOS, "eos"),
+];
+
+/// Writes the element hierarchy as pretty-printed JSON
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::json::to_json;
+/// # use batyr::document::reader::Reader;
+/// let root = Reader::new("<p>Snow.</p>").run().unwrap();
+/// let value: serde_json::Value = serde_json::from_str(&to_json(&root)).unwrap();
+///
+/// assert_eq!(value["tag"], "p");
+/// assert_eq!(value["line"], 1);
+/// assert_eq!(value["break"], serde_json::json!({"Atomic": 1}));
+/// assert_eq!(value["tokens"][0]["kind"], "word");
+/// assert_eq!(value["tokens"][0]["text"], "Snow");
+/// assert_eq!(value["tokens"][1]["format"], serde_json::json!(["fs", "eos"]));
+/// ```
+pub fn to_json(root: &ElementType) -> String {
+    serde_json::to_string_pretty(root).unwrap()
+}
+
+/// Scenes and elements with their pages
+#[derive(serde::Serialize)]
+struct PageMap<'a> {
+    scenes: &'a [SceneInfo],
+    elements: &'a [ElementInfo],
+}
+
+/// Writes the page map of a formatted document as pretty-printed
+/// JSON
+///
+/// Each scene has its <tt>number</tt>, <tt>heading</tt>, the source
+/// <tt>line</tt> of its slug, its <tt>first_page</tt>, the
+/// <tt>page_line</tt> of that page where the slug begins and its
+/// <tt>last_page</tt>.  Each element has its <tt>tag</tt>, its
+/// <tt>source_line</tt>, and the <tt>page</tt> and <tt>line</tt> of
+/// the page where its text begins.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::json::page_map;
+/// # use batyr::document::reader::Reader;
+/// let mut xml = String::from("<screenplay><head/><body>\n");
+/// xml.push_str("<slug>INT. HOUSE - DAY</slug>\n");
+///
+/// for _ in 0..40 {
+///     xml.push_str("<p>Waiting.</p>\n");
+/// }
+///
+/// xml.push_str("<slug>EXT. HOUSE - NIGHT</slug>\n</body></screenplay>");
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(&xml).run().unwrap());
+///
+/// let map: serde_json::Value = serde_json::from_str(&page_map(&formatter)).unwrap();
+/// let slug = &map["elements"][0];
+/// let last = &map["elements"][41];
+///
+/// assert_eq!(map["scenes"][1]["number"], "2");
+/// assert_eq!(map["scenes"][1]["line"], 43);
+/// assert_eq!(map["scenes"][1]["page_line"], last["line"]);
+/// assert_eq!(slug["tag"], "slug");
+/// assert_eq!(slug["source_line"], 2);
+/// assert_eq!(slug["page"], 1);
+/// assert_eq!(slug["line"], 1);
+/// assert_eq!(last["tag"], "slug");
+/// assert_eq!(last["page"], 2);
+/// ```
+pub fn page_map(formatter: &Formatter) -> String {
+    let map = PageMap {
+        scenes: &formatter.scenes,
+        elements: &formatter.elements,
+    };
+
+    serde_json::to_string_pretty(&map).unwrap()
+}
+
+impl Serialize for ElementType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("tag", self.tag())?;
+
+        if self.line() > 0 {
+            map.serialize_entry("line", &self.line())?;
+        }
+
+        if let Some(info) = self.break_info() {
+            map.serialize_entry("break", info)?;
+        }
+
+        if let Some((width, count)) = self.line_count() {
+            map.serialize_entry("lines", &LineCount { width, count })?;
+        }
+
+        if let Some(tokens) = self.tokens() {
+            map.serialize_entry("tokens", tokens)?;
+        }
+
+        if let Some(children) = self.children() {
+            map.serialize_entry("children", children)?;
+        }
+
+        map.end()
+    }
+}
+
+/// Line count made by the reader
+#[derive(serde::Serialize)]
+struct LineCount {
+    width: usize,
+    count: usize,
+}
+
+impl Serialize for TokenType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let dpy = self.display_flags();
+        let frm = self.format_flags();
+
+        let display: Vec<&str> = DISPLAY_FLAGS.iter()
+            .filter(|(flag, _)| dpy.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect();
+
+        let format: Vec<&str> = FORMAT_FLAGS.iter()
+            .filter(|(flag, _)| frm.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect();
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("text", &self.text())?;
+
+        if !display.is_empty() {
+            map.serialize_entry("display", &display)?;
+        }
+
+        if !format.is_empty() {
+            map.serialize_entry("format", &format)?;
+        }
+
+        map.end()
+    }
+}
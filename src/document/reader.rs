@@ -52,15 +52,26 @@
 //!
 //! [screenplay schema]: <http://www.matchlock.com/batyr/screenplay.xsd>
 
+use quick_xml::events::BytesStart;
 use quick_xml::events::BytesText;
 use quick_xml::events::Event;
 use quick_xml::name::QName;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
 
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::rc::Rc;
 use std::str;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use crate::document::*;
 use crate::text;
-use crate::text::parser::Parser;
+use crate::text::parser::{
+    is_stripped_control, EllipsisSpacing, Parser, SentenceSpacing, TabPolicy
+};
 
 #[macro_use]
 mod macros;
@@ -70,6 +81,9 @@ mod macros;
 pub enum State {
     Act       (TextElement     <Act       >),
     Authors   (ContainerElement<Authors   >),
+    B         (TextElement     <B         >),
+    BackMatter(ContainerElement<BackMatter>),
+    Blank     (EmptyElement    <Blank     >),
     Body      (ContainerElement<Body      >),
     Br        (EmptyElement    <Br        >),
     Contact   (TextElement     <Contact   >),
@@ -78,17 +92,23 @@ pub enum State {
     Dir       (TextElement     <Dir       >),
     Em        (TextElement     <Em        >),
     End       (TextElement     <End       >),
+    Episode   (TextElement     <Episode   >),
+    FrontMatter(ContainerElement<FrontMatter>),
     FullName  (TextElement     <FullName  >),
     Head      (ContainerElement<Head      >),
     Note      (TextElement     <Note      >),
     Open      (TextElement     <Open      >),
     P         (TextElement     <P         >),
     PageBreak (EmptyElement    <PageBreak >),
+    ProductionNumber(TextElement<ProductionNumber>),
     Screenplay(ContainerElement<Screenplay>),
     Series    (TextElement     <Series    >),
+    Shot      (TextElement     <Shot      >),
     Slug      (TextElement     <Slug      >),
+    Source    (TextElement     <Source    >),
     Title     (TextElement     <Title     >),
     Trans     (TextElement     <Trans     >),
+    U         (TextElement     <U         >),
 }
 
 impl State {
@@ -97,13 +117,30 @@ impl State {
     fn on_exit(self) -> ElementType {
         match self {
             State::Act(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
-                elem.break_info = BreakType::Atomic(1);
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
+
+                let w = elem.attributes.right_margin
+                    - elem.attributes.left_margin + 1;
+
+                let n = text::count_lines(&elem.tokens[..], w);
+                elem.line_count = Some((w, n));
+
+                elem.break_info = BreakType::Atomic(n);
                 ElementType::Act(elem)
             },
             State::Authors(elem) => {
                 ElementType::Authors(elem)
             },
+            State::B(elem) => {
+                ElementType::B(elem)
+            },
+            State::BackMatter(elem) => {
+                ElementType::BackMatter(elem)
+            },
+            State::Blank(mut elem) => {
+                elem.break_info = BreakType::Disposable(elem.attributes.lines);
+                ElementType::Blank(elem)
+            },
             State::Body(elem) => {
                 ElementType::Body(elem)
             },
@@ -112,144 +149,188 @@ impl State {
                 ElementType::Br(elem)
             },
             State::Contact(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
                 ElementType::Contact(elem)
             },
             State::Cue(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
-                elem.break_info = BreakType::Forbidden(1);
-                ElementType::Cue(elem)
-            },
-            State::D(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
-                State::remove_leading_eos(&mut elem.tokens);
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
 
-                if elem.attributes.indent > 0 {
-                    elem.tokens.insert(0, TokenType::Space(
-                        Token::from(elem.attributes.indent)
-                    ));
-                }
-                
                 let w = elem.attributes.right_margin
-                    - elem.attributes.left_margin + 1;
-
-                let b = State::find_break_points(&elem.tokens[..], w);
-
-                if b.len() == 1 {
-                    elem.break_info = BreakType::Atomic(b[0].line_no);
+                    - elem.attributes.tab_stop + 1;
 
-                } else if b.len() > 1 {
-                    elem.break_info = BreakType::List(b);
-                }
+                let n = text::count_lines(&elem.tokens[..], w);
+                elem.line_count = Some((w, n));
 
+                elem.break_info = BreakType::Forbidden(n);
+                ElementType::Cue(elem)
+            },
+            State::D(mut elem) => {
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
+                State::remove_leading_punct_flags(Arc::make_mut(&mut elem.tokens));
                 ElementType::D(elem)
             },
             State::Dir(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
+                ElementType::Dir(elem)
+            },
+            State::Em(elem) => {
+                ElementType::Em(elem)
+            },
+            State::End(mut elem) => {
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
 
                 let w = elem.attributes.right_margin
                     - elem.attributes.left_margin + 1;
 
                 let n = text::count_lines(&elem.tokens[..], w);
+                elem.line_count = Some((w, n));
 
-                elem.break_info = BreakType::Forbidden(n);
-                
-                ElementType::Dir(elem)
+                elem.break_info = BreakType::Atomic(n);
+                ElementType::End(elem)
             },
-            State::Em(elem) => {
-                ElementType::Em(elem)
+            State::Episode(mut elem) => {
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
+                ElementType::Episode(elem)
             },
-            State::End(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
-                elem.break_info = BreakType::Atomic(1);
-                ElementType::End(elem)
+            State::FrontMatter(elem) => {
+                ElementType::FrontMatter(elem)
             },
             State::FullName(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
                 ElementType::FullName(elem)
             },
             State::Head(elem) => {
                 ElementType::Head(elem)
             },
             State::Note(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
                 ElementType::Note(elem)
             },
             State::Open(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
                 elem.break_info = BreakType::Atomic(1);
                 ElementType::Open(elem)
             },
             State::P(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
-                State::remove_leading_eos(&mut elem.tokens);
-
-                if elem.attributes.indent > 0 {
-                    elem.tokens.insert(0, TokenType::Space(
-                        Token::from(elem.attributes.indent)
-                    ));
-                }
-                
-                let w = elem.attributes.right_margin
-                    - elem.attributes.left_margin + 1;
-
-                let b = State::find_break_points(&elem.tokens[..], w);
-
-                if b.len() == 1 {
-                    elem.break_info = BreakType::Atomic(b[0].line_no);
-
-                } else if b.len() > 1 {
-                    elem.break_info = BreakType::List(b);
-                }
-
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
+                State::remove_leading_punct_flags(Arc::make_mut(&mut elem.tokens));
                 ElementType::P(elem)
             },
             State::PageBreak(mut elem) => {
                 elem.break_info = BreakType::Mandatory;
                 ElementType::PageBreak(elem)
             },
+            State::ProductionNumber(mut elem) => {
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
+                ElementType::ProductionNumber(elem)
+            },
             State::Screenplay(elem) => {
                 ElementType::Screenplay(elem)
             },
             State::Series(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
                 elem.break_info = BreakType::Atomic(1);
                 ElementType::Series(elem)
             },
+            State::Shot(mut elem) => {
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
+                ElementType::Shot(elem)
+            },
             State::Slug(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
-
-                let w = elem.attributes.right_margin
-                    - elem.attributes.left_margin + 1;
-
-                let n = text::count_lines(&elem.tokens[..], w);
-                
-                elem.break_info = BreakType::Forbidden(n);
-
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
                 ElementType::Slug(elem)
             },
+            State::Source(mut elem) => {
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
+                ElementType::Source(elem)
+            },
             State::Title(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
 
                 let w = elem.attributes.right_margin
                     - elem.attributes.left_margin + 1;
 
                 let n = text::count_lines(&elem.tokens[..], w);
+                elem.line_count = Some((w, n));
                 
                 elem.break_info = BreakType::Atomic(n);
 
                 ElementType::Title(elem)
             },
             State::Trans(mut elem) => {
-                State::trim_whitespace(&mut elem.tokens);
+                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
                 elem.break_info = BreakType::Atomic(1);
                 ElementType::Trans(elem)
             },
+            State::U(elem) => {
+                ElementType::U(elem)
+            },
         }
     }
 
     fn on_pause(&self) {}
 
+    /// Fills in the break information of dialogue, stage direction,
+    /// personal direction and scene headings
+    ///
+    /// These take the most work to break into lines, and each one
+    /// can be done on its own once its text is complete.  Text that
+    /// is already in the cache is not broken again.
+    fn find_breaks(elem: &mut ElementType, cache: Option<&BreakCache>) {
+        match elem {
+            ElementType::D(elem) => {
+                let w = elem.attributes.right_margin
+                    - elem.attributes.left_margin + 1;
+
+                let b = break_points(
+                    cache, &elem.tokens[..], w, elem.first_indent(), elem.hang()
+                );
+
+                if b.len() == 1 {
+                    elem.break_info = BreakType::Atomic(b[0].line_no);
+
+                } else if b.len() > 1 {
+                    elem.break_info = BreakType::List(b);
+                }
+            },
+            ElementType::Dir(elem) => {
+                let w = elem.attributes.right_margin
+                    - elem.attributes.left_margin + 1;
+
+                let n = line_count(cache, &elem.tokens[..], w);
+                elem.line_count = Some((w, n));
+
+                elem.break_info = BreakType::Forbidden(n);
+            },
+            ElementType::P(elem) => {
+                let w = elem.attributes.right_margin
+                    - elem.attributes.left_margin + 1;
+
+                let b = break_points(
+                    cache, &elem.tokens[..], w, elem.first_indent(), elem.hang()
+                );
+
+                if b.len() == 1 {
+                    elem.break_info = BreakType::Atomic(b[0].line_no);
+
+                } else if b.len() > 1 {
+                    elem.break_info = BreakType::List(b);
+                }
+            },
+            ElementType::Shot(elem) => {
+                let n = elem.lines().len();
+
+                elem.break_info = BreakType::Forbidden(n);
+            },
+            ElementType::Slug(elem) => {
+                let n = elem.lines().len();
+                
+                elem.break_info = BreakType::Forbidden(n);
+            },
+            _ => (),
+        }
+    }
+
     fn on_resume(mut self, child: ElementType) -> Self {
         match self {
             State::Act(ref mut elem) => {
@@ -258,6 +339,13 @@ impl State {
             State::Authors(ref mut elem) => {
                 elem.children.push(child);
             },
+            State::B(ref mut elem) => {
+                State::resume_text_element(elem, child);
+            },
+            State::BackMatter(ref mut elem) => {
+                elem.children.push(child);
+            },
+            State::Blank(_) => (),
             State::Body(ref mut elem) => {
                 elem.children.push(child);
             },
@@ -280,6 +368,12 @@ impl State {
             State::End(ref mut elem) => {
                 State::resume_text_element(elem, child);
             },
+            State::Episode(ref mut elem) => {
+                State::resume_text_element(elem, child);
+            },
+            State::FrontMatter(ref mut elem) => {
+                elem.children.push(child);
+            },
             State::FullName(ref mut elem) => {
                 State::resume_text_element(elem, child);
             },
@@ -296,21 +390,33 @@ impl State {
                 State::resume_text_element(elem, child);
             },
             State::PageBreak(_) => (),
+            State::ProductionNumber(ref mut elem) => {
+                State::resume_text_element(elem, child);
+            },
             State::Screenplay(ref mut elem) => {
                 elem.children.push(child);
             },
             State::Series(ref mut elem) => {
                 State::resume_text_element(elem, child);
             },
+            State::Shot(ref mut elem) => {
+                State::resume_text_element(elem, child);
+            },
             State::Slug(ref mut elem) => {
                 State::resume_text_element(elem, child);
             },
+            State::Source(ref mut elem) => {
+                State::resume_text_element(elem, child);
+            },
             State::Title(ref mut elem) => {
                 State::resume_text_element(elem, child);
             },
             State::Trans(ref mut elem) => {
                 State::resume_text_element(elem, child);
             },
+            State::U(ref mut elem) => {
+                State::resume_text_element(elem, child);
+            },
         }
 
         self
@@ -324,10 +430,16 @@ impl State {
                     dpy: Default::default(),
                     frm: FormatFlags::MLB,
                 };
-                elem.tokens.push(TokenType::LineBreak(token));
+                Arc::make_mut(&mut elem.tokens).push(TokenType::LineBreak(token));
+            },
+            ElementType::B(child) => {
+                Arc::make_mut(&mut elem.tokens).extend_from_slice(&child.tokens);
             },
             ElementType::Em(child) => {
-                elem.tokens.extend(child.tokens.into_iter());
+                Arc::make_mut(&mut elem.tokens).extend_from_slice(&child.tokens);
+            },
+            ElementType::U(child) => {
+                Arc::make_mut(&mut elem.tokens).extend_from_slice(&child.tokens);
             },
             _ => {},
         }
@@ -343,93 +455,80 @@ impl State {
         }
     }
 
-    fn remove_leading_eos(tokens: &mut TokenList) {
-        if let Some(TokenType::Punct(token)) = tokens.first_mut() {
-            token.frm.remove(FormatFlags::EOS);
-        }
+    fn is_blank(tokens: &TokenList) -> bool {
+        tokens.iter().all(|token| matches!(token, TokenType::Space(_)))
     }
-    
-    fn find_break_points(tokens: &[TokenType], line_length: usize)
-                   -> BreakPointList
-    {
-        let mut break_points: BreakPointList = Vec::new();
-        let mut n: usize = 1; // line count
-        let mut x: usize = 0; // char count
-        let mut at_end_of_sentence: bool = false;
-
-        for (i, token) in tokens.iter().enumerate() {
-            let frm = token.format_flags();
-
-            if at_end_of_sentence {
-                if let TokenType::Punct(_) = token {
-                    at_end_of_sentence = false;
-                }
-            }
-            
-            if frm.intersects(FormatFlags::MLB) {
-                break_points.push(BreakPoint {
-                    token_index: i + 1,
-                    discard_flag: true,
-                    line_no: n,
-                });
-                
-                n += 1;
-                x = 0;
-                at_end_of_sentence = false;
-            
-            } else if frm.intersects(FormatFlags::EOS)
-                && !frm.intersects(FormatFlags::DLB)
-            {
-                x += token.length();
-                at_end_of_sentence = true;
-
-            } else if frm.intersects(FormatFlags::DLB) {
-                if at_end_of_sentence || frm.intersects(FormatFlags::EOS) {
-                    break_points.push(BreakPoint {
-                        token_index: i + 1,
-                        discard_flag: frm.intersects(FormatFlags::DOB),
-                        line_no: n,
-                    });
-
-                    at_end_of_sentence = false;
-                }
-
-                if !text::next_word_fits(tokens, line_length, i, x) {
-                    n += 1;
-                    x = 0;
-                    
-                } else {
-                    x += token.length();
-                }
 
-            } else {
-                x += token.length();
+    /// Clears the sentence and break flags from an ellipsis or dash
+    /// that opens the text, so that neither a line nor a page breaks
+    /// right after it
+    fn remove_leading_punct_flags(tokens: &mut TokenList) {
+        for token in tokens.iter_mut() {
+            match token {
+                TokenType::Open(_) => (),
+                TokenType::Punct(token) => {
+                    token.frm.remove(FormatFlags::FS | FormatFlags::EOS
+                                     | FormatFlags::DLB);
+                },
+                _ => break,
             }
         }
-
-        if x >= line_length {
-            n += 1;
-        }
-        
-        break_points.push(BreakPoint {
-            token_index: tokens.len(),
-            discard_flag: false,
-            line_no: n,
-        });
-        
-        break_points
     }
 }
 
+/// Time spent in each pass of [`Reader::try_run_timed`]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ReadTiming {
+    /// Parsing the XML and the text of the elements
+    pub parse: Duration,
+    /// Linking dialogue trains and marking scene endings for the
+    /// page breaker
+    pub breaks: Duration,
+}
+
 /// Input driver
 ///
 /// Accumulates a hierarchy of [`ElementType`] variants.
 pub struct Reader<'a> {
+    source: &'a str,
     xml_reader: quick_xml::Reader<&'a [u8]>,
     stack: Vec<State>,
+    start_lines: Vec<usize>,
+    line_pos: (usize, usize),
     next_act_no: i32,
     next_scene_no: i32,
+    last_scene: Option<(i32, Option<char>)>,
+    scene_labels: HashSet<(i32, Option<char>)>,
+    resolve_scenes: bool,
+    scene_warnings: Vec<String>,
     numbering: Numbering,
+    spacing: Spacing,
+    slug_caps: bool,
+    autocase: bool,
+    autocase_element: Option<bool>,
+    casing: Casing,
+    sentence_spacing: SentenceSpacing,
+    ellipsis_spacing: EllipsisSpacing,
+    abbreviations: Option<Rc<Vec<String>>>,
+    authority: Authority,
+    contd: String,
+    version: SchemaVersion,
+    variables: HashMap<String, String>,
+    unresolved: Vec<String>,
+    alt: Option<AltGroup>,
+    alt_choice: Option<usize>,
+    alternates: Vec<Alternates>,
+    scene_pages: Option<HashMap<String, i32>>,
+    references: Vec<(String, Option<i32>)>,
+    list: Option<ListGroup>,
+    list_tail: bool,
+    target: Option<String>,
+    tabs: TabPolicy,
+    tab_line: Option<usize>,
+    control_chars: Vec<(usize, char)>,
+    parallel: bool,
+    layout: Layout,
+    cache: Option<Arc<BreakCache>>,
     /// Document root
     pub root: Option<ElementType>,
 }
@@ -446,15 +545,311 @@ impl<'a> Reader<'a> {
     /// ```
     pub fn new(xml_string: &'a str) -> Self {
         Reader {
+            source: xml_string,
             xml_reader: quick_xml::Reader::from_str(xml_string),
             stack: Vec::with_capacity(16),
+            start_lines: Vec::with_capacity(16),
+            line_pos: (0, 1),
             next_act_no: 1,
             next_scene_no: 1,
+            last_scene: None,
+            scene_labels: HashSet::new(),
+            resolve_scenes: false,
+            scene_warnings: Vec::new(),
             numbering: Numbering::None,
+            spacing: Spacing::Single,
+            slug_caps: false,
+            autocase: false,
+            autocase_element: None,
+            casing: Casing::Default,
+            sentence_spacing: Default::default(),
+            ellipsis_spacing: Default::default(),
+            abbreviations: None,
+            authority: Authority::None,
+            contd: CONTD_TEXT.to_string(),
+            version: SchemaVersion::CURRENT,
+            variables: HashMap::new(),
+            unresolved: Vec::new(),
+            alt: None,
+            alt_choice: None,
+            alternates: Vec::new(),
+            scene_pages: None,
+            references: Vec::new(),
+            list: None,
+            list_tail: false,
+            target: None,
+            tabs: Default::default(),
+            tab_line: None,
+            control_chars: Vec::new(),
+            parallel: false,
+            layout: Layout::default(),
+            cache: None,
             root: None,
         }
     }
 
+    /// Typeset the given variant of every <tt>&lt;alt&gt;</tt>
+    /// group, overriding the <tt>choose</tt> attributes
+    ///
+    /// Groups with fewer variants use their last one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\
+    ///            <cue>EMMA</cue>\
+    ///            <alt choose=\"1\"><d>Late again.</d><d>You're late.</d></alt>\
+    ///            </body></screenplay>";
+    ///
+    /// let Some(ElementType::Screenplay(root)) = Reader::new(xml)
+    ///     .choose_alternate(2)
+    ///     .run() else {
+    ///     panic!();
+    /// };
+    /// let Some(ElementType::Body(body)) = root.children.last() else {
+    ///     panic!();
+    /// };
+    /// let Some(ElementType::D(d)) = body.children.last() else {
+    ///     panic!();
+    /// };
+    ///
+    /// assert_eq!(d.text(), "You're late.");
+    /// assert_eq!(root.alternates()[0].chosen, 2);
+    /// assert_eq!(root.alternates()[0].variants.len(), 2);
+    /// ```
+    pub fn choose_alternate(mut self, n: usize) -> Self {
+        self.alt_choice = Some(n);
+        self
+    }
+
+    /// Resolve <tt>&lt;ref&gt;</tt> elements with the first page of
+    /// each scene, by scene number, as found by a first layout
+    ///
+    /// Without it, references read as <tt>Sc. 12 (p. ?)</tt>.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\
+    ///            <p>As in <ref scene=\"12\"/>, and <ref scene=\"40\"/>.</p>\
+    ///            </body></screenplay>";
+    ///
+    /// let pages = HashMap::from([("12".to_string(), 34)]);
+    /// let Some(ElementType::Screenplay(root)) = Reader::new(xml)
+    ///     .scene_pages(pages)
+    ///     .run() else {
+    ///     panic!();
+    /// };
+    /// let Some(ElementType::Body(body)) = root.children.last() else {
+    ///     panic!();
+    /// };
+    /// let Some(ElementType::P(p)) = body.children.last() else {
+    ///     panic!();
+    /// };
+    ///
+    /// assert_eq!(p.text(), "As in Sc. 12 (p. 34), and Sc. 40 (p. ?).");
+    /// assert_eq!(root.references(), [("12".to_string(), Some(34)),
+    ///                                ("40".to_string(), None)]);
+    /// ```
+    pub fn scene_pages(mut self, pages: HashMap<String, i32>) -> Self {
+        self.scene_pages = Some(pages);
+        self
+    }
+
+    /// Read for the given draft target, such as <tt>network</tt>,
+    /// <tt>streaming</tt>, or <tt>intl</tt>
+    ///
+    /// Elements whose <tt>when</tt> attribute lists other targets are
+    /// skipped, along with everything inside them.  Without a target,
+    /// every element is read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<body>\
+    ///            <cue>EMMA</cue>\
+    ///            <d when=\"network\">Darn it.</d>\
+    ///            <d when=\"streaming intl\">Damn it.</d>\
+    ///            </body>";
+    ///
+    /// let Some(ElementType::Body(body)) = Reader::new(xml)
+    ///     .target("network")
+    ///     .run() else {
+    ///     panic!();
+    /// };
+    ///
+    /// assert_eq!(body.children.len(), 2);
+    /// let Some(ElementType::D(d)) = body.children.last() else {
+    ///     panic!();
+    /// };
+    /// assert_eq!(d.text(), "Darn it.");
+    /// ```
+    pub fn target<S: Into<String>>(mut self, target: S) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Give a scene whose number repeats an earlier one, or goes
+    /// back, the highest number so far with the next addition letter,
+    /// as in 12A after 12
+    ///
+    /// Either way such scenes are reported; see
+    /// [`ContainerElement::scene_number_warnings`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head/><body>\
+    ///            <slug number=\"12\">INT. HALL - DAY</slug>\
+    ///            <slug number=\"12\">INT. STAIRS - DAY</slug>\
+    ///            <slug number=\"3\">INT. ATTIC - DAY</slug>\
+    ///            <slug>INT. ROOF - DAY</slug>\
+    ///            </body></screenplay>";
+    ///
+    /// let Some(ElementType::Screenplay(root)) = Reader::new(xml)
+    ///     .resolve_scene_numbers(true)
+    ///     .run() else {
+    ///     panic!();
+    /// };
+    /// let Some(ElementType::Body(body)) = root.children.last() else {
+    ///     panic!();
+    /// };
+    /// let labels: Vec<_> = body.children.iter().filter_map(|child| match child {
+    ///     ElementType::Slug(slug) => Some((slug.attributes.number,
+    ///                                      slug.attributes.addition)),
+    ///     _ => None,
+    /// }).collect();
+    ///
+    /// assert_eq!(labels, [(12, None), (12, Some('A')), (12, Some('B')),
+    ///                     (13, None)]);
+    /// assert_eq!(root.scene_number_warnings().len(), 2);
+    /// ```
+    pub fn resolve_scene_numbers(mut self, value: bool) -> Self {
+        self.resolve_scenes = value;
+        self
+    }
+
+    /// Set the handling of tab characters in text elements
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::reader::Reader;
+    /// # use batyr::text::parser::TabPolicy;
+    /// let xml = "<body>\n<p>Name:\tEMMA</p>\n</body>";
+    ///
+    /// assert!(Reader::new(xml).try_run().is_ok());
+    /// assert_eq!(Reader::new(xml).tabs(TabPolicy::Error).try_run().unwrap_err(),
+    ///            "Tab character in text on line 2");
+    /// ```
+    pub fn tabs(mut self, tabs: TabPolicy) -> Self {
+        self.tabs = tabs;
+        self
+    }
+
+    /// Find the line breaks of all text elements in parallel once the
+    /// document has been read, instead of one at a time as each
+    /// element closes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::reader::Reader;
+    /// # use batyr::document::sample::generate;
+    /// let xml = generate(10, 7);
+    /// let serial = Reader::new(&xml).try_run().unwrap().unwrap();
+    /// let parallel = Reader::new(&xml).parallel(true).try_run().unwrap().unwrap();
+    ///
+    /// assert_eq!(format!("{:?}", parallel), format!("{:?}", serial));
+    /// ```
+    #[cfg(feature = "parallel")]
+    pub fn parallel(mut self, value: bool) -> Self {
+        self.parallel = value;
+        self
+    }
+
+    /// Sets the margins and tab stops of the text elements
+    ///
+    /// A screenplay that names a reference book in its
+    /// <tt>authority</tt> attribute gets the book's dialogue tab
+    /// stops instead.
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Reuses the break points of text that has not changed since an
+    /// earlier reading with the same cache
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use batyr::document::reader::{BreakCache, Reader};
+    /// let cache = Arc::new(BreakCache::new());
+    /// let first = "<body><p>Snow.</p><p>Rain.</p></body>";
+    /// let second = "<body><p>Snow.</p><p>Hail.</p></body>";
+    ///
+    /// Reader::new(first).cache(cache.clone()).run();
+    /// assert_eq!((cache.hits(), cache.len()), (0, 2));
+    ///
+    /// Reader::new(second).cache(cache.clone()).run();
+    /// assert_eq!((cache.hits(), cache.len()), (1, 2));
+    /// ```
+    pub fn cache(mut self, cache: Arc<BreakCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Checks whether an element is meant for another target
+    fn is_excluded(&self, event: &BytesStart) -> bool {
+        match (&self.target, string_attr!(event, b"when")) {
+            (Some(target), Some(when)) => {
+                !when.split_whitespace().any(|x| x == target)
+            },
+            _ => false,
+        }
+    }
+
+    /// Refuses an element that is newer than the document's schema
+    /// version
+    fn check_version(&self, event: &BytesStart) -> Result<(), String> {
+        let name = event.local_name().into_inner();
+        let introduced = SchemaVersion::introducing(name);
+
+        if introduced > self.version {
+            return Err(format!(
+                "<{}> needs schema version {}, but the document is version {}",
+                String::from_utf8_lossy(name), introduced, self.version
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the line of the source the XML reader has reached,
+    /// counting on from the last call
+    fn current_line(&mut self) -> usize {
+        let (pos, line) = self.line_pos;
+        let end = self.xml_reader.buffer_position();
+
+        let line = line + self.source.as_bytes()[pos..end].iter()
+            .filter(|&&x| x == b'\n')
+            .count();
+
+        self.line_pos = (end, line);
+        line
+    }
+
     /// Push a state onto the stack
     fn push(&mut self, next: State) {
         if let Some(prev) = self.stack.last() {
@@ -462,13 +857,35 @@ impl<'a> Reader<'a> {
         }
 
         next.on_enter();
+
+        // Indexed by depth, since states are sometimes dropped
+        // without being popped.
+        let line = self.current_line();
+        self.start_lines.truncate(self.stack.len());
+        self.start_lines.push(line);
+
         self.stack.push(next);
     }
 
     /// Pop a state off the stack
     fn pop(&mut self) {
         if let Some(prev) = self.stack.pop() {
-            let elem = prev.on_exit();
+            let mut elem = prev.on_exit();
+            let depth = self.stack.len();
+
+            if let Some(line) = self.start_lines.get(depth) {
+                elem.set_line(*line);
+            }
+
+            if !self.parallel {
+                State::find_breaks(&mut elem, self.cache.as_deref());
+            }
+
+            // Variants are held back until the group closes.
+            if let Some(group) = self.alt.as_mut().filter(|g| g.depth == depth) {
+                group.variants.push(elem);
+                return;
+            }
 
             if let Some(next) = self.stack.pop() {
                 self.stack.push(next.on_resume(elem));
@@ -479,71 +896,231 @@ impl<'a> Reader<'a> {
         }
     }
 
-    /// Process XML events
-    ///
-    /// # Examples
-    ///
+    /// Closes an <tt>&lt;ol&gt;</tt> list, resuming the paragraph it
+    /// interrupted
+    fn close_list(&mut self) {
+        let group = match self.list.take() {
+            Some(group) => group,
+            None => return,
+        };
+
+        match group.paragraph {
+            Some(indent) => {
+                let elem = TextElement::new(P {
+                    indent,
+                    left_margin: self.layout.p_begin,
+                    right_margin: self.layout.p_end,
+                    page_break_before: false,
+                    keep_with_next: false,
+                    train: Vec::new(),
+                    padding_before: 0,
+                    padding_after: self.spacing.blank_lines(),
+                });
+
+                self.push(State::P(elem));
+                self.list_tail = true;
+            },
+            None => {
+                if let Some(State::Body(body)) = self.stack.last_mut() {
+                    if let Some(ElementType::P(elem)) = body.children.last_mut() {
+                        elem.attributes.padding_after
+                            = self.spacing.blank_lines();
+                    }
+                }
+            },
+        }
+    }
+
+    /// Closes an <tt>&lt;alt&gt;</tt> group, keeping the chosen
+    /// variant and recording the rest
+    fn close_alt(&mut self) {
+        let group = match self.alt.take() {
+            Some(group) => group,
+            None => return,
+        };
+
+        if group.variants.is_empty() {
+            return;
+        }
+
+        let n = group.variants.len();
+        let chosen = self.alt_choice.unwrap_or(group.choose).clamp(1, n);
+
+        let variants = group.variants.iter().map(|variant| match variant {
+            ElementType::D(elem) => elem.text(),
+            _ => String::new(),
+        }).collect();
+
+        self.alternates.push(Alternates {
+            cue: group.cue,
+            chosen,
+            variants,
+        });
+
+        let elem = group.variants.into_iter().nth(chosen - 1).unwrap();
+
+        if let Some(next) = self.stack.pop() {
+            self.stack.push(next.on_resume(elem));
+        }
+    }
+
+    /// Process XML events
+    ///
+    /// Returns nothing if the input is rejected; use
+    /// [`Reader::try_run`] to find out why.
+    ///
+    /// # Examples
+    ///
     /// ```
     /// # use batyr::document::reader::Reader;
     /// let reader = Reader::new("<em>Ulysses</em>");
     /// let root = reader.run();
     /// assert!(root.is_some());
     /// ```
-    pub fn run(mut self) -> Option<ElementType> {
+    pub fn run(self) -> Option<ElementType> {
+        self.try_run().ok().flatten()
+    }
+
+    /// Process XML events, failing on input that is not well-formed
+    /// or is rejected by the reader's settings, such as tabs under
+    /// [`TabPolicy::Error`]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::reader::Reader;
+    /// assert!(Reader::new("<p>Snow.</p>").try_run().is_ok());
+    /// assert!(Reader::new("<p>Snow.</q>").try_run().is_err());
+    /// ```
+    pub fn try_run(self) -> Result<Option<ElementType>, String> {
+        self.try_run_timed().map(|(root, _)| root)
+    }
+
+    /// Process XML events like [`Reader::try_run`], also measuring
+    /// the time spent in each pass
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::reader::Reader;
+    /// # use std::time::Duration;
+    /// let xml = "<screenplay><head/><body><p>Snow.</p></body></screenplay>";
+    /// let (root, timing) = Reader::new(xml).try_run_timed().unwrap();
+    ///
+    /// assert!(root.is_some());
+    /// assert!(timing.parse > Duration::ZERO);
+    /// assert!(timing.breaks < Duration::from_secs(1));
+    /// ```
+    pub fn try_run_timed(mut self)
+                         -> Result<(Option<ElementType>, ReadTiming), String>
+    {
+        let mut timing = ReadTiming::default();
+        let start = Instant::now();
+
         loop {
-            match self.xml_reader.read_event().unwrap() {
+            match self.xml_reader.read_event().map_err(|e| e.to_string())? {
                 Event::Start(ref event) => {
+                    if self.is_excluded(event) {
+                        self.xml_reader.read_to_end(event.name())
+                            .map_err(|e| e.to_string())?;
+                        continue;
+                    }
+
+                    self.check_version(event)?;
+
                     match event.local_name().into_inner() {
                         b"act" => {
                             let number = self.next_act_no;
                             self.next_act_no += 1;
 
                             let elem = TextElement::new(Act {
-                                number: number,
-                                padding_before: if number == 1 {
-                                    0
-                                } else {
-                                    -1
-                                },
+                                number,
+                                left_margin: self.layout.centered_left_margin(),
+                                right_margin: self.layout.centered_right_margin(),
+                                page_break_before: bool_attr!(event, b"breakBefore")
+                                    .unwrap_or(number > 1),
+                                padding_before: 0,
                                 padding_after: 1,
                             });
 
                             self.push(State::Act(elem));
                         },
+                        b"alt" => {
+                            let choose = numeric_attr!(event, b"choose", usize)
+                                .unwrap_or(1);
+
+                            let cue = match self.stack.last() {
+                                Some(State::Body(parent)) => {
+                                    match parent.children.last() {
+                                        Some(ElementType::Cue(cue)) => cue.name(),
+                                        _ => String::new(),
+                                    }
+                                },
+                                _ => String::new(),
+                            };
+
+                            self.alt = Some(AltGroup {
+                                depth: self.stack.len(),
+                                cue,
+                                choose,
+                                variants: Vec::new(),
+                            });
+                        },
                         b"authors" => {
                             let elem = ContainerElement::new(Authors {});
                             self.push(State::Authors(elem));
                         },
+                        b"b" => {
+                            let elem = TextElement::new(B {});
+                            self.push(State::B(elem));
+                        },
+                        b"backMatter" => {
+                            let elem = ContainerElement::new(BackMatter {});
+                            self.push(State::BackMatter(elem));
+                        },
                         b"body" => {
                             let elem = ContainerElement::new(Body {});
                             self.push(State::Body(elem));
                         },
                         b"contact" => {
                             let elem = TextElement::new(Contact {
-                                left_margin: CONTACT_BEGIN,
-                                right_margin: CONTACT_END,
+                                left_margin: self.layout.contact_begin,
+                                right_margin: self.layout.contact_end,
                             });
                             self.push(State::Contact(elem));
                         },
                         b"cue" => {
+                            let ext = string_attr!(event, b"ext")
+                                .map(|x| x.trim()
+                                     .trim_start_matches('(')
+                                     .trim_end_matches(')')
+                                     .trim()
+                                     .to_string())
+                                .filter(|x| !x.is_empty());
+
                             let elem = TextElement::new(Cue {
-                                tab_stop: CUE_BEGIN,
+                                tab_stop: self.layout.cue_begin,
+                                right_margin: self.layout.right_margin,
+                                ext,
                                 train: Vec::new(),
-                                padding_before: 1,
+                                page_break_before: bool_attr!(event, b"breakBefore")
+                                    .unwrap_or(false),
+                                padding_before: self.spacing.blank_lines() as i32,
                                 padding_after: 0,
                             });
 
+                            self.autocase_element = bool_attr!(event, b"autocase");
                             self.push(State::Cue(elem));
                         },
                         b"d" => {
                             let indent
-                                = numeric_attr!(event, b"indent", usize)
+                                = numeric_attr!(event, b"indent", i32)
                                 .unwrap_or(0);
 
                             let elem = TextElement::new(D {
-                                indent: indent,
-                                left_margin: D_BEGIN,
-                                right_margin: D_END,
+                                indent,
+                                left_margin: self.layout.d_begin,
+                                right_margin: self.layout.d_end,
                                 padding_before: 0,
                                 padding_after: 0,
                             });
@@ -552,8 +1129,8 @@ impl<'a> Reader<'a> {
                         },
                         b"dir" => {
                             let elem = TextElement::new(Dir {
-                                left_margin: DIR_BEGIN,
-                                right_margin: DIR_END,
+                                left_margin: self.layout.dir_begin,
+                                right_margin: self.layout.dir_end,
                                 padding_before: 0,
                                 padding_after: 0,
                             });
@@ -564,12 +1141,30 @@ impl<'a> Reader<'a> {
                             self.push(State::Em(elem));
                         },
                         b"end" => {
+                            let place = enum_attr!(
+                                event, b"place", Placement,
+                                Placement::from
+                            ).unwrap_or_default();
+
                             let elem = TextElement::new(End {
+                                left_margin: self.layout.centered_left_margin(),
+                                right_margin: self.layout.centered_right_margin(),
+                                place,
+                                page_break_before: bool_attr!(event, b"breakBefore")
+                                    .unwrap_or(false),
                                 padding_before: 1,
                                 padding_after: 0,
                             });
                             self.push(State::End(elem));
                         },
+                        b"episode" => {
+                            let elem = TextElement::new(Episode {});
+                            self.push(State::Episode(elem));
+                        },
+                        b"frontMatter" => {
+                            let elem = ContainerElement::new(FrontMatter {});
+                            self.push(State::FrontMatter(elem));
+                        },
                         b"fullName" => {
                             let elem = TextElement::new(FullName {});
                             self.push(State::FullName(elem));
@@ -579,57 +1174,304 @@ impl<'a> Reader<'a> {
                             self.push(State::Head(elem));
                         },
                         b"note" => {
-                            let elem = TextElement::new(Note {});
+                            let align = enum_attr!(
+                                event, b"align", Alignment,
+                                Alignment::from
+                            ).unwrap_or(Alignment::Center);
+
+                            let elem = TextElement::new(Note {
+                                align,
+                            });
                             self.push(State::Note(elem));
                         },
                         b"open" => {
+                            let tab_stop = numeric_attr!(event, b"tabStop", usize)
+                                .unwrap_or(self.layout.p_begin);
+
+                            let after_act = match self.stack.last() {
+                                Some(State::Body(parent)) => matches!(
+                                    parent.children.last(),
+                                    Some(ElementType::Act(_))
+                                ),
+                                _ => false,
+                            };
+
+                            let padding_before = if after_act {
+                                numeric_attr!(event, b"afterAct", i32)
+                                    .unwrap_or(1)
+                            } else {
+                                0
+                            };
+
                             let elem = TextElement::new(Open {
-                                tab_stop: P_BEGIN,
-                                padding_before: 0,
+                                tab_stop,
+                                page_break_before: bool_attr!(event, b"breakBefore")
+                                    .unwrap_or(false),
+                                padding_before,
                                 padding_after: 1,
+                                after_act,
                             });
+                            self.autocase_element = bool_attr!(event, b"autocase");
                             self.push(State::Open(elem));
                         },
+                        b"li" => {
+                            let group = match self.list.as_mut() {
+                                Some(group) => group,
+                                None => {
+                                    self.xml_reader.read_to_end(event.name())
+                                        .unwrap();
+                                    continue;
+                                },
+                            };
+
+                            let label = format!("{}.", group.next);
+
+                            let padding_before = match group.next == 1
+                                && !group.follows_text
+                            {
+                                true => self.spacing.blank_lines() as i32,
+                                false => 0,
+                            };
+
+                            group.next += 1;
+
+                            // Items are paragraphs that hang past
+                            // their numbers.
+                            let mut elem = TextElement::new(P {
+                                indent: -(label.len() as i32 + 1),
+                                left_margin: self.layout.p_begin,
+                                right_margin: self.layout.p_end,
+                                page_break_before: false,
+                                keep_with_next: false,
+                                train: Vec::new(),
+                                padding_before,
+                                padding_after: 0,
+                            });
+
+                            Arc::make_mut(&mut elem.tokens).push(TokenType::Word(
+                                Token::from(label.as_str())
+                            ));
+
+                            self.push(State::P(elem));
+                        },
+                        b"ol" => {
+                            let mut group = ListGroup {
+                                next: 1,
+                                follows_text: false,
+                                paragraph: None,
+                            };
+
+                            // A list inside a paragraph ends it, and
+                            // the paragraph picks up after the list.
+                            if let Some(State::P(elem)) = self.stack.last_mut() {
+                                group.paragraph = Some(elem.attributes.indent);
+                                elem.attributes.padding_after = 0;
+
+                                if State::is_blank(&elem.tokens) {
+                                    self.stack.pop();
+                                } else {
+                                    group.follows_text = true;
+                                    self.pop();
+                                }
+                            }
+
+                            self.list = Some(group);
+                        },
                         b"p" => {
-                            let indent = numeric_attr!(event, b"indent", usize)
+                            let indent = numeric_attr!(event, b"indent", i32)
                                 .unwrap_or(0);
 
                             let elem = TextElement::new(P {
-                                indent: indent,
-                                left_margin: P_BEGIN,
-                                right_margin: P_END,
-                                padding_before: 1,
-                                padding_after: 1,
+                                indent,
+                                left_margin: self.layout.p_begin,
+                                right_margin: self.layout.p_end,
+                                page_break_before: bool_attr!(event, b"breakBefore")
+                                    .unwrap_or(false),
+                                keep_with_next: bool_attr!(event, b"keepWithNext")
+                                    .unwrap_or(false),
+                                train: Vec::new(),
+                                padding_before: self.spacing.blank_lines() as i32,
+                                padding_after: self.spacing.blank_lines(),
                             });
 
                             self.push(State::P(elem));
                         },
+                        b"productionNumber" => {
+                            let elem = TextElement::new(ProductionNumber {});
+                            self.push(State::ProductionNumber(elem));
+                        },
                         b"screenplay" => {
+                            let lang = enum_attr!(
+                                event, b"lang", Language,
+                                Language::from
+                            ).unwrap_or_default();
+
                             let numbering = enum_attr!(
                                 event, b"numbering", Numbering,
-                                |x| Numbering::from(x)
+                                Numbering::from
                             ).unwrap_or(Numbering::None);
+
+                            let spacing = enum_attr!(
+                                event, b"spacing", Spacing,
+                                Spacing::from
+                            ).unwrap_or(Spacing::Single);
                             
+                            self.sentence_spacing = enum_attr!(
+                                event, b"sentenceSpacing", SentenceSpacing,
+                                SentenceSpacing::from
+                            ).unwrap_or(lang.sentence_spacing());
+
+                            self.ellipsis_spacing = enum_attr!(
+                                event, b"ellipsisSpacing", EllipsisSpacing,
+                                EllipsisSpacing::from
+                            ).unwrap_or_default();
+
+                            self.abbreviations = string_attr!(event, b"abbreviations")
+                                .map(|s| Rc::new(
+                                    s.split_whitespace()
+                                        .map(|x| x.trim_end_matches('.').to_string())
+                                        .collect()
+                                ));
+
+                            let justify = bool_attr!(event, b"justify")
+                                .unwrap_or(false);
+
+                            let slug_caps = bool_attr!(event, b"slugCaps")
+                                .unwrap_or(false);
+
+                            let autocase = bool_attr!(event, b"autocase")
+                                .unwrap_or(false);
+
+                            let casing = enum_attr!(
+                                event, b"casing", Casing,
+                                Casing::from
+                            ).unwrap_or(lang.casing());
+
+                            let profile = enum_attr!(
+                                event, b"profile", Profile,
+                                Profile::from
+                            ).unwrap_or(Profile::Film);
+
+                            let authority = enum_attr!(
+                                event, b"authority", Authority,
+                                Authority::from
+                            ).unwrap_or_default();
+
+                            let version = match string_attr!(event, b"version") {
+                                Some(s) => match SchemaVersion::parse(&s) {
+                                    Some(v) if v.is_supported() => v,
+                                    Some(v) => return Err(format!(
+                                        "Document is schema version {}, \
+                                         but this batyr reads up to {}",
+                                        v, SchemaVersion::CURRENT
+                                    )),
+                                    None => return Err(format!(
+                                        "Invalid schema version \"{}\"", s
+                                    )),
+                                },
+                                None => SchemaVersion::CURRENT,
+                            };
+
+                            let lines_before_break
+                                = numeric_attr!(event, b"linesBeforeBreak", usize);
+
+                            let lines_after_break
+                                = numeric_attr!(event, b"linesAfterBreak", usize);
+
+                            let break_after_dir = bool_attr!(event, b"breakAfterDir");
+
+                            let defaults = lang.markers();
+
+                            let markers = Markers {
+                                more: string_attr!(event, b"moreText")
+                                    .unwrap_or(defaults.more),
+                                contd: string_attr!(event, b"contdText")
+                                    .unwrap_or(defaults.contd),
+                                continued: string_attr!(event, b"continuedText")
+                                    .unwrap_or(defaults.continued),
+                                continued_count: enum_attr!(
+                                    event, b"continuedCount", ContinuedCount,
+                                    ContinuedCount::from
+                                ).unwrap_or_default(),
+                                continued_numbers: bool_attr!(event, b"continuedNumbers")
+                                    .unwrap_or(true),
+                            };
+
                             let elem = ContainerElement::new(Screenplay {
-                                numbering: numbering,
+                                numbering,
+                                spacing,
+                                justify,
+                                slug_caps,
+                                autocase,
+                                casing,
+                                profile,
+                                authority,
+                                markers,
+                                lines_before_break,
+                                lines_after_break,
+                                break_after_dir,
+                                version,
+                                unresolved: Vec::new(),
+                                alternates: Vec::new(),
+                                references: Vec::new(),
+                                control_chars: Vec::new(),
+                                scene_warnings: Vec::new(),
+                                lang,
                             });
 
                             self.numbering = elem.attributes.numbering;
+                            self.spacing = elem.attributes.spacing;
+                            self.slug_caps = elem.attributes.slug_caps;
+                            self.autocase = elem.attributes.autocase;
+                            self.casing = elem.attributes.casing;
+                            self.authority = elem.attributes.authority;
+                            self.layout = self.layout.with_authority(self.authority);
+                            self.contd = elem.attributes.markers.contd.clone();
+                            self.version = version;
                             self.push(State::Screenplay(elem));
                         },
                         b"series" => {
                             let elem = TextElement::new(Series {
-                                left_margin: LEFT_MARGIN + 2 * INDENT,
-                                right_margin: RIGHT_MARGIN - 2 * INDENT,
+                                left_margin: self.layout.centered_left_margin(),
+                                right_margin: self.layout.centered_right_margin(),
                                 padding_before: 0,
                                 padding_after: 1,
                             });
                             self.push(State::Series(elem));
                         },
+                        b"shot" => {
+                            let elem = TextElement::new(Shot {
+                                train: Vec::new(),
+                                left_margin: self.layout.p_begin,
+                                right_margin: self.layout.p_end,
+                                page_break_before: bool_attr!(event, b"breakBefore")
+                                    .unwrap_or(false),
+                                padding_before: 1,
+                                padding_after: 1,
+                            });
+
+                            self.autocase_element = bool_attr!(event, b"autocase");
+                            self.push(State::Shot(elem));
+                        },
                         b"slug" => {
-                            let number;
-                            
-	                    if let Some(n)
+                            let mut number;
+                            let mut addition = char_attr!(event, b"addition");
+
+                            let continued = bool_attr!(event, b"continued")
+                                .unwrap_or(false);
+                            let numbered = bool_attr!(event, b"numbered")
+                                .unwrap_or(true);
+
+                            if let (true, Some((n, c))) = (continued, self.last_scene) {
+                                number = n;
+                                addition = c;
+
+                            } else if !numbered {
+                                // Kept out of the sequence altogether.
+                                number = 0;
+                                addition = None;
+
+                            } else if let Some(n)
                                 = numeric_attr!(event, b"number", i32)
                             {
 	                        number = n;
@@ -640,6 +1482,11 @@ impl<'a> Reader<'a> {
                                 self.next_scene_no += 1;
 	                    }
 
+                            if !continued && numbered {
+                                (number, addition) = self.check_scene_number(number,
+                                                                             addition);
+                            }
+
                             let mut padding_before: i32 = 2;
                             
                             if let Some(State::Body(parent)) = self.stack.last() {
@@ -651,47 +1498,234 @@ impl<'a> Reader<'a> {
                                 }
                             }
                             
+                            if numbered {
+                                self.last_scene = Some((number, addition));
+                            }
+
+                            let suffix = match string_attr!(event, b"suffix") {
+                                Some(s) => self.new_parser(&s, Vec::new(),
+                                                           Default::default())
+                                    .run()
+                                    .get_tokens(),
+                                None => Vec::new(),
+                            };
+
                             let elem = TextElement::new(Slug {
-                                number: number,
-                                addition: char_attr!(event, b"addition"),
+                                number,
+                                addition,
                                 train: Vec::new(),
-                                left_margin: P_BEGIN,
-                                right_margin: P_END,
-                                padding_before: padding_before,
+                                left_margin: self.layout.p_begin,
+                                right_margin: self.layout.p_end,
+                                page_break_before: bool_attr!(event, b"breakBefore")
+                                    .unwrap_or(false),
+                                padding_before,
                                 padding_after: 1,
                                 numbering: self.numbering,
+                                continued,
+                                numbered: numbered || continued,
+                                suffix,
                             });
                             
+                            self.autocase_element = bool_attr!(event, b"autocase");
                             self.push(State::Slug(elem));
                         },
+                        b"source" => {
+                            let elem = TextElement::new(Source {});
+                            self.push(State::Source(elem));
+                        },
                         b"title" => {
                             let elem = TextElement::new(Title {
-                                left_margin: LEFT_MARGIN + 2 * INDENT,
-                                right_margin: RIGHT_MARGIN - 2 * INDENT,
+                                left_margin: self.layout.centered_left_margin(),
+                                right_margin: self.layout.centered_right_margin(),
                                 padding_before: 0,
                                 padding_after: 1,
                             });
                             self.push(State::Title(elem));
                         },
                         b"trans" => {
+                            let align = enum_attr!(
+                                event, b"align", Alignment,
+                                Alignment::from
+                            ).unwrap_or(self.authority.trans_align());
+
                             let elem = TextElement::new(Trans {
-                                tab_stop: TRANS_BEGIN,
-                                right_margin: TRANS_END,
+                                tab_stop: self.layout.trans_begin,
+                                right_margin: self.layout.trans_end,
+                                align,
+                                page_break_before: bool_attr!(event, b"breakBefore")
+                                    .unwrap_or(false),
                                 padding_before: 1,
                                 padding_after: 1,
                             });
+                            self.autocase_element = bool_attr!(event, b"autocase");
                             self.push(State::Trans(elem));
                         },
+                        b"u" => {
+                            let elem = TextElement::new(U {});
+                            self.push(State::U(elem));
+                        },
+                        _ => (),
+                    }
+                },
+                Event::End(ref event) => {
+                    if event.local_name().into_inner() == b"alt" {
+                        self.close_alt();
+                        continue;
+                    }
+
+                    if event.local_name().into_inner() == b"ol" {
+                        self.close_list();
+                        continue;
+                    }
+
+                    // One space separates the number from the item.
+                    if event.local_name().into_inner() == b"li" {
+                        if let Some(State::P(elem)) = self.stack.last_mut() {
+                            let tokens = Arc::make_mut(&mut elem.tokens);
+
+                            if let Some(TokenType::Space(_)) = tokens.get(1) {
+                                tokens.remove(1);
+                            }
+
+                            tokens.insert(1, TokenType::Space(Token::from(1)));
+                        }
+                    }
+
+                    // Nothing followed the list in its paragraph.
+                    if event.local_name().into_inner() == b"p" && self.list_tail {
+                        self.list_tail = false;
+
+                        if let Some(State::P(elem)) = self.stack.last() {
+                            if State::is_blank(&elem.tokens) {
+                                self.stack.pop();
+                                continue;
+                            }
+                        }
+                    }
+
+                    // Force caps once the whole element is in,
+                    // including any emphasized text.
+                    let autocase = self.autocase_element.unwrap_or(self.autocase);
+
+                    match self.stack.last_mut() {
+                        Some(State::Slug(elem)) if self.slug_caps || autocase => {
+                            text::to_screenplay_caps(&mut Arc::make_mut(&mut elem.tokens)[..],
+                                                     self.casing);
+                            text::to_screenplay_caps(&mut elem.attributes.suffix,
+                                                     self.casing);
+                        },
+                        Some(State::Shot(elem)) if self.slug_caps || autocase => {
+                            text::to_screenplay_caps(&mut Arc::make_mut(&mut elem.tokens)[..],
+                                                     self.casing);
+                        },
+                        Some(State::Cue(elem)) if autocase => {
+                            text::to_screenplay_caps(&mut Arc::make_mut(&mut elem.tokens)[..],
+                                                     self.casing);
+                        },
+                        Some(State::Open(elem)) if autocase => {
+                            text::to_screenplay_caps(&mut Arc::make_mut(&mut elem.tokens)[..],
+                                                     self.casing);
+                        },
+                        Some(State::Trans(elem)) if autocase => {
+                            text::to_screenplay_caps(&mut Arc::make_mut(&mut elem.tokens)[..],
+                                                     self.casing);
+                        },
                         _ => (),
                     }
+
+                    // The extension follows the name, in parentheses.
+                    if let Some(State::Cue(elem)) = self.stack.last_mut() {
+                        if let Some(ext) = &elem.attributes.ext {
+                            State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
+
+                            let text = format!(" ({})", ext);
+
+                            let tokens = Arc::make_mut(&mut elem.tokens);
+
+                            *tokens = Parser::new(&text, mem::take(tokens),
+                                                  Default::default())
+                                .run()
+                                .get_tokens();
+                        }
+                    }
+
+                    // (CONT'D) goes last, after any suffix.
+                    if !self.contd.is_empty() {
+                        if let Some(State::Slug(elem)) = self.stack.last_mut() {
+                            if elem.attributes.continued {
+                                State::trim_whitespace(Arc::make_mut(&mut elem.tokens));
+
+                                let tokens = if elem.attributes.suffix.is_empty() {
+                                    Arc::make_mut(&mut elem.tokens)
+                                } else {
+                                    &mut elem.attributes.suffix
+                                };
+
+                                let text = format!(" {}", self.contd);
+
+                                *tokens = Parser::new(&text, mem::take(tokens),
+                                                      Default::default())
+                                    .run()
+                                    .get_tokens();
+                            }
+                        }
+                    }
+
+                    self.pop();
                 },
-                Event::End(_) => self.pop(),
 	        Event::Empty(ref event) => {
+                    if self.is_excluded(event) {
+                        continue;
+                    }
+
+                    self.check_version(event)?;
+
                     match event.local_name().into_inner() {
+                        b"blank" => {
+                            let lines = numeric_attr!(event, b"lines", usize)
+                                .unwrap_or(1);
+
+                            self.push(State::Blank(
+                                EmptyElement::new(Blank { lines })
+                            ));
+                            self.pop();
+                        },
+                        b"body" => {
+                            self.push(State::Body(ContainerElement::new(Body {})));
+                            self.pop();
+                        },
                         b"br" => {
                             self.push(State::Br(EmptyElement::new(Br {})));
                             self.pop();
                         },
+                        b"define" => {
+                            let name = string_attr!(event, b"name");
+                            let value = string_attr!(event, b"value");
+
+                            if let (Some(name), Some(value)) = (name, value) {
+                                self.variables.insert(name, value);
+                            }
+                        },
+                        b"ref" => {
+                            let scene = string_attr!(event, b"scene")
+                                .unwrap_or_default();
+                            let page = self.scene_pages.as_ref()
+                                .and_then(|pages| pages.get(&scene))
+                                .copied();
+
+                            // Escaped spaces keep the full stops from
+                            // ending sentences.
+                            let text = match page {
+                                Some(page) => format!("Sc.\\ {} (p.\\ {})", scene, page),
+                                None => format!("Sc.\\ {} (p.\\ ?)", scene),
+                            };
+
+                            if !self.references.iter().any(|(x, _)| *x == scene) {
+                                self.references.push((scene, page));
+                            }
+
+                            self.on_text(&BytesText::new(&text));
+                        },
                         b"pageBreak" => {
                             self.push(State::PageBreak(
                                 EmptyElement::new(PageBreak {})
@@ -702,85 +1736,7 @@ impl<'a> Reader<'a> {
                     }
                 },
 	        Event::Text(ref event) => {
-                    match self.stack.pop() {
-                        Some(State::Act(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::Act(elem));
-                        },
-                        Some(State::Contact(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::Contact(elem));
-                        },
-                        Some(State::Cue(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::Cue(elem));
-                        },
-                        Some(State::D(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::D(elem));
-                        },
-                        Some(State::Dir(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::Dir(elem));
-                        },
-                        Some(State::Em(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          DisplayFlags::EM);
-                            self.stack.push(State::Em(elem));
-                        },
-                        Some(State::End(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::End(elem));
-                        },
-                        Some(State::FullName(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::FullName(elem));
-                        },
-                        Some(State::Note(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::Note(elem));
-                        },
-                        Some(State::Open(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::Open(elem));
-                        },
-                        Some(State::P(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::P(elem));
-                        },
-                        Some(State::Series(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::Series(elem));
-                        },
-                        Some(State::Slug(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::Slug(elem));
-                        },
-                        Some(State::Title(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::Title(elem));
-                        },
-                        Some(State::Trans(mut elem)) => {
-                            elem.tokens = self.parse_text(event, elem.tokens,
-                                                          Default::default());
-                            self.stack.push(State::Trans(elem));
-                        },
-                        Some(state) => self.stack.push(state),
-                        None => (),
-                    }
+                    self.on_text(event);
                 },
 	        Event::Comment(_) => (), // ignore comments
 	        Event::CData(_) => (), // not handled
@@ -790,7 +1746,21 @@ impl<'a> Reader<'a> {
 	        Event::Eof => break,
             }
         }
+
+        timing.parse = start.elapsed();
+        let start = Instant::now();
         
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            if let Some(root) = &mut self.root {
+                find_breaks_in_parallel(root, self.cache.as_deref());
+            }
+        }
+
+        if let Some(cache) = &self.cache {
+            cache.finish();
+        }
+
         // post-processing
         match &mut self.root {
             Some(ElementType::Screenplay(ref mut root)) => {
@@ -798,21 +1768,678 @@ impl<'a> Reader<'a> {
                     build_trains(body);
                     mark_scene_endings(body);
                 }
+
+                if root.attributes.profile == Profile::Multicam {
+                    add_cast_and_set_lists(root, &self.layout);
+                }
+
+                root.attributes.unresolved = self.unresolved.clone();
+                root.attributes.alternates = self.alternates.clone();
+                root.attributes.references = self.references.clone();
+                root.attributes.control_chars = self.control_chars.clone();
+                root.attributes.scene_warnings = self.scene_warnings.clone();
             },
             _ => (),
         }
+
+        timing.breaks = start.elapsed();
+
+        if let Some(line) = self.tab_line {
+            return Err(format!("Tab character in text on line {}", line));
+        }
         
-        self.root
+        Ok((self.root, timing))
+    }
+
+    /// Adds text to the element on top of the stack
+    fn on_text(&mut self, event: &BytesText) {
+        match self.stack.pop() {
+            Some(State::Act(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Act(elem));
+            },
+            Some(State::Contact(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Contact(elem));
+            },
+            Some(State::Cue(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Cue(elem));
+            },
+            Some(State::D(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::D(elem));
+            },
+            Some(State::Dir(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Dir(elem));
+            },
+            Some(State::B(mut elem)) => {
+                let dpy = self.inline_flags(DisplayFlags::BOLD);
+                elem.tokens = self.parse_inline_text(event, elem.tokens,
+                                                     dpy);
+                self.stack.push(State::B(elem));
+            },
+            Some(State::Em(mut elem)) => {
+                let dpy = self.inline_flags(DisplayFlags::EM);
+                elem.tokens = self.parse_inline_text(event, elem.tokens,
+                                                     dpy);
+                self.stack.push(State::Em(elem));
+            },
+            Some(State::End(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::End(elem));
+            },
+            Some(State::Episode(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Episode(elem));
+            },
+            Some(State::FullName(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::FullName(elem));
+            },
+            Some(State::Note(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Note(elem));
+            },
+            Some(State::Open(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Open(elem));
+            },
+            Some(State::P(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::P(elem));
+            },
+            Some(State::ProductionNumber(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::ProductionNumber(elem));
+            },
+            Some(State::Series(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Series(elem));
+            },
+            Some(State::Shot(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Shot(elem));
+            },
+            Some(State::Slug(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Slug(elem));
+            },
+            Some(State::Source(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Source(elem));
+            },
+            Some(State::Title(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Title(elem));
+            },
+            Some(State::Trans(mut elem)) => {
+                elem.tokens = self.parse_text(event, elem.tokens,
+                                              Default::default());
+                self.stack.push(State::Trans(elem));
+            },
+            Some(State::U(mut elem)) => {
+                let dpy = self.inline_flags(DisplayFlags::UL);
+                elem.tokens = self.parse_inline_text(event, elem.tokens,
+                                                     dpy);
+                self.stack.push(State::U(elem));
+            },
+            Some(state) => self.stack.push(state),
+            None => (),
+        }
+    }
+
+    /// Checks a scene number against the scenes before it, warning
+    /// about one that repeats or goes back below the highest so far,
+    /// and returns the number to use
+    fn check_scene_number(&mut self, number: i32, addition: Option<char>)
+                          -> (i32, Option<char>)
+    {
+        let label = |n: i32, c: Option<char>| match c {
+            Some(c) => format!("{}{}", n, c),
+            None => format!("{}", n),
+        };
+
+        let latest = self.scene_labels.iter().max().copied();
+
+        let problem = match latest {
+            _ if self.scene_labels.contains(&(number, addition)) => {
+                "repeats an earlier scene number".to_string()
+            },
+            Some(last) if (number, addition) < last => {
+                format!("comes after scene {}", label(last.0, last.1))
+            },
+            _ => {
+                self.scene_labels.insert((number, addition));
+                return (number, addition);
+            },
+        };
+
+        let line = self.current_line();
+        let mut warning = format!("scene {} on line {} {}",
+                                  label(number, addition), line, problem);
+        let mut result = (number, addition);
+
+        if let (true, Some((n, c))) = (self.resolve_scenes, latest) {
+            let mut next = c.map_or(Some('A'), |c| match c {
+                'A'..='Y' => Some((c as u8 + 1) as char),
+                _ => None,
+            });
+
+            while next.is_some_and(|c| self.scene_labels.contains(&(n, Some(c)))) {
+                next = next.filter(|&c| c < 'Z').map(|c| (c as u8 + 1) as char);
+            }
+
+            if next.is_some() {
+                result = (n, next);
+                self.next_scene_no = n + 1;
+                warning.push_str(&format!("; numbered {}", label(n, next)));
+            }
+        }
+
+        self.scene_warnings.push(warning);
+        self.scene_labels.insert(result);
+        result
     }
 
-    fn parse_text(&mut self, event: &BytesText, tokens: TokenList,
+    /// Replaces each <tt>{NAME}</tt> reference with the value given
+    /// by a <tt>&lt;define&gt;</tt> element
+    ///
+    /// References to names that have not been defined are left as
+    /// they are and recorded.  Braces around anything other than a
+    /// name are not references.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::ElementType;
+    /// # use batyr::document::reader::Reader;
+    /// let xml = "<screenplay><head>\
+    ///            <define name=\"BAR\" value=\"JOE&apos;S TAVERN\"/>\
+    ///            </head><body>\
+    ///            <slug>INT. {BAR} - NIGHT</slug>\
+    ///            <p>{HERO} enters {BAR}. {not a name}</p>\
+    ///            </body></screenplay>";
+    ///
+    /// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+    ///     panic!();
+    /// };
+    /// let Some(ElementType::Body(body)) = root.children.last() else {
+    ///     panic!();
+    /// };
+    /// let Some(ElementType::Slug(slug)) = body.children.first() else {
+    ///     panic!();
+    /// };
+    ///
+    /// assert_eq!(slug.text(), "INT. JOE'S TAVERN - NIGHT");
+    /// assert_eq!(root.unresolved_variables(), ["HERO"]);
+    /// ```
+    fn expand_variables(&mut self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(i) = rest.find('{') {
+            result.push_str(&rest[..i]);
+            rest = &rest[i..];
+
+            let name = rest[1..].find('}').map(|j| &rest[1..j + 1]);
+
+            let name = match name {
+                Some(name) if is_variable_name(name) => name,
+                _ => {
+                    result.push('{');
+                    rest = &rest[1..];
+                    continue;
+                },
+            };
+
+            match self.variables.get(name) {
+                Some(value) => result.push_str(value),
+                None => {
+                    result.push_str(&rest[..name.len() + 2]);
+
+                    if !self.unresolved.iter().any(|x| x == name) {
+                        self.unresolved.push(name.to_string());
+                    }
+                },
+            }
+
+            rest = &rest[name.len() + 2..];
+        }
+
+        result.push_str(rest);
+        result
+    }
+
+    /// Sets up a text parser with the document's settings
+    fn new_parser(&self, text: &str, tokens: TokenList, dpy: DisplayFlags)
+        -> Parser
+    {
+        let mut parser = Parser::new(text, tokens, dpy)
+            .tabs(self.tabs)
+            .sentence_spacing(self.sentence_spacing)
+            .ellipsis_spacing(self.ellipsis_spacing);
+
+        if let Some(abbreviations) = &self.abbreviations {
+            parser = parser.abbreviations(abbreviations.clone());
+        }
+
+        parser
+    }
+
+    /// Display flags for the text of an inline element, given its own
+    /// flag and those of the inline elements around it
+    ///
+    /// Emphasis within emphasis adds capitals.
+    fn inline_flags(&self, own: DisplayFlags) -> DisplayFlags {
+        let mut dpy = own;
+
+        for state in self.stack.iter() {
+            match state {
+                State::B(_) => dpy.insert(DisplayFlags::BOLD),
+                State::Em(_) if dpy.intersects(DisplayFlags::EM) => {
+                    dpy.insert(DisplayFlags::CAPS);
+                },
+                State::Em(_) => dpy.insert(DisplayFlags::EM),
+                State::U(_) => dpy.insert(DisplayFlags::UL),
+                _ => (),
+            }
+        }
+
+        dpy
+    }
+
+    /// Parses the text of an inline element, set in capitals if the
+    /// display flags call for them
+    fn parse_inline_text(&mut self, event: &BytesText, tokens: Arc<TokenList>,
+                         dpy: DisplayFlags)
+        -> Arc<TokenList>
+    {
+        let n = tokens.len();
+        let mut tokens = self.parse_text(event, tokens, dpy);
+
+        if dpy.intersects(DisplayFlags::CAPS) {
+            for token in Arc::make_mut(&mut tokens)[n..].iter_mut() {
+                token.make_uppercase(self.casing);
+            }
+        }
+
+        tokens
+    }
+
+    fn parse_text(&mut self, event: &BytesText, tokens: Arc<TokenList>,
                   dpy: DisplayFlags)
-        -> TokenList
+        -> Arc<TokenList>
     {
+        if self.tabs == TabPolicy::Error && self.tab_line.is_none() {
+            if let Some(k) = event.iter().position(|&x| x == b'\t') {
+                let start = self.xml_reader.buffer_position() - event.len();
+                let lines = self.source.as_bytes()[..start + k].iter()
+                    .filter(|&&x| x == b'\n')
+                    .count();
+
+                self.tab_line = Some(lines + 1);
+            }
+        }
+
         let text = event.unescape().unwrap();
-        let mut parser = Parser::new(&text, tokens, dpy);
+
+        // The parser strips control characters; note where they were.
+        if text.chars().any(is_stripped_control) {
+            let start = self.xml_reader.buffer_position() - event.len();
+            let mut line = self.source.as_bytes()[..start].iter()
+                .filter(|&&x| x == b'\n')
+                .count() + 1;
+
+            for ch in text.chars() {
+                if ch == '\n' {
+                    line += 1;
+                } else if is_stripped_control(ch)
+                    && !self.control_chars.contains(&(line, ch))
+                {
+                    self.control_chars.push((line, ch));
+                }
+            }
+        }
+
+        let text = self.expand_variables(&text);
+
+        // The tokens are only shared once the element is done, so
+        // this takes them without a copy.
+        let mut parser = self.new_parser(&text, Arc::unwrap_or_clone(tokens), dpy);
         parser = parser.run();
-        parser.get_tokens()
+        Arc::new(parser.get_tokens())
+    }
+}
+
+/// Finds the break points of indented text, or takes them from the
+/// cache
+fn break_points(cache: Option<&BreakCache>, tokens: &[TokenType],
+                w: usize, indent: usize, hang: usize) -> BreakPointList
+{
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return breaking::find_indented_break_points(tokens, w, indent, hang),
+    };
+
+    let key = BreakCache::key(0, tokens, &[w, indent, hang]);
+
+    match cache.get(key) {
+        Some(Breaks::Points(b)) => b,
+        _ => {
+            let b = breaking::find_indented_break_points(tokens, w, indent, hang);
+            cache.insert(key, Breaks::Points(b.clone()));
+            b
+        },
+    }
+}
+
+/// Counts the lines of unbroken text, or takes the count from the
+/// cache
+fn line_count(cache: Option<&BreakCache>, tokens: &[TokenType], w: usize) -> usize {
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return text::count_lines(tokens, w),
+    };
+
+    let key = BreakCache::key(1, tokens, &[w]);
+
+    match cache.get(key) {
+        Some(Breaks::Lines(n)) => n,
+        _ => {
+            let n = text::count_lines(tokens, w);
+            cache.insert(key, Breaks::Lines(n));
+            n
+        },
+    }
+}
+
+/// Fills in the break information of every element in the tree at
+/// once, sharing the work among threads
+#[cfg(feature = "parallel")]
+fn find_breaks_in_parallel(root: &mut ElementType, cache: Option<&BreakCache>) {
+    fn collect<'a>(elem: &'a mut ElementType, leaves: &mut Vec<&'a mut ElementType>) {
+        if elem.has_children() {
+            for child in elem.children_mut().unwrap().iter_mut() {
+                collect(child, leaves);
+            }
+        } else {
+            leaves.push(elem);
+        }
+    }
+
+    let mut leaves: Vec<&mut ElementType> = Vec::new();
+    collect(root, &mut leaves);
+    leaves.into_par_iter().for_each(|elem| State::find_breaks(elem, cache));
+}
+
+/// Break information kept from one reading of a document to the next
+///
+/// Breaking dialogue and stage direction into lines is most of the
+/// work of reading a long screenplay, and an edit usually touches only
+/// a few elements.  A reader given a cache looks up each element by
+/// its tokens and column geometry, and breaks only the text it has not
+/// seen before.  Entries that a reading does not use are dropped when
+/// it ends, so the cache holds no more than one document's worth.
+///
+/// The tokens themselves are still parsed on every reading, since
+/// they depend on variables, abbreviations and the surrounding text.
+#[derive(Debug, Default)]
+pub struct BreakCache {
+    entries: Mutex<CacheEntries>,
+}
+
+/// Digest of an element's text and geometry
+type CacheKey = [u8; 32];
+
+#[derive(Debug, Default)]
+struct CacheEntries {
+    previous: HashMap<CacheKey, Breaks>,
+    current: HashMap<CacheKey, Breaks>,
+    counted: usize,
+    hits: usize,
+}
+
+/// Cached result for one element's text
+#[derive(Debug, Clone)]
+enum Breaks {
+    Points(BreakPointList),
+    Lines(usize),
+}
+
+impl BreakCache {
+    /// Constructs an empty cache
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Number of elements whose break information came from the
+    /// cache during the last complete reading
+    pub fn hits(&self) -> usize {
+        self.entries.lock().unwrap().hits
+    }
+
+    /// Number of elements held after the last complete reading
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().previous.len()
+    }
+
+    /// Tells whether the last complete reading left nothing to reuse
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Digests an element's text together with the geometry it is
+    /// broken to
+    ///
+    /// A SHA-256 digest, unlike the standard hasher, cannot in practice
+    /// give two texts the same key, and does not change from one Rust
+    /// release to the next.
+    fn key(kind: u8, tokens: &[TokenType], geometry: &[usize]) -> CacheKey {
+        let mut hasher = Sha256::new();
+        hasher.update([kind]);
+
+        for n in geometry.iter() {
+            hasher.update((*n as u64).to_le_bytes());
+        }
+
+        for token in tokens.iter() {
+            let text = token.text();
+
+            hasher.update(token.kind().as_bytes());
+            hasher.update((text.len() as u64).to_le_bytes());
+            hasher.update(text.as_bytes());
+            hasher.update(token.display_flags().bits().to_le_bytes());
+            hasher.update(token.format_flags().bits().to_le_bytes());
+        }
+
+        hasher.finalize().into()
+    }
+
+    fn get(&self, key: CacheKey) -> Option<Breaks> {
+        let mut entries = self.entries.lock().unwrap();
+
+        if let Some(breaks) = entries.current.get(&key).cloned() {
+            entries.counted += 1;
+            return Some(breaks);
+        }
+
+        let breaks = entries.previous.remove(&key)?;
+        entries.current.insert(key, breaks.clone());
+        entries.counted += 1;
+        Some(breaks)
+    }
+
+    fn insert(&self, key: CacheKey, breaks: Breaks) {
+        self.entries.lock().unwrap().current.insert(key, breaks);
+    }
+
+    /// Keeps what the reading just ended used, and forgets the rest
+    fn finish(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.previous = mem::take(&mut entries.current);
+        entries.hits = mem::take(&mut entries.counted);
+    }
+}
+
+/// Generates cast and set list pages at the start of the front matter
+///
+/// Characters are listed in order of first appearance, without cue
+/// extensions such as (O.S.).  Sets are the interior and exterior
+/// scene headings without the time of day.
+fn add_cast_and_set_lists(root: &mut ContainerElement<Screenplay>,
+                          layout: &Layout)
+{
+    let mut cast: Vec<String> = Vec::new();
+    let mut sets: Vec<String> = Vec::new();
+
+    if let Some(body) = root.body() {
+        for child in body.children.iter() {
+            match child {
+                ElementType::Cue(elem) => {
+                    let name = elem.name();
+
+                    if !name.is_empty() && !cast.contains(&name) {
+                        cast.push(name);
+                    }
+                },
+                ElementType::Slug(elem) => {
+                    let text = elem.text();
+
+                    if !["INT", "EXT", "I/E"].iter().any(|s| text.starts_with(s)) {
+                        continue;
+                    }
+
+                    let set = match text.rfind(" - ") {
+                        Some(i) => text[..i].to_string(),
+                        None => text,
+                    };
+
+                    if !sets.contains(&set) {
+                        sets.push(set);
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+
+    let list = |heading: &str, items: &[String]| -> ElementList {
+        let mut elements: ElementList = Vec::new();
+
+        let mut title = TextElement::new(Title {
+            left_margin: layout.centered_left_margin(),
+            right_margin: layout.centered_right_margin(),
+            padding_before: 0,
+            padding_after: 1,
+        });
+        title.tokens = Arc::new(Parser::new(heading, Vec::new(), Default::default())
+            .run().get_tokens());
+        elements.push(State::Title(title).on_exit());
+
+        for item in items {
+            let mut p = TextElement::new(P {
+                indent: 0,
+                left_margin: layout.p_begin,
+                right_margin: layout.p_end,
+                page_break_before: false,
+                keep_with_next: false,
+                train: Vec::new(),
+                padding_before: 0,
+                padding_after: 0,
+            });
+            p.tokens = Arc::new(Parser::new(item, Vec::new(), Default::default())
+                .run().get_tokens());
+
+            let mut elem = State::P(p).on_exit();
+            State::find_breaks(&mut elem, None);
+            elements.push(elem);
+        }
+
+        elements
+    };
+
+    let page_break = || {
+        State::PageBreak(EmptyElement::new(PageBreak {})).on_exit()
+    };
+
+    let mut generated = list("CAST LIST", &cast);
+    generated.push(page_break());
+    generated.extend(list("SET LIST", &sets));
+
+    let front = root.children.iter_mut().find_map(|child| match child {
+        ElementType::FrontMatter(elem) => Some(elem),
+        _ => None,
+    });
+
+    match front {
+        Some(front) => {
+            generated.push(page_break());
+            generated.append(&mut front.children);
+            front.children = generated;
+        },
+        None => {
+            let mut front = ContainerElement::new(FrontMatter {});
+            front.children = generated;
+
+            let i = root.children.iter()
+                .position(|child| matches!(child, ElementType::Body(_)))
+                .unwrap_or(root.children.len());
+            root.children.insert(i, ElementType::FrontMatter(front));
+        },
+    }
+}
+
+/// An open <tt>&lt;ol&gt;</tt> list
+struct ListGroup {
+    /// Number of the next item
+    next: usize,
+    /// Whether the list follows text in the same paragraph
+    follows_text: bool,
+    /// Indent of the paragraph the list interrupts, if any
+    paragraph: Option<i32>,
+}
+
+/// An open <tt>&lt;alt&gt;</tt> group
+struct AltGroup {
+    /// Stack depth of the variants' parent
+    depth: usize,
+    /// Character speaking the variants
+    cue: String,
+    /// Variant chosen in the source, counting from 1
+    choose: usize,
+    /// Variants read so far
+    variants: Vec<ElementType>,
+}
+
+/// Checks for a variable name: a letter or underscore, followed by
+/// letters, digits, and underscores
+fn is_variable_name(s: &str) -> bool {
+    let mut chars = s.chars();
+
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_alphanumeric() || c == '_')
+        },
+        _ => false,
     }
 }
 
@@ -820,7 +2447,7 @@ fn build_trains(body: &mut ContainerElement<Body>) {
     let n = body.children.len();
     let mut trains: Vec<(usize, Vec<BreakType>)> = Vec::new();
     
-    for i in 0 .. n - 1 {
+    for i in 0 .. n.saturating_sub(1) {
         match &body.children[i] {
             ElementType::Cue(_) => {
                 let j = trains.len();
@@ -852,157 +2479,134 @@ fn build_trains(body: &mut ContainerElement<Body>) {
         }
     }
 
-    for i in 0 .. n - 1 {
-        match &body.children[i] {
-            ElementType::Slug(_) => {
-                let j = trains.len();
-                
-                trains.push((i, Vec::new()));
+    // Headings, and paragraphs kept with the next element, carry the
+    // break information of what follows them.
+    for i in 0 .. n.saturating_sub(1) {
+        let keeps = match &body.children[i] {
+            ElementType::Shot(_) | ElementType::Slug(_) => true,
+            ElementType::P(elem) => elem.attributes.keep_with_next,
+            _ => false,
+        };
 
-                for k in i + 1 .. n {
-                    match &mut body.children[k] {
-                        ElementType::Cue(elem) => {
-                            trains[j].1.push(elem.break_info.clone());
-                            trains[j].1.extend_from_slice(&elem.attributes.train[..]);
-                            break;
-                        },
-                        ElementType::P(elem) => {
-                            trains[j].1.push(elem.break_info.clone());
-                            break;
-                        },
-                        _ => break,
-                    }
-                }
-            },
-            _ => (),
+        if keeps {
+            let j = trains.len();
+
+            trains.push((i, Vec::new()));
+
+            match body.children.get_mut(i + 1) {
+                Some(ElementType::Cue(elem)) => {
+                    trains[j].1.push(elem.break_info.clone());
+                    trains[j].1.extend_from_slice(&elem.attributes.train[..]);
+                },
+                Some(ElementType::P(elem)) => {
+                    trains[j].1.push(elem.break_info.clone());
+                },
+                _ => (),
+            }
         }
     }
 
     for (i, train) in trains {
         match &mut body.children[i] {
+            ElementType::Shot(elem) => {
+                elem.attributes.train = train;
+            },
             ElementType::Slug(elem) => {
                 elem.attributes.train = train;
             },
+            ElementType::P(elem) => {
+                elem.attributes.train = train;
+            },
             _ => (),
         }
     }
 }
 
+/// Returns the at_scene_end flag of an element that can end a scene
+fn scene_end_flag(elem: &mut ElementType) -> Option<&mut bool> {
+    match elem {
+        ElementType::Act     (elem) => Some(&mut elem.at_scene_end),
+        ElementType::Cue     (elem) => Some(&mut elem.at_scene_end),
+        ElementType::D       (elem) => Some(&mut elem.at_scene_end),
+        ElementType::Dir     (elem) => Some(&mut elem.at_scene_end),
+        ElementType::Em      (elem) => Some(&mut elem.at_scene_end),
+        ElementType::End     (elem) => Some(&mut elem.at_scene_end),
+        ElementType::FullName(elem) => Some(&mut elem.at_scene_end),
+        ElementType::Open    (elem) => Some(&mut elem.at_scene_end),
+        ElementType::P       (elem) => Some(&mut elem.at_scene_end),
+        ElementType::Series  (elem) => Some(&mut elem.at_scene_end),
+        ElementType::Shot    (elem) => Some(&mut elem.at_scene_end),
+        ElementType::Slug    (elem) => Some(&mut elem.at_scene_end),
+        ElementType::Title   (elem) => Some(&mut elem.at_scene_end),
+        ElementType::Trans   (elem) => Some(&mut elem.at_scene_end),
+        _ => None,
+    }
+}
+
 fn mark_scene_endings(body: &mut ContainerElement<Body>) {
     let n = body.children.len();
-    
-    for i in 1..n {
-        match &body.children[i] {
-            ElementType::Slug(_) => {
-                for j in i - 1 ..= 0 {
-                    match &mut body.children[j] {
-                        ElementType::Act(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::Cue(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::D(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::Dir(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::Em(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::End(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::FullName(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::Open(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::P(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::Series(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::Slug(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::Title(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        ElementType::Trans(elem) => {
-                            elem.at_scene_end = true;
-                            break;
-                        },
-                        _ => (),
-                    }
+
+    // Scan forward, keeping track of the last element that can end a
+    // scene, and flag it when the next Slug comes along.  Elements
+    // such as Br or PageBreak in between are passed over.
+    let mut last = None;
+
+    for i in 0..n {
+        if let ElementType::Slug(_) = body.children[i] {
+            if let Some(j) = last {
+                if let Some(flag) = scene_end_flag(&mut body.children[j]) {
+                    *flag = true;
                 }
-            },
-            _ => (),
+            }
+        }
+
+        if scene_end_flag(&mut body.children[i]).is_some() {
+            last = Some(i);
         }
     }
 
     // If a D or Dir is at_scene_end, propagate the at_scene_end flag
-    // to the preceding Cue.  If a P element at the end of a scene is
-    // immediately preceded by a Slug, propagate the at_scene_end flag
-    // to the Slug.
-    for i in 1..n {
-        match &body.children[i] {
-            ElementType::D(d_elem) => {
-                if d_elem.at_scene_end {
-                    for j in i - 1 ..= 0 {
-                        match &mut body.children[j] {
-                            ElementType::Cue(cue_elem) => {
-                                cue_elem.at_scene_end = true;
-                                break;
-                            },
-                            ElementType::D(_) => (),
-                            ElementType::Dir(_) => (),
-                            _ => break,
-                        }
-                    }
-                }
+    // to the Cue that opens its dialogue.
+    let mut cue = None;
+
+    for i in 0..n {
+        let at_scene_end = match &body.children[i] {
+            ElementType::Cue(_) => {
+                cue = Some(i);
+                false
             },
-            ElementType::Dir(dir_elem) => {
-                if dir_elem.at_scene_end {
-                    for j in i - 1 ..= 0 {
-                        match &mut body.children[j] {
-                            ElementType::Cue(cue_elem) => {
-                                cue_elem.at_scene_end = true;
-                                break;
-                            },
-                            ElementType::D(_) => (),
-                            ElementType::Dir(_) => (),
-                            _ => break,
-                        }
-                    }
-                }
+            ElementType::D(elem) => elem.at_scene_end,
+            ElementType::Dir(elem) => elem.at_scene_end,
+            _ => {
+                cue = None;
+                false
             },
-            ElementType::P(p_elem) => {
-                if p_elem.at_scene_end {
-                    match &mut body.children[i - 1] {
-                        ElementType::Slug(slug_elem) => {
-                            slug_elem.at_scene_end = true;
-                        },
-                        _ => (),
-                    }
+        };
+
+        if at_scene_end {
+            if let Some(j) = cue {
+                if let ElementType::Cue(cue_elem) = &mut body.children[j] {
+                    cue_elem.at_scene_end = true;
                 }
-            },
-            _ => (),
+            }
+        }
+    }
+
+    // If a P element at the end of a scene is immediately preceded by
+    // a Slug or Shot, propagate the at_scene_end flag to it.
+    for i in 1..n {
+        if let ElementType::P(p_elem) = &body.children[i] {
+            if p_elem.at_scene_end {
+                match &mut body.children[i - 1] {
+                    ElementType::Shot(shot_elem) => {
+                        shot_elem.at_scene_end = true;
+                    },
+                    ElementType::Slug(slug_elem) => {
+                        slug_elem.at_scene_end = true;
+                    },
+                    _ => (),
+                }
+            }
         }
     }
 }
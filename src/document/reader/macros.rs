@@ -25,7 +25,7 @@ macro_rules! bool_attr {
             let mut value: Option<bool> = None;
 
             for attr in $event.attributes() {
-	        if let Some(attr) = attr.ok() {
+	        if let Ok(attr) = attr {
                     match attr.key {
 		        QName($name) => {
                             if let Ok(s) = str::from_utf8(&attr.value) {
@@ -56,7 +56,7 @@ macro_rules! char_attr {
             let mut value: Option<char> = None;
 
             for attr in $event.attributes() {
-	        if let Some(attr) = attr.ok() {
+	        if let Ok(attr) = attr {
                     match attr.key {
 		        QName($name) => {
                             if let Ok(s) = str::from_utf8(&attr.value) {
@@ -80,7 +80,7 @@ macro_rules! enum_attr {
             let mut value: Option<$type> = None;
 
             for attr in $event.attributes() {
-	        if let Some(attr) = attr.ok() {
+	        if let Ok(attr) = attr {
                     match attr.key {
 		        QName($name) => {
                             if let Ok(s) = str::from_utf8(&attr.value) {
@@ -103,7 +103,7 @@ macro_rules! numeric_attr {
             let mut value: Option<$type> = None;
             
             for attr in $event.attributes() {
-	        if let Some(attr) = attr.ok() {
+	        if let Ok(attr) = attr {
                     match attr.key {
 		        QName($name) => {
                             if let Ok(s) = str::from_utf8(&attr.value) {
@@ -129,10 +129,10 @@ macro_rules! string_attr {
             let mut value: Option<String> = None;
 
             for attr in $event.attributes() {
-	        if let Some(attr) = attr.ok() {
+	        if let Ok(attr) = attr {
                     match attr.key {
 		        QName($name) => {
-                            if let Ok(s) = str::from_utf8(&attr.value) {
+                            if let Ok(s) = attr.unescape_value() {
                                 value = Some(s.to_string());
                             }
 		        },
@@ -0,0 +1,806 @@
+// Batyr Production Reports
+// Copyright (C) 2023 Gene Yu
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Production reports derived from the element hierarchy
+//!
+//! Reports are drawn from the document before it is formatted, so
+//! they do not depend on pagination.
+
+use crate::document::*;
+use crate::document::formatter::Formatter;
+use crate::text::tokens::TokenType;
+
+/// How a character figures in a scene
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Appearance {
+    /// Not in the scene
+    None,
+    /// Named in capitals in the stage directions, but has no lines
+    Mentioned,
+    /// Has a cue in the scene
+    Speaks,
+}
+
+impl Appearance {
+    /// Returns the mark for the appearance matrix
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Appearance::None => "",
+            Appearance::Mentioned => "M",
+            Appearance::Speaks => "S",
+        }
+    }
+}
+
+/// Matrix of characters by scenes, a rough day-out-of-days
+///
+/// Characters are the names in the cues, in order of first
+/// appearance.  A character is marked as speaking in a scene with one
+/// of their cues, and as mentioned in a scene whose stage directions
+/// name them in capitals.  Anything before the first scene heading is
+/// not counted.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::ElementType;
+/// # use batyr::document::reader::Reader;
+/// # use batyr::document::report::{Appearance, Appearances};
+/// let xml = "<screenplay><head/><body>\
+///            <slug>INT. KITCHEN - DAY</slug>\
+///            <p>EMMA waits for THOMAS.</p>\
+///            <cue>EMMA</cue><d>Late again.</d>\
+///            <slug>EXT. STREET - DAY</slug>\
+///            <cue>THOMAS (V.O.)</cue><d>Coming!</d>\
+///            </body></screenplay>";
+///
+/// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+///     panic!();
+/// };
+/// let report = Appearances::from(&root);
+///
+/// assert_eq!(report.characters, vec!["EMMA", "THOMAS"]);
+/// assert_eq!(report.matrix[1], vec![Appearance::Mentioned, Appearance::Speaks]);
+/// assert_eq!(report.to_csv(), "Character,1,2,Scenes\n\
+///                              EMMA,S,,1\n\
+///                              THOMAS,M,S,2\n");
+/// ```
+#[derive(Debug)]
+pub struct Appearances {
+    /// Character names, one per row
+    pub characters: Vec<String>,
+    /// Scene numbers and headings, one per column
+    pub scenes: Vec<(String, String)>,
+    /// Appearance of each character (row) in each scene (column)
+    pub matrix: Vec<Vec<Appearance>>,
+}
+
+impl From<&ContainerElement<Screenplay>> for Appearances {
+    fn from(root: &ContainerElement<Screenplay>) -> Self {
+        let mut report = Appearances {
+            characters: Vec::new(),
+            scenes: Vec::new(),
+            matrix: Vec::new(),
+        };
+
+        let body = root.children.iter().find_map(|child| match child {
+            ElementType::Body(elem) => Some(elem),
+            _ => None,
+        });
+
+        let body = match body {
+            Some(body) => body,
+            None => return report,
+        };
+
+        // The whole cast must be known before looking for mentions.
+        for child in body.children.iter() {
+            match child {
+                ElementType::Cue(elem) => {
+                    let name = elem.name();
+
+                    if !name.is_empty() && !report.characters.contains(&name) {
+                        report.characters.push(name);
+                    }
+                },
+                ElementType::Slug(elem) if !elem.attributes.continued => {
+                    report.scenes.push((elem.label(), elem.text()));
+                },
+                _ => (),
+            }
+        }
+
+        report.matrix = vec![
+            vec![Appearance::None; report.scenes.len()];
+            report.characters.len()
+        ];
+
+        let mut scene: Option<usize> = None;
+
+        for child in body.children.iter() {
+            match child {
+                ElementType::Slug(elem) if !elem.attributes.continued => {
+                    scene = Some(scene.map_or(0, |j| j + 1));
+                },
+                ElementType::Cue(elem) => {
+                    let name = elem.name();
+                    let i = report.characters.iter().position(|c| *c == name);
+
+                    if let (Some(i), Some(j)) = (i, scene) {
+                        report.matrix[i][j] = Appearance::Speaks;
+                    }
+                },
+                ElementType::P(elem) => {
+                    let j = match scene {
+                        Some(j) => j,
+                        None => continue,
+                    };
+
+                    let text = elem.text();
+
+                    for (i, name) in report.characters.iter().enumerate() {
+                        if report.matrix[i][j] == Appearance::None
+                            && mentions(&text, name)
+                        {
+                            report.matrix[i][j] = Appearance::Mentioned;
+                        }
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        report
+    }
+}
+
+impl Appearances {
+    /// Writes the matrix as comma-separated values, with a header row
+    /// of scene numbers and a final column counting the scenes each
+    /// character appears in
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+
+        let mut header = vec!["Character".to_string()];
+        header.extend(self.scenes.iter().map(|(label, _)| label.clone()));
+        header.push("Scenes".to_string());
+        csv.push_str(&csv_row(&header));
+
+        for (name, row) in self.characters.iter().zip(self.matrix.iter()) {
+            let mut fields = vec![name.clone()];
+            fields.extend(row.iter().map(|a| a.as_str().to_string()));
+
+            let n = row.iter().filter(|a| **a != Appearance::None).count();
+            fields.push(n.to_string());
+
+            csv.push_str(&csv_row(&fields));
+        }
+
+        csv
+    }
+}
+
+/// Lists the variants of every <tt>&lt;alt&gt;</tt> group, marking
+/// the one that is typeset
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::ElementType;
+/// # use batyr::document::reader::Reader;
+/// # use batyr::document::report::list_alternates;
+/// let xml = "<screenplay><head/><body>\
+///            <cue>EMMA</cue>\
+///            <alt choose=\"2\"><d>Late again.</d><d>You're late.</d></alt>\
+///            </body></screenplay>";
+///
+/// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+///     panic!();
+/// };
+///
+/// assert_eq!(list_alternates(&root), "EMMA\n  \
+///                                     1. Late again.\n\
+///                                     * 2. You're late.\n");
+/// ```
+pub fn list_alternates(root: &ContainerElement<Screenplay>) -> String {
+    let mut groups = Vec::new();
+
+    for alt in root.alternates() {
+        let mut group = format!("{}\n", alt.cue);
+
+        for (i, variant) in alt.variants.iter().enumerate() {
+            let mark = if i + 1 == alt.chosen { "*" } else { " " };
+            group.push_str(&format!("{} {}. {}\n", mark, i + 1, variant));
+        }
+
+        groups.push(group);
+    }
+
+    groups.join("\n")
+}
+
+/// Longest first line shown under a scene in the outline
+const OUTLINE_LINE_LENGTH: usize = 60;
+
+/// Nested outline of acts, scenes, and the first line of each scene
+///
+/// Page numbers come from a formatter that has been run on the same
+/// document; until then they are zero.  Scenes before the first act
+/// are collected under an act without a heading.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::ElementType;
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::reader::Reader;
+/// # use batyr::document::report::Outline;
+/// let xml = "<screenplay><head/><body>\
+///            <act>ACT ONE</act>\
+///            <slug>INT. KITCHEN - DAY</slug>\
+///            <p>EMMA waits for THOMAS.</p>\
+///            <slug>EXT. STREET - DAY</slug>\
+///            <cue>THOMAS</cue><d>Coming!</d>\
+///            </body></screenplay>";
+///
+/// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+///     panic!();
+/// };
+/// let mut outline = Outline::from(&root);
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(ElementType::Screenplay(root));
+/// outline.set_pages(&formatter);
+///
+/// assert_eq!(outline.to_string(), "ACT ONE, page 1\n  \
+///                                  1. INT. KITCHEN - DAY, page 1\n     \
+///                                  EMMA waits for THOMAS.\n  \
+///                                  2. EXT. STREET - DAY, page 1\n     \
+///                                  THOMAS: Coming!\n");
+/// ```
+#[derive(Debug)]
+pub struct Outline {
+    /// Acts in order
+    pub acts: Vec<OutlineAct>,
+}
+
+/// Act entry in an [`Outline`]
+#[derive(Debug)]
+pub struct OutlineAct {
+    /// Act heading, if the act has one
+    pub heading: Option<String>,
+    /// Page where the act begins
+    pub page: i32,
+    /// Scenes in the act
+    pub scenes: Vec<OutlineScene>,
+}
+
+/// Scene entry in an [`Outline`]
+#[derive(Debug)]
+pub struct OutlineScene {
+    /// Scene number, including any addition
+    pub number: String,
+    /// Text of the slug line
+    pub heading: String,
+    /// Opening of the first stage direction or speech in the scene
+    pub first_line: String,
+    /// Page where the scene begins
+    pub page: i32,
+}
+
+impl From<&ContainerElement<Screenplay>> for Outline {
+    fn from(root: &ContainerElement<Screenplay>) -> Self {
+        let mut outline = Outline { acts: Vec::new() };
+
+        let body = root.children.iter().find_map(|child| match child {
+            ElementType::Body(elem) => Some(elem),
+            _ => None,
+        });
+
+        let body = match body {
+            Some(body) => body,
+            None => return outline,
+        };
+
+        let mut cue: Option<String> = None;
+
+        for child in body.children.iter() {
+            match child {
+                ElementType::Act(elem) => {
+                    outline.acts.push(OutlineAct {
+                        heading: Some(elem.text()),
+                        page: 0,
+                        scenes: Vec::new(),
+                    });
+                },
+                ElementType::Slug(elem) if !elem.attributes.continued => {
+                    if outline.acts.is_empty() {
+                        outline.acts.push(OutlineAct {
+                            heading: None,
+                            page: 0,
+                            scenes: Vec::new(),
+                        });
+                    }
+
+                    let act = outline.acts.last_mut().unwrap();
+
+                    act.scenes.push(OutlineScene {
+                        number: elem.label(),
+                        heading: elem.text(),
+                        first_line: String::new(),
+                        page: 0,
+                    });
+                },
+                ElementType::Cue(elem) => {
+                    cue = Some(elem.name());
+                },
+                ElementType::P(elem) => {
+                    outline.set_first_line(elem.text());
+                },
+                ElementType::D(elem) => {
+                    let text = match &cue {
+                        Some(name) => format!("{}: {}", name, elem.text()),
+                        None => elem.text(),
+                    };
+
+                    outline.set_first_line(text);
+                },
+                _ => (),
+            }
+        }
+
+        outline
+    }
+}
+
+impl Outline {
+    /// Copies page numbers from a formatter that has been run on the
+    /// same document
+    pub fn set_pages(&mut self, formatter: &Formatter) {
+        let headed = self.acts.iter_mut().filter(|act| act.heading.is_some());
+
+        for (act, info) in headed.zip(formatter.acts.iter()) {
+            act.page = info.page;
+        }
+
+        let scenes = self.acts.iter_mut().flat_map(|act| act.scenes.iter_mut());
+
+        for (scene, info) in scenes.zip(formatter.scenes.iter()) {
+            scene.page = info.first_page;
+        }
+
+        for act in self.acts.iter_mut().filter(|act| act.heading.is_none()) {
+            if let Some(scene) = act.scenes.first() {
+                act.page = scene.page;
+            }
+        }
+    }
+
+    /// Fills in the first line of the latest scene, if it does not
+    /// have one yet
+    fn set_first_line(&mut self, text: String) {
+        let scene = self.acts.last_mut()
+            .and_then(|act| act.scenes.last_mut());
+
+        if let Some(scene) = scene {
+            if scene.first_line.is_empty() {
+                scene.first_line = shorten(&text, OUTLINE_LINE_LENGTH);
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Outline {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for act in self.acts.iter() {
+            let indent = match &act.heading {
+                Some(heading) => {
+                    writeln!(f, "{}, page {}", heading, act.page)?;
+                    "  "
+                },
+                None => "",
+            };
+
+            for scene in act.scenes.iter() {
+                let label = match scene.number.is_empty() {
+                    true => String::new(),
+                    false => format!("{}. ", scene.number),
+                };
+
+                writeln!(f, "{}{}{}, page {}",
+                         indent, label, scene.heading, scene.page)?;
+
+                if !scene.first_line.is_empty() {
+                    writeln!(f, "{}{:w$}{}",
+                             indent, "", scene.first_line, w = label.len())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// ADR (looping) sheet listing every line of dialogue
+///
+/// Each line gets an ID made of its scene number and its position in
+/// the scene, such as <tt>12.3</tt>, so that notes from the mixing
+/// stage can point back into the script.  Lines before the first
+/// scene heading are numbered on their own.  Page numbers come from a
+/// formatter that has been run on the same document; until then they
+/// are zero.  The timecode column is left empty for the editor.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::ElementType;
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::reader::Reader;
+/// # use batyr::document::report::AdrSheet;
+/// let xml = "<screenplay><head/><body>\
+///            <slug>INT. KITCHEN - DAY</slug>\
+///            <cue>EMMA</cue><d>Late again.</d>\
+///            <cue>THOMAS (O.S.)</cue><d>Coming!</d>\
+///            <slug>EXT. STREET - DAY</slug>\
+///            <cue>THOMAS</cue><d>Wait, up.</d>\
+///            </body></screenplay>";
+///
+/// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+///     panic!();
+/// };
+/// let mut sheet = AdrSheet::from(&root);
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(ElementType::Screenplay(root));
+/// sheet.set_pages(&formatter);
+///
+/// assert_eq!(sheet.to_csv(), "ID,Scene,Page,Character,Line,Timecode\n\
+///                             1.1,1,1,EMMA,Late again.,\n\
+///                             1.2,1,1,THOMAS,Coming!,\n\
+///                             2.1,2,1,THOMAS,\"Wait, up.\",\n");
+/// ```
+#[derive(Debug)]
+pub struct AdrSheet {
+    /// Lines of dialogue in order
+    pub lines: Vec<AdrLine>,
+}
+
+/// Line of dialogue on an [`AdrSheet`]
+#[derive(Debug)]
+pub struct AdrLine {
+    /// Scene number and position in the scene
+    pub id: String,
+    /// Scene number, including any addition, if in a scene
+    pub scene: Option<String>,
+    /// Page where the line begins
+    pub page: i32,
+    /// Character speaking, without cue extensions
+    pub character: String,
+    /// Text of the line
+    pub text: String,
+}
+
+impl From<&ContainerElement<Screenplay>> for AdrSheet {
+    fn from(root: &ContainerElement<Screenplay>) -> Self {
+        let mut sheet = AdrSheet { lines: Vec::new() };
+
+        let body = root.children.iter().find_map(|child| match child {
+            ElementType::Body(elem) => Some(elem),
+            _ => None,
+        });
+
+        let body = match body {
+            Some(body) => body,
+            None => return sheet,
+        };
+
+        let mut scene: Option<String> = None;
+        let mut cue = String::new();
+        let mut n: usize = 0;
+
+        for child in body.children.iter() {
+            match child {
+                ElementType::Slug(elem) if !elem.attributes.continued => {
+                    scene = Some(elem.label());
+                    n = 0;
+                },
+                ElementType::Cue(elem) => {
+                    cue = elem.name();
+                },
+                ElementType::D(elem) => {
+                    n += 1;
+
+                    let id = match &scene {
+                        Some(label) => format!("{}.{}", label, n),
+                        None => n.to_string(),
+                    };
+
+                    sheet.lines.push(AdrLine {
+                        id,
+                        scene: scene.clone(),
+                        page: 0,
+                        character: cue.clone(),
+                        text: elem.text(),
+                    });
+                },
+                _ => (),
+            }
+        }
+
+        sheet
+    }
+}
+
+impl AdrSheet {
+    /// Copies page numbers from a formatter that has been run on the
+    /// same document
+    pub fn set_pages(&mut self, formatter: &Formatter) {
+        for (line, page) in self.lines.iter_mut()
+            .zip(formatter.dialogue_pages.iter())
+        {
+            line.page = *page;
+        }
+    }
+
+    /// Writes the sheet as comma-separated values, with a header row
+    pub fn to_csv(&self) -> String {
+        let mut csv = csv_row(&[
+            "ID", "Scene", "Page", "Character", "Line", "Timecode"
+        ].map(String::from));
+
+        for line in self.lines.iter() {
+            csv.push_str(&csv_row(&[
+                line.id.clone(),
+                line.scene.clone().unwrap_or_default(),
+                line.page.to_string(),
+                line.character.clone(),
+                line.text.clone(),
+                String::new(),
+            ]));
+        }
+
+        csv
+    }
+}
+
+/// Dialogue statistics for each character, for casting breakdowns
+///
+/// Characters are the names in the cues, in order of first
+/// appearance.  Each speech is a cue with the dialogue that follows
+/// it, and each dialogue element counts as one line, as on an
+/// [`AdrSheet`].  Words are counted in the dialogue text, so personal
+/// direction is left out, and a contraction, possessive or hyphenated
+/// word counts once.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::ElementType;
+/// # use batyr::document::reader::Reader;
+/// # use batyr::document::report::CharacterReport;
+/// let xml = "<screenplay><head/><body>\
+///            <slug>INT. KITCHEN - DAY</slug>\
+///            <cue>EMMA</cue><d>Late again.</d>\
+///            <cue>THOMAS (O.S.)</cue><dir>calling</dir><d>Coming!</d>\
+///            <slug>EXT. STREET - DAY</slug>\
+///            <cue>THOMAS</cue><d>Wait up.</d><dir>panting</dir><d>Please wait.</d>\
+///            </body></screenplay>";
+///
+/// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+///     panic!();
+/// };
+/// let report = CharacterReport::from(&root);
+///
+/// assert_eq!(report.characters[1].name, "THOMAS");
+/// assert_eq!(report.characters[1].scenes, 2);
+/// assert_eq!(report.characters[1].lines, 3);
+/// assert_eq!(report.characters[1].words, 5);
+/// assert_eq!(report.to_csv(), "Character,Scenes,Speeches,Lines,Words\n\
+///                              EMMA,1,1,1,2\n\
+///                              THOMAS,2,2,3,5\n");
+///
+/// let xml = "<screenplay><head/><body>\
+///            <cue>EMMA</cue><d>It's Mr. McCue's well-known car.</d>\
+///            </body></screenplay>";
+///
+/// let Some(ElementType::Screenplay(root)) = Reader::new(xml).run() else {
+///     panic!();
+/// };
+/// assert_eq!(CharacterReport::from(&root).characters[0].words, 5);
+/// ```
+#[derive(Debug)]
+pub struct CharacterReport {
+    /// Statistics for each character
+    pub characters: Vec<CharacterStats>,
+}
+
+/// Row of a [`CharacterReport`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterStats {
+    /// Character name, without cue extensions
+    pub name: String,
+    /// Number of scenes with a cue for the character
+    pub scenes: usize,
+    /// Number of cues
+    pub speeches: usize,
+    /// Number of dialogue elements
+    pub lines: usize,
+    /// Number of words of dialogue
+    pub words: usize,
+}
+
+impl From<&ContainerElement<Screenplay>> for CharacterReport {
+    fn from(root: &ContainerElement<Screenplay>) -> Self {
+        let mut report = CharacterReport { characters: Vec::new() };
+
+        let body = root.children.iter().find_map(|child| match child {
+            ElementType::Body(elem) => Some(elem),
+            _ => None,
+        });
+
+        let body = match body {
+            Some(body) => body,
+            None => return report,
+        };
+
+        // Scene each character was last counted in
+        let mut last_scene: Vec<Option<usize>> = Vec::new();
+        let mut scene: Option<usize> = None;
+        let mut cur: Option<usize> = None;
+
+        for child in body.children.iter() {
+            match child {
+                ElementType::Slug(elem) if !elem.attributes.continued => {
+                    scene = Some(scene.map_or(0, |j| j + 1));
+                },
+                ElementType::Cue(elem) => {
+                    let name = elem.name();
+
+                    if name.is_empty() {
+                        cur = None;
+                        continue;
+                    }
+
+                    let i = match report.characters.iter()
+                        .position(|c| c.name == name)
+                    {
+                        Some(i) => i,
+                        None => {
+                            report.characters.push(CharacterStats {
+                                name,
+                                scenes: 0,
+                                speeches: 0,
+                                lines: 0,
+                                words: 0,
+                            });
+                            last_scene.push(None);
+                            report.characters.len() - 1
+                        },
+                    };
+
+                    let stats = &mut report.characters[i];
+                    stats.speeches += 1;
+
+                    if scene.is_some() && last_scene[i] != scene {
+                        stats.scenes += 1;
+                        last_scene[i] = scene;
+                    }
+
+                    cur = Some(i);
+                },
+                ElementType::D(elem) => {
+                    if let Some(i) = cur {
+                        let stats = &mut report.characters[i];
+                        stats.lines += 1;
+                        stats.words += count_words(&elem.tokens);
+                    }
+                },
+                ElementType::Dir(_) => (),
+                _ => {
+                    cur = None;
+                },
+            }
+        }
+
+        report
+    }
+}
+
+/// Counts the words of a text as a reader would
+///
+/// The parser splits contractions, possessives and hyphenated words
+/// into several tokens, so words are counted on the text instead: a
+/// word is a run of letters or digits between spaces and dashes.
+fn count_words(tokens: &[TokenType]) -> usize {
+    let text: String = tokens.iter().map(|token| token.text()).collect();
+
+    text.split_whitespace()
+        .flat_map(|run| run.split("--"))
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+impl CharacterReport {
+    /// Writes the report as comma-separated values, with a header row
+    pub fn to_csv(&self) -> String {
+        let mut csv = csv_row(&[
+            "Character", "Scenes", "Speeches", "Lines", "Words"
+        ].map(String::from));
+
+        for stats in self.characters.iter() {
+            csv.push_str(&csv_row(&[
+                stats.name.clone(),
+                stats.scenes.to_string(),
+                stats.speeches.to_string(),
+                stats.lines.to_string(),
+                stats.words.to_string(),
+            ]));
+        }
+
+        csv
+    }
+}
+
+/// Cuts text down to at most <tt>n</tt> characters at a word
+/// boundary, adding an ellipsis if anything was cut
+fn shorten(text: &str, n: usize) -> String {
+    if text.chars().count() <= n {
+        return text.to_string();
+    }
+
+    let mut result = String::new();
+
+    for word in text.split(' ') {
+        if result.chars().count() + word.chars().count() + 4 > n {
+            break;
+        }
+
+        if !result.is_empty() {
+            result.push(' ');
+        }
+
+        result.push_str(word);
+    }
+
+    result.push_str("...");
+    result
+}
+
+/// Checks whether the text names the character as a whole word, in
+/// capitals
+fn mentions(text: &str, name: &str) -> bool {
+    text.match_indices(name).any(|(i, _)| {
+        let before = text[..i].chars().next_back();
+        let after = text[i + name.len()..].chars().next();
+
+        !before.is_some_and(char::is_alphanumeric)
+            && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Joins fields into a line of CSV, quoting where needed
+fn csv_row(fields: &[String]) -> String {
+    let quoted: Vec<String> = fields.iter().map(|field| {
+        if field.contains([',', '"', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.clone()
+        }
+    }).collect();
+
+    format!("{}\n", quoted.join(","))
+}
@@ -0,0 +1,208 @@
+// Batyr Sample Generator
+// Copyright (C) 2023 Gene Yu
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Synthetic screenplays for stress testing
+//!
+//! [`generate`] writes a screenplay of nonsense scenes, conversations
+//! and monologues that conforms to the schema and runs to roughly the
+//! requested number of pages.  The same seed always gives the same
+//! document, so a slow or broken pagination can be reproduced from
+//! the seed alone.
+
+use crate::document::Layout;
+
+const WORDS: [&str; 48] = [
+    "the", "a", "door", "window", "light", "rain", "street", "car",
+    "table", "glass", "letter", "phone", "coat", "stairs", "night",
+    "morning", "slowly", "again", "never", "always", "quietly", "back",
+    "across", "into", "under", "over", "with", "without", "still",
+    "looks", "turns", "waits", "opens", "closes", "takes", "drops",
+    "finds", "leaves", "knows", "wants", "thinks", "remembers", "old",
+    "cold", "empty", "small", "last", "same",
+];
+
+const NAMES: [&str; 12] = [
+    "ANNA", "BEN", "CLARA", "DANIEL", "EDITH", "FRANK", "GRACE",
+    "HENRY", "IRIS", "JACK", "LENA", "MAX",
+];
+
+const PLACES: [&str; 10] = [
+    "KITCHEN", "OFFICE", "DINER", "HOTEL LOBBY", "PARKING LOT",
+    "TRAIN STATION", "BEDROOM", "ROOFTOP", "POLICE STATION", "BAR",
+];
+
+const DIRECTIONS: [&str; 6] = [
+    "beat", "quietly", "to herself", "to himself", "laughing",
+    "not looking up",
+];
+
+const TRANSITIONS: [&str; 3] = ["CUT TO:", "DISSOLVE TO:", "SMASH CUT TO:"];
+
+/// Typed columns available to stage direction and dialogue, for
+/// estimating the length of the document
+const P_WIDTH: usize = 57;
+const D_WIDTH: usize = 34;
+
+/// Small deterministic random number generator (SplitMix64)
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number from <tt>lo</tt> up to and including
+    /// <tt>hi</tt>
+    fn range(&mut self, lo: usize, hi: usize) -> usize {
+        lo + (self.next() % (hi - lo + 1) as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.range(0, items.len() - 1)]
+    }
+
+    /// Makes up a sentence of nonsense
+    fn sentence(&mut self, end: &str) -> String {
+        let n = self.range(4, 14);
+        let mut words: Vec<String> = Vec::with_capacity(n);
+
+        for i in 0..n {
+            let word = self.pick(&WORDS);
+
+            if i == 0 {
+                let mut chars = word.chars();
+                let first = chars.next().unwrap().to_uppercase();
+                words.push(format!("{}{}", first, chars.as_str()));
+            } else {
+                words.push(word.to_string());
+            }
+        }
+
+        format!("{}{}", words.join(" "), end)
+    }
+
+    /// Makes up a paragraph of the given number of sentences
+    fn paragraph(&mut self, n: usize, ends: &[&str]) -> String {
+        (0..n).map(|_| {
+            let end = self.pick(ends);
+            self.sentence(end)
+        }).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Estimates the lines taken by text typed in the given width
+fn lines(text: &str, width: usize) -> usize {
+    text.chars().count() / width + 1
+}
+
+/// Generates a screenplay of roughly the given number of pages
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::formatter::Formatter;
+/// # use batyr::document::reader::Reader;
+/// # use batyr::document::sample::generate;
+/// let xml = generate(20, 42);
+/// assert_eq!(xml, generate(20, 42));
+///
+/// let mut formatter = Formatter::new();
+/// formatter.run(Reader::new(&xml).try_run().unwrap().unwrap());
+///
+/// let pages = formatter.body.len() - 1; // without the title page
+/// assert!((15..=25).contains(&pages), "{} pages", pages);
+/// ```
+pub fn generate(pages: usize, seed: u64) -> String {
+    let mut random = Random(seed);
+    // Page breaks, (MORE) and CONTINUED take about an eighth of a
+    // page that the estimates below leave out.
+    let target = pages * Layout::default().lines_per_page() * 7 / 8;
+    let mut total: usize = 0;
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<screenplay numbering=\"full\">\n");
+    xml.push_str("<head>\n");
+    xml.push_str(&format!("  <title>SAMPLE {}</title>\n", seed));
+    xml.push_str("  <authors>\n    <fullName>Batyr</fullName>\n  </authors>\n");
+    xml.push_str("</head>\n<body>\n");
+    xml.push_str("  <open>FADE IN:</open>\n");
+
+    while total < target {
+        let exterior = random.range(0, 2) == 0;
+        let place = random.pick(&PLACES);
+        let time = if random.range(0, 1) == 0 { "DAY" } else { "NIGHT" };
+
+        xml.push_str(&format!(
+            "  <slug>{}. {} - {}</slug>\n",
+            if exterior { "EXT" } else { "INT" }, place, time
+        ));
+        total += 3;
+
+        let a = random.pick(&NAMES);
+        let b = random.pick(&NAMES);
+        let cast = if a == b { vec![a] } else { vec![a, b] };
+
+        for _ in 0..random.range(1, 6) {
+            if random.range(0, 2) == 0 {
+                let n = random.range(1, 4);
+                let p = random.paragraph(n, &["."]);
+                total += lines(&p, P_WIDTH) + 1;
+                xml.push_str(&format!("  <p>{}</p>\n", p));
+            }
+
+            // A monologue now and then, otherwise a quick exchange
+            let (speeches, sentences) = if random.range(0, 9) == 0 {
+                (1, (8, 30))
+            } else {
+                (random.range(1, 6), (1, 3))
+            };
+
+            for i in 0..speeches {
+                let cue = cast[i % cast.len()];
+                xml.push_str(&format!("  <cue>{}</cue>\n", cue));
+                total += 2;
+
+                if random.range(0, 5) == 0 {
+                    let dir = random.pick(&DIRECTIONS);
+                    xml.push_str(&format!("  <dir>{}</dir>\n", dir));
+                    total += 1;
+                }
+
+                let n = random.range(sentences.0, sentences.1);
+                let d = random.paragraph(n, &[".", ".", "?", "!"]);
+                total += lines(&d, D_WIDTH);
+                xml.push_str(&format!("  <d>{}</d>\n", d));
+            }
+        }
+
+        if random.range(0, 3) == 0 {
+            xml.push_str(&format!("  <trans>{}</trans>\n",
+                                  random.pick(&TRANSITIONS)));
+            total += 2;
+        }
+    }
+
+    xml.push_str("  <trans>FADE OUT.</trans>\n");
+    xml.push_str("  <end>THE END</end>\n");
+    xml.push_str("</body>\n</screenplay>\n");
+    xml
+}
@@ -0,0 +1,326 @@
+// Batyr Validation
+// Copyright (C) 2023 Gene Yu
+//
+// This program is free software: you can redistribute it and/or
+// modify it under the terms of the GNU General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful, but
+// WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see
+// <https://www.gnu.org/licenses/>.
+
+//! Checks a document against the screenplay schema
+//!
+//! The reader assumes valid input and passes over anything it does
+//! not recognize, so a misspelled tag or a paragraph in the wrong
+//! place quietly drops out of the typed pages.  [`validate`] walks
+//! the source with the content models of the [screenplay schema]
+//! built in, and reports unknown elements, elements where the schema
+//! does not allow them, and missing required children, each with its
+//! line number.  It is no substitute for a full schema validator such
+//! as <tt>xmllint</tt>; attribute values, for one, are left to the
+//! reader.
+//!
+//! As with the reader, a fragment of the schema is accepted in place
+//! of a whole screenplay, so any known element may be the root.
+//!
+//! [screenplay schema]: <http://www.matchlock.com/batyr/screenplay.xsd>
+
+use quick_xml::events::{BytesStart, Event};
+
+/// Content model of an element
+struct Model {
+    /// Elements allowed as children, in schema order
+    children: &'static [&'static str],
+    /// Children that must appear, with their minimum counts
+    required: &'static [(&'static str, usize)],
+    /// The children must follow the schema order
+    ordered: bool,
+    /// Text is allowed between the children
+    mixed: bool,
+    /// Attributes that must be present
+    attributes: &'static [&'static str],
+}
+
+const EMPTY: Model = Model {
+    children: &[],
+    required: &[],
+    ordered: false,
+    mixed: false,
+    attributes: &[],
+};
+
+const TEXT: Model = Model {
+    children: &["b", "br", "em", "ref", "u"],
+    mixed: true,
+    ..EMPTY
+};
+
+const INLINE: Model = Model {
+    children: &["b", "em", "u"],
+    mixed: true,
+    ..EMPTY
+};
+
+const MATTER: Model = Model {
+    children: &["br", "p", "pageBreak", "title"],
+    ..EMPTY
+};
+
+/// Looks up the content model of a schema element
+fn model(name: &str) -> Option<Model> {
+    let model = match name {
+        "act" | "close" | "contact" | "cue" | "dir" | "end" | "episode" |
+        "fullName" | "li" | "note" | "open" | "p" | "productionNumber" |
+        "series" | "shot" | "slug" | "source" | "title" | "trans" => TEXT,
+        "alt" => Model {
+            children: &["d"],
+            required: &[("d", 2)],
+            ..EMPTY
+        },
+        "authors" => Model {
+            children: &["fullName"],
+            required: &[("fullName", 1)],
+            ..EMPTY
+        },
+        "b" | "em" | "u" => INLINE,
+        "backMatter" | "frontMatter" => MATTER,
+        "blank" | "br" | "pageBreak" => EMPTY,
+        "body" => Model {
+            children: &["act", "alt", "blank", "br", "close", "cue", "d",
+                        "dir", "end", "ol", "open", "p", "pageBreak",
+                        "shot", "slug", "trans"],
+            ..EMPTY
+        },
+        "d" => Model {
+            children: &["b", "br", "em", "ol", "ref", "u"],
+            ..TEXT
+        },
+        "define" => Model {
+            attributes: &["name", "value"],
+            ..EMPTY
+        },
+        "head" => Model {
+            children: &["define", "series", "title", "episode",
+                        "productionNumber", "authors", "source", "note",
+                        "contact"],
+            required: &[("title", 1), ("authors", 1)],
+            ordered: true,
+            ..EMPTY
+        },
+        "ol" => Model {
+            children: &["li"],
+            required: &[("li", 1)],
+            ..EMPTY
+        },
+        "ref" => Model {
+            attributes: &["scene"],
+            ..EMPTY
+        },
+        "screenplay" => Model {
+            children: &["head", "frontMatter", "body", "backMatter"],
+            required: &[("head", 1), ("body", 1)],
+            ordered: true,
+            ..EMPTY
+        },
+        _ => return None,
+    };
+
+    Some(model)
+}
+
+/// An element whose end tag has not been seen yet
+struct Open {
+    name: String,
+    line: usize,
+    model: Option<Model>,
+    /// Number of each allowed child seen so far
+    counts: Vec<usize>,
+    /// Position in schema order of the last child seen
+    last: usize,
+}
+
+/// Checks a document against the content models of the screenplay
+/// schema
+///
+/// Returns a description of each problem found, sorted by line.  A
+/// document that is not well-formed XML gets a single problem for
+/// the first syntax error, after any found before it.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::validate::validate;
+/// let xml = "<screenplay>\n\
+///            <head><title>SNOW</title></head>\n\
+///            <body>\n\
+///            <slug>EXT. FIELD - DAY</slug>\n\
+///            <para>Snow.</para>\n\
+///            <cue>AMY<p>Hi.</p></cue>\n\
+///            </body>\n\
+///            </screenplay>";
+///
+/// assert_eq!(validate(xml), vec![
+///     "<head> on line 2 is missing <authors>",
+///     "unknown element <para> on line 5",
+///     "<p> is not allowed in <cue> on line 6",
+/// ]);
+///
+/// assert!(validate("<p>Snow <em>falls</em>.</p>").is_empty());
+///
+/// // The missing <body> is found at the end, but reported first.
+/// assert_eq!(validate("<screenplay><head><title>SNOW</title>\n\
+///                      <authors/></head>\n<p/></screenplay>"), vec![
+///     "<screenplay> on line 1 is missing <body>",
+///     "<authors> on line 2 is missing <fullName>",
+///     "<p> is not allowed in <screenplay> on line 3",
+/// ]);
+/// ```
+pub fn validate(source: &str) -> Vec<String> {
+    let mut xml_reader = quick_xml::Reader::from_str(source);
+    let mut stack: Vec<Open> = Vec::new();
+    let mut problems: Vec<(usize, String)> = Vec::new();
+
+    // Line counting picks up where it left off.
+    let mut line = 1;
+    let mut counted = 0;
+
+    loop {
+        let start = xml_reader.buffer_position();
+        line += source.as_bytes()[counted..start].iter()
+            .filter(|&&x| x == b'\n')
+            .count();
+        counted = start;
+
+        match xml_reader.read_event() {
+            Ok(Event::Start(ref event)) => {
+                let open = enter(event, line, &mut stack, &mut problems);
+                stack.push(open);
+            },
+            Ok(Event::Empty(ref event)) => {
+                let open = enter(event, line, &mut stack, &mut problems);
+                leave(open, &mut problems);
+            },
+            Ok(Event::End(_)) => {
+                if let Some(open) = stack.pop() {
+                    leave(open, &mut problems);
+                }
+            },
+            Ok(Event::Text(ref event)) => {
+                let blank = event.iter().all(|x| x.is_ascii_whitespace());
+
+                if let Some(Open { name, model: Some(model), .. }) = stack.last() {
+                    if !blank && !model.mixed {
+                        let first = event.iter()
+                            .take_while(|x| x.is_ascii_whitespace())
+                            .filter(|&&x| x == b'\n')
+                            .count();
+
+                        problems.push((line + first, format!(
+                            "text is not allowed in <{}> on line {}",
+                            name, line + first
+                        )));
+                    }
+                }
+            },
+            Ok(Event::Eof) => break,
+            Ok(_) => (),
+            Err(e) => {
+                problems.push((line, format!("{} on line {}", e, line)));
+                break;
+            },
+        }
+    }
+
+    // Missing children are found at the end tag, so they come out
+    // of order.
+    problems.sort_by_key(|(line, _)| *line);
+    problems.into_iter().map(|(_, problem)| problem).collect()
+}
+
+/// Checks a start tag against its parent and opens it
+fn enter(event: &BytesStart, line: usize, stack: &mut [Open],
+         problems: &mut Vec<(usize, String)>) -> Open
+{
+    let name = String::from_utf8_lossy(event.local_name().into_inner())
+        .to_string();
+    let model = model(&name);
+
+    if model.is_none() {
+        problems.push((line, format!("unknown element <{}> on line {}",
+                                     name, line)));
+
+    } else if let Some(parent) = stack.last_mut() {
+        if let Some(parent_model) = &parent.model {
+            match parent_model.children.iter().position(|x| *x == name) {
+                Some(i) => {
+                    if parent_model.ordered && i < parent.last {
+                        problems.push((line, format!(
+                            "<{}> is out of order in <{}> on line {}",
+                            name, parent.name, line
+                        )));
+                    }
+
+                    parent.counts[i] += 1;
+                    parent.last = parent.last.max(i);
+                },
+                None => {
+                    problems.push((line, format!(
+                        "<{}> is not allowed in <{}> on line {}",
+                        name, parent.name, line
+                    )));
+                },
+            }
+        }
+    }
+
+    if let Some(model) = &model {
+        for attribute in model.attributes {
+            if !matches!(event.try_get_attribute(*attribute), Ok(Some(_))) {
+                problems.push((line, format!(
+                    "<{}> on line {} is missing the {} attribute",
+                    name, line, attribute
+                )));
+            }
+        }
+    }
+
+    let counts = vec![0; model.as_ref().map_or(0, |x| x.children.len())];
+
+    Open {
+        name,
+        line,
+        model,
+        counts,
+        last: 0,
+    }
+}
+
+/// Checks that an element has all of its required children
+fn leave(open: Open, problems: &mut Vec<(usize, String)>) {
+    let Some(model) = &open.model else {
+        return;
+    };
+
+    for (child, min) in model.required {
+        let i = model.children.iter().position(|x| x == child).unwrap();
+
+        if open.counts[i] < *min {
+            let count = match min {
+                1 => String::new(),
+                n => format!("at least {} ", n),
+            };
+
+            problems.push((open.line, format!(
+                "<{}> on line {} is missing {}<{}>",
+                open.name, open.line, count, child
+            )));
+        }
+    }
+}
@@ -15,154 +15,705 @@
 // along with this program.  If not, see
 // <https://www.gnu.org/licenses/>.
 
-//! Writes formatted pages to the standard output
+//! Writes formatted pages to an output stream
 //!
 //! # Examples
 //!
-//! ```rust,no_run
-//! use batyr::document::formatter::Page;
+//! ```
+//! use batyr::document::formatter::{Page, Row};
 //! use batyr::document::writer::Writer;
 //! use batyr::text::{Line, Segment};
 //!
 //! let page = Page {
 //!     number: 1,
 //!     height: 55,
-//!     lines: vec![Some(Line::from(Segment::from("foo")))],
+//!     lines: vec![Row::Text(Line::from(Segment::from("foo")))],
 //!     footer: Vec::new(),
 //! };
 //!
-//! let mut writer = Writer::new("WORKING TITLE");
-//! let result = writer.run(vec![page]);
+//! let mut writer = Writer::new(Vec::new(), "WORKING TITLE");
+//! writer.run(vec![page]).unwrap();
+//!
+//! let output = String::from_utf8(writer.into_inner()).unwrap();
+//! assert!(output.contains("%%Title: WORKING TITLE"));
+//! assert!(output.contains("(foo)"));
 //! ```
 use std::error::Error;
-use std::fs;
-use std::io::{self, Write};
+use std::io::Write;
 use std::str;
+use std::sync::mpsc::Receiver;
 
 use encoding::{Encoding, EncoderTrap};
 use encoding::all::ISO_8859_15;
 use regex::Regex;
 
-use crate::PROGRAM_NAME;
-use crate::PROLOGUE_FILE;
-
 use crate::document::*;
 use crate::document::formatter::*;
 use crate::text::*;
 
+/// The PostScript prologue built into the library
+pub const PROLOGUE: &str = include_str!("../../data/prologue.ps");
+
+/// Procedures the writer calls, which every prologue must define
+pub const PROCEDURES: [&str; 5] = ["page-begin", "page-end", "ushow", "bshow", "ubshow"];
+
+/// Lists the procedures the writer calls that a prologue does not
+/// define
+///
+/// A prologue written for an older version may lack the procedures
+/// added since, and output using it would fail in the printer.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::writer::{missing_procedures, PROLOGUE};
+/// assert!(missing_procedures(PROLOGUE).is_empty());
+///
+/// let old = "/page-begin { } def\n/page-end { } def\n/ushow {\n} def\n";
+/// assert_eq!(missing_procedures(old), ["bshow", "ubshow"]);
+/// ```
+pub fn missing_procedures(prologue: &str) -> Vec<&'static str> {
+    PROCEDURES.iter()
+        .filter(|name| !prologue.split_whitespace().any(|word| {
+            word.strip_prefix('/').map(|w| w.trim_end_matches('{')) == Some(**name)
+        }))
+        .copied()
+        .collect()
+}
+
+/// Line ending written after each line of output
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Newline {
+    /// Line feed, as on Unix
+    #[default]
+    Lf,
+    /// Carriage return and line feed, as on Windows
+    CrLf,
+}
+
+impl Newline {
+    /// Returns the line ending as a string
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::writer::Newline;
+    /// assert_eq!(Newline::Lf.as_str(), "\n");
+    /// assert_eq!(Newline::from("crlf").as_str(), "\r\n");
+    /// ```
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+        }
+    }
+}
+
+impl From<&str> for Newline {
+    fn from(s: &str) -> Self {
+        match s {
+            "crlf" => Newline::CrLf,
+            _ => Newline::Lf,
+        }
+    }
+}
+
+/// Two-sided printing, as requested from the printer
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Duplex {
+    /// Print on one side of the paper
+    #[default]
+    Simplex,
+    /// Print on both sides, turning the sheet on its long edge
+    LongEdge,
+    /// Print on both sides, turning the sheet on its short edge
+    ShortEdge,
+}
+
+impl From<&str> for Duplex {
+    fn from(s: &str) -> Self {
+        match s {
+            "long" => Duplex::LongEdge,
+            "short" => Duplex::ShortEdge,
+            _ => Duplex::Simplex,
+        }
+    }
+}
+
+/// Where the page number goes on the page
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum PageNumberPlace {
+    /// Top right corner, after the running header
+    #[default]
+    TopRight,
+    /// Centered at the top of the page
+    TopCenter,
+    /// Centered at the bottom of the page
+    BottomCenter,
+}
+
+impl From<&str> for PageNumberPlace {
+    fn from(s: &str) -> Self {
+        match s {
+            "top-center" => PageNumberPlace::TopCenter,
+            "bottom-center" => PageNumberPlace::BottomCenter,
+            _ => PageNumberPlace::TopRight,
+        }
+    }
+}
+
+/// How page numbers are printed
+///
+/// A template is copied out with <tt>{n}</tt> replaced by the page
+/// number, <tt>{i}</tt> by the number in lowercase Roman numerals,
+/// and <tt>{I}</tt> in uppercase.  Front matter pages are counted
+/// from 1 on their own, and are left unnumbered unless they have a
+/// template.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::writer::PageNumbers;
+/// let numbers = PageNumbers {
+///     body: "-{n}-".to_string(),
+///     front_matter: Some("{i}".to_string()),
+///     ..Default::default()
+/// };
+///
+/// assert_eq!(numbers.body_label(12), "-12-");
+/// assert_eq!(numbers.front_matter_label(4).unwrap(), "iv");
+/// assert_eq!(PageNumbers::default().body_label(12), "12.");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageNumbers {
+    /// Template for pages of the body
+    pub body: String,
+    /// Template for front matter pages, if they are numbered
+    pub front_matter: Option<String>,
+    /// Where the number goes
+    pub place: PageNumberPlace,
+}
+
+impl Default for PageNumbers {
+    fn default() -> Self {
+        Self {
+            body: "{n}.".to_string(),
+            front_matter: None,
+            place: PageNumberPlace::TopRight,
+        }
+    }
+}
+
+impl PageNumbers {
+    /// Returns the label of a page of the body
+    pub fn body_label(&self, n: usize) -> String {
+        PageNumbers::fill(&self.body, n)
+    }
+
+    /// Returns the label of a front matter page, if they are numbered
+    pub fn front_matter_label(&self, n: usize) -> Option<String> {
+        self.front_matter.as_ref()
+            .map(|template| PageNumbers::fill(template, n))
+    }
+
+    /// Returns the column where a running header starts, so that it
+    /// clears the page number
+    pub fn header_column(&self, header: &str, layout: &Layout) -> usize {
+        // two spaces between the header and the page number
+        let n = header.chars().count();
+
+        match self.place {
+            PageNumberPlace::TopRight => layout.page_no_begin.saturating_sub(n + 2),
+            _ => (layout.right_margin + 1).saturating_sub(n),
+        }
+    }
+
+    /// Returns the column and line where a page number label goes
+    pub fn label_position(&self, label: &str, layout: &Layout)
+                          -> (usize, usize)
+    {
+        let n = label.chars().count();
+
+        match self.place {
+            PageNumberPlace::TopRight => (layout.page_no_begin, layout.header_line),
+            PageNumberPlace::TopCenter => (layout.centered(n), layout.header_line),
+            PageNumberPlace::BottomCenter => (layout.centered(n), layout.footer_line),
+        }
+    }
+
+    fn fill(template: &str, n: usize) -> String {
+        let roman = roman_numerals(n);
+
+        template.replace("{n}", &n.to_string())
+            .replace("{i}", &roman)
+            .replace("{I}", &roman.to_uppercase())
+    }
+}
+
+/// Writes a number in lowercase Roman numerals
+fn roman_numerals(mut n: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "m"), (900, "cm"), (500, "d"), (400, "cd"),
+        (100, "c"), (90, "xc"), (50, "l"), (40, "xl"),
+        (10, "x"), (9, "ix"), (5, "v"), (4, "iv"), (1, "i"),
+    ];
+
+    let mut result = String::new();
+
+    for (value, numeral) in NUMERALS {
+        while n >= value {
+            result.push_str(numeral);
+            n -= value;
+        }
+    }
+
+    result
+}
+
 /// Output driver
-pub struct Writer {
+pub struct Writer<W: Write> {
+    out: W,
     title: String,
+    header: String,
+    creator: String,
+    prologue: String,
+    newline: Newline,
+    stamp: Option<String>,
+    copies: usize,
+    collate: bool,
+    duplex: Duplex,
+    page_numbers: PageNumbers,
+    layout: Layout,
+    front_page_no: usize,
+    body_started: bool,
     real_page_no: usize,
 }
 
-impl Writer {
+impl<W: Write> Writer<W> {
     /// Creates a document writer
-    pub fn new(title: &str) -> Writer {
+    pub fn new(out: W, title: &str) -> Writer<W> {
         Writer {
+            out,
             title: title.to_string(),
+            header: String::new(),
+            creator: "batyr".to_string(),
+            prologue: PROLOGUE.to_string(),
+            newline: Newline::Lf,
+            stamp: None,
+            copies: 1,
+            collate: false,
+            duplex: Duplex::Simplex,
+            page_numbers: PageNumbers::default(),
+            layout: Layout::default(),
+            front_page_no: 0,
+            body_started: false,
             real_page_no: 1,
         }
     }
 
-    /// Writes the document to the standard output
+    /// Sets the running header printed before each page number
+    pub fn header(mut self, header: &str) -> Self {
+        self.header = header.to_string();
+        self
+    }
+
+    /// Sets the program name recorded in the document header
+    pub fn creator(mut self, creator: &str) -> Self {
+        self.creator = creator.to_string();
+        self
+    }
+
+    /// Replaces the built-in PostScript prologue
+    pub fn prologue(mut self, prologue: &str) -> Self {
+        self.prologue = prologue.to_string();
+        self
+    }
+
+    /// Sets the line ending for the output
+    pub fn newline(mut self, newline: Newline) -> Self {
+        self.newline = newline;
+        self
+    }
+
+    /// Records a draft hash in the document header comments
+    pub fn stamp(mut self, hash: &str) -> Self {
+        self.stamp = Some(hash.to_string());
+        self
+    }
+
+    /// Asks the printer for the given number of copies
+    ///
+    /// The print settings go into a document setup section, which
+    /// PostScript printers and spoolers pass on to the device with
+    /// <tt>setpagedevice</tt>.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::writer::{Duplex, Writer};
+    /// let mut writer = Writer::new(Vec::new(), "FOO")
+    ///     .copies(12)
+    ///     .collate(true)
+    ///     .duplex(Duplex::LongEdge);
+    /// writer.run(Vec::new()).unwrap();
+    ///
+    /// let ps = String::from_utf8(writer.into_inner()).unwrap();
+    /// assert!(ps.contains("%%Requirements: numcopies(12) collate duplex"));
+    /// assert!(ps.contains("<< /NumCopies 12 >> setpagedevice"));
+    /// assert!(ps.contains("<< /Duplex true /Tumble false >> setpagedevice"));
+    /// ```
+    pub fn copies(mut self, n: usize) -> Self {
+        self.copies = n;
+        self
+    }
+
+    /// Asks the printer to collate multiple copies
+    pub fn collate(mut self, value: bool) -> Self {
+        self.collate = value;
+        self
+    }
+
+    /// Asks the printer to print on both sides of the paper
+    pub fn duplex(mut self, duplex: Duplex) -> Self {
+        self.duplex = duplex;
+        self
+    }
+
+    /// Sets how page numbers are printed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::formatter::Page;
+    /// # use batyr::document::writer::{PageNumberPlace, PageNumbers, Writer};
+    /// let page = |number| Page {
+    ///     number: number,
+    ///     height: 55,
+    ///     lines: Vec::new(),
+    ///     footer: Vec::new(),
+    /// };
+    ///
+    /// let mut writer = Writer::new(Vec::new(), "FOO")
+    ///     .page_numbers(PageNumbers {
+    ///         body: "- {n} -".to_string(),
+    ///         front_matter: Some("{i}".to_string()),
+    ///         place: PageNumberPlace::BottomCenter,
+    ///     });
+    /// writer.run(vec![page(-1), page(0), page(0), page(1)]).unwrap();
+    ///
+    /// let ps = String::from_utf8(writer.into_inner()).unwrap();
+    /// assert!(ps.contains("(ii) show"));
+    /// assert!(ps.contains("288 48 moveto (- 1 -) show"));
+    /// ```
+    pub fn page_numbers(mut self, numbers: PageNumbers) -> Self {
+        self.page_numbers = numbers;
+        self
+    }
+
+    /// Sets the page geometry used to place lines, running headers
+    /// and page numbers
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Consumes the writer, returning the output stream
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    /// Writes the document to the output stream
     pub fn run(&mut self, pages: PageList) -> Result<(), Box<dyn Error>> {
-        self.write_prologue(pages.len())?;
+        self.write_prologue(&pages.len().to_string())?;
 
         for page in pages {
-            self.start_a_new_page(page.number)?;
+            self.write_page(page)?;
+        }
 
-            let mut y = (TOP_LINE as f32 * LINE_HEIGHT as f32).round() as i32;
+        self.writeln("%%Trailer")
+    }
 
-            for line in page.lines {
-                match line {
-                    Some(line) => {
-                        let x = (line.column as f32 * CHAR_WIDTH).round() as i32;
+    /// Writes the document to the output stream as the pages arrive
+    /// from a streaming formatter
+    ///
+    /// The page count is not known up front, so it is deferred to the
+    /// trailer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::sync::mpsc;
+    /// # use batyr::document::formatter::{Page, Streamed};
+    /// # use batyr::document::writer::Writer;
+    /// let (sender, receiver) = mpsc::sync_channel(4);
+    ///
+    /// sender.send(Streamed::Head {
+    ///     title: "FOO".to_string(),
+    ///     header: String::new(),
+    /// }).unwrap();
+    ///
+    /// for number in [-1, 1] {
+    ///     sender.send(Streamed::Page(Page {
+    ///         number: number,
+    ///         height: 55,
+    ///         lines: Vec::new(),
+    ///         footer: Vec::new(),
+    ///     })).unwrap();
+    /// }
+    ///
+    /// drop(sender);
+    ///
+    /// let mut writer = Writer::new(Vec::new(), "");
+    /// writer.stream(receiver).unwrap();
+    ///
+    /// let ps = String::from_utf8(writer.into_inner()).unwrap();
+    /// assert!(ps.contains("%%Title: FOO"));
+    /// assert!(ps.contains("%%Pages: (atend)"));
+    /// assert!(ps.ends_with("%%Trailer\n%%Pages: 2\n"));
+    /// ```
+    pub fn stream(&mut self, pages: Receiver<Streamed>)
+                  -> Result<(), Box<dyn Error>>
+    {
+        let mut started = false;
+        let mut page_count = 0;
 
-                        writeln(&format!("{} {} moveto {}", x, y, line.ps()))?;
+        for message in pages {
+            match message {
+                Streamed::Head { title, header } => {
+                    self.title = title;
+                    self.header = header;
+                },
+                Streamed::Page(page) => {
+                    if !started {
+                        self.write_prologue("(atend)")?;
+                        started = true;
+                    }
 
-                        y -= LINE_HEIGHT.round() as i32;
-                    },
-                    None => {
-                        y -= LINE_HEIGHT.round() as i32;
-                    },
-                }
+                    self.write_page(page)?;
+                    page_count += 1;
+                },
             }
+        }
 
-            if !page.footer.is_empty() {
-                y = ((BOTTOM_LINE + page.footer.len() - 1) as f32 * LINE_HEIGHT)
-                    .round() as i32;
-
-                for line in page.footer.iter() {
-                    match line {
-			Some(line) => {
-                            let x = (line.column as f32 * CHAR_WIDTH).round() as i32;
-		            writeln(&format!("{} {} moveto {}", x, y, line.ps()))?;
-                            y -= LINE_HEIGHT.round() as i32;
-			},
-			None => {
-                            y -= LINE_HEIGHT.round() as i32;
-			},
-                    }
-                }
+        if !started {
+            self.write_prologue("(atend)")?;
+        }
+
+        self.writeln("%%Trailer")?;
+        self.writeln(&format!("%%Pages: {}", page_count))
+    }
+
+    #[doc(hidden)]
+    fn write_page(&mut self, page: Page) -> Result<(), Box<dyn Error>> {
+        self.start_a_new_page(page.number)?;
+        let layout = self.layout;
+
+        let mut y = (layout.top_line as f32 * layout.line_height).round() as i32;
+
+        for row in page.lines {
+            match row {
+                Row::Text(line) => {
+                    let x = (line.column as f32 * layout.char_width).round() as i32;
+
+                    self.writeln(&format!("{} {} moveto {}", x, y, line.ps()))?;
+
+                    y -= layout.line_height.round() as i32;
+                },
+                Row::Spacing(n) => {
+                    y -= n as i32 * layout.line_height.round() as i32;
+                },
             }
+        }
+
+        if !page.footer.is_empty() {
+            let h: usize = page.footer.iter().map(Row::height).sum();
+
+            y = ((layout.bottom_line + h - 1) as f32 * layout.line_height)
+                .round() as i32;
 
-            writeln("page-end")?;
+            for row in page.footer.iter() {
+                match row {
+                    Row::Text(line) => {
+                        let x = (line.column as f32 * layout.char_width).round() as i32;
+                        self.writeln(&format!("{} {} moveto {}", x, y, line.ps()))?;
+                        y -= layout.line_height.round() as i32;
+                    },
+                    Row::Spacing(n) => {
+                        y -= *n as i32 * layout.line_height.round() as i32;
+                    },
+                }
+            }
         }
 
-        writeln("%%Trailer")
+        self.writeln("page-end")
     }
 
     #[doc(hidden)]
-    fn write_prologue(&mut self, page_count: usize) -> Result<(), Box<dyn Error>> {
+    fn write_prologue(&mut self, num_pages: &str) -> Result<(), Box<dyn Error>> {
         let   title_pat = Regex::new(r"@title@")?;
         let creator_pat = Regex::new(r"@creator@")?;
         let   pages_pat = Regex::new(r"@pages@")?;
 
-        let creator = PROGRAM_NAME.to_string();
-	
-        let num_pages = format!("{}", page_count);
-        let mut prologue = fs::read_to_string(&*PROLOGUE_FILE)?;
+        let mut prologue = self.prologue.replace("\r\n", "\n");
+
+        let mut comments = String::new();
+
+        if let Some(hash) = &self.stamp {
+            comments.push_str(&format!("%%DraftHash: {}\n", hash));
+        }
+
+        let requirements = self.requirements();
+
+        if !requirements.is_empty() {
+            comments.push_str(&format!("%%Requirements: {}\n", requirements));
+        }
+
+        if !comments.is_empty() {
+            match prologue.find("%%EndComments") {
+                Some(i) => prologue.insert_str(i, &comments),
+                None => prologue.push_str(&comments),
+            }
+        }
+
+        let setup = self.setup();
+
+        if !setup.is_empty() {
+            if !prologue.ends_with('\n') {
+                prologue.push('\n');
+            }
+
+            prologue.push_str(&setup);
+        }
+
+        if self.newline != Newline::Lf {
+            prologue = prologue.replace('\n', self.newline.as_str());
+        }
 
         prologue = title_pat.replace(&prologue, &self.title).to_string();
-        prologue = creator_pat.replace(&prologue, &creator).to_string();
-        prologue = pages_pat.replace(&prologue, &num_pages).to_string();
+        prologue = creator_pat.replace(&prologue, &self.creator).to_string();
+        prologue = pages_pat.replace(&prologue, num_pages).to_string();
+
+        self.write(&prologue)
+    }
+
+    /// Lists the printer features the document asks for, in the
+    /// form of a DSC requirements comment
+    fn requirements(&self) -> String {
+        let mut features: Vec<String> = Vec::new();
+
+        if self.copies > 1 {
+            features.push(format!("numcopies({})", self.copies));
+        }
+
+        if self.collate {
+            features.push("collate".to_string());
+        }
+
+        if self.duplex != Duplex::Simplex {
+            features.push("duplex".to_string());
+        }
 
-        write(&prologue)
+        features.join(" ")
+    }
+
+    /// Returns the document setup section with the print settings,
+    /// or nothing if the printer defaults will do
+    fn setup(&self) -> String {
+        let mut features: Vec<(&str, String)> = Vec::new();
+
+        match self.duplex {
+            Duplex::Simplex => (),
+            Duplex::LongEdge => features.push((
+                "*Duplex DuplexNoTumble",
+                "<< /Duplex true /Tumble false >> setpagedevice".to_string(),
+            )),
+            Duplex::ShortEdge => features.push((
+                "*Duplex DuplexTumble",
+                "<< /Duplex true /Tumble true >> setpagedevice".to_string(),
+            )),
+        }
+
+        if self.collate {
+            features.push((
+                "*Collate True",
+                "<< /Collate true >> setpagedevice".to_string(),
+            ));
+        }
+
+        if features.is_empty() && self.copies <= 1 {
+            return String::new();
+        }
+
+        let mut setup = String::from("%%BeginSetup\n");
+
+        for (feature, ps) in features {
+            setup.push_str(&format!("%%BeginFeature: {}\n{}\n%%EndFeature\n",
+                                    feature, ps));
+        }
+
+        if self.copies > 1 {
+            setup.push_str(&format!("<< /NumCopies {} >> setpagedevice\n",
+                                    self.copies));
+        }
+
+        setup.push_str("%%EndSetup\n");
+        setup
     }
 
     #[doc(hidden)]
     fn start_a_new_page(&mut self, page_no: i32) -> Result<(), Box<dyn Error>> {
-        writeln(&format!("%%Page: {} {}", self.real_page_no, self.real_page_no))?;
-        writeln("page-begin")?;
+        self.writeln(&format!("%%Page: {} {}", self.real_page_no, self.real_page_no))?;
+        self.writeln("page-begin")?;
+
+        let label = if page_no > 0 {
+            self.body_started = true;
+            Some(self.page_numbers.body_label(page_no as usize))
+
+        } else if page_no == 0 && !self.body_started {
+            self.front_page_no += 1;
+            self.page_numbers.front_matter_label(self.front_page_no)
+
+        } else {
+            None
+        };
 
-        if page_no > 0 {
-            let s = format!("{}.", page_no);
-            let x = (PAGE_NO_BEGIN as f32 * CHAR_WIDTH).round() as i32;
-            let y = (HEADER_LINE as f32 * LINE_HEIGHT as f32).round() as i32;
-            let line = Line::from(Segment::from(s));
-            writeln(&format!("{} {} moveto {}", x, y, line.ps()))?;
+        let layout = self.layout;
+        let top = (layout.header_line as f32 * layout.line_height).round() as i32;
+
+        if page_no > 0 && !self.header.is_empty() {
+            let column = self.page_numbers.header_column(&self.header, &layout);
+            let x = (column as f32 * layout.char_width).round() as i32;
+            let line = Line::from(Segment::from(self.header.as_str()));
+            self.writeln(&format!("{} {} moveto {}", x, top, line.ps()))?;
+        }
+
+        if let Some(label) = label {
+            let (column, y) = self.page_numbers.label_position(&label, &layout);
+            let x = (column as f32 * layout.char_width).round() as i32;
+            let y = (y as f32 * layout.line_height).round() as i32;
+            let line = Line::from(Segment::from(label));
+            self.writeln(&format!("{} {} moveto {}", x, y, line.ps()))?;
         }
         
         self.real_page_no += 1;
 
         Ok(())
     }
-}
 
-/// Converts UTF-8 characters to ISO/IEC 8859-15 and writes them to
-/// the standard output
-fn write(text: &str) -> Result<(), Box<dyn Error>> {
-    let chars = ISO_8859_15.encode(text, EncoderTrap::Replace)?;
-    io::stdout().write_all(&chars)?;
-    Ok(())
-}
+    /// Converts UTF-8 characters to ISO/IEC 8859-15 and writes them to
+    /// the output stream
+    fn write(&mut self, text: &str) -> Result<(), Box<dyn Error>> {
+        let chars = ISO_8859_15.encode(text, EncoderTrap::Replace)?;
+        self.out.write_all(&chars)?;
+        Ok(())
+    }
 
-/// Converts UTF-8 characters to ISO/IEC 8859-15 and writes them to
-/// the standard output, appending a newline
-fn writeln(text: &str) -> Result<(), Box<dyn Error>> {
-    let mut chars = ISO_8859_15.encode(text, EncoderTrap::Replace)?;
-    chars.push(b'\n');
-    io::stdout().write_all(&chars)?;
-    Ok(())
+    /// Converts UTF-8 characters to ISO/IEC 8859-15 and writes them to
+    /// the output stream, appending a newline
+    fn writeln(&mut self, text: &str) -> Result<(), Box<dyn Error>> {
+        let mut chars = ISO_8859_15.encode(text, EncoderTrap::Replace)?;
+        chars.extend_from_slice(self.newline.as_str().as_bytes());
+        self.out.write_all(&chars)?;
+        Ok(())
+    }
 }
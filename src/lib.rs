@@ -75,15 +75,17 @@
 //! Output: [`goodrich.pdf`]
 //!
 //! Batyr can also show you its internal element representation using
-//! the <tt>-e</tt> flag.  The internal element representation will
-//! also be printed if the input file contains a fragment of the
-//! screenplay schema:
+//! the <tt>-e</tt> flag: a tree of elements, each with the line of
+//! the source where it begins, its break information and, for text
+//! the reader has counted, its lines.  The internal element
+//! representation will also be printed if the input file contains a
+//! fragment of the screenplay schema:
 //!
 //! ```sh
 //! $ cat minimal.tyr
 //! <br/>
 //! $ batyr minimal.tyr
-//! Br(EmptyElement { attributes: Br, break_info: Disposable(1) })
+//! br, line 1: disposable, 1 line
 //! $
 //! ```
 //!
@@ -99,59 +101,638 @@
 //!
 //! [`goodrich.pdf`]: <http://www.matchlock.com/batyr/goodrich.pdf>
 
-use std::env;
+use std::collections::HashMap;
 use std::error::Error;
-use std::ffi::OsStr;
+use std::ffi::OsString;
 use std::fs;
-use std::path::Path;
-use std::path::PathBuf;
-
-use clap::Parser;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Instant;
 
+use encoding::{DecoderTrap, EncoderTrap, Encoding, EncodingRef};
+use encoding::all::{ISO_8859_1, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use encoding::label::encoding_from_whatwg_label;
 use lazy_static::lazy_static;
+use regex::bytes;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
 
 use crate::document::*;
-use crate::document::reader::Reader;
-use crate::document::formatter::Formatter;
-use crate::document::writer::Writer;
+use crate::document::breaking::BreakPolicy;
+use crate::document::reader::{BreakCache, Reader};
+use crate::document::formatter::{Formatter, FormatterObserver, FormatterOptions,
+                                 Page};
+use crate::document::html::{HtmlMode, HtmlWriter};
+use crate::document::report::{AdrSheet, CharacterReport, Outline};
+use crate::document::validate::validate;
+use crate::document::writer::{Duplex, Newline, PageNumbers, Writer};
+use crate::text::parser::TabPolicy;
 
 pub mod document;
 pub mod text;
 
 // configuration
 
-lazy_static! {
-    #[doc(hidden)]
-    pub static ref PROGRAM_NAME: String = match get_program_name() {
-        Some(name) => name,
-        None => "batyr".to_string(),
-    };
+/// The smallest accepted number of lines per page
+pub const MIN_LINES_PER_PAGE: usize = 20;
 
-    /// Path to prologue.ps
-    static ref PROLOGUE_FILE: PathBuf
-        = PathBuf::from("/home/gene/share/batyr/prologue.ps");
-}
+/// Number of finished pages that may wait for the writer when
+/// streaming
+pub const STREAM_PAGES: usize = 8;
 
-/// Command-line arguments
-#[derive(Parser, Default, Debug)]
-#[clap(author="Gene Yu", version, about="Screenplay Typewriter")]
-pub struct Arguments {
+/// Where to find the XML input
+#[derive(Debug, Clone)]
+pub enum InputSource {
     /// An XML file conforming to the screenplay schema
-    pub input_file: PathBuf,
+    File(PathBuf),
+    /// XML text already in memory
+    Text(String),
+}
 
-    #[clap(short, long)]
-    /// Show the internal element representation instead of the usual output.
-    pub elements: bool,
+impl Default for InputSource {
+    fn default() -> Self {
+        InputSource::Text(String::new())
+    }
+}
+
+/// Character encoding of an input file
+///
+/// # Examples
+///
+/// ```
+/// use batyr::InputEncoding;
+///
+/// let bytes = b"<p>Caf\xe9 \x93noir\x94</p>";
+///
+/// assert!(InputEncoding::Utf8.decode(bytes).is_err());
+/// assert_eq!(InputEncoding::Auto.decode(bytes).unwrap(),
+///            "<p>Caf\u{e9} \u{201c}noir\u{201d}</p>");
+/// assert_eq!(InputEncoding::from("latin-1").decode(b"Caf\xe9").unwrap(),
+///            "Caf\u{e9}");
+/// ```
+///
+/// Left to itself, the reader goes by the byte order mark or the
+/// encoding in the XML declaration, and refuses input that does not
+/// match them:
+///
+/// ```
+/// # use batyr::InputEncoding;
+/// let latin = b"<?xml version='1.0' encoding='iso-8859-15'?><p>\xa4</p>";
+/// assert_eq!(InputEncoding::Auto.decode(latin).unwrap(),
+///            "<?xml version='1.0' encoding='iso-8859-15'?><p>\u{20ac}</p>");
+///
+/// let bom = b"\xef\xbb\xbf<p>Caf\xc3\xa9</p>";
+/// assert_eq!(InputEncoding::Auto.decode(bom).unwrap(), "<p>Caf\u{e9}</p>");
+///
+/// let mismatch = b"\xef\xbb\xbf<?xml version=\"1.0\" encoding=\"windows-1252\"?>";
+/// assert!(InputEncoding::Auto.decode(mismatch).is_err());
+///
+/// let wrong = b"<?xml version=\"1.0\" encoding=\"utf-8\"?><p>Caf\xe9</p>";
+/// assert!(InputEncoding::Auto.decode(wrong).is_err());
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum InputEncoding {
+    /// The encoding given by the byte order mark or the XML
+    /// declaration, if any, or else UTF-8 if the file is valid UTF-8,
+    /// and Windows-1252 otherwise
+    #[default]
+    Auto,
+    /// UTF-8
+    Utf8,
+    /// Windows-1252, as written by older word processors on Windows
+    Windows1252,
+    /// ISO 8859-1
+    Latin1,
+}
+
+impl InputEncoding {
+    /// Decodes the contents of an input file
+    ///
+    /// A byte order mark is dropped.
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, String> {
+        let (encoding, body) = self.detect(bytes)?;
+
+        if encoding.name() == UTF_8.name() {
+            return match std::str::from_utf8(body) {
+                Ok(text) => Ok(text.to_string()),
+                Err(e) => Err(format!(
+                    "input is not valid UTF-8 at byte {}; \
+                     try --input-encoding windows-1252",
+                    bytes.len() - body.len() + e.valid_up_to()
+                )),
+            };
+        }
+
+        encoding.decode(body, DecoderTrap::Strict)
+            .map_err(|_| format!("input is not valid {}", encoding.name()))
+    }
+
+    /// Works out the encoding of an input file
+    ///
+    /// Returns the encoding with the bytes that follow the byte order
+    /// mark, if any.  An encoding other than [`InputEncoding::Auto`]
+    /// overrides the file's own declaration.
+    pub fn detect<'b>(&self, bytes: &'b [u8])
+                      -> Result<(EncodingRef, &'b [u8]), String>
+    {
+        let (bom, body): (Option<EncodingRef>, &[u8]) = match bytes {
+            [0xef, 0xbb, 0xbf, rest @ ..] => (Some(UTF_8), rest),
+            [0xff, 0xfe, rest @ ..] => (Some(UTF_16LE), rest),
+            [0xfe, 0xff, rest @ ..] => (Some(UTF_16BE), rest),
+            _ => (None, bytes),
+        };
+
+        match self {
+            InputEncoding::Utf8 => return Ok((UTF_8, body)),
+            InputEncoding::Windows1252 => return Ok((WINDOWS_1252, bytes)),
+            InputEncoding::Latin1 => return Ok((ISO_8859_1, bytes)),
+            InputEncoding::Auto => (),
+        }
+
+        let declared = match declared_encoding(body) {
+            Some(label) => match encoding_from_whatwg_label(&label) {
+                Some(encoding) => Some((label, encoding)),
+                None => return Err(format!(
+                    "unknown encoding \"{}\" in the XML declaration", label
+                )),
+            },
+            None => None,
+        };
+
+        match (bom, declared) {
+            (Some(bom), Some((label, encoding)))
+                if bom.name() != encoding.name() =>
+            {
+                Err(format!(
+                    "the XML declaration says \"{}\", \
+                     but the byte order mark says {}", label, bom.name()
+                ))
+            },
+            (Some(bom), _) => Ok((bom, body)),
+            (None, Some((_, encoding))) => Ok((encoding, body)),
+            (None, None) => match std::str::from_utf8(body) {
+                Ok(_) => Ok((UTF_8, body)),
+                Err(_) => Ok((WINDOWS_1252, body)),
+            },
+        }
+    }
+}
+
+/// Returns the encoding named in the XML declaration, if any
+fn declared_encoding(bytes: &[u8]) -> Option<String> {
+    lazy_static! {
+        static ref ENCODING: bytes::Regex = bytes::Regex::new(
+            r#"^<\?xml[^>]*?\sencoding\s*=\s*["']([A-Za-z0-9._:-]+)["']"#
+        ).unwrap();
+    }
+
+    ENCODING.captures(bytes)
+        .map(|caps| String::from_utf8_lossy(&caps[1]).to_string())
+}
+
+impl From<&str> for InputEncoding {
+    fn from(s: &str) -> Self {
+        match s {
+            "utf-8" => InputEncoding::Utf8,
+            "windows-1252" => InputEncoding::Windows1252,
+            "latin-1" => InputEncoding::Latin1,
+            _ => InputEncoding::Auto,
+        }
+    }
+}
+
+/// What to produce from a screenplay
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Emit {
+    /// The typeset document in PostScript
+    #[default]
+    Document,
+    /// A plain-text outline of acts and scenes with page numbers
+    Outline,
+    /// An ADR sheet listing every line of dialogue, as CSV
+    Adr,
+    /// Scenes, speeches, lines and words of dialogue for each
+    /// character, as CSV
+    Characters,
+}
+
+impl From<&str> for Emit {
+    fn from(s: &str) -> Self {
+        match s {
+            "outline" => Emit::Outline,
+            "adr" => Emit::Adr,
+            "characters" => Emit::Characters,
+            _ => Emit::Document,
+        }
+    }
 }
 
-impl From<&str> for Arguments {
-    // This method is for testing.
+/// Output format of the typeset document
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::OutputFormat;
+/// let mut xml = String::from("<screenplay><head><title>FOO</title>");
+/// xml.push_str("<authors><fullName>BAR</fullName></authors></head>");
+/// xml.push_str("<body><p>Hello <em>there</em>.</p></body></screenplay>");
+///
+/// let options = batyr::RenderOptions::from_text(xml)
+///     .format(OutputFormat::from("html"));
+/// let root = batyr::read(&options).unwrap();
+/// let mut out = Vec::new();
+/// batyr::write(root, &options, &mut out).unwrap();
+///
+/// let html = String::from_utf8(out).unwrap();
+/// assert!(html.starts_with("<!DOCTYPE html>"));
+/// assert!(html.contains("<title>FOO</title>"));
+/// assert!(html.contains("Hello <u>there</u>."));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// PostScript, ready for the printer
+    #[default]
+    PostScript,
+    /// HTML pages styled to look typewritten, for publishing drafts
+    Html,
+    /// The element hierarchy as JSON instead of pages, for other
+    /// tools; see [`document::json`]
+    Json,
+}
+
+impl From<&str> for OutputFormat {
     fn from(s: &str) -> Self {
-        Self {
-            input_file: PathBuf::from(s),
-            elements: false,
+        match s {
+            "html" => OutputFormat::Html,
+            "json" => OutputFormat::Json,
+            _ => OutputFormat::PostScript,
+        }
+    }
+}
+
+/// Library configuration for reading and rendering a screenplay
+///
+/// # Examples
+///
+/// ```
+/// use batyr::RenderOptions;
+///
+/// let options = RenderOptions::from_file("goodrich.tyr")
+///     .allow_missing_head(true)
+///     .lines_per_page(54);
+///
+/// assert!(options.allow_missing_head);
+/// assert_eq!(options.lines_per_page, Some(54));
+/// assert!(!options.elements);
+/// ```
+#[derive(Default, Debug, Clone)]
+pub struct RenderOptions {
+    /// Source of the XML input
+    pub input: InputSource,
+    /// Character encoding of an input file
+    pub input_encoding: InputEncoding,
+    /// Show the internal element representation instead of the usual
+    /// output
+    pub elements: bool,
+    /// What to produce
+    pub emit: Emit,
+    /// Output format of the typeset document
+    pub format: OutputFormat,
+    /// How HTML output sets out the text of each page
+    pub html_mode: HtmlMode,
+    /// Typeset the document even if the title or authors are missing
+    pub allow_missing_head: bool,
+    /// Number of lines on each page of the body, if not the default
+    pub lines_per_page: Option<usize>,
+    /// Fewest blank lines above the text of an end element, if not
+    /// the default
+    pub end_gap: Option<usize>,
+    /// Rules for breaking stage direction and dialogue across pages,
+    /// where the screenplay does not set them
+    pub break_policy: BreakPolicy,
+    /// Program name recorded in the document header, if not "batyr"
+    pub creator: Option<String>,
+    /// PostScript prologue to use instead of the built-in one
+    pub prologue: Option<String>,
+    /// Line ending for the output
+    pub newline: Newline,
+    /// Warn about scenes longer than this many pages
+    pub max_scene_pages: Option<usize>,
+    /// Check the source against the screenplay schema before reading
+    /// it, and fail on any problem found
+    pub validate: bool,
+    /// Fail if any line of output runs past the right margin
+    pub verify: bool,
+    /// Warn about likely mistakes in the document, such as misspelled
+    /// transitions
+    pub lint: bool,
+    /// Report at most this many validation, lint or verification
+    /// problems, with a count of the rest
+    pub max_errors: Option<usize>,
+    /// Transitions to accept in addition to the built-in list
+    pub transitions: Vec<String>,
+    /// Print a content hash on the title page and in the document
+    /// header comments
+    pub stamp_hash: bool,
+    /// Variant to typeset from every alternate group, overriding the
+    /// choices in the document
+    pub alternate: Option<usize>,
+    /// Draft target for conditional content, such as "network"
+    pub target: Option<String>,
+    /// Give scenes whose numbers repeat or go back an addition letter
+    /// instead
+    pub resolve_scene_numbers: bool,
+    /// What to do with tab characters in text
+    pub tabs: TabPolicy,
+    /// Number of copies to ask the printer for, if more than one
+    pub copies: Option<usize>,
+    /// Ask the printer to collate the copies
+    pub collate: bool,
+    /// Ask the printer to print on both sides of the paper
+    pub duplex: Duplex,
+    /// How page numbers are printed
+    pub page_numbers: PageNumbers,
+    /// Page geometry: margins, tab stops and the lines of the page
+    pub layout: Layout,
+    /// Write each page as soon as it is formatted, instead of holding
+    /// the whole document in memory
+    pub stream: bool,
+    /// Compress the typeset output with gzip; element dumps are
+    /// written as they are
+    pub compress: bool,
+    /// Find line breaks on all cores once the document has been read;
+    /// ignored without the <tt>parallel</tt> feature
+    pub parallel: bool,
+    /// Report the time spent in each stage on standard error
+    pub timing: bool,
+    /// Write a copy of the source with page numbers on slugs and cues
+    /// to this file
+    pub annotate_pages: Option<PathBuf>,
+    /// Write the page and line where each scene and element landed to
+    /// this file as JSON
+    pub page_map: Option<PathBuf>,
+    /// Break points kept between readings, so that text which has not
+    /// changed is not broken into lines again
+    pub cache: Option<Arc<BreakCache>>,
+}
+
+impl RenderOptions {
+    /// Construct options for reading the given file
+    pub fn from_file<P: Into<PathBuf>>(path: P) -> Self {
+        RenderOptions {
+            input: InputSource::File(path.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Construct options for reading XML text already in memory
+    pub fn from_text<S: Into<String>>(text: S) -> Self {
+        RenderOptions {
+            input: InputSource::Text(text.into()),
+            ..Default::default()
         }
     }
+
+    /// Set the character encoding of an input file
+    pub fn input_encoding(mut self, encoding: InputEncoding) -> Self {
+        self.input_encoding = encoding;
+        self
+    }
+
+    /// Show the internal element representation instead of the usual
+    /// output
+    pub fn elements(mut self, value: bool) -> Self {
+        self.elements = value;
+        self
+    }
+
+    /// Set what to produce
+    pub fn emit(mut self, emit: Emit) -> Self {
+        self.emit = emit;
+        self
+    }
+
+    /// Set the output format of the typeset document
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set how HTML output sets out the text of each page
+    pub fn html_mode(mut self, mode: HtmlMode) -> Self {
+        self.html_mode = mode;
+        self
+    }
+
+    /// Typeset the document even if the title or authors are missing
+    pub fn allow_missing_head(mut self, value: bool) -> Self {
+        self.allow_missing_head = value;
+        self
+    }
+
+    /// Set the number of lines on each page of the body
+    pub fn lines_per_page(mut self, n: usize) -> Self {
+        self.lines_per_page = Some(n);
+        self
+    }
+
+    /// Set the fewest blank lines above the text of an end element,
+    /// such as "END OF ACT ONE"
+    pub fn end_gap(mut self, n: usize) -> Self {
+        self.end_gap = Some(n);
+        self
+    }
+
+    /// Set the rules for breaking stage direction and dialogue across
+    /// pages
+    pub fn break_policy(mut self, policy: BreakPolicy) -> Self {
+        self.break_policy = policy;
+        self
+    }
+
+    /// Set the program name recorded in the document header
+    pub fn creator<S: Into<String>>(mut self, creator: S) -> Self {
+        self.creator = Some(creator.into());
+        self
+    }
+
+    /// Replace the built-in PostScript prologue
+    pub fn prologue<S: Into<String>>(mut self, prologue: S) -> Self {
+        self.prologue = Some(prologue.into());
+        self
+    }
+
+    /// Set the line ending for the output
+    pub fn newline(mut self, newline: Newline) -> Self {
+        self.newline = newline;
+        self
+    }
+
+    /// Warn about scenes longer than the given number of pages
+    pub fn max_scene_pages(mut self, n: usize) -> Self {
+        self.max_scene_pages = Some(n);
+        self
+    }
+
+    /// Check the source against the screenplay schema before reading
+    /// it
+    pub fn validate(mut self, value: bool) -> Self {
+        self.validate = value;
+        self
+    }
+
+    /// Fail if any line of output runs past the right margin
+    pub fn verify(mut self, value: bool) -> Self {
+        self.verify = value;
+        self
+    }
+
+    /// Warn about likely mistakes in the document
+    pub fn lint(mut self, value: bool) -> Self {
+        self.lint = value;
+        self
+    }
+
+    /// Report at most the given number of validation, lint or
+    /// verification problems
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let xml = "<body><para/><note/><slug>INT. HALL</slug><scene/></body>";
+    /// let options = batyr::RenderOptions::from_text(xml)
+    ///     .validate(true)
+    ///     .max_errors(2);
+    ///
+    /// assert_eq!(batyr::read(&options).unwrap_err().to_string(),
+    ///            "unknown element <para> on line 1\n\
+    ///             <note> is not allowed in <body> on line 1\n\
+    ///             and 1 more problem");
+    /// ```
+    pub fn max_errors(mut self, n: usize) -> Self {
+        self.max_errors = Some(n);
+        self
+    }
+
+    /// Print a content hash on the title page and in the document
+    /// header comments
+    pub fn stamp_hash(mut self, value: bool) -> Self {
+        self.stamp_hash = value;
+        self
+    }
+
+    /// Give scenes whose numbers repeat or go back the highest number
+    /// so far with an addition letter, as in 12A
+    pub fn resolve_scene_numbers(mut self, value: bool) -> Self {
+        self.resolve_scene_numbers = value;
+        self
+    }
+
+    /// Typeset the given variant from every alternate group,
+    /// counting from 1
+    pub fn alternate(mut self, n: usize) -> Self {
+        self.alternate = Some(n);
+        self
+    }
+
+    /// Compress the output with gzip
+    pub fn compress(mut self, value: bool) -> Self {
+        self.compress = value;
+        self
+    }
+
+    /// Write each page as soon as it is formatted
+    pub fn stream(mut self, value: bool) -> Self {
+        self.stream = value;
+        self
+    }
+
+    /// Find line breaks on all cores once the document has been read
+    pub fn parallel(mut self, value: bool) -> Self {
+        self.parallel = value;
+        self
+    }
+
+    /// Report the time spent in each stage on standard error
+    pub fn timing(mut self, value: bool) -> Self {
+        self.timing = value;
+        self
+    }
+
+    /// Write a copy of the source with page numbers on slugs and cues
+    /// to the given file
+    pub fn annotate_pages<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.annotate_pages = Some(path.into());
+        self
+    }
+
+    /// Write the page and line where each scene and element landed to
+    /// the given file as JSON
+    pub fn page_map<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.page_map = Some(path.into());
+        self
+    }
+
+    /// Keep break points in the given cache, reusing them for text
+    /// that has not changed since the last reading
+    pub fn cache(mut self, cache: Arc<BreakCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Read only the conditional content meant for the given target
+    pub fn target<S: Into<String>>(mut self, target: S) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Set what to do with tab characters in text
+    pub fn tabs(mut self, tabs: TabPolicy) -> Self {
+        self.tabs = tabs;
+        self
+    }
+
+    /// Ask the printer for the given number of copies
+    pub fn copies(mut self, n: usize) -> Self {
+        self.copies = Some(n);
+        self
+    }
+
+    /// Ask the printer to collate the copies
+    pub fn collate(mut self, value: bool) -> Self {
+        self.collate = value;
+        self
+    }
+
+    /// Ask the printer to print on both sides of the paper
+    pub fn duplex(mut self, duplex: Duplex) -> Self {
+        self.duplex = duplex;
+        self
+    }
+
+    /// Set how page numbers are printed
+    pub fn page_numbers(mut self, numbers: PageNumbers) -> Self {
+        self.page_numbers = numbers;
+        self
+    }
+
+    /// Set the page geometry
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Accept these transitions in addition to the built-in list
+    pub fn transitions<I, S>(mut self, transitions: I) -> Self
+        where I: IntoIterator<Item = S>, S: Into<String>
+    {
+        self.transitions.extend(transitions.into_iter().map(Into::into));
+        self
+    }
 }
 
 /// Reads an XML input string and construct an element hierarchy from
@@ -159,52 +740,895 @@ impl From<&str> for Arguments {
 ///
 /// # Examples
 ///
-/// ```rust,no_run
-/// let args = batyr::Arguments::from("dummy.tyr");
-/// let root = batyr::read(&args).unwrap();
 /// ```
-pub fn read(args: &Arguments) -> Result<ElementType, Box<dyn Error>> {
-    let xml_string = fs::read_to_string(&args.input_file).unwrap();
-    let reader = Reader::new(&xml_string);
-    reader.run().ok_or("No elements!".into())
+/// let options = batyr::RenderOptions::from_text("<br/>");
+/// let root = batyr::read(&options).unwrap();
+/// ```
+pub fn read(options: &RenderOptions) -> Result<ElementType, Box<dyn Error>> {
+    let start = Instant::now();
+
+    let xml_string = read_source(options)?;
+    let read_time = start.elapsed();
+
+    if options.validate {
+        let problems = validate(&xml_string);
+
+        if !problems.is_empty() {
+            return Err(limit_problems(problems, options.max_errors)
+                       .join("\n").into());
+        }
+    }
+
+    let (root, timing) = new_reader(&xml_string, options).try_run_timed()?;
+    let mut root = root.ok_or("No elements!")?;
+
+    // References to scenes need the pages of a first layout.
+    if has_references(&root) {
+        let pages = scene_pages(&root, page_options(options)?);
+
+        root = new_reader(&xml_string, options)
+            .scene_pages(pages)
+            .try_run()?
+            .ok_or("No elements!")?;
+    }
+
+    if options.timing {
+        let (elements, tokens) = count_elements(&root);
+
+        eprintln!("timing: read {:.1?}", read_time);
+        eprintln!("timing: parse {:.1?} ({} elements, {} tokens)",
+                  timing.parse, elements, tokens);
+        eprintln!("timing: breaks {:.1?}", timing.breaks);
+    }
+
+    Ok(root)
+}
+
+/// Sets up a reader for the source as the options ask
+fn new_reader<'a>(xml_string: &'a str, options: &RenderOptions) -> Reader<'a> {
+    let mut reader = Reader::new(xml_string);
+
+    if let Some(n) = options.alternate {
+        reader = reader.choose_alternate(n);
+    }
+
+    if let Some(target) = &options.target {
+        reader = reader.target(target.as_str());
+    }
+
+    #[cfg(feature = "parallel")]
+    if options.parallel {
+        reader = reader.parallel(true);
+    }
+
+    if let Some(cache) = &options.cache {
+        reader = reader.cache(cache.clone());
+    }
+
+    reader.tabs(options.tabs)
+        .layout(options.layout)
+        .resolve_scene_numbers(options.resolve_scene_numbers)
+}
+
+/// Tells whether a screenplay has <tt>&lt;ref&gt;</tt> elements to
+/// resolve
+fn has_references(root: &ElementType) -> bool {
+    match root {
+        ElementType::Screenplay(elem) => !elem.references().is_empty(),
+        _ => false,
+    }
 }
 
-/// Writes an element hierarchy to the standard output in Postscript
+/// Lays a screenplay out once to find the first page of each scene,
+/// by scene number
+fn scene_pages(root: &ElementType, formatter_options: FormatterOptions)
+               -> HashMap<String, i32>
+{
+    let mut formatter = Formatter::with_options(formatter_options);
+    formatter.run(root.clone());
+
+    formatter.scenes.iter()
+        .map(|scene| (scene.number.clone(), scene.first_page))
+        .collect()
+}
+
+/// Returns the formatter options that decide where the pages break
+fn page_options(options: &RenderOptions)
+                -> Result<FormatterOptions, Box<dyn Error>>
+{
+    let mut formatter_options = FormatterOptions {
+        break_policy: options.break_policy,
+        layout: options.layout,
+        ..FormatterOptions::default()
+    };
+
+    if let Some(n) = options.lines_per_page {
+        let top_line = options.layout.top_line;
+
+        if !(MIN_LINES_PER_PAGE..=top_line).contains(&n) {
+            return Err(format!(
+                "lines per page must be between {} and {}",
+                MIN_LINES_PER_PAGE, top_line
+            ).into());
+        }
+
+        formatter_options.layout = options.layout.with_lines_per_page(n);
+    }
+
+    if let Some(n) = options.end_gap {
+        formatter_options.end_gap = n;
+    }
+
+    Ok(formatter_options)
+}
+
+/// Keeps the first problems up to the limit, if any, and counts the
+/// rest in a last line
+fn limit_problems(mut problems: Vec<String>, max: Option<usize>)
+                  -> Vec<String>
+{
+    if let Some(n) = max.filter(|n| *n < problems.len()) {
+        let rest = problems.len() - n;
+        problems.truncate(n);
+        problems.push(format!("and {} more problem{}", rest,
+                              if rest == 1 { "" } else { "s" }));
+    }
+
+    problems
+}
+
+/// Reads the XML input, decoding a file in the given encoding
+fn read_source(options: &RenderOptions) -> Result<String, Box<dyn Error>> {
+    match &options.input {
+        InputSource::File(path) => {
+            let bytes = fs::read(path)?;
+            Ok(options.input_encoding.decode(&bytes)?)
+        },
+        InputSource::Text(text) => Ok(text.clone()),
+    }
+}
+
+/// Counts the elements in a hierarchy, and the tokens in their text
+fn count_elements(root: &ElementType) -> (usize, usize) {
+    root.iter_with_depth().fold((0, 0), |(elements, tokens), (_, elem)| {
+        (elements + 1, tokens + elem.tokens().map_or(0, |tokens| tokens.len()))
+    })
+}
+
+/// Computes a content hash of an element hierarchy
+///
+/// Returns the SHA-256 digest of the canonical form of the elements,
+/// from [`ElementType::canonical`], in hexadecimal.  Drafts that read
+/// to the same elements have the same hash, however their XML is laid
+/// out, and the hash stays the same from one version of Batyr to the
+/// next.
+///
+/// # Examples
+///
+/// ```
+/// let read = |xml: &str| {
+///     batyr::read(&batyr::RenderOptions::from_text(xml)).unwrap()
+/// };
+///
+/// let a = batyr::content_hash(&read("<p>Hello,   world.</p>"));
+/// let b = batyr::content_hash(&read("<p>Hello,\n  world.</p>"));
+/// let c = batyr::content_hash(&read("<p>Hello, world!</p>"));
+///
+/// assert_eq!(a.len(), 64);
+/// assert_eq!(a, b);
+/// assert_ne!(a, c);
+/// ```
+pub fn content_hash(root: &ElementType) -> String {
+    let digest = Sha256::digest(root.canonical().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A screenplay read into memory
+///
+/// [`parse`] makes one from XML text, and [`Document::paginate`] lays
+/// it out into pages, which can then be written one at a time with
+/// [`Page::render`].  None of these stages print anything or touch
+/// the file system, so they suit programs that embed Batyr, such as
+/// an editor with a live preview.  Warnings and hints are left to the
+/// caller, through [`Document::root`].
+///
+/// # Examples
+///
+/// ```
+/// use batyr::OutputFormat;
+/// use batyr::document::Layout;
+///
+/// let mut xml = String::from("<screenplay><head><title>FOO</title>");
+/// xml.push_str("<authors><fullName>BAR</fullName></authors></head>");
+/// xml.push_str("<body><p>Hello <em>there</em>.</p></body></screenplay>");
+///
+/// let document = batyr::parse(&xml).unwrap();
+/// let pages = document.paginate(&Layout::default()).unwrap();
+/// assert_eq!(pages.len(), 2); // title page and one page of body
+///
+/// let mut out = Vec::new();
+/// pages[1].render(&mut out, OutputFormat::Html).unwrap();
+///
+/// let html = String::from_utf8(out).unwrap();
+/// assert!(html.contains("Hello <u>there</u>."));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Document {
+    source: String,
+    layout: Layout,
+    root: ElementType,
+}
+
+/// Reads a screenplay from XML text
+///
+/// Any element of the screenplay schema may be the root, as with
+/// [`read`], though only a screenplay has pages.  A screenplay with
+/// <tt>&lt;ref&gt;</tt> elements is laid out once to find the pages
+/// of the scenes it refers to, then read again.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::document::ElementType;
+/// let xml = "<screenplay><head/><body>\
+///            <p>See <ref scene=\"2\"/>.</p>\
+///            <slug>INT. HALL - DAY</slug>\
+///            <slug>INT. STAIRS - DAY</slug>\
+///            </body></screenplay>";
+/// let document = batyr::parse(xml).unwrap();
+///
+/// let ElementType::Screenplay(root) = document.root() else {
+///     panic!();
+/// };
+///
+/// assert_eq!(root.references(), [("2".to_string(), Some(1))]);
+/// ```
+pub fn parse(source: &str) -> Result<Document, Box<dyn Error>> {
+    let layout = Layout::default();
+
+    Ok(Document {
+        source: source.to_string(),
+        layout,
+        root: read_with_layout(source, layout)?,
+    })
+}
+
+/// Reads XML text with the line lengths of the given page geometry
+fn read_with_layout(source: &str, layout: Layout)
+                    -> Result<ElementType, Box<dyn Error>>
+{
+    let root = Reader::new(source).layout(layout).try_run()?;
+    let root = root.ok_or("No elements!")?;
+
+    if !has_references(&root) {
+        return Ok(root);
+    }
+
+    let pages = scene_pages(&root, FormatterOptions {
+        layout,
+        ..FormatterOptions::default()
+    });
+
+    let root = Reader::new(source).layout(layout).scene_pages(pages).try_run()?;
+    Ok(root.ok_or("No elements!")?)
+}
+
+impl Document {
+    /// Returns the element hierarchy
+    pub fn root(&self) -> &ElementType {
+        &self.root
+    }
+
+    /// Lays the screenplay out into pages
+    ///
+    /// The title page and any front matter come first, numbered 0 or
+    /// less; see [`Page::number`].  The line breaks depend on the
+    /// page geometry, so for any layout other than the default the
+    /// source is read again, and an error reading it is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::document::Layout;
+    /// let xml = "<screenplay><head/><body><cue>AMY</cue>\
+    ///            <d>Snow.</d></body></screenplay>";
+    /// let document = batyr::parse(xml).unwrap();
+    ///
+    /// let layout = Layout { cue_begin: 30, ..Layout::default() };
+    /// let pages = document.paginate(&layout).unwrap();
+    /// let amy = pages.last().unwrap().lines.iter()
+    ///     .find_map(|row| match row {
+    ///         batyr::document::formatter::Row::Text(line) => Some(line),
+    ///         _ => None,
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert_eq!((amy.column, amy.text().as_str()), (30, "AMY"));
+    /// ```
+    pub fn paginate(&self, layout: &Layout) -> Result<Vec<Page>, Box<dyn Error>> {
+        let root = match *layout == self.layout {
+            true => self.root.clone(),
+            false => read_with_layout(&self.source, *layout)?,
+        };
+
+        let mut formatter = Formatter::with_options(FormatterOptions {
+            layout: *layout,
+            ..FormatterOptions::default()
+        });
+
+        formatter.run(root);
+        Ok(formatter.body)
+    }
+}
+
+impl Page {
+    /// Writes the page as a document of its own, in PostScript or
+    /// HTML
+    ///
+    /// The page is placed with the default page geometry and without
+    /// a running header.  Pages laid out for another geometry go
+    /// through [`Writer`] or [`HtmlWriter`], whose <tt>layout</tt>
+    /// builders take the same [`Layout`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::OutputFormat;
+    /// # use batyr::document::formatter::Page;
+    /// let page = Page {
+    ///     number: 7,
+    ///     height: 55,
+    ///     lines: Vec::new(),
+    ///     footer: Vec::new(),
+    /// };
+    ///
+    /// let mut out = Vec::new();
+    /// page.render(&mut out, OutputFormat::PostScript).unwrap();
+    ///
+    /// let ps = String::from_utf8(out).unwrap();
+    /// assert!(ps.contains("%%Pages: 1"));
+    /// assert!(ps.contains("(7.)"));
+    /// ```
+    pub fn render<W: Write>(&self, out: &mut W, backend: OutputFormat)
+                            -> Result<(), Box<dyn Error>>
+    {
+        match backend {
+            OutputFormat::PostScript => {
+                Writer::new(out, "").run(vec![self.clone()])
+            },
+            OutputFormat::Html => {
+                HtmlWriter::new(out, "").run(vec![self.clone()])
+            },
+            OutputFormat::Json => {
+                Err("a page is written as PostScript or HTML".into())
+            },
+        }
+    }
+}
+
+/// Writes an element hierarchy to an output stream in Postscript, or
+/// in HTML if the options ask for it
+///
+/// If the elements option is set, or if the root is not a screenplay,
+/// the internal element representation is written instead.  With the
+/// JSON format the element hierarchy is written as JSON, whatever the
+/// root, and never compressed.
 ///
 /// # Examples
 ///
-/// ```rust,no_run
-/// # let args = batyr::Arguments::from("dummy.tyr");
-/// let root = batyr::read(&args).unwrap();
-/// batyr::write(root, &args);
 /// ```
-pub fn write(root: ElementType, args: &Arguments)
-             -> Result<(), Box<dyn Error>>
+/// let mut xml = String::from("<screenplay><head><title>FOO</title>");
+/// xml.push_str("<authors><fullName>BAR</fullName></authors></head>");
+/// xml.push_str("<body><p>Hello.</p></body></screenplay>");
+///
+/// let options = batyr::RenderOptions::from_text(xml);
+/// let root = batyr::read(&options).unwrap();
+/// let mut out = Vec::new();
+/// batyr::write(root, &options, &mut out).unwrap();
+///
+/// let ps = String::from_utf8(out).unwrap();
+/// assert!(ps.starts_with("%!PS"));
+/// assert!(ps.contains("(Hello.)"));
+///
+/// let options = batyr::RenderOptions::from_text("<p>Snow.</p>")
+///     .format(batyr::OutputFormat::Json)
+///     .compress(true);
+/// let root = batyr::read(&options).unwrap();
+/// let mut out = Vec::new();
+/// batyr::write(root, &options, &mut out).unwrap();
+///
+/// assert!(String::from_utf8(out).unwrap().starts_with("{"));
+/// ```
+pub fn write<W: Write>(root: ElementType, options: &RenderOptions, out: &mut W)
+                       -> Result<(), Box<dyn Error>>
+{
+    write_with_observers(root, options, out, Vec::new())
+}
+
+/// Writes an element hierarchy like [`write`], calling the observers
+/// back as the pages are laid out
+///
+/// # Examples
+///
+/// ```
+/// # use std::io::Read;
+/// # use flate2::read::GzDecoder;
+/// let mut xml = String::from("<screenplay><head><title>FOO</title>");
+/// xml.push_str("<authors><fullName>BAR</fullName></authors></head>");
+/// xml.push_str("<body><p>Hello.</p></body></screenplay>");
+///
+/// let options = batyr::RenderOptions::from_text(xml).compress(true);
+/// let root = batyr::read(&options).unwrap();
+/// let mut out = Vec::new();
+/// batyr::write_with_observers(root, &options, &mut out, Vec::new()).unwrap();
+///
+/// let mut ps = String::new();
+/// GzDecoder::new(&out[..]).read_to_string(&mut ps).unwrap();
+/// assert!(ps.starts_with("%!PS"));
+///
+/// // A fragment is dumped as it is.
+/// let options = batyr::RenderOptions::from_text("<p>Snow.</p>").compress(true);
+/// let root = batyr::read(&options).unwrap();
+/// let mut out = Vec::new();
+/// batyr::write_with_observers(root, &options, &mut out, Vec::new()).unwrap();
+///
+/// assert!(String::from_utf8(out).unwrap().starts_with("p, line 1"));
+/// ```
+pub fn write_with_observers<W: Write>(root: ElementType,
+                                      options: &RenderOptions,
+                                      out: &mut W,
+                                      observers: Vec<Box<dyn FormatterObserver>>)
+                                      -> Result<(), Box<dyn Error>>
+{
+    // Element dumps are for reading on screen, and JSON for other
+    // tools.
+    let typeset = !options.elements
+        && options.format != OutputFormat::Json
+        && matches!(root, ElementType::Screenplay(_));
+
+    if options.compress && typeset {
+        let mut encoder = GzEncoder::new(out, Compression::default());
+        render(root, options, &mut encoder, observers)?;
+        encoder.finish()?;
+        Ok(())
+    } else {
+        render(root, options, out, observers)
+    }
+}
+
+/// Formats and writes the document, or whatever else the options ask
+/// for
+fn render<W: Write>(root: ElementType, options: &RenderOptions, out: &mut W,
+                    observers: Vec<Box<dyn FormatterObserver>>)
+                    -> Result<(), Box<dyn Error>>
 {
+    if options.format == OutputFormat::Json {
+        return write_json(&root, out);
+    }
+
     match root {
-        ElementType::Screenplay(_) => {
-            if args.elements {
-                eprintln!("{:?}", &root);
+        ElementType::Screenplay(ref elem) => {
+            if options.elements {
+                write!(out, "{}", root.dump())?;
             } else {
-                let mut formatter = Formatter::new();
+                if !options.allow_missing_head {
+                    let hints = elem.check_head();
+
+                    if !hints.is_empty() {
+                        return Err(hints.join("; ").into());
+                    }
+                }
+
+                if let Some(hint) = elem.check_body() {
+                    return Err(hint.into());
+                }
+
+                for name in elem.unresolved_variables() {
+                    eprintln!("warning: undefined variable {{{}}}", name);
+                }
+
+                for (scene, _) in elem.references().iter()
+                    .filter(|(_, page)| page.is_none())
+                {
+                    eprintln!("warning: reference to unknown scene {}", scene);
+                }
+
+                for warning in elem.scene_number_warnings() {
+                    eprintln!("warning: {}", warning);
+                }
+
+                for (line, ch) in elem.control_characters() {
+                    eprintln!("warning: removed control character U+{:04X} \
+                               from the text on line {}", *ch as u32, line);
+                }
+
+                if options.lint {
+                    let warnings = elem.check_lint(&options.transitions);
+
+                    for warning in limit_problems(warnings, options.max_errors) {
+                        eprintln!("warning: {}", warning);
+                    }
+                }
+
+                // The character report does not depend on pagination.
+                if options.emit == Emit::Characters {
+                    write!(out, "{}", CharacterReport::from(elem).to_csv())?;
+                    return Ok(());
+                }
+
+                let mut formatter_options = page_options(options)?;
+
+                let hash = if options.stamp_hash {
+                    Some(content_hash(&root))
+                } else {
+                    None
+                };
+
+                if let Some(hash) = &hash {
+                    formatter_options.stamp = Some(format!("Draft {}", &hash[..16]));
+                }
+
+                let mut outline = match options.emit {
+                    Emit::Outline => Some(Outline::from(elem)),
+                    _ => None,
+                };
+
+                let mut adr = match options.emit {
+                    Emit::Adr => Some(AdrSheet::from(elem)),
+                    _ => None,
+                };
+
+                let mut formatter = Formatter::with_options(formatter_options);
+
+                for observer in observers {
+                    formatter.add_observer(observer);
+                }
+
+                if options.stream && options.emit == Emit::Document {
+                    if options.verify {
+                        return Err("cannot verify the output while streaming".into());
+                    }
+
+                    let (sender, receiver) = mpsc::sync_channel(STREAM_PAGES);
+                    formatter.stream(sender);
+
+                    let start = Instant::now();
+
+                    let (result, formatter) = thread::scope(|scope| {
+                        let handle = scope.spawn(move || {
+                            formatter.run(root);
+                            formatter
+                        });
+
+                        let result = match options.format {
+                            OutputFormat::PostScript => {
+                                new_writer(out, options, "", "", &hash)
+                                    .stream(receiver)
+                            },
+                            OutputFormat::Html => {
+                                new_html_writer(out, options, "", "", &hash)
+                                    .stream(receiver)
+                            },
+                            OutputFormat::Json => unreachable!(), // see above
+                        };
+
+                        (result, handle.join())
+                    });
+
+                    result?;
+
+                    if options.timing {
+                        eprintln!("timing: format and write {:.1?}, streamed",
+                                  start.elapsed());
+                    }
+
+                    let formatter = formatter.map_err(|_| "formatter failed")?;
+                    warn_about_long_scenes(&formatter, options);
+                    write_annotated_source(&formatter, options)?;
+                    write_page_map(&formatter, options)?;
+                    return Ok(());
+                }
+
+                let start = Instant::now();
                 formatter.run(root);
 
-                let mut writer = Writer::new(&formatter.title);
-                writer.run(formatter.body)?;
+                if options.timing {
+                    eprintln!("timing: format {:.1?} ({} pages)",
+                              start.elapsed(), formatter.body.len());
+                }
+
+                if options.verify {
+                    let problems = formatter.verify();
+
+                    if !problems.is_empty() {
+                        return Err(limit_problems(problems, options.max_errors)
+                                   .join("\n").into());
+                    }
+                }
+
+                warn_about_long_scenes(&formatter, options);
+                write_annotated_source(&formatter, options)?;
+                write_page_map(&formatter, options)?;
+
+                if let Some(outline) = &mut outline {
+                    outline.set_pages(&formatter);
+                    write!(out, "{}", outline)?;
+                    return Ok(());
+                }
+
+                if let Some(adr) = &mut adr {
+                    adr.set_pages(&formatter);
+                    write!(out, "{}", adr.to_csv())?;
+                    return Ok(());
+                }
+
+                let start = Instant::now();
+
+                match options.format {
+                    OutputFormat::PostScript => new_writer(
+                        out, options, &formatter.title, &formatter.header, &hash
+                    ).run(formatter.body)?,
+                    OutputFormat::Html => new_html_writer(
+                        out, options, &formatter.title, &formatter.header, &hash
+                    ).contents(&formatter.acts, &formatter.scenes, &formatter.elements)
+                        .run(formatter.body)?,
+                    OutputFormat::Json => unreachable!(), // see above
+                }
+
+                if options.timing {
+                    eprintln!("timing: write {:.1?}", start.elapsed());
+                }
             }
         },
-        _ => eprintln!("{:?}", &root),
+        _ => write!(out, "{}", root.dump())?,
     }
 
     Ok(())
 }
 
-#[doc(hidden)]
-fn get_program_name() -> Option<String> {
-    env::current_exe().ok()
-        .as_ref()
-        .map(Path::new)
-        .and_then(Path::file_name)
-        .and_then(OsStr::to_str)
-        .map(String::from)
+/// Writes the element hierarchy as JSON
+fn write_json<W: Write>(root: &ElementType, out: &mut W)
+                        -> Result<(), Box<dyn Error>>
+{
+    #[cfg(feature = "json")]
+    {
+        writeln!(out, "{}", document::json::to_json(root))?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "json"))]
+    {
+        let _ = (root, out);
+        Err("batyr was built without the json feature".into())
+    }
+}
+
+/// Sets up a document writer according to the options
+fn new_writer<W: Write>(out: W, options: &RenderOptions, title: &str,
+                        header: &str, hash: &Option<String>) -> Writer<W>
+{
+    let mut writer = Writer::new(out, title)
+        .header(header)
+        .newline(options.newline)
+        .collate(options.collate)
+        .duplex(options.duplex)
+        .page_numbers(options.page_numbers.clone())
+        .layout(options.layout);
+
+    if let Some(n) = options.copies {
+        writer = writer.copies(n);
+    }
+
+    if let Some(creator) = &options.creator {
+        writer = writer.creator(creator);
+    }
+
+    if let Some(prologue) = &options.prologue {
+        writer = writer.prologue(prologue);
+    }
+
+    if let Some(hash) = hash {
+        writer = writer.stamp(hash);
+    }
+
+    writer
+}
+
+/// Sets up an HTML writer according to the options
+fn new_html_writer<W: Write>(out: W, options: &RenderOptions, title: &str,
+                             header: &str, hash: &Option<String>)
+                             -> HtmlWriter<W>
+{
+    let mut writer = HtmlWriter::new(out, title)
+        .header(header)
+        .newline(options.newline)
+        .page_numbers(options.page_numbers.clone())
+        .layout(options.layout)
+        .mode(options.html_mode);
+
+    if let Some(creator) = &options.creator {
+        writer = writer.creator(creator);
+    }
+
+    if let Some(hash) = hash {
+        writer = writer.stamp(hash);
+    }
+
+    writer
+}
+
+/// Prints a warning for each scene longer than the limit, if any
+fn warn_about_long_scenes(formatter: &Formatter, options: &RenderOptions) {
+    if let Some(max_pages) = options.max_scene_pages {
+        for scene in formatter.scenes.iter()
+            .filter(|scene| scene.page_count() > max_pages)
+        {
+            eprintln!(
+                "warning: scene {} ({}) runs {} pages, \
+                 from page {} to {}, more than {}",
+                scene.number, scene.heading, scene.page_count(),
+                scene.first_page, scene.last_page, max_pages
+            );
+        }
+    }
+}
+
+/// Writes the source with page numbers on slugs and cues, if asked
+fn write_annotated_source(formatter: &Formatter, options: &RenderOptions)
+                          -> Result<(), Box<dyn Error>>
+{
+    if let Some(path) = &options.annotate_pages {
+        let source = read_source(options)?;
+        let annotated = annotate::annotate_pages(
+            &source, options.target.as_deref(), formatter
+        );
+
+        // The copy keeps the encoding and byte order mark of the
+        // original, so that its XML declaration still holds.
+        let contents = match &options.input {
+            InputSource::File(input) => {
+                let bytes = fs::read(input)?;
+                let (encoding, body) = options.input_encoding.detect(&bytes)?;
+                let mut contents = bytes[..bytes.len() - body.len()].to_vec();
+
+                contents.extend(
+                    encoding.encode(&annotated, EncoderTrap::Strict)?
+                );
+                contents
+            },
+            InputSource::Text(_) => annotated.into_bytes(),
+        };
+
+        write_atomic(path, contents)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the page map if the options ask for one
+fn write_page_map(formatter: &Formatter, options: &RenderOptions)
+                  -> Result<(), Box<dyn Error>>
+{
+    if let Some(path) = &options.page_map {
+        #[cfg(feature = "json")]
+        write_atomic(path, document::json::page_map(formatter) + "\n")?;
+
+        #[cfg(not(feature = "json"))]
+        {
+            let _ = (formatter, path);
+            return Err("batyr was built without the json feature".into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Output file that appears under its name only once it is complete
+///
+/// Writes go to a hidden temporary file in the same directory, which
+/// [`commit`] renames over the target.  Anyone reading the target
+/// sees either the old file or the new one, never a truncated one.
+/// If the file is dropped without being committed, the temporary
+/// file is removed and the target is left alone.
+///
+/// [`commit`]: AtomicFile::commit
+///
+/// # Examples
+///
+/// ```
+/// use std::fs;
+/// use std::io::Write;
+/// use batyr::AtomicFile;
+///
+/// let path = std::env::temp_dir().join("batyr-atomic-example.ps");
+/// fs::write(&path, "old").unwrap();
+///
+/// let mut file = AtomicFile::create(&path).unwrap();
+/// file.write_all(b"%!PS").unwrap();
+/// assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+///
+/// file.commit().unwrap();
+/// assert_eq!(fs::read_to_string(&path).unwrap(), "%!PS");
+/// # fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct AtomicFile {
+    path: PathBuf,
+    temp_path: PathBuf,
+    file: Option<fs::File>,
+}
+
+impl AtomicFile {
+    /// Opens a temporary file beside the target for writing
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let name = path.file_name().ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidInput, "output path is not a file name"
+        ))?;
+
+        // The process ID keeps concurrent runs out of each other's way.
+        let mut temp_name = OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{}.tmp", process::id()));
+
+        let temp_path = path.with_file_name(temp_name);
+        let file = fs::File::create(&temp_path)?;
+
+        Ok(Self {
+            path,
+            temp_path,
+            file: Some(file),
+        })
+    }
+
+    /// Flushes the contents to disk and moves them into place
+    pub fn commit(mut self) -> io::Result<()> {
+        if let Some(file) = self.file.take() {
+            let result = file.sync_all()
+                .and_then(|_| fs::rename(&self.temp_path, &self.path));
+
+            if result.is_err() {
+                let _ = fs::remove_file(&self.temp_path);
+            }
+
+            return result;
+        }
+
+        Ok(())
+    }
+
+    fn file(&mut self) -> io::Result<&mut fs::File> {
+        self.file.as_mut()
+            .ok_or_else(|| io::Error::other("output file already committed"))
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file()?.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// Writes a whole file atomically, like [`fs::write`]
+///
+/// See [`AtomicFile`].
+pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C)
+                                                    -> io::Result<()>
+{
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(contents.as_ref())?;
+    file.commit()
 }
@@ -1,26 +0,0 @@
-// Batyr Main Driver
-// Copyright (C) 2023 Gene Yu
-//
-// This program is free software: you can redistribute it and/or
-// modify it under the terms of the GNU General Public License as
-// published by the Free Software Foundation, either version 3 of the
-// License, or (at your option) any later version.
-//
-// This program is distributed in the hope that it will be useful, but
-// WITHOUT ANY WARRANTY; without even the implied warranty of
-// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
-// General Public License for more details.
-//
-// You should have received a copy of the GNU General Public License
-// along with this program.  If not, see
-// <https://www.gnu.org/licenses/>.
-
-use std::error::Error;
-
-use clap::Parser;
-
-fn main() -> Result<(), Box<dyn Error>> {
-    let args = batyr::Arguments::parse();
-    let root = batyr::read(&args).unwrap();
-    batyr::write(root, &args)
-}
@@ -36,12 +36,56 @@ pub mod parser;
 /// Different sets of display flags require different Postscript
 /// commands.  Each line is split put into [`Segment`]s, based on the
 /// number of display state changes there are.
+///
+/// A line made from tokens has as few segments as its display flags
+/// allow: neighbouring segments always differ in their flags, and no
+/// segment is empty.  Every segment costs the backends an operation
+/// of its own, such as a PostScript <tt>show</tt>.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::text::Line;
+/// # use batyr::text::tokens::*;
+/// let em = |mut token: TokenType| {
+///     if let TokenType::Word(ref mut t) = token { t.dpy = DisplayFlags::EM; }
+///     if let TokenType::Space(ref mut t) = token { t.dpy = DisplayFlags::EM; }
+///     token
+/// };
+/// let br = TokenType::LineBreak(Token {
+///     data: LineBreakData {},
+///     dpy: Default::default(),
+///     frm: FormatFlags::MLB,
+/// });
+///
+/// let tokens = vec![br.clone(),
+///                   em(TokenType::Word(Token::from("never"))),
+///                   em(TokenType::Space(Token::from(1))),
+///                   br.clone(),
+///                   em(TokenType::Word(Token::from("ever"))),
+///                   TokenType::Space(Token::from(1)),
+///                   TokenType::Word(Token::from("again"))];
+///
+/// let line = Line::from(&tokens[..]);
+/// let segments: Vec<(&str, DisplayFlags)> = line.segments.iter()
+///     .map(|x| (x.text.as_str(), x.dpy))
+///     .collect();
+///
+/// assert_eq!(segments, vec![("never ever", DisplayFlags::EM),
+///                           (" again", DisplayFlags::empty())]);
+///
+/// assert!(line.segments.windows(2).all(|x| x[0].dpy != x[1].dpy));
+/// assert!(Line::from(&[br][..]).segments.is_empty());
+/// ```
 #[derive(Debug, Clone)]
 pub struct Line {
     /// The start column
     pub column: usize,
     /// The line segments in order from left to right
     pub segments: Vec<Segment>,
+    /// Name of the element the line was set from, such as "cue", or
+    /// None for a line the formatter adds itself, such as "(MORE)"
+    pub tag: Option<&'static str>,
 }
 
 impl Line {
@@ -71,6 +115,8 @@ pub struct Segment {
     pub text: String,
     /// The Postscript command to print the line segment
     pub ps: String,
+    /// The display flags shared by the tokens in the segment
+    pub dpy: DisplayFlags,
 }
 
 lazy_static! {
@@ -95,6 +141,7 @@ impl From<String> for Segment {
         Self {
             text: s,
             ps: format!("({}) show ", ps),
+            dpy: Default::default(),
         }
     }
 }
@@ -110,6 +157,7 @@ impl From<&str> for Segment {
         Self {
             text: s.to_string(),
             ps: format!("({}) show ", ps),
+            dpy: Default::default(),
         }
     }
 }
@@ -119,83 +167,46 @@ impl From<Segment> for Line {
         Self {
             column: 0,
             segments: vec![segment],
+            tag: None,
         }
     }
 }
 
 // Each state change requires a distinct set of Postscript commands.
 // The following conversion splits a line of output into segments,
-// and intersperses Postscript commands.
+// and intersperses Postscript commands.  Line breaks print nothing
+// and have no say in the display state, so they never split a
+// segment.
 impl From<&[TokenType]> for Line {
     fn from(tokens: &[TokenType]) -> Line {
         let mut segments: Vec<Segment> = Vec::new();
-        let mut state_changes: Vec<usize> = Vec::new();
-
-        let mut dpy: DisplayFlags = match tokens.first() {
-            Some(token) => token.display_flags(),
-            None => Default::default(),
-        };
+        let mut dpy: Option<DisplayFlags> = None;
+        let mut start = 0;
 
-        state_changes.push(0);
-        
         for (i, token) in tokens.iter().enumerate() {
-            match token {
-                TokenType::Close(token) => {
-                    if dpy != token.dpy {
-                        dpy = token.dpy;
-                        state_changes.push(i);
-                    }
-                },
-                TokenType::LineBreak(_) => {},
-                TokenType::Open(token) => {
-                    if dpy != token.dpy {
-                        dpy = token.dpy;
-                        state_changes.push(i);
-                    }
-                },
-                TokenType::Punct(token) => {
-                    if dpy != token.dpy {
-                        dpy = token.dpy;
-                        state_changes.push(i);
-                    }
-                },
-                TokenType::Space(token) => {
-                    if dpy != token.dpy {
-                        dpy = token.dpy;
-                        state_changes.push(i);
-                    }
-                },
-                TokenType::Symbol(token) => {
-                    if dpy != token.dpy {
-                        dpy = token.dpy;
-                        state_changes.push(i);
-                    }
-                },
-                TokenType::Word(token) => {
-                    if dpy != token.dpy {
-                        dpy = token.dpy;
-                        state_changes.push(i);
-                    }
+            if let TokenType::LineBreak(_) = token {
+                continue;
+            }
+
+            match dpy {
+                Some(flags) if flags != token.display_flags() => {
+                    segments.push((&tokens[start..i]).into());
+                    start = i;
+                    dpy = Some(token.display_flags());
                 },
+                Some(_) => {},
+                None => dpy = Some(token.display_flags()),
             }
         }
 
-        state_changes.push(tokens.len());
-
-        let mut iter = state_changes.windows(2);
-
-        while let Some(state_change) = iter.next() {
-            let i = state_change[0];
-            let j = state_change[1];
-
-            if j - i > 0 {
-                segments.push((&tokens[i..j]).into());
-            }
+        if dpy.is_some() {
+            segments.push((&tokens[start..]).into());
         }
 
         Line {
             column: 0,
-            segments: segments,
+            segments,
+            tag: None,
         }
     }
 }
@@ -206,10 +217,9 @@ impl From<&[TokenType]> for Segment {
         let mut text = String::new();
         let mut ps = String::new();
 
-        let dpy: DisplayFlags = match tokens.first() {
-            Some(token) => token.display_flags(),
-            None => Default::default(),
-        };
+        let dpy: DisplayFlags = tokens.iter()
+            .find(|token| !matches!(token, TokenType::LineBreak(_)))
+            .map_or(Default::default(), TokenType::display_flags);
 
         // Postscript prefix
         ps.push('(');
@@ -266,15 +276,20 @@ impl From<&[TokenType]> for Segment {
         // Postscript suffix
         ps.push_str(") ");
 
-        if dpy.intersects(DisplayFlags::EM) {
-            ps.push_str("ushow ");
-        } else {
-            ps.push_str("show ");
+        let underline = dpy.intersects(DisplayFlags::EM | DisplayFlags::UL);
+        let bold = dpy.intersects(DisplayFlags::BOLD);
+
+        match (underline, bold) {
+            (true, true) => ps.push_str("ubshow "),
+            (true, false) => ps.push_str("ushow "),
+            (false, true) => ps.push_str("bshow "),
+            (false, false) => ps.push_str("show "),
         }
 
         Segment {
-            text: text,
-            ps: ps,
+            text,
+            ps,
+            dpy,
         }
     }
 }
@@ -308,7 +323,9 @@ pub fn next_word_fits(tokens: &[TokenType], line_length: usize, i: usize, x: usi
             return u <= line_length;
             
         } else if frm.intersects(FormatFlags::DLB) {
-            if frm.intersects(FormatFlags::DOB) {
+            if tokens[j].is_soft_hyphen() {
+                return u < line_length; // room for the hyphen
+            } else if frm.intersects(FormatFlags::DOB) {
                 return u <= line_length;
             } else {
                 return u + len <= line_length;
@@ -323,6 +340,66 @@ pub fn next_word_fits(tokens: &[TokenType], line_length: usize, i: usize, x: usi
     u <= line_length
 }
 
+/// Measures how far each break opportunity reaches into the line
+///
+/// For each discretionary break, the entry holds the columns taken by
+/// the break token and the word that follows it, up to and including
+/// whatever the next break leaves on the line.  A break at column
+/// <tt>x</tt> can be passed over if <tt>x</tt> plus its reach is no
+/// more than the line length, which is what [`next_word_fits`]
+/// decides by looking ahead.  The reaches come from a single pass
+/// from the end, so breaking a run of text takes linear time however
+/// long its words are.  Entries for other tokens are zero.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::text::parser::Parser;
+/// # use batyr::text::{lookahead, next_word_fits};
+/// let tokens = Parser::new("Emma waits by the door", Vec::new(),
+///                          Default::default())
+///     .run()
+///     .get_tokens();
+/// let reach = lookahead(&tokens[..]);
+///
+/// for (i, n) in reach.iter().enumerate() {
+///     if *n > 0 {
+///         for x in 0..20 {
+///             assert_eq!(x + n <= 12, next_word_fits(&tokens[..], 12, i, x));
+///         }
+///     }
+/// }
+/// ```
+pub fn lookahead(tokens: &[TokenType]) -> Vec<usize> {
+    let mut reach: Vec<usize> = vec![0; tokens.len()];
+    let mut run: usize = 0; // columns up to the next break
+
+    for (i, token) in tokens.iter().enumerate().rev() {
+        let frm = token.format_flags();
+        let len = token.length();
+
+        if frm.intersects(FormatFlags::MLB) {
+            run = 0;
+
+        } else if frm.intersects(FormatFlags::DLB) {
+            reach[i] = len + run;
+
+            run = if token.is_soft_hyphen() {
+                1 // room for the hyphen
+            } else if frm.intersects(FormatFlags::DOB) {
+                0
+            } else {
+                len
+            };
+
+        } else {
+            run += len;
+        }
+    }
+
+    reach
+}
+
 /// Counts the number of lines it will take to render a slice of
 /// tokens in a column of a given line length
 ///
@@ -337,6 +414,7 @@ pub fn next_word_fits(tokens: &[TokenType], line_length: usize, i: usize, x: usi
 /// assert_eq!(count_lines(&tokens[..], 4), 2);
 /// ```
 pub fn count_lines(tokens: &[TokenType], line_length: usize) -> usize {
+    let reach = lookahead(tokens);
     let mut n: usize = 1; // line count
     let mut x: usize = 0; // char count
 
@@ -348,7 +426,7 @@ pub fn count_lines(tokens: &[TokenType], line_length: usize) -> usize {
             x = 0;
 
         } else if frm.intersects(FormatFlags::DLB) {
-            if !next_word_fits(tokens, line_length, i, x) {
+            if x + reach[i] > line_length {
                 n += 1;
                 x = 0;
                 
@@ -381,24 +459,98 @@ pub fn count_lines(tokens: &[TokenType], line_length: usize) -> usize {
 /// let lines = linebreak_fill(&tokens[..], 6);
 /// assert_eq!(lines.len(), 2);
 /// ```
+///
+/// A soft hyphen breaks a word that does not fit, and shows as a
+/// hyphen at the end of the line.
+///
+/// ```
+/// # use batyr::text::parser::Parser;
+/// # use batyr::text::linebreak_fill;
+/// let parser = Parser::new("an extra\u{00ad}ordinary day", Vec::new(),
+///                          Default::default()).run();
+/// let lines = linebreak_fill(&parser.get_tokens()[..], 10);
+/// let text: Vec<String> = lines.iter().map(|line| line.text()).collect();
+/// assert_eq!(text, vec!["an extra-", "ordinary", "day"]);
+/// ```
 pub fn linebreak_fill(tokens: &[TokenType], line_length: usize) -> Vec<Line> {
-    // tuple (index, discard)
-    let mut splits: Vec<(usize, bool)> = Vec::new();
-    let mut x: usize = 0;
+    linebreak_hang(tokens, line_length, 0)
+}
+
+/// Breaks a token list into lines with a hanging indent
+///
+/// Works like [`linebreak_fill`], except that a line that wraps
+/// continues <tt>hang</tt> columns to the right, in a column that
+/// much narrower.  A mandatory line break starts again at the margin,
+/// as in lyrics.  Continuation lines have <tt>line.column</tt> set to
+/// <tt>hang</tt>, and the others to 0.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::text::parser::Parser;
+/// # use batyr::text::linebreak_hang;
+/// let parser = Parser::new("1) Buy milk and eggs", Vec::new(),
+///                          Default::default()).run();
+/// let lines = linebreak_hang(&parser.get_tokens()[..], 12, 3);
+/// let text: Vec<(usize, String)> = lines.iter()
+///     .map(|line| (line.column, line.text()))
+///     .collect();
+///
+/// assert_eq!(text, vec![(0, "1) Buy milk".to_string()),
+///                       (3, "and eggs".to_string())]);
+/// ```
+pub fn linebreak_hang(tokens: &[TokenType], line_length: usize, hang: usize)
+                      -> Vec<Line>
+{
+    linebreak_indent(tokens, line_length, 0, hang)
+}
+
+/// Breaks a token list into lines with a first-line indent and a
+/// hanging indent
+///
+/// The first line begins <tt>indent</tt> columns to the right, and is
+/// that much narrower; lines that wrap begin <tt>hang</tt> columns to
+/// the right.  Lines after a mandatory line break start again at the
+/// margin.  Each line has <tt>line.column</tt> set to where it
+/// begins.  To go on with text broken off at a page break, pass the
+/// hang as the indent.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::text::parser::Parser;
+/// # use batyr::text::linebreak_indent;
+/// let parser = Parser::new("Emma waits by the door", Vec::new(),
+///                          Default::default()).run();
+/// let lines = linebreak_indent(&parser.get_tokens()[..], 15, 5, 0);
+/// let text: Vec<(usize, String)> = lines.iter()
+///     .map(|line| (line.column, line.text()))
+///     .collect();
+///
+/// assert_eq!(text, vec![(5, "Emma waits".to_string()),
+///                       (0, "by the door".to_string())]);
+/// ```
+pub fn linebreak_indent(tokens: &[TokenType], line_length: usize,
+                        indent: usize, hang: usize) -> Vec<Line>
+{
+    // tuple (index, discard, wrapped)
+    let mut splits: Vec<(usize, bool, bool)> = Vec::new();
+    let mut x: usize = indent;
+    let reach = lookahead(tokens);
     
-    splits.push((0, false));
+    splits.push((0, false, false));
 
     for (i, token) in tokens.iter().enumerate() {
         let frm = token.format_flags();
 
         if frm.intersects(FormatFlags::MLB) {
-            splits.push((i + 1, true));
+            splits.push((i + 1, true, false));
             x = 0;
 
         } else if frm.intersects(FormatFlags::DLB) {
-            if !next_word_fits(tokens, line_length, i, x) {
-                splits.push((i + 1, frm.intersects(FormatFlags::DOB)));
-                x = 0;
+            if x + reach[i] > line_length {
+                splits.push((i + 1, frm.intersects(FormatFlags::DOB), true));
+                x = hang;
 
             } else {
                 x += token.length();
@@ -409,7 +561,7 @@ pub fn linebreak_fill(tokens: &[TokenType], line_length: usize) -> Vec<Line> {
         }
     }
 
-    splits.push((tokens.len(), false));
+    splits.push((tokens.len(), false, false));
 
     let mut lines: Vec<Line> = Vec::new();
     let mut iter = splits.windows(2);
@@ -420,9 +572,16 @@ pub fn linebreak_fill(tokens: &[TokenType], line_length: usize) -> Vec<Line> {
             true => split[1].0 - 1,  // discard the current token
             false => split[1].0,     // retain the current token
         };
-        
-        if j - i > 0 {
-            lines.push((&tokens[i..j]).into());
+
+        let n = lines.len();
+        push_line(&mut lines, tokens, i, j, split[1].1);
+
+        if lines.len() > n {
+            if split[0].2 {
+                lines[n].column = hang;
+            } else if i == 0 {
+                lines[n].column = indent;
+            }
         }
     }
 
@@ -493,10 +652,122 @@ pub fn linebreak_balance(tokens: &[TokenType], line_length: usize)
             false => split[1].0,     // retain the current token
         };
 
-        if j - i > 0 {
-            lines.push((&tokens[i..j]).into());
-        }
+        push_line(&mut lines, tokens, i, j, split[1].1);
     }
 
     lines
 }
+
+/// Appends the tokens <tt>i..j</tt> as a new line
+///
+/// If the discarded break token is a soft hyphen, a visible hyphen
+/// takes its place at the end of the line.
+fn push_line(lines: &mut Vec<Line>, tokens: &[TokenType], i: usize, j: usize,
+             discard: bool)
+{
+    if discard && tokens[j].is_soft_hyphen() {
+        let mut line = tokens[i..j].to_vec();
+        line.push(TokenType::Punct(Token::new(PunctData { text: "-".to_string() },
+                                              tokens[j].display_flags(),
+                                              Default::default())));
+        lines.push((&line[..]).into());
+
+    } else if j - i > 0 {
+        lines.push((&tokens[i..j]).into());
+    }
+}
+
+/// Words left in lowercase by [`title_case`], except at either end
+const MINOR_WORDS: [&str; 14] = [
+    "a", "an", "and", "as", "at", "but", "by", "for", "in", "nor", "of",
+    "on", "or", "the",
+];
+
+/// Converts a token list to capitals in the screenplay style
+///
+/// Words and symbols are uppercased according to the casing policy,
+/// except that the <tt>Mc</tt> of a surname keeps its small
+/// <tt>c</tt>, as in <tt>McCUE</tt>.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::text::parser::Parser;
+/// # use batyr::text::tokens::*;
+/// # use batyr::text::to_screenplay_caps;
+/// let mut tokens = Parser::new("int. McCue's office - day", Vec::new(),
+///                              Default::default()).run().get_tokens();
+/// to_screenplay_caps(&mut tokens, Casing::Default);
+///
+/// let text: String = tokens.iter().map(TokenType::text).collect();
+/// assert_eq!(text, "INT.  McCUE'S OFFICE - DAY");
+/// ```
+pub fn to_screenplay_caps(tokens: &mut [TokenType], casing: Casing) {
+    for token in tokens.iter_mut() {
+        match token {
+            TokenType::Word(token) if token.data.text.starts_with("Mc")
+                && token.data.text.chars().count() > 2 =>
+            {
+                token.data.text = format!("Mc{}",
+                                          uppercase(&token.data.text[2..], casing));
+            },
+            _ => token.make_uppercase(casing),
+        }
+    }
+}
+
+/// Converts a token list to title case
+///
+/// Each word gets an initial capital and the rest in lowercase.
+/// Articles, short conjunctions and short prepositions stay in
+/// lowercase unless they begin or end the list, and so does a word
+/// after an apostrophe.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::text::parser::Parser;
+/// # use batyr::text::tokens::*;
+/// # use batyr::text::title_case;
+/// let mut tokens = Parser::new("IT'S A WONDERFUL LIFE OF THE MIND",
+///                              Vec::new(), Default::default())
+///     .run()
+///     .get_tokens();
+/// title_case(&mut tokens);
+///
+/// let text: String = tokens.iter().map(TokenType::text).collect();
+/// assert_eq!(text, "It's a Wonderful Life of the Mind");
+/// ```
+pub fn title_case(tokens: &mut [TokenType]) {
+    let words: Vec<usize> = tokens.iter()
+        .enumerate()
+        .filter(|(_, token)| matches!(token, TokenType::Word(_)))
+        .map(|(i, _)| i)
+        .collect();
+
+    let first = words.first().copied();
+    let last = words.last().copied();
+
+    for i in words.iter().copied() {
+        let after_apostrophe = i > 0 && tokens[i - 1].text() == "'";
+
+        if let TokenType::Word(token) = &mut tokens[i] {
+            let lower = token.data.text.to_lowercase();
+
+            let minor = after_apostrophe
+                || (Some(i) != first && Some(i) != last
+                    && MINOR_WORDS.contains(&lower.as_str()));
+
+            token.data.text = if minor {
+                lower
+            } else {
+                let mut chars = lower.chars();
+
+                match chars.next() {
+                    Some(c) => c.to_uppercase().chain(chars).collect(),
+                    None => lower,
+                }
+            };
+        }
+    }
+}
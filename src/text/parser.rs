@@ -64,8 +64,14 @@
 //!   preceded by a <tt>Punct</tt> token containing a full stop, in
 //!   which case two space characters are copied into the space token.
 //!   Between the space token and the preceding <tt>Punct</tt>, any
-//!   number of <tt>Close</tt> tokens may appear; they are ignored
-//!   with respect to the length of the space token.
+//!   number of <tt>Close</tt> tokens, typewriter quotation marks and
+//!   apostrophes may appear; they are ignored with respect to the
+//!   length of the space token.  The space token after a sentence
+//!   carries the end-of-sentence flag of the <tt>Punct</tt> token
+//!   before it, so the page breaker finds the same sentence ends.
+//!   An ellipsis, an abbreviation, or punctuation with no words
+//!   before it gets a single space; see [`EllipsisSpacing`] and
+//!   [`ABBREVIATIONS`].
 //!
 //!   In the <tt>Escape</tt> state, when the escaped character is a
 //!   whitespace character, the machine consumes whitespace
@@ -83,9 +89,167 @@
 //! corresponding [`TokenType`] variant.
 
 use std::collections::VecDeque;
+use std::rc::Rc;
 
 use crate::text::tokens::*;
 
+/// Checks for a control character that the parser strips from text
+///
+/// These are the C0 control characters other than tab, line feed and
+/// carriage return, and delete.  They turn up in text pasted from old
+/// word processor files, where the bell, vertical tab and form feed
+/// once meant something to a typewriter or printer.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::text::parser::is_stripped_control;
+/// assert!(is_stripped_control('\u{0007}'));
+/// assert!(is_stripped_control('\u{000c}'));
+/// assert!(!is_stripped_control('\t'));
+/// assert!(!is_stripped_control('a'));
+/// ```
+pub fn is_stripped_control(ch: char) -> bool {
+    match ch {
+        '\u{0009}' | '\u{000a}' | '\u{000d}' => false,
+        '\u{0000}'..='\u{001f}' | '\u{007f}' => true,
+        _ => false,
+    }
+}
+
+/// Abbreviations whose full stop does not end a sentence
+///
+/// A full stop after one of these words gets an ordinary space, and
+/// is no place for a line break, so a title stays with its name.
+pub const ABBREVIATIONS: [&str; 14] = [
+    "Mr", "Mrs", "Ms", "Dr", "St", "Mt", "Jr", "Sr", "Prof", "Capt",
+    "Lt", "Sgt", "Rev", "vs",
+];
+
+/// Number of spaces after a full stop
+///
+/// Typewriter convention, and the default, is two spaces between
+/// sentences; many writers now prefer one.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::text::parser::{Parser, SentenceSpacing};
+/// # use batyr::text::tokens::TokenType;
+/// let text = |spacing| {
+///     Parser::new("Stop.  Go.", Vec::new(), Default::default())
+///         .sentence_spacing(spacing)
+///         .run()
+///         .get_tokens()
+///         .iter()
+///         .map(TokenType::text)
+///         .collect::<String>()
+/// };
+///
+/// assert_eq!(text(SentenceSpacing::Double), "Stop.  Go.");
+/// assert_eq!(text(SentenceSpacing::from("single")), "Stop. Go.");
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SentenceSpacing {
+    /// One space
+    Single,
+    /// Two spaces
+    #[default]
+    Double,
+}
+
+impl From<&str> for SentenceSpacing {
+    fn from(s: &str) -> Self {
+        match s {
+            "single" => SentenceSpacing::Single,
+            _ => SentenceSpacing::Double,
+        }
+    }
+}
+
+/// Number of spaces after an ellipsis
+///
+/// By default an ellipsis typed as three full stops is spaced like a
+/// full stop, as it always has been.  Writers who use it to trail off
+/// within a sentence can ask for one space instead, which also goes
+/// for the ellipsis character.  An ellipsis or dash that opens the
+/// text always takes one space.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::text::parser::{EllipsisSpacing, Parser};
+/// # use batyr::text::tokens::TokenType;
+/// let text = |input, spacing| {
+///     Parser::new(input, Vec::new(), Default::default())
+///         .ellipsis_spacing(spacing)
+///         .run()
+///         .get_tokens()
+///         .iter()
+///         .map(TokenType::text)
+///         .collect::<String>()
+/// };
+///
+/// assert_eq!(text("Well... no.", EllipsisSpacing::default()), "Well...  no.");
+/// assert_eq!(text("Well... no.", EllipsisSpacing::from("single")),
+///            "Well... no.");
+/// assert_eq!(text("Well\u{2026} no.", EllipsisSpacing::Single), "Well... no.");
+/// assert_eq!(text("... no.", EllipsisSpacing::Sentence), "... no.");
+/// assert_eq!(text("\"-- no.", EllipsisSpacing::Sentence), "\"-- no.");
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum EllipsisSpacing {
+    /// One space
+    Single,
+    /// The same spacing as after a full stop
+    #[default]
+    Sentence,
+}
+
+impl From<&str> for EllipsisSpacing {
+    fn from(s: &str) -> Self {
+        match s {
+            "single" => EllipsisSpacing::Single,
+            _ => EllipsisSpacing::Sentence,
+        }
+    }
+}
+
+/// What to do with tab characters in text
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::text::parser::TabPolicy;
+/// assert_eq!(TabPolicy::from("expand"), TabPolicy::Expand(8));
+/// assert_eq!(TabPolicy::from("error"), TabPolicy::Error);
+/// assert_eq!(TabPolicy::from("collapse"), TabPolicy::Collapse);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum TabPolicy {
+    /// Treat tabs like any other whitespace
+    #[default]
+    Collapse,
+    /// Pad with spaces to the next multiple of the given tab stop,
+    /// counting from the start of the text element
+    Expand(usize),
+    /// Reject text that contains tabs
+    Error,
+}
+
+impl From<&str> for TabPolicy {
+    fn from(s: &str) -> Self {
+        match s {
+            "expand" => TabPolicy::Expand(TAB_STOP),
+            "error" => TabPolicy::Error,
+            _ => TabPolicy::Collapse,
+        }
+    }
+}
+
+/// Default tab stop width for [`TabPolicy::Expand`]
+pub const TAB_STOP: usize = 8;
+
 /// Driver for parsing text element contents
 pub struct Parser {
     /// The current state
@@ -93,6 +257,9 @@ pub struct Parser {
 
     /// For stepping through the input string a character at a time in order
     buffer: VecDeque<char>,
+
+    /// Tab character handling
+    tabs: TabPolicy,
 }
 
 impl Parser {
@@ -112,11 +279,142 @@ impl Parser {
     /// let tokens: TokenList = Vec::new();
     /// let parser = Parser::new("foo bar", tokens, Default::default());
     /// ```
+    ///
+    /// Carriage returns are treated as line feeds, and invisible
+    /// formatting characters such as the zero-width space and the
+    /// byte order mark are dropped, so text typed on any platform
+    /// produces the same tokens.  So are the control characters
+    /// listed under [`is_stripped_control`], except that a vertical
+    /// tab or form feed still separates the words on either side:
+    ///
+    /// ```
+    /// # use batyr::text::parser::Parser;
+    /// let unix = Parser::new("foo\nbar", Vec::new(), Default::default())
+    ///     .run()
+    ///     .get_tokens();
+    /// let windows = Parser::new("\u{feff}foo\r\nbar\u{200b}", Vec::new(),
+    ///                           Default::default())
+    ///     .run()
+    ///     .get_tokens();
+    /// let pasted = Parser::new("f\u{0007}oo\u{000c}bar", Vec::new(),
+    ///                          Default::default())
+    ///     .run()
+    ///     .get_tokens();
+    /// assert_eq!(format!("{:?}", unix), format!("{:?}", windows));
+    /// assert_eq!(format!("{:?}", unix), format!("{:?}", pasted));
+    /// ```
     pub fn new(input_string: &str, tokens: TokenList, dpy: DisplayFlags) -> Self {
         Parser {
             state: StateMachine::Scan(State::new(tokens, dpy)),
-            buffer: VecDeque::from_iter(input_string.chars()),
+            buffer: Parser::normalize(input_string),
+            tabs: Default::default(),
+        }
+    }
+
+    /// Set the tab character handling
+    ///
+    /// Only [`TabPolicy::Expand`] changes the tokens; the parser
+    /// collapses tabs otherwise, and leaves it to the caller to
+    /// reject them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::text::parser::{Parser, TabPolicy};
+    /// # use batyr::text::tokens::TokenType;
+    /// let tokens = Parser::new("ab\tc", Vec::new(), Default::default())
+    ///     .tabs(TabPolicy::Expand(4))
+    ///     .run()
+    ///     .get_tokens();
+    /// let text: String = tokens.iter().map(TokenType::text).collect();
+    /// assert_eq!(text, "ab  c");
+    /// ```
+    pub fn tabs(mut self, tabs: TabPolicy) -> Self {
+        self.tabs = tabs;
+        self
+    }
+
+    /// Add to the abbreviations after which a full stop does not end
+    /// the sentence
+    ///
+    /// The words are matched without their full stop, ignoring case,
+    /// in addition to the built-in [`ABBREVIATIONS`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::rc::Rc;
+    /// # use batyr::text::parser::Parser;
+    /// # use batyr::text::tokens::TokenType;
+    /// let text = |abbreviations: Vec<String>| {
+    ///     Parser::new("Mr. Pink, Insp. Clouseau.  Go.", Vec::new(),
+    ///                 Default::default())
+    ///         .abbreviations(Rc::new(abbreviations))
+    ///         .run()
+    ///         .get_tokens()
+    ///         .iter()
+    ///         .map(TokenType::text)
+    ///         .collect::<String>()
+    /// };
+    ///
+    /// assert_eq!(text(Vec::new()), "Mr. Pink, Insp.  Clouseau.  Go.");
+    /// assert_eq!(text(vec!["insp".to_string()]),
+    ///            "Mr. Pink, Insp. Clouseau.  Go.");
+    /// ```
+    pub fn abbreviations(mut self, abbreviations: Rc<Vec<String>>) -> Self {
+        if let StateMachine::Scan(state) = &mut self.state {
+            state.abbreviations = Some(abbreviations);
+        }
+
+        self
+    }
+
+    /// Set the number of spaces after a full stop
+    pub fn sentence_spacing(mut self, spacing: SentenceSpacing) -> Self {
+        if let StateMachine::Scan(state) = &mut self.state {
+            state.sentence_spacing = spacing;
+        }
+
+        self
+    }
+
+    /// Set the number of spaces after an ellipsis
+    pub fn ellipsis_spacing(mut self, spacing: EllipsisSpacing) -> Self {
+        if let StateMachine::Scan(state) = &mut self.state {
+            state.ellipsis_spacing = spacing;
+        }
+
+        self
+    }
+
+    /// Maps line endings to line feeds and drops invisible formatting
+    /// and control characters
+    fn normalize(input_string: &str) -> VecDeque<char> {
+        let mut buffer = VecDeque::new();
+        let mut chars = input_string.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\u{000d}' => { // Carriage return
+                    if chars.peek() != Some(&'\u{000a}') {
+                        buffer.push_back('\u{000a}');
+                    }
+                },
+                '\u{200b}' |    // Zero width space
+                '\u{200c}' |    // Zero width non-joiner
+                '\u{200d}' |    // Zero width joiner
+                '\u{2060}' |    // Word joiner
+                '\u{feff}' => (), // Zero width no-break space (BOM)
+                '\u{000b}' |    // Line tabulation
+                '\u{000c}' => { // Form feed
+                    buffer.push_back('\u{000a}');
+                },
+                _ if is_stripped_control(ch) => (),
+                _ => buffer.push_back(ch),
+            }
         }
+
+        buffer
     }
 
     /// Consume the input string and generate tokens.
@@ -137,6 +435,16 @@ impl Parser {
                 break;
             }
 
+            if let (Some('\u{0009}'), TabPolicy::Expand(stop)) =
+                (self.buffer.front(), self.tabs)
+            {
+                if !matches!(self.state, StateMachine::Escape(_)) {
+                    self.state = self.state.flush().expand_tab(stop);
+                    self.buffer.pop_front();
+                    continue;
+                }
+            }
+
             if let Some(ch) = self.buffer.front() {
                 let consume_flag: bool;
                 
@@ -169,6 +477,12 @@ struct State<Data> {
     tokens: TokenList,
     /// Counter for word tokens
     word_count: usize,
+    /// Spaces after a full stop
+    sentence_spacing: SentenceSpacing,
+    /// Spaces after an ellipsis
+    ellipsis_spacing: EllipsisSpacing,
+    /// Abbreviations in addition to the built-in ones
+    abbreviations: Option<Rc<Vec<String>>>,
     /// Changes in the display state cause line segmentation downstream.
     dpy: DisplayFlags,
     /// Format flags are used by the line breaking algorithm, and for
@@ -184,34 +498,97 @@ impl State<ScanData> {
     fn new(tokens: TokenList, dpy: DisplayFlags) -> Self {
         State {
             data: ScanData {},
-            tokens: tokens,
+            tokens,
             word_count: 0,
-            dpy: dpy,
+            sentence_spacing: Default::default(),
+            ellipsis_spacing: Default::default(),
+            abbreviations: None,
+            dpy,
             frm: Default::default(),
         }
     }
 }
 
+/// Checks for closing punctuation that may follow the end of a
+/// sentence
+///
+/// Besides the [`TokenType::Close`] characters, typewriter quotation
+/// marks count, since they close a quote as often as they open one,
+/// and so do apostrophes used as single quotation marks.
+fn is_closing(token: &TokenType) -> bool {
+    match token {
+        TokenType::Close(_) => true,
+        TokenType::Punct(token) => token.data.text == "'",
+        TokenType::Symbol(token) => token.data.text == "\"",
+        _ => false,
+    }
+}
+
 #[doc(hidden)]
 impl<Data> State<Data> {
+    /// Finds the punctuation before any closing quotes and brackets
+    fn preceding_punct(&self) -> Option<&Token<PunctData>> {
+        match self.tokens.iter().rev().find(|x| !is_closing(x)) {
+            Some(TokenType::Punct(token)) => Some(token),
+            _ => None,
+        }
+    }
+
     fn at_full_stop(&self) -> bool {
-        for token in self.tokens.iter().rev() {
-            match token {
-                TokenType::Close(_) => {},
-                TokenType::Punct(token) => {
-                    return token.frm.intersects(FormatFlags::FS);
-                },
-               _ => break,
-            }
+        self.preceding_punct()
+            .is_some_and(|token| token.frm.intersects(FormatFlags::FS))
+    }
+
+    fn at_end_of_sentence(&self) -> bool {
+        self.preceding_punct()
+            .is_some_and(|token| token.frm.intersects(FormatFlags::EOS))
+    }
+
+    fn at_abbreviation(&self) -> bool {
+        let mut iter = self.tokens.iter().rev().skip_while(|x| is_closing(x));
+
+        match (iter.next(), iter.next()) {
+            (Some(TokenType::Punct(punct)), Some(TokenType::Word(word)))
+                if punct.data.text == "." =>
+            {
+                let word = word.data.text.as_str();
+
+                ABBREVIATIONS.iter().any(|x| x.eq_ignore_ascii_case(word))
+                    || self.abbreviations.as_ref().is_some_and(|list| {
+                        list.iter().any(|x| x.eq_ignore_ascii_case(word))
+                    })
+            },
+            _ => false,
         }
+    }
 
-        return false;
+    fn at_ellipsis(&self) -> bool {
+        let mut iter = self.tokens.iter().rev().skip_while(|x| is_closing(x));
+
+        match (iter.next(), iter.next()) {
+            (Some(TokenType::Punct(a)), _) if a.data.text == "..." => true,
+            (Some(TokenType::Punct(a)), Some(TokenType::Punct(b))) => {
+                a.data.text == "." && b.data.text == "."
+            },
+            _ => false,
+        }
+    }
+
+    /// Checks for punctuation with no words before it, as in dialogue
+    /// that trails in from an ellipsis or a dash
+    fn at_leading_punct(&self) -> bool {
+        self.tokens.iter().any(|x| matches!(x, TokenType::Punct(_)))
+            && self.tokens.iter().all(|x| matches!(
+                x,
+                TokenType::Close(_) | TokenType::Open(_) |
+                TokenType::Punct(_) | TokenType::Space(_)
+            ))
     }
 
     fn remove_preceding_full_stop_flag(&mut self) {
         for token in self.tokens.iter_mut().rev() {
             match token {
-                TokenType::Close(_) => {},
+                token if is_closing(token) => {},
                 TokenType::Punct(token) => {
                     token.frm.remove(FormatFlags::FS | FormatFlags::EOS);
                 },
@@ -270,7 +647,7 @@ impl StateMachine {
                         ch if ch.is_whitespace() => { // collapse whitespace
                             state.data.text.push(' ');
                             state.data.count += 1;
-                            (StateMachine::Escape(state.into()), true)
+                            (StateMachine::Escape(state), true)
                         },
                         '\u{005c}' => { // Backslash
                             state.data.text.push(ch.clone());
@@ -290,7 +667,7 @@ impl StateMachine {
                     match ch {
                         ch if ch.is_whitespace() => {
                             state.data.count += 1;
-                            (StateMachine::Escape(state.into()), true)
+                            (StateMachine::Escape(state), true)
                         },
                         _ => {
                             // Break out of Escape state without
@@ -347,6 +724,10 @@ impl StateMachine {
                         state.frm.insert(FormatFlags::DLB);
                         (StateMachine::Scan(state.into()), true)
                     },
+                    '\u{00ad}' => { // Soft hyphen
+                        state.frm.insert(FormatFlags::DLB | FormatFlags::DOB);
+                        (StateMachine::Scan(state.into()), true)
+                    },
                     '\u{2013}' => { // En dash
                         state.data.text.push_str("-");
                         state.frm.insert(FormatFlags::EOS);
@@ -395,6 +776,7 @@ impl StateMachine {
                     '\u{003b}' |    // Semicolon
                     '\u{003f}' |    // Question mark
                     '\u{00a1}' |    // Inverted exclamation mark
+                    '\u{00ad}' |    // Soft hyphen
                     '\u{00bf}' |    // Inverted question mark
                     '\u{2013}' |    // En dash
                     '\u{2014}' |    // Em dash
@@ -523,6 +905,30 @@ impl StateMachine {
         }
     }
 
+    /// Pad with spaces to the next tab stop.
+    ///
+    /// The column is counted from the last mandatory line break, or
+    /// from the start of the text element.
+    fn expand_tab(self, stop: usize) -> Self {
+        match self {
+            StateMachine::Scan(mut state) => {
+                let column: usize = state.tokens.iter()
+                    .rev()
+                    .take_while(|x| !x.format_flags().intersects(FormatFlags::MLB))
+                    .map(|x| x.length())
+                    .sum();
+
+                let stop = stop.max(1);
+                let mut token = Token::<SpaceData>::from(stop - column % stop);
+                token.dpy = state.dpy;
+                state.tokens.push(TokenType::Space(token));
+
+                StateMachine::Scan(state)
+            },
+            _ => self,
+        }
+    }
+
     /// Provide access to the token list through the enum wrapper.
     fn get_tokens(self) -> TokenList {
         match self {
@@ -567,6 +973,9 @@ impl From<State<CloseData>> for State<ScanData> {
             data: ScanData {},
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -581,6 +990,9 @@ impl From<State<ScanData>> for State<CloseData> {
             },
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -618,6 +1030,9 @@ impl From<State<EscapeData>> for State<ScanData> {
             data: ScanData {},
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -633,6 +1048,9 @@ impl From<State<ScanData>> for State<EscapeData> {
             },
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -651,6 +1069,9 @@ impl From<State<OpenData>> for State<ScanData> {
             data: ScanData {},
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -665,6 +1086,9 @@ impl From<State<ScanData>> for State<OpenData> {
             },
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -683,6 +1107,9 @@ impl From<State<PunctData>> for State<ScanData> {
             data: ScanData {},
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -697,6 +1124,9 @@ impl From<State<ScanData>> for State<PunctData> {
             },
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -714,18 +1144,45 @@ impl From<State<SpaceData>> for State<ScanData> {
         }
             
         if !discard_flag {
-            let text = if state.at_full_stop() {
+            let leading = state.at_leading_punct();
+
+            // Not the end of a sentence after all
+            if leading || state.at_abbreviation() {
+                state.remove_preceding_full_stop_flag();
+            }
+
+            let end_of_sentence = if leading {
+                false
+            } else if state.at_ellipsis()
+                && state.ellipsis_spacing == EllipsisSpacing::Single
+            {
+                false
+            } else {
+                state.at_full_stop()
+            };
+
+            let text = if end_of_sentence
+                && state.sentence_spacing == SentenceSpacing::Double
+            {
                 "  ".to_string()
             } else {
                 " ".to_string()
             };
 
+            // Carry the end of the sentence over any closing quotes
+            // to the space, where the page breaker looks for it
+            let mut frm = state.frm | FormatFlags::DLB | FormatFlags::DOB;
+
+            if !leading && state.at_end_of_sentence() {
+                frm.insert(FormatFlags::EOS);
+            }
+
             state.tokens.push(TokenType::Space(Token::<SpaceData> {
                 data: SpaceData {
-                    text: text,
+                    text,
                 },
                 dpy: state.dpy,
-                frm: state.frm | FormatFlags::DLB | FormatFlags::DOB,
+                frm,
             }));
         }
         
@@ -733,6 +1190,9 @@ impl From<State<SpaceData>> for State<ScanData> {
             data: ScanData {},
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -747,6 +1207,9 @@ impl From<State<ScanData>> for State<SpaceData> {
             },
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -765,6 +1228,9 @@ impl From<State<SymbolData>> for State<ScanData> {
             data: ScanData {},
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -779,6 +1245,9 @@ impl From<State<ScanData>> for State<SymbolData> {
             },
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -797,6 +1266,9 @@ impl From<State<WordData>> for State<ScanData> {
             data: ScanData {},
             tokens: state.tokens,
             word_count: state.word_count + 1,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
@@ -811,6 +1283,9 @@ impl From<State<ScanData>> for State<WordData> {
             },
             tokens: state.tokens,
             word_count: state.word_count,
+            sentence_spacing: state.sentence_spacing,
+            ellipsis_spacing: state.ellipsis_spacing,
+            abbreviations: state.abbreviations,
             dpy: state.dpy,
             frm: Default::default(),
         }
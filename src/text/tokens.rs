@@ -52,9 +52,9 @@ impl<Data> Token<Data> {
     /// ```
     pub fn new(data: Data, dpy: DisplayFlags, frm: FormatFlags) -> Self {
         Self {
-            data: data,
-            dpy: dpy,
-            frm: frm,
+            data,
+            dpy,
+            frm,
         }
     }
 }
@@ -118,6 +118,19 @@ impl TokenType {
         }
     }
 
+    /// Names the kind of token, as in the JSON export
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TokenType::Close    (_) => "close",
+            TokenType::LineBreak(_) => "lineBreak",
+            TokenType::Open     (_) => "open",
+            TokenType::Punct    (_) => "punct",
+            TokenType::Space    (_) => "space",
+            TokenType::Symbol   (_) => "symbol",
+            TokenType::Word     (_) => "word",
+        }
+    }
+
     /// Retrieves the display flags from the associated generic token
     ///
     /// # Examples
@@ -163,16 +176,124 @@ impl TokenType {
             TokenType::Word     (token) => token.frm,
         }
     }
+
+    /// Converts the token text to uppercase in place
+    ///
+    /// Only word and symbol tokens carry cased letters; the other
+    /// token types are left as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::text::tokens::*;
+    /// let mut token = TokenType::Word(Token::from("café"));
+    /// token.make_uppercase(Casing::Default);
+    /// assert_eq!(token.text(), "CAFÉ");
+    /// ```
+    pub fn make_uppercase(&mut self, casing: Casing) {
+        match self {
+            TokenType::Symbol(token) => {
+                token.data.text = uppercase(&token.data.text, casing);
+            },
+            TokenType::Word(token) => {
+                token.data.text = uppercase(&token.data.text, casing);
+            },
+            _ => (),
+        }
+    }
+
+    /// Checks if the token is a soft hyphen
+    ///
+    /// A soft hyphen is an empty punctuation token that marks a
+    /// discretionary break inside a word.  It takes no room on the
+    /// line, and is replaced by a hyphen if the break is taken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use batyr::text::tokens::*;
+    /// let token = TokenType::Punct(Token::new(PunctData { text: String::new() },
+    ///                                         Default::default(),
+    ///                                         FormatFlags::DLB | FormatFlags::DOB));
+    /// assert!(token.is_soft_hyphen());
+    /// assert!(!TokenType::Space(Token::from(1)).is_soft_hyphen());
+    /// ```
+    pub fn is_soft_hyphen(&self) -> bool {
+        match self {
+            TokenType::Punct(token) => {
+                token.data.text.is_empty()
+                    && token.frm.contains(FormatFlags::DLB | FormatFlags::DOB)
+            },
+            _ => false,
+        }
+    }
 }
 
 /// Token accumulator
 pub type TokenList = Vec<TokenType>;
 
+/// Uppercasing policy
+///
+/// The Unicode default case mapping keeps diacritics (<tt>é</tt>
+/// becomes <tt>É</tt>) and maps both <tt>i</tt> and the Turkish
+/// dotless <tt>ı</tt> to <tt>I</tt>.  The Turkic policy maps
+/// <tt>i</tt> to the dotted capital <tt>İ</tt> (<tt>U+0130</tt>)
+/// instead.  Note that <tt>İ</tt> has no Latin-9 equivalent, so it is
+/// printed as a question mark.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Casing {
+    Default,
+    Turkic,
+}
+
+impl From<&str> for Casing {
+    fn from(s: &str) -> Self {
+        match s {
+            "turkic" => Casing::Turkic,
+            _ => Casing::Default,
+        }
+    }
+}
+
+/// Converts text to uppercase according to the casing policy
+///
+/// The result may be longer than the input, as in <tt>ß</tt> to
+/// <tt>SS</tt>, so count lines after the conversion.
+///
+/// # Examples
+///
+/// ```
+/// # use batyr::text::tokens::*;
+/// assert_eq!(uppercase("señor", Casing::Default), "SEÑOR");
+/// assert_eq!(uppercase("straße", Casing::Default), "STRASSE");
+/// assert_eq!(uppercase("istanbul", Casing::Default), "ISTANBUL");
+/// assert_eq!(uppercase("istanbul", Casing::Turkic), "İSTANBUL");
+/// assert_eq!(uppercase("ılık", Casing::Turkic), "ILIK");
+/// ```
+pub fn uppercase(text: &str, casing: Casing) -> String {
+    match casing {
+        Casing::Default => text.to_uppercase(),
+        Casing::Turkic => {
+            text.chars().map(|c| match c {
+                'i' => String::from("\u{0130}"),
+                _ => c.to_uppercase().collect(),
+            }).collect()
+        },
+    }
+}
+
 bitflags! {
     /// Display feature selection
+    ///
+    /// Emphasis is underlined.  Emphasis nested inside emphasis adds
+    /// a second style, so its text is both underlined and in
+    /// capitals.
     #[derive(Default)]
     pub struct DisplayFlags: u32 {
         const EM    = 0b00000001; // emphasis
+        const CAPS  = 0b00000010; // capitals (nested emphasis)
+        const UL    = 0b00000100; // underline
+        const BOLD  = 0b00001000; // bold (double-strike)
     }
 }
 
@@ -264,6 +385,7 @@ pub struct OpenData {
 /// | ;     | <tt>U+003b</tt> | Semicolon                 | <tt>0x3b</tt>      |
 /// | ?     | <tt>U+003f</tt> | Question mark             | <tt>0x3f</tt>      |
 /// | ¡     | <tt>U+00a1</tt> | Inverted exclamation mark | <tt>0x3f</tt>      |
+/// |       | <tt>U+00ad</tt> | Soft hyphen               | <tt>0x2d</tt>      |
 /// | ¿     | <tt>U+00bf</tt> | Inverted question mark    | <tt>0xbf</tt>      |
 /// | –     | <tt>U+2013</tt> | En-dash                   | <tt>0x2c</tt>      |
 /// | —     | <tt>U+2014</tt> | Em-dash                   | <tt>0x2c2c</tt>    |
@@ -340,7 +462,7 @@ pub struct SymbolData {
 impl From<String> for SymbolData {
     fn from(text: String) -> Self {
         Self {
-	    text: text,
+	    text,
         }
     }
 }
@@ -503,7 +625,7 @@ impl From<&str> for WordData {
 impl From<String> for WordData {
     fn from(text: String) -> Self {
         Self {
-	    text: text,
+	    text,
         }
     }
 }